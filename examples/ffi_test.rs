@@ -3,7 +3,7 @@
 //! This demonstrates the cross-platform interface that would be used
 //! from Swift, Kotlin, Python, etc.
 
-use note::models::{Block, Note};
+use note::models::{Block, Note, Properties};
 
 fn main() {
     println!("=== LibnoteDocument FFI Example ===\n");
@@ -12,6 +12,7 @@ fn main() {
     let mut note = Note {
         id: "doc-1".to_string(),
         title: "Document".to_string(),
+        properties: Properties::default(),
         blocks: Vec::new(),
     };
     println!("✓ Created new LibnoteDocument");
@@ -1,5 +1,11 @@
+mod anchors;
+mod mentions;
 mod span;
+mod text;
 mod tree;
 
+pub use anchors::*;
+pub use mentions::*;
 pub use span::*;
+pub use text::*;
 pub use tree::*;
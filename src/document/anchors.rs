@@ -0,0 +1,138 @@
+use crate::{
+    document::plain_text,
+    models::{Block, ContainerBlock, Inline, LeafBlock},
+};
+
+/// Turn heading text into the slug used by `[[note#Heading]]` links
+///
+/// Letters and digits are lowercased, runs of whitespace, `-` and `_` become a
+/// single `-`, and everything else is dropped, so `Q3: Goals & Risks` becomes
+/// `q3-goals-risks`.
+#[must_use]
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    let mut separator = false;
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if separator && !slug.is_empty() {
+                slug.push('-');
+            }
+            separator = false;
+            slug.extend(c.to_lowercase());
+        } else if c.is_whitespace() || c == '-' || c == '_' {
+            separator = true;
+        }
+    }
+    slug
+}
+
+/// Slugs of every heading, in document order
+#[must_use]
+pub fn heading_slugs(blocks: &[Block]) -> Vec<String> {
+    let mut anchors = Anchors::default();
+    anchors.walk(blocks);
+    anchors.headings
+}
+
+/// Every `^block-id` marker, in document order
+#[must_use]
+pub fn block_ids(blocks: &[Block]) -> Vec<String> {
+    let mut anchors = Anchors::default();
+    anchors.walk(blocks);
+    anchors.blocks
+}
+
+#[derive(Default)]
+struct Anchors {
+    headings: Vec<String>,
+    blocks: Vec<String>,
+}
+
+impl Anchors {
+    fn walk(&mut self, blocks: &[Block]) {
+        for block in blocks {
+            match block {
+                Block::Leaf {
+                    leaf: LeafBlock::Heading { content, .. },
+                } => {
+                    let text: String = content
+                        .iter()
+                        .filter(|inline| !matches!(inline, Inline::Tag { .. }))
+                        .map(|inline| plain_text(std::slice::from_ref(inline)))
+                        .collect();
+                    self.headings.push(slugify(&text));
+                    self.inlines(content);
+                }
+                Block::Leaf {
+                    leaf: LeafBlock::Paragraph { content },
+                } => self.inlines(content),
+                Block::Leaf { .. } => {}
+                Block::Container { container } => match container {
+                    ContainerBlock::Quote { blocks }
+                    | ContainerBlock::Div {
+                        children: blocks, ..
+                    } => self.walk(blocks),
+                    ContainerBlock::List { items, .. } => {
+                        for item in items {
+                            self.walk(&item.blocks);
+                        }
+                    }
+                    ContainerBlock::Table { .. } => {}
+                },
+                Block::DefinitionList { items } => {
+                    for item in items {
+                        self.walk(&item.definition);
+                    }
+                }
+                Block::FootnoteDefinition { content, .. } => self.walk(content),
+            }
+        }
+    }
+
+    fn inlines(&mut self, inlines: &[Inline]) {
+        self.blocks
+            .extend(inlines.iter().filter_map(|inline| match inline {
+                Inline::BlockId { id } => Some(id.clone()),
+                _ => None,
+            }));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Inline {
+        Inline::Text {
+            text: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn slugs_drop_punctuation_and_join_words() {
+        assert_eq!(slugify("Q3: Goals & Risks"), "q3-goals-risks");
+        assert_eq!(slugify("  already-a_slug  "), "already-a-slug");
+        assert_eq!(slugify("Überblick"), "überblick");
+        assert_eq!(slugify("?!"), "");
+    }
+
+    #[test]
+    fn collects_headings_and_block_ids_at_any_depth() {
+        let blocks = vec![
+            Block::heading(1, vec![text("Plan")]),
+            Block::heading(2, vec![text("TODO Ship it")]),
+            Block::paragraph(vec![
+                text("Decided"),
+                Inline::BlockId {
+                    id: "decision".into(),
+                },
+            ]),
+            Block::quote(vec![Block::paragraph(vec![Inline::BlockId {
+                id: "quoted".into(),
+            }])]),
+        ];
+
+        assert_eq!(heading_slugs(&blocks), vec!["plan", "todo-ship-it"]);
+        assert_eq!(block_ids(&blocks), vec!["decision", "quoted"]);
+    }
+}
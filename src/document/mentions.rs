@@ -0,0 +1,252 @@
+use crate::{
+    document::{TextSpan, inlines_at_mut, visit_inlines},
+    error::{DocumentError, DocumentResult},
+    models::{Block, Inline},
+};
+
+/// A name found in the plain text of a note
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct TextMatch {
+    /// Inline container holding the match, see [`crate::document::TextRun`]
+    pub path: Vec<u64>,
+    /// Index of the `Inline::Text` in the container, followed by indices into
+    /// the formatting (bold, italic, ...) it is nested in
+    pub inline: Vec<u64>,
+    /// Character span of the match in that text
+    pub span: TextSpan,
+    /// The matched text as written
+    pub text: String,
+}
+
+/// Find whole-word, case-insensitive occurrences of any of `names`
+///
+/// Only plain text is searched; link text, code and math are skipped. Where
+/// names overlap the longest one at the earliest position wins.
+#[must_use]
+pub fn find_names(blocks: &[Block], names: &[&str]) -> Vec<TextMatch> {
+    let names: Vec<Vec<char>> = names
+        .iter()
+        .map(|name| name.trim().chars().collect::<Vec<_>>())
+        .filter(|name| !name.is_empty())
+        .collect();
+
+    let mut matches = Vec::new();
+    if names.is_empty() {
+        return matches;
+    }
+    visit_inlines(blocks, |path, inlines| {
+        let mut inline = Vec::new();
+        find_in_inlines(inlines, &names, path, &mut inline, &mut matches);
+    });
+    matches
+}
+
+/// Replace a match with a link to `target`
+///
+/// # Errors
+///
+/// Returns [`DocumentError::InvalidIndex`] if the match no longer points at
+/// text, or an error if the text there is no longer the matched text.
+pub fn link_match(blocks: &mut [Block], found: &TextMatch, target: &str) -> DocumentResult<()> {
+    let mut inlines = inlines_at_mut(blocks, &found.path).ok_or(DocumentError::InvalidIndex)?;
+    let (&last, nesting) = found
+        .inline
+        .split_last()
+        .ok_or(DocumentError::InvalidIndex)?;
+    for &index in nesting {
+        inlines = match inlines.get_mut(index_of(index)?) {
+            Some(
+                Inline::Bold { content }
+                | Inline::Italic { content }
+                | Inline::Strikethrough { content }
+                | Inline::Superscript { content }
+                | Inline::Subscript { content },
+            ) => content,
+            _ => return Err(DocumentError::InvalidIndex),
+        };
+    }
+
+    let index = index_of(last)?;
+    let Some(Inline::Text { text }) = inlines.get(index) else {
+        return Err(DocumentError::InvalidIndex);
+    };
+    let chars: Vec<char> = text.chars().collect();
+    let (start, end) = (index_of(found.span.start)?, index_of(found.span.end)?);
+    if start > end
+        || end > chars.len()
+        || chars[start..end].iter().collect::<String>() != found.text
+    {
+        return Err(DocumentError::other(format!(
+            "\"{}\" is no longer at the given position",
+            found.text
+        )));
+    }
+
+    let mut replacement = Vec::new();
+    if start > 0 {
+        replacement.push(Inline::Text {
+            text: chars[..start].iter().collect(),
+        });
+    }
+    replacement.push(Inline::Link {
+        text: vec![Inline::Text {
+            text: found.text.clone(),
+        }],
+        target: target.to_string(),
+    });
+    if end < chars.len() {
+        replacement.push(Inline::Text {
+            text: chars[end..].iter().collect(),
+        });
+    }
+    inlines.splice(index..=index, replacement);
+    Ok(())
+}
+
+fn index_of(value: u64) -> DocumentResult<usize> {
+    usize::try_from(value).map_err(|_| DocumentError::InvalidIndex)
+}
+
+fn find_in_inlines(
+    inlines: &[Inline],
+    names: &[Vec<char>],
+    path: &[u64],
+    inline: &mut Vec<u64>,
+    matches: &mut Vec<TextMatch>,
+) {
+    for (i, item) in inlines.iter().enumerate() {
+        inline.push(i as u64);
+        match item {
+            Inline::Text { text } => {
+                for (start, end) in find_in_text(text, names) {
+                    matches.push(TextMatch {
+                        path: path.to_vec(),
+                        inline: inline.clone(),
+                        span: TextSpan {
+                            start: start as u64,
+                            end: end as u64,
+                        },
+                        text: text.chars().skip(start).take(end - start).collect(),
+                    });
+                }
+            }
+            Inline::Bold { content }
+            | Inline::Italic { content }
+            | Inline::Strikethrough { content }
+            | Inline::Superscript { content }
+            | Inline::Subscript { content } => {
+                find_in_inlines(content, names, path, inline, matches);
+            }
+            _ => {}
+        }
+        inline.pop();
+    }
+}
+
+/// Character spans of whole-word, case-insensitive matches in `text`
+fn find_in_text(text: &str, names: &[Vec<char>]) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = text.chars().collect();
+    let is_word = |i: usize| chars.get(i).is_some_and(|c| c.is_alphanumeric());
+
+    let mut spans = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let longest = (start == 0 || !is_word(start - 1))
+            .then(|| {
+                names
+                    .iter()
+                    .filter(|name| {
+                        let end = start + name.len();
+                        end <= chars.len()
+                            && !is_word(end)
+                            && chars[start..end]
+                                .iter()
+                                .zip(name.iter())
+                                .all(|(a, b)| a.to_lowercase().eq(b.to_lowercase()))
+                    })
+                    .map(Vec::len)
+                    .max()
+            })
+            .flatten();
+
+        match longest {
+            Some(len) => {
+                spans.push((start, start + len));
+                start += len;
+            }
+            None => start += 1,
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> Inline {
+        Inline::Text {
+            text: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn finds_whole_words_outside_links() {
+        let blocks = vec![
+            Block::paragraph(vec![
+                text("The roadmap, the Roadmaps and the product roadmap. "),
+                Inline::Link {
+                    text: vec![text("roadmap")],
+                    target: "roadmap".into(),
+                },
+                Inline::Bold {
+                    content: vec![text("ROADMAP")],
+                },
+            ]),
+            Block::paragraph(vec![Inline::Code {
+                code: "roadmap".into(),
+            }]),
+        ];
+
+        let found: Vec<(Vec<u64>, u64, u64, String)> =
+            find_names(&blocks, &["Roadmap", "product roadmap"])
+                .into_iter()
+                .map(|m| (m.inline, m.span.start, m.span.end, m.text))
+                .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (vec![0], 4, 11, "roadmap".to_string()),
+                (vec![0], 34, 49, "product roadmap".to_string()),
+                (vec![2, 0], 0, 7, "ROADMAP".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn linking_a_match_splits_the_text() {
+        let mut blocks = vec![Block::quote(vec![Block::paragraph(vec![Inline::Italic {
+            content: vec![text("see the Plan today")],
+        }])])];
+        let found = find_names(&blocks, &["plan"]).remove(0);
+        assert_eq!(found.path, vec![0, 0]);
+
+        link_match(&mut blocks, &found, "plan").unwrap();
+
+        assert_eq!(
+            blocks,
+            vec![Block::quote(vec![Block::paragraph(vec![Inline::Italic {
+                content: vec![
+                    text("see the "),
+                    Inline::Link {
+                        text: vec![text("Plan")],
+                        target: "plan".into(),
+                    },
+                    text(" today"),
+                ],
+            }])])]
+        );
+        assert!(link_match(&mut blocks, &found, "plan").is_err());
+    }
+}
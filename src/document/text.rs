@@ -0,0 +1,301 @@
+use crate::models::{Block, ContainerBlock, Inline, LeafBlock};
+
+/// The plain text of one inline container inside a note
+///
+/// `path` locates the container in the block tree: the first element is the
+/// index into `Note::blocks`, and each further element descends into a
+/// container (quote/div/footnote child, list item then child, table row then
+/// column with the header row at 0, definition item then 0 for the term or
+/// `1 + n` for the n-th definition block).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextRun {
+    pub path: Vec<u64>,
+    pub text: String,
+}
+
+/// Collect the [`plain_text`] of every block, in document order
+#[must_use]
+pub fn text_runs(blocks: &[Block]) -> Vec<TextRun> {
+    let mut runs = Vec::new();
+    visit_inlines(blocks, |path, inlines| {
+        let text = plain_text(inlines);
+        if !text.is_empty() {
+            runs.push(TextRun {
+                path: path.to_vec(),
+                text,
+            });
+        }
+    });
+    runs
+}
+
+/// Call `visit` with the path (see [`TextRun::path`]) and content of every
+/// inline container, in document order
+pub fn visit_inlines(blocks: &[Block], mut visit: impl FnMut(&[u64], &[Inline])) {
+    let mut path = Vec::new();
+    walk_blocks(blocks, &mut path, &mut visit);
+}
+
+/// Call `visit` with the text and target of every link, including links
+/// nested in formatting
+pub fn visit_links_mut(blocks: &mut [Block], mut visit: impl FnMut(&mut Vec<Inline>, &mut String)) {
+    fn walk(inlines: &mut [Inline], visit: &mut dyn FnMut(&mut Vec<Inline>, &mut String)) {
+        for inline in inlines {
+            match inline {
+                Inline::Link { text, target } => visit(text, target),
+                Inline::Bold { content }
+                | Inline::Italic { content }
+                | Inline::Strikethrough { content }
+                | Inline::Superscript { content }
+                | Inline::Subscript { content } => walk(content, visit),
+                _ => {}
+            }
+        }
+    }
+
+    let mut paths = Vec::new();
+    visit_inlines(blocks, |path, _| paths.push(path.to_vec()));
+    for path in paths {
+        if let Some(inlines) = inlines_at_mut(blocks, &path) {
+            walk(inlines, &mut visit);
+        }
+    }
+}
+
+/// Call `visit` with the name of every `Inline::Tag`, including tags inside
+/// formatting
+pub fn visit_tags_mut(blocks: &mut [Block], mut visit: impl FnMut(&mut String)) {
+    fn walk(inlines: &mut [Inline], visit: &mut dyn FnMut(&mut String)) {
+        for inline in inlines {
+            match inline {
+                Inline::Tag { name } => visit(name),
+                Inline::Bold { content }
+                | Inline::Italic { content }
+                | Inline::Strikethrough { content }
+                | Inline::Superscript { content }
+                | Inline::Subscript { content } => walk(content, visit),
+                _ => {}
+            }
+        }
+    }
+
+    let mut paths = Vec::new();
+    visit_inlines(blocks, |path, _| paths.push(path.to_vec()));
+    for path in paths {
+        if let Some(inlines) = inlines_at_mut(blocks, &path) {
+            walk(inlines, &mut visit);
+        }
+    }
+}
+
+/// The inline container at `path` (see [`TextRun::path`]), if there is one
+pub fn inlines_at_mut<'a>(blocks: &'a mut [Block], path: &[u64]) -> Option<&'a mut Vec<Inline>> {
+    let (&first, rest) = path.split_first()?;
+    block_inlines_mut(blocks.get_mut(usize::try_from(first).ok()?)?, rest)
+}
+
+fn block_inlines_mut<'a>(block: &'a mut Block, rest: &[u64]) -> Option<&'a mut Vec<Inline>> {
+    match block {
+        Block::Leaf {
+            leaf: LeafBlock::Paragraph { content } | LeafBlock::Heading { content, .. },
+        } if rest.is_empty() => Some(content),
+        Block::Leaf { .. } => None,
+        Block::Container { container } => match container {
+            ContainerBlock::Quote { blocks }
+            | ContainerBlock::Div {
+                children: blocks, ..
+            } => inlines_at_mut(blocks, rest),
+            ContainerBlock::List { items, .. } => {
+                let (&item, rest) = rest.split_first()?;
+                inlines_at_mut(
+                    &mut items.get_mut(usize::try_from(item).ok()?)?.blocks,
+                    rest,
+                )
+            }
+            ContainerBlock::Table { headers, rows, .. } => {
+                let &[row, column] = rest else {
+                    return None;
+                };
+                let row = match row.checked_sub(1) {
+                    None => headers,
+                    Some(row) => rows.get_mut(usize::try_from(row).ok()?)?,
+                };
+                row.get_mut(usize::try_from(column).ok()?)
+            }
+        },
+        Block::DefinitionList { items } => {
+            let (&item, rest) = rest.split_first()?;
+            let item = items.get_mut(usize::try_from(item).ok()?)?;
+            match rest.split_first()? {
+                (0, []) => Some(&mut item.term),
+                (0, _) => None,
+                (&definition, rest) => {
+                    let index = usize::try_from(definition - 1).ok()?;
+                    block_inlines_mut(item.definition.get_mut(index)?, rest)
+                }
+            }
+        }
+        Block::FootnoteDefinition { content, .. } => inlines_at_mut(content, rest),
+    }
+}
+
+/// Concatenate the `Inline::Text` content of the given inlines, with tags
+/// written as `#tag`
+#[must_use]
+pub fn plain_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    push_text(inlines, &mut text);
+    text
+}
+
+fn push_text(inlines: &[Inline], out: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Text { text } => out.push_str(text),
+            Inline::Tag { name } => {
+                out.push('#');
+                out.push_str(name);
+            }
+            Inline::Bold { content }
+            | Inline::Italic { content }
+            | Inline::Strikethrough { content }
+            | Inline::Superscript { content }
+            | Inline::Subscript { content }
+            | Inline::Link { text: content, .. } => push_text(content, out),
+            _ => {}
+        }
+    }
+}
+
+type Visitor<'a> = dyn FnMut(&[u64], &[Inline]) + 'a;
+
+fn walk_blocks(blocks: &[Block], path: &mut Vec<u64>, visit: &mut Visitor) {
+    for (i, block) in blocks.iter().enumerate() {
+        path.push(i as u64);
+        walk_block(block, path, visit);
+        path.pop();
+    }
+}
+
+fn walk_block(block: &Block, path: &mut Vec<u64>, visit: &mut Visitor) {
+    match block {
+        Block::Leaf {
+            leaf: LeafBlock::Paragraph { content } | LeafBlock::Heading { content, .. },
+        } => visit(path, content),
+        Block::Leaf { .. } => {}
+        Block::Container { container } => match container {
+            ContainerBlock::Quote { blocks }
+            | ContainerBlock::Div {
+                children: blocks, ..
+            } => walk_blocks(blocks, path, visit),
+            ContainerBlock::List { items, .. } => {
+                for (i, item) in items.iter().enumerate() {
+                    path.push(i as u64);
+                    walk_blocks(&item.blocks, path, visit);
+                    path.pop();
+                }
+            }
+            ContainerBlock::Table { headers, rows, .. } => {
+                for (r, row) in std::iter::once(headers).chain(rows).enumerate() {
+                    path.push(r as u64);
+                    for (c, cell) in row.iter().enumerate() {
+                        path.push(c as u64);
+                        visit(path, cell);
+                        path.pop();
+                    }
+                    path.pop();
+                }
+            }
+        },
+        Block::DefinitionList { items } => {
+            for (i, item) in items.iter().enumerate() {
+                path.push(i as u64);
+                path.push(0);
+                visit(path, &item.term);
+                path.pop();
+                for (d, definition) in item.definition.iter().enumerate() {
+                    path.push(d as u64 + 1);
+                    walk_block(definition, path, visit);
+                    path.pop();
+                }
+                path.pop();
+            }
+        }
+        Block::FootnoteDefinition { content, .. } => walk_blocks(content, path, visit),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{DefinitionItem, ListStyle};
+
+    fn text(value: &str) -> Inline {
+        Inline::Text {
+            text: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn runs_follow_block_paths() {
+        let blocks = vec![
+            Block::heading(1, vec![text("Title")]),
+            Block::list(
+                ListStyle::Unordered { bullet: b'-' },
+                vec![
+                    vec![Block::paragraph(vec![text("first")])],
+                    vec![Block::quote(vec![Block::paragraph(vec![
+                        text("deep "),
+                        Inline::Bold {
+                            content: vec![text("bold")],
+                        },
+                    ])])],
+                ],
+            ),
+            Block::table(
+                vec![vec![text("h")]],
+                vec![vec![vec![text("cell")]]],
+                None,
+                None,
+            ),
+            Block::definition_list(vec![DefinitionItem {
+                term: vec![text("term")],
+                definition: vec![Block::paragraph(vec![text("meaning")])],
+            }]),
+        ];
+
+        let runs = text_runs(&blocks);
+        let summary: Vec<(Vec<u64>, &str)> = runs
+            .iter()
+            .map(|run| (run.path.clone(), run.text.as_str()))
+            .collect();
+
+        assert_eq!(
+            summary,
+            vec![
+                (vec![0], "Title"),
+                (vec![1, 0, 0], "first"),
+                (vec![1, 1, 0, 0], "deep bold"),
+                (vec![2, 0, 0], "h"),
+                (vec![2, 1, 0], "cell"),
+                (vec![3, 0, 0], "term"),
+                (vec![3, 0, 1], "meaning"),
+            ]
+        );
+    }
+
+    #[test]
+    fn code_and_math_are_not_text() {
+        let blocks = vec![
+            Block::code_block(None, "let x = 1;".into()),
+            Block::paragraph(vec![
+                Inline::Code { code: "x".into() },
+                Inline::Math {
+                    content: "y".into(),
+                },
+            ]),
+        ];
+
+        assert_eq!(text_runs(&blocks), Vec::new());
+    }
+}
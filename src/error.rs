@@ -108,9 +108,9 @@ pub type DocumentResult<T> = Result<T, DocumentError>;
 /// Serialization-related errors
 #[derive(Debug, Error, uniffi::Error)]
 pub enum SerializationError {
-    /// Invalid UTF-8 encoding
-    #[error("Invalid UTF-8 encoding")]
-    InvalidUtf8,
+    /// Invalid UTF-8 encoding, starting at the given byte offset
+    #[error("Invalid UTF-8 encoding at byte {offset}")]
+    InvalidUtf8 { offset: u64 },
 
     /// Unsupported format
     #[error("Unsupported format: {0}")]
@@ -126,6 +126,12 @@ pub enum SerializationError {
 }
 
 impl SerializationError {
+    /// Create an invalid UTF-8 error at the given byte offset
+    #[must_use]
+    pub fn invalid_utf8(offset: u64) -> Self {
+        Self::InvalidUtf8 { offset }
+    }
+
     /// Create an unsupported format error
     pub fn unsupported_format(format: impl Into<String>) -> Self {
         Self::UnsupportedFormat(format.into())
@@ -142,6 +148,12 @@ impl SerializationError {
     }
 }
 
+impl From<core::str::Utf8Error> for SerializationError {
+    fn from(err: core::str::Utf8Error) -> Self {
+        Self::invalid_utf8(err.valid_up_to() as u64)
+    }
+}
+
 /// Result type for serialization operations
 pub type SerializationResult<T> = Result<T, SerializationError>;
 
@@ -164,6 +176,10 @@ pub enum RepositoryError {
     #[error("Invalid note ID: {0}")]
     InvalidId(String),
 
+    /// A stored note could not be read or written in its format
+    #[error(transparent)]
+    Serialization(#[from] SerializationError),
+
     /// General repository error
     #[error("Repository error: {0}")]
     Other(String),
@@ -272,8 +288,9 @@ mod tests {
 
     #[test]
     fn test_serialization_error_utf8() {
-        let err = SerializationError::InvalidUtf8;
+        let err = SerializationError::invalid_utf8(7);
         assert!(err.to_string().contains("UTF-8"));
+        assert!(err.to_string().contains("byte 7"));
     }
 
     #[test]
@@ -1,17 +1,110 @@
 use std::fmt::Debug;
 
-use crate::models::{Attachment, LinkTarget, Note};
+use crate::{
+    error::SerializationResult,
+    models::{
+        Attachment, Block, BlockKind, ContainerBlock, Inline, InlineKind, LeafBlock, LinkTarget,
+        Note,
+    },
+};
 
+pub mod frontmatter;
 pub mod markdown;
 pub mod org;
+#[cfg(test)]
+mod round_trip;
+
+/// The model nodes a format keeps through a round trip, see
+/// [`NoteSerialization::capabilities`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, uniffi::Record)]
+pub struct Capabilities {
+    pub blocks: Vec<BlockKind>,
+    pub inlines: Vec<InlineKind>,
+    /// Whether `Note::properties` are kept
+    pub properties: bool,
+}
+
+impl Capabilities {
+    /// Whether every block and inline in the note, however deeply nested, is
+    /// of a kind this format keeps
+    #[must_use]
+    pub fn supports(&self, note: &Note) -> bool {
+        (self.properties || note.properties.is_empty()) && self.supports_blocks(&note.blocks)
+    }
+
+    fn supports_blocks(&self, blocks: &[Block]) -> bool {
+        blocks.iter().all(|block| {
+            self.blocks.contains(&block.kind())
+                && match block {
+                    Block::Leaf {
+                        leaf: LeafBlock::Paragraph { content } | LeafBlock::Heading { content, .. },
+                    } => self.supports_inlines(content),
+                    Block::Leaf { .. } => true,
+                    Block::Container { container } => match container {
+                        ContainerBlock::Quote { blocks }
+                        | ContainerBlock::Div {
+                            children: blocks, ..
+                        } => self.supports_blocks(blocks),
+                        ContainerBlock::List { items, .. } => {
+                            items.iter().all(|item| self.supports_blocks(&item.blocks))
+                        }
+                        ContainerBlock::Table {
+                            headers,
+                            rows,
+                            caption,
+                            ..
+                        } => headers
+                            .iter()
+                            .chain(rows.iter().flatten())
+                            .chain(caption)
+                            .all(|cell| self.supports_inlines(cell)),
+                    },
+                    Block::DefinitionList { items } => items.iter().all(|item| {
+                        self.supports_inlines(&item.term) && self.supports_blocks(&item.definition)
+                    }),
+                    Block::FootnoteDefinition { content, .. } => self.supports_blocks(content),
+                }
+        })
+    }
+
+    fn supports_inlines(&self, inlines: &[Inline]) -> bool {
+        inlines.iter().all(|inline| {
+            self.inlines.contains(&inline.kind())
+                && match inline {
+                    Inline::Bold { content }
+                    | Inline::Italic { content }
+                    | Inline::Strikethrough { content }
+                    | Inline::Superscript { content }
+                    | Inline::Subscript { content }
+                    | Inline::Link { text: content, .. } => self.supports_inlines(content),
+                    _ => true,
+                }
+        })
+    }
+}
 
 #[uniffi::trait_interface]
 pub trait NoteSerialization: Send + Sync + Debug {
     /// Deserialize bytes into a Note
-    fn deserialize(&self, data: &[u8], id_hint: Option<&str>) -> Note;
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the bytes are not valid for this format, such as
+    /// [`SerializationError::InvalidUtf8`](crate::error::SerializationError::InvalidUtf8)
+    /// for text formats
+    fn deserialize(&self, data: &[u8], id_hint: Option<&str>) -> SerializationResult<Note>;
 
     /// Serialize a Note into bytes
     fn serialize(&self, note: &Note) -> Vec<u8>;
+
+    /// The blocks and inlines that survive [`Self::serialize`] followed by
+    /// [`Self::deserialize`] unchanged
+    ///
+    /// Formats document their own caveats, such as content a kind only
+    /// round-trips with. Nothing is promised by default.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
 }
 
 #[uniffi::trait_interface]
@@ -26,3 +119,8 @@ pub trait NoteMetadata: Send + Sync + Debug {
         Vec::new()
     }
 }
+
+/// A format that can both round-trip notes and extract metadata from them
+pub trait NoteFormat: NoteSerialization + NoteMetadata {}
+
+impl<T: NoteSerialization + NoteMetadata + ?Sized> NoteFormat for T {}
@@ -0,0 +1,848 @@
+//! YAML front matter
+//!
+//! Covers the block-style subset of YAML that note apps write: nested
+//! mappings, `- item` sequences, flow `[a, b]` / `{k: v}` collections,
+//! quoted strings, `|` / `>` block scalars and comments. Plain scalars are
+//! typed as null, booleans, numbers, ISO dates or text. Anchors, tags and
+//! multi-document streams are not supported.
+
+use std::{fmt::Write, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::{
+    error::{SerializationError, SerializationResult},
+    models::{Properties, PropertyValue},
+};
+
+static NUMBER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[-+]?(?:[0-9]+(?:\.[0-9]*)?|\.[0-9]+)(?:[eE][-+]?[0-9]+)?$").unwrap()
+});
+
+static DATE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^[0-9]{4}-[0-9]{2}-[0-9]{2}(?:[Tt ][0-9]{1,2}:[0-9]{2}(?::[0-9]{2}(?:\.[0-9]+)?)?(?:\s*(?:Z|[-+][0-9]{1,2}(?::?[0-9]{2})?))?)?$",
+    )
+    .unwrap()
+});
+
+/// Split `---` delimited front matter from the rest of a document
+///
+/// Returns the YAML between the delimiters and the body after the closing
+/// `---` (or `...`) line, or `None` if the document has no front matter.
+#[must_use]
+pub fn split(input: &str) -> Option<(&str, &str)> {
+    let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+    let (first, mut rest) = input.split_once('\n')?;
+    if first.trim_end() != "---" {
+        return None;
+    }
+
+    let yaml = rest;
+    let mut offset = 0;
+    loop {
+        let (line, next) = rest.split_once('\n').unwrap_or((rest, ""));
+        if matches!(line.trim_end(), "---" | "...") {
+            return Some((&yaml[..offset], next));
+        }
+        if next.is_empty() {
+            return None;
+        }
+        offset += line.len() + 1;
+        rest = next;
+    }
+}
+
+/// Parse front matter YAML into properties
+///
+/// # Errors
+///
+/// Returns [`SerializationError::DeserializationFailed`] naming the front
+/// matter line that could not be parsed
+pub fn parse(yaml: &str) -> SerializationResult<Properties> {
+    let mut parser = Parser {
+        lines: yaml
+            .lines()
+            .enumerate()
+            .map(|(index, raw)| Line::new(index + 1, raw))
+            .collect(),
+        position: 0,
+    };
+
+    let Some(first) = parser.peek() else {
+        return Ok(Properties::new());
+    };
+    let properties = parser.parse_mapping(first.indent)?;
+    match parser.peek() {
+        None => Ok(properties),
+        Some(line) => Err(error(line.number, "unexpected indentation")),
+    }
+}
+
+/// Parse a single-line value: a scalar or a flow collection
+///
+/// # Errors
+///
+/// Returns an error for unterminated quotes or unbalanced brackets
+pub fn parse_value(text: &str) -> SerializationResult<PropertyValue> {
+    parse_inline(text.trim(), 1)
+}
+
+/// Write properties as block-style YAML, without the `---` delimiters
+#[must_use]
+pub fn to_yaml(properties: &Properties) -> String {
+    let mut out = String::new();
+    write_mapping(properties, 0, &mut out);
+    out
+}
+
+/// A value on a single line, using flow style for collections
+#[must_use]
+pub fn inline_value(value: &PropertyValue) -> String {
+    write_inline(value, false)
+}
+
+fn error(line: usize, message: &str) -> SerializationError {
+    SerializationError::deserialization_failed(format!("front matter line {line}: {message}"))
+}
+
+#[derive(Debug, Clone)]
+struct Line<'a> {
+    number: usize,
+    indent: usize,
+    /// Content after the indentation, without trailing whitespace
+    text: &'a str,
+    raw: &'a str,
+}
+
+impl<'a> Line<'a> {
+    fn new(number: usize, raw: &'a str) -> Self {
+        let raw = raw.trim_end_matches('\r');
+        let text = raw.trim();
+        Self {
+            number,
+            indent: raw.len() - raw.trim_start_matches(' ').len(),
+            text,
+            raw,
+        }
+    }
+
+    fn is_blank(&self) -> bool {
+        self.text.is_empty() || self.text.starts_with('#')
+    }
+
+    fn is_sequence_item(&self) -> bool {
+        self.text == "-" || self.text.starts_with("- ")
+    }
+}
+
+struct Parser<'a> {
+    lines: Vec<Line<'a>>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&mut self) -> Option<Line<'a>> {
+        while self.lines.get(self.position).is_some_and(Line::is_blank) {
+            self.position += 1;
+        }
+        self.lines.get(self.position).cloned()
+    }
+
+    fn parse_block(&mut self, indent: usize) -> SerializationResult<PropertyValue> {
+        match self.peek() {
+            Some(line) if line.is_sequence_item() => self.parse_sequence(indent),
+            _ => Ok(PropertyValue::Map {
+                entries: self.parse_mapping(indent)?,
+            }),
+        }
+    }
+
+    fn parse_mapping(&mut self, indent: usize) -> SerializationResult<Properties> {
+        let mut properties = Properties::new();
+        while let Some(line) = self.peek() {
+            if line.indent < indent {
+                break;
+            }
+            if line.indent > indent {
+                return Err(error(line.number, "unexpected indentation"));
+            }
+            let Some((key, rest)) = split_key(line.text, line.number)? else {
+                return Err(error(line.number, "expected `key: value`"));
+            };
+
+            self.position += 1;
+            let value = self.parse_entry_value(indent, rest, line.number)?;
+            properties.insert(key, value);
+        }
+        Ok(properties)
+    }
+
+    /// The value of a `key:` entry, which may continue on following lines
+    fn parse_entry_value(
+        &mut self,
+        indent: usize,
+        rest: &str,
+        number: usize,
+    ) -> SerializationResult<PropertyValue> {
+        let rest = strip_comment(rest).trim();
+
+        if rest.is_empty() {
+            return match self.peek() {
+                Some(next) if next.indent > indent => self.parse_block(next.indent),
+                Some(next) if next.indent == indent && next.is_sequence_item() => {
+                    self.parse_sequence(indent)
+                }
+                _ => Ok(PropertyValue::Null),
+            };
+        }
+
+        if let Some(header) = rest.strip_prefix(['|', '>'])
+            && header.chars().all(|c| matches!(c, '-' | '+' | '0'..='9'))
+        {
+            return Ok(PropertyValue::text(self.block_scalar(
+                indent,
+                rest.starts_with('>'),
+                header,
+            )));
+        }
+
+        // Flow collections may wrap onto following lines
+        let mut text = rest.to_string();
+        while flow_depth(&text) > 0 {
+            let Some(next) = self.lines.get(self.position) else {
+                break;
+            };
+            text.push(' ');
+            text.push_str(strip_comment(next.text).trim());
+            self.position += 1;
+        }
+
+        parse_inline(&text, number)
+    }
+
+    fn parse_sequence(&mut self, indent: usize) -> SerializationResult<PropertyValue> {
+        let mut items = Vec::new();
+        while let Some(line) = self.peek() {
+            if line.indent < indent || (line.indent == indent && !line.is_sequence_item()) {
+                break;
+            }
+            if line.indent > indent {
+                return Err(error(line.number, "unexpected indentation"));
+            }
+
+            let content = line.text[1..].trim_start();
+            let item = if content.is_empty() {
+                self.position += 1;
+                match self.peek() {
+                    Some(next) if next.indent > indent => self.parse_block(next.indent)?,
+                    _ => PropertyValue::Null,
+                }
+            } else if content == "-"
+                || content.starts_with("- ")
+                || split_key(content, line.number)?.is_some()
+            {
+                // A nested sequence or mapping starting on the item's line:
+                // reparse the rest of the line at the column it starts in
+                let column = line.indent + (line.text.len() - content.len());
+                self.lines[self.position] = Line {
+                    indent: column,
+                    text: content,
+                    ..line
+                };
+                self.parse_block(column)?
+            } else {
+                self.position += 1;
+                parse_inline(strip_comment(content).trim(), line.number)?
+            };
+            items.push(item);
+        }
+        Ok(PropertyValue::List { items })
+    }
+
+    fn block_scalar(&mut self, indent: usize, folded: bool, header: &str) -> String {
+        let mut lines = Vec::new();
+        while let Some(line) = self.lines.get(self.position) {
+            if !line.text.is_empty() && line.indent <= indent {
+                break;
+            }
+            lines.push(line.raw);
+            self.position += 1;
+        }
+
+        let content_indent = lines
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start_matches(' ').len())
+            .min()
+            .unwrap_or(0);
+        let lines: Vec<&str> = lines
+            .iter()
+            .map(|line| line.get(content_indent..).unwrap_or(""))
+            .collect();
+
+        let mut text = if folded {
+            let mut text = String::new();
+            for (index, line) in lines.iter().enumerate() {
+                if index > 0 {
+                    let previous_blank = lines[index - 1].is_empty();
+                    text.push(if line.is_empty() || previous_blank {
+                        '\n'
+                    } else {
+                        ' '
+                    });
+                }
+                text.push_str(line);
+            }
+            text
+        } else {
+            lines.join("\n")
+        };
+
+        let body_length = text.trim_end_matches('\n').len();
+        if header.contains('+') {
+            text.push('\n');
+        } else {
+            text.truncate(body_length);
+            if !header.contains('-') && body_length > 0 {
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+/// Split `key: rest` where the colon is followed by whitespace or the end
+fn split_key(text: &str, number: usize) -> SerializationResult<Option<(String, &str)>> {
+    if text.starts_with(['"', '\'']) {
+        let (key, length) = parse_quoted(text, number)?;
+        let after = text[length..].trim_start();
+        return Ok(after
+            .strip_prefix(':')
+            .filter(|rest| rest.is_empty() || rest.starts_with([' ', '\t']))
+            .map(|rest| (key, rest)));
+    }
+    if text.starts_with(['[', '{', '#']) || text == "-" || text.starts_with("- ") {
+        return Ok(None);
+    }
+
+    let mut previous = ' ';
+    for (index, c) in text.char_indices() {
+        if c == '#' && previous.is_whitespace() {
+            return Ok(None);
+        }
+        if c == ':' {
+            let rest = &text[index + 1..];
+            if rest.is_empty() || rest.starts_with([' ', '\t']) {
+                let key = text[..index].trim_end();
+                return Ok((!key.is_empty()).then(|| (key.to_string(), rest)));
+            }
+        }
+        previous = c;
+    }
+    Ok(None)
+}
+
+/// Drop a trailing ` # comment`, ignoring `#` inside quotes
+fn strip_comment(text: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    for (index, c) in text.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') if previous.is_whitespace() => return &text[..index],
+            _ => {}
+        }
+        previous = c;
+    }
+    text
+}
+
+/// Unclosed `[` and `{` outside quotes
+fn flow_depth(text: &str) -> i32 {
+    if !text.starts_with(['[', '{']) {
+        return 0;
+    }
+    let mut depth = 0;
+    let mut quote = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+fn parse_inline(text: &str, number: usize) -> SerializationResult<PropertyValue> {
+    if text.starts_with(['[', '{', '"', '\'']) {
+        let mut flow = Flow {
+            chars: text.char_indices().collect(),
+            position: 0,
+            text,
+            number,
+        };
+        let value = flow.value()?;
+        flow.skip_whitespace();
+        if flow.position < flow.chars.len() {
+            return Err(error(number, "unexpected text after value"));
+        }
+        Ok(value)
+    } else {
+        Ok(plain_scalar(text))
+    }
+}
+
+/// Type a plain (unquoted) scalar
+fn plain_scalar(text: &str) -> PropertyValue {
+    match text {
+        "" | "~" | "null" | "Null" | "NULL" => PropertyValue::Null,
+        "true" | "True" | "TRUE" => PropertyValue::Bool { value: true },
+        "false" | "False" | "FALSE" => PropertyValue::Bool { value: false },
+        _ if NUMBER.is_match(text) => PropertyValue::Number {
+            value: text.to_string(),
+        },
+        _ if DATE.is_match(text) => PropertyValue::Date {
+            value: text.to_string(),
+        },
+        _ => PropertyValue::text(text),
+    }
+}
+
+/// Parse a quoted string at the start of `text`, returning it and the number
+/// of bytes consumed
+fn parse_quoted(text: &str, number: usize) -> SerializationResult<(String, usize)> {
+    let mut chars = text.char_indices();
+    let Some((_, quote)) = chars.next() else {
+        return Err(error(number, "expected a quoted string"));
+    };
+    let mut value = String::new();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\'' if quote == '\'' => {
+                if text[index + 1..].starts_with('\'') {
+                    chars.next();
+                    value.push('\'');
+                } else {
+                    return Ok((value, index + 1));
+                }
+            }
+            '"' if quote == '"' => return Ok((value, index + 1)),
+            '\\' if quote == '"' => {
+                let Some((_, escape)) = chars.next() else {
+                    break;
+                };
+                match escape {
+                    'n' => value.push('\n'),
+                    't' => value.push('\t'),
+                    'r' => value.push('\r'),
+                    '0' => value.push('\0'),
+                    'u' => {
+                        let hex: String = chars.by_ref().take(4).map(|(_, c)| c).collect();
+                        let decoded = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| error(number, "invalid \\u escape"))?;
+                        value.push(decoded);
+                    }
+                    other => value.push(other),
+                }
+            }
+            _ => value.push(c),
+        }
+    }
+    Err(error(number, "unterminated quoted string"))
+}
+
+/// Recursive-descent parser for flow collections and quoted scalars
+struct Flow<'a> {
+    chars: Vec<(usize, char)>,
+    position: usize,
+    text: &'a str,
+    number: usize,
+}
+
+impl Flow<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).map(|&(_, c)| c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.chars
+            .get(self.position)
+            .map_or(self.text.len(), |&(offset, _)| offset)
+    }
+
+    fn value(&mut self) -> SerializationResult<PropertyValue> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('[') => {
+                self.position += 1;
+                let mut items = Vec::new();
+                while !self.close(']')? {
+                    items.push(self.value()?);
+                    self.separator(']')?;
+                }
+                Ok(PropertyValue::List { items })
+            }
+            Some('{') => {
+                self.position += 1;
+                let mut entries = Properties::new();
+                while !self.close('}')? {
+                    let key = match self.value()? {
+                        PropertyValue::Null => String::new(),
+                        key => key.as_str().unwrap_or_default().to_string(),
+                    };
+                    self.skip_whitespace();
+                    let value = if self.peek() == Some(':') {
+                        self.position += 1;
+                        self.value()?
+                    } else {
+                        PropertyValue::Null
+                    };
+                    entries.insert(key, value);
+                    self.separator('}')?;
+                }
+                Ok(PropertyValue::Map { entries })
+            }
+            Some('"' | '\'') => {
+                let start = self.offset();
+                let (value, length) = parse_quoted(&self.text[start..], self.number)?;
+                let end = start + length;
+                while self.offset() < end {
+                    self.position += 1;
+                }
+                Ok(PropertyValue::text(value))
+            }
+            _ => {
+                let start = self.offset();
+                while let Some(c) = self.peek() {
+                    let next = self.chars.get(self.position + 1).map(|&(_, c)| c);
+                    if matches!(c, ',' | ']' | '}')
+                        || (c == ':' && next.is_none_or(|n| n.is_whitespace() || n == ','))
+                    {
+                        break;
+                    }
+                    self.position += 1;
+                }
+                Ok(plain_scalar(self.text[start..self.offset()].trim()))
+            }
+        }
+    }
+
+    /// Consume the closing bracket if it is next
+    fn close(&mut self, bracket: char) -> SerializationResult<bool> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c == bracket => {
+                self.position += 1;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Err(error(self.number, &format!("missing '{bracket}'"))),
+        }
+    }
+
+    fn separator(&mut self, bracket: char) -> SerializationResult<()> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(',') => {
+                self.position += 1;
+                Ok(())
+            }
+            Some(c) if c == bracket => Ok(()),
+            Some(c) => Err(error(
+                self.number,
+                &format!("expected ',' or '{bracket}', found '{c}'"),
+            )),
+            None => Err(error(self.number, &format!("missing '{bracket}'"))),
+        }
+    }
+}
+
+fn write_mapping(properties: &Properties, indent: usize, out: &mut String) {
+    for (key, value) in properties.iter() {
+        out.push_str(&" ".repeat(indent));
+        out.push_str(&write_scalar(key, false));
+        out.push(':');
+        match value {
+            PropertyValue::Null => out.push('\n'),
+            PropertyValue::List { items } if !items.is_empty() => {
+                out.push('\n');
+                write_sequence(items, indent + 2, out);
+            }
+            PropertyValue::Map { entries } if !entries.is_empty() => {
+                out.push('\n');
+                write_mapping(entries, indent + 2, out);
+            }
+            value => {
+                out.push(' ');
+                out.push_str(&write_inline(value, false));
+                out.push('\n');
+            }
+        }
+    }
+}
+
+fn write_sequence(items: &[PropertyValue], indent: usize, out: &mut String) {
+    for item in items {
+        let mut nested = String::new();
+        match item {
+            PropertyValue::List { items } if !items.is_empty() => {
+                write_sequence(items, indent + 2, &mut nested);
+            }
+            PropertyValue::Map { entries } if !entries.is_empty() => {
+                write_mapping(entries, indent + 2, &mut nested);
+            }
+            value => {
+                out.push_str(&" ".repeat(indent));
+                out.push_str("- ");
+                out.push_str(&write_inline(value, false));
+                out.push('\n');
+                continue;
+            }
+        }
+        // Start the nested block on the item's own line: `- key: value`
+        out.push_str(&" ".repeat(indent));
+        out.push_str("- ");
+        out.push_str(&nested[indent + 2..]);
+    }
+}
+
+fn write_inline(value: &PropertyValue, in_flow: bool) -> String {
+    match value {
+        PropertyValue::Null => "null".to_string(),
+        PropertyValue::Bool { value } => value.to_string(),
+        PropertyValue::Number { value } if NUMBER.is_match(value) => value.clone(),
+        PropertyValue::Date { value } if DATE.is_match(value) => value.clone(),
+        PropertyValue::Number { value }
+        | PropertyValue::Date { value }
+        | PropertyValue::Text { value } => write_scalar(value, in_flow),
+        PropertyValue::List { items } => {
+            let items: Vec<String> = items.iter().map(|item| write_inline(item, true)).collect();
+            format!("[{}]", items.join(", "))
+        }
+        PropertyValue::Map { entries } => {
+            let entries: Vec<String> = entries
+                .iter()
+                .map(|(key, value)| {
+                    format!("{}: {}", write_scalar(key, true), write_inline(value, true))
+                })
+                .collect();
+            format!("{{{}}}", entries.join(", "))
+        }
+    }
+}
+
+/// Text as a plain scalar when it would read back unchanged, else quoted
+fn write_scalar(text: &str, in_flow: bool) -> String {
+    let plain = !text.is_empty()
+        && text.trim() == text
+        && !text.starts_with([
+            '-', '?', ':', ',', '[', ']', '{', '}', '#', '&', '*', '!', '|', '>', '\'', '"', '%',
+            '@', '`',
+        ])
+        && !text.contains(": ")
+        && !text.contains(" #")
+        && !text.ends_with(':')
+        && !text.chars().any(char::is_control)
+        && !(in_flow && text.contains([',', '[', ']', '{', '}', ':']))
+        && plain_scalar(text) == PropertyValue::text(text);
+    if plain {
+        return text.to_string();
+    }
+
+    let mut quoted = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> PropertyValue {
+        PropertyValue::text(value)
+    }
+
+    fn list(items: &[&str]) -> PropertyValue {
+        PropertyValue::List {
+            items: items.iter().map(|item| text(item)).collect(),
+        }
+    }
+
+    #[test]
+    fn splits_delimited_front_matter() {
+        assert_eq!(
+            split("---\ntitle: A\n---\n# Body\n"),
+            Some(("title: A\n", "# Body\n"))
+        );
+        assert_eq!(split("---\n---\nbody"), Some(("", "body")));
+        assert_eq!(split("---\nno closing delimiter\n"), None);
+        assert_eq!(split("# No front matter\n---\n"), None);
+    }
+
+    #[test]
+    fn parses_typed_values() {
+        let yaml = "\
+title: \"Weekly: review\"
+count: 3
+ratio: -1.5e3
+draft: false
+created: 2024-05-01
+updated: 2024-05-01T09:30:00Z
+version: 1.2.3
+empty:
+tags: [work, 'q3 plan']  # trailing comment
+aliases:
+  - Review
+  - Weekly
+";
+        let properties = parse(yaml).unwrap();
+
+        let expected: Properties = [
+            ("title", text("Weekly: review")),
+            ("count", PropertyValue::Number { value: "3".into() }),
+            (
+                "ratio",
+                PropertyValue::Number {
+                    value: "-1.5e3".into(),
+                },
+            ),
+            ("draft", PropertyValue::Bool { value: false }),
+            (
+                "created",
+                PropertyValue::Date {
+                    value: "2024-05-01".into(),
+                },
+            ),
+            (
+                "updated",
+                PropertyValue::Date {
+                    value: "2024-05-01T09:30:00Z".into(),
+                },
+            ),
+            ("version", text("1.2.3")),
+            ("empty", PropertyValue::Null),
+            ("tags", list(&["work", "q3 plan"])),
+            ("aliases", list(&["Review", "Weekly"])),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+        assert_eq!(properties, expected);
+    }
+
+    #[test]
+    fn parses_nested_blocks() {
+        let yaml = "\
+project:
+  name: Sync
+  owners:
+  - ana
+  - raj
+tasks:
+  - title: Design
+    done: true
+  - - nested
+summary: |
+  Line one
+  Line two
+";
+        let properties = parse(yaml).unwrap();
+
+        let Some(PropertyValue::Map { entries: project }) = properties.get("project") else {
+            panic!("expected a map: {properties:?}");
+        };
+        assert_eq!(project.get("name"), Some(&text("Sync")));
+        assert_eq!(project.get("owners"), Some(&list(&["ana", "raj"])));
+
+        let Some(PropertyValue::List { items: tasks }) = properties.get("tasks") else {
+            panic!("expected a list: {properties:?}");
+        };
+        let PropertyValue::Map { entries: task } = &tasks[0] else {
+            panic!("expected a map: {tasks:?}");
+        };
+        assert_eq!(task.get("done"), Some(&PropertyValue::Bool { value: true }));
+        assert_eq!(tasks[1], list(&["nested"]));
+
+        assert_eq!(
+            properties.get("summary"),
+            Some(&text("Line one\nLine two\n"))
+        );
+    }
+
+    #[test]
+    fn reports_the_failing_line() {
+        for (yaml, line) in [
+            ("title: A\njust text\n", 2),
+            ("tags: [a, b\n", 1),
+            ("a: 1\n    b: 2\n", 2),
+            ("quote: \"open\n", 1),
+        ] {
+            match parse(yaml) {
+                Err(SerializationError::DeserializationFailed(message)) => {
+                    assert!(
+                        message.contains(&format!("line {line}")),
+                        "{yaml:?}: {message}"
+                    );
+                }
+                other => panic!("expected an error for {yaml:?}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn written_yaml_reads_back_unchanged() {
+        let yaml = "\
+title: \"2024-05-01\"
+plain: hello world
+quoted: \"needs: quoting\"
+count: 42
+when: 2024-05-01
+tags:
+  - a
+  - b, c
+  - \"#not a comment\"
+meta:
+  nested:
+    deep: true
+  list: []
+people:
+  - name: Ana
+    role: lead
+  - - x
+    - y
+empty:
+escapes: \"tab\\there\\nnewline \\\"q\\\"\"
+";
+        let properties = parse(yaml).unwrap();
+
+        assert_eq!(to_yaml(&properties), yaml);
+        assert_eq!(parse(&to_yaml(&properties)).unwrap(), properties);
+    }
+}
@@ -1,37 +1,111 @@
 #![allow(clippy::too_many_lines)]
 
-use std::fmt::Write;
+mod blocks;
+mod inlines;
+#[cfg(test)]
+mod spec;
+
+use std::{fmt::Write, sync::LazyLock};
 
+use inlines::References;
 use regex::Regex;
 
 use crate::{
-    formats::{NoteMetadata, NoteSerialization},
+    document::visit_inlines,
+    error::SerializationResult,
+    formats::{Capabilities, NoteMetadata, NoteSerialization, frontmatter},
     models::{
-        Alignment, Attachment, AttachmentType, Block, Blocks, ContainerBlock, DefinitionItem,
-        Inline, LeafBlock, LinkTarget, ListStyle, Note, Numbering, NumberingStyle, NumberingType,
+        Alignment, Attachment, AttachmentType, Block, BlockKind, ContainerBlock, DefinitionItem,
+        Inline, InlineKind, LeafBlock, LinkTarget, ListItem, ListStyle, Note, Numbering,
+        NumberingStyle, Properties, PropertyValue,
     },
 };
 
-#[derive(Debug, uniffi::Record)]
-pub struct MarkdownFormat;
+/// The kinds of `> [!KIND]` alert, lowercased as in the alert's classes
+pub(crate) const ALERT_KINDS: [&str; 5] = ["note", "tip", "important", "warning", "caution"];
+
+/// `^block-id` at the end of a line, after whitespace or on its own
+static BLOCK_ID: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?:^|\s)\^([A-Za-z0-9-]+)$").unwrap());
+
+/// The GitHub Flavored Markdown extensions to CommonMark, each of which can
+/// be turned off; all are on by default
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct GfmOptions {
+    /// Pipe tables, with `:---:` delimiter rows setting column alignment
+    pub tables: bool,
+    /// `- [ ]` and `- [x]` list items
+    pub task_lists: bool,
+    /// `www.example.com`, `https://example.com` and `me@example.com` as links
+    /// without the `<>`
+    pub autolinks: bool,
+    /// `~text~` and `~~text~~`
+    pub strikethrough: bool,
+    /// `[^label]` references and `[^label]: text` definitions
+    pub footnotes: bool,
+    /// `> [!NOTE]` callouts, parsed into a [`ContainerBlock::Div`] with the
+    /// classes `alert` and the lowercased kind
+    pub alerts: bool,
+}
 
-impl MarkdownFormat {
-    fn extract_yaml_title(content: &str) -> Option<String> {
-        let re = Regex::new(r"(?s)^---\s*(.*?)\s*---").ok()?;
-        let caps = re.captures(content)?;
-        let yaml_block = caps.get(1)?.as_str();
+impl GfmOptions {
+    /// Plain CommonMark, with every extension off
+    #[must_use]
+    pub const fn none() -> Self {
+        Self {
+            tables: false,
+            task_lists: false,
+            autolinks: false,
+            strikethrough: false,
+            footnotes: false,
+            alerts: false,
+        }
+    }
+}
 
-        for line in yaml_block.lines() {
-            if let Some(title_val) = line.strip_prefix("title:") {
-                return Some(title_val.trim().trim_matches('"').to_string());
-            }
+impl Default for GfmOptions {
+    fn default() -> Self {
+        Self {
+            tables: true,
+            task_lists: true,
+            autolinks: true,
+            strikethrough: true,
+            footnotes: true,
+            alerts: true,
+        }
+    }
+}
+
+/// `text` without its leading blank lines and trailing whitespace, keeping
+/// the indentation of its first line
+fn trim_blank_lines(text: &str) -> &str {
+    let first = text
+        .find(|c: char| !c.is_whitespace())
+        .unwrap_or(text.len());
+    let start = text[..first].rfind('\n').map_or(0, |newline| newline + 1);
+    text[start..].trim_end()
+}
+
+#[derive(Debug, Default, uniffi::Record)]
+pub struct MarkdownFormat {
+    pub gfm: GfmOptions,
+}
+
+impl MarkdownFormat {
+    /// Split front matter from the body and parse it
+    fn parse_frontmatter(content: &str) -> SerializationResult<(Properties, &str)> {
+        match frontmatter::split(content) {
+            Some((yaml, body)) => Ok((frontmatter::parse(yaml)?, trim_blank_lines(body))),
+            None => Ok((Properties::new(), trim_blank_lines(content))),
         }
-        None
     }
 
-    fn strip_yaml_frontmatter(content: &str) -> String {
-        let re = Regex::new(r"(?s)^---\s*.*?\s*---\s*").unwrap();
-        re.replace(content, "").trim().to_string()
+    fn frontmatter_title(properties: &Properties) -> Option<String> {
+        properties
+            .get("title")
+            .and_then(PropertyValue::as_str)
+            .map(ToString::to_string)
     }
 
     fn strip_extension(name: &str) -> String {
@@ -74,131 +148,172 @@ impl MarkdownFormat {
         out
     }
 
+    /// Continuation lines are indented by four spaces
     fn serialize_footnote(label: &str, content: &[Block]) -> String {
-        let mut out = String::new();
-        write!(&mut out, "[^{label}]: ").unwrap();
-        let content_str = serialize_blocks(content);
-        out.push_str(&content_str.replace('\n', "\n    "));
+        let mut out = format!("[^{label}]:");
+        let content = serialize_blocks(content);
+        let mut lines = content.lines();
+        if let Some(line) = lines.next() {
+            out.push(' ');
+            out.push_str(line);
+        }
         out.push('\n');
+        for line in lines {
+            if !line.is_empty() {
+                out.push_str("    ");
+                out.push_str(line);
+            }
+            out.push('\n');
+        }
         out
     }
 
     fn serialize_container(container: &ContainerBlock) -> String {
         match container {
             ContainerBlock::List { style, items } => Self::serialize_list(style, items),
-            ContainerBlock::Table { headers, rows, .. } => Self::serialize_table(headers, rows),
+            ContainerBlock::Table {
+                headers,
+                rows,
+                alignments,
+                ..
+            } => Self::serialize_table(headers, rows, alignments.as_deref()),
             ContainerBlock::Quote { blocks } => Self::serialize_quote(blocks),
-            ContainerBlock::Div { children, .. } => Self::serialize_div(children),
+            ContainerBlock::Div {
+                classes, children, ..
+            } => Self::serialize_div(classes, children),
         }
     }
 
-    fn serialize_list(style: &crate::models::ListStyle, items: &[Vec<Block>]) -> String {
+    fn serialize_list(style: &ListStyle, items: &[ListItem]) -> String {
         let mut out = String::new();
 
-        for (i, item_blocks) in items.iter().enumerate() {
-            let prefix = match style {
-                crate::models::ListStyle::Ordered { .. } => format!("{}. ", i + 1),
-                crate::models::ListStyle::Unordered { bullet } => format!("{} ", *bullet as char),
+        for (i, item) in items.iter().enumerate() {
+            let marker = match style {
+                ListStyle::Ordered {
+                    numbering:
+                        Numbering {
+                            style: NumberingStyle::Paren,
+                            ..
+                        },
+                } => format!("{})", i + 1),
+                ListStyle::Ordered { .. } => format!("{}.", i + 1),
+                ListStyle::Unordered { bullet } => (*bullet as char).to_string(),
             };
+            let mut first = marker.clone();
+            if let Some(checkbox) = item.checkbox {
+                first.push(' ');
+                first.push_str(checkbox.marker());
+            }
 
-            if let Some((first, rest)) = item_blocks.split_first() {
-                let first_serialized = serialize_blocks(core::slice::from_ref(first));
-                let mut lines = first_serialized.lines();
-                if let Some(first_line) = lines.next() {
-                    out.push_str(&prefix);
-                    out.push_str(first_line);
-                    out.push('\n');
-                }
-                for line in lines {
-                    out.push_str("  ");
+            // Continuation lines line up with the content after the marker
+            let content = serialize_blocks(&item.blocks);
+            let mut lines = content.lines();
+            out.push_str(&first);
+            if let Some(line) = lines.next() {
+                out.push(' ');
+                out.push_str(line);
+            }
+            out.push('\n');
+            for line in lines {
+                if !line.is_empty() {
+                    out.push_str(&" ".repeat(marker.len() + 1));
                     out.push_str(line);
-                    out.push('\n');
-                }
-
-                for block in rest {
-                    for nested_line in serialize_blocks(core::slice::from_ref(block)).lines() {
-                        out.push_str("  ");
-                        out.push_str(nested_line);
-                        out.push('\n');
-                    }
                 }
+                out.push('\n');
             }
         }
         out
     }
 
-    fn serialize_table(headers: &[Vec<Inline>], rows: &[Vec<Vec<Inline>>]) -> String {
-        let mut out = String::new();
-
-        // headers
-        for (i, header) in headers.iter().enumerate() {
-            if i > 0 {
-                out.push('|');
-            }
-            out.push(' ');
-            out.push_str(&serialize_inlines(header));
-            out.push(' ');
-        }
-        out.push('\n');
+    /// Rows have outer pipes, so that a table of one column is still a table
+    fn serialize_table(
+        headers: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+        alignments: Option<&[Alignment]>,
+    ) -> String {
+        let row = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+        let cells = |cells: &[Vec<Inline>]| {
+            cells
+                .iter()
+                .map(|cell| serialize_inlines(cell).replace('|', "\\|"))
+                .collect()
+        };
 
-        // separator
-        for i in 0..headers.len() {
-            if i > 0 {
-                out.push('|');
-            }
-            out.push_str(" --- ");
-        }
-        out.push('\n');
+        let delimiters = (0..headers.len())
+            .map(
+                |i| match alignments.and_then(|alignments| alignments.get(i)) {
+                    Some(Alignment::Center) => ":---:".to_string(),
+                    Some(Alignment::Right) => "---:".to_string(),
+                    Some(Alignment::Left) | None => "---".to_string(),
+                },
+            )
+            .collect();
 
-        // rows
-        for row in rows {
-            for (i, cell) in row.iter().enumerate() {
-                if i > 0 {
-                    out.push('|');
-                }
-                out.push(' ');
-                out.push_str(&serialize_inlines(cell));
-                out.push(' ');
-            }
-            out.push('\n');
+        let mut out = row(cells(headers));
+        out.push_str(&row(delimiters));
+        for body_row in rows {
+            out.push_str(&row(cells(body_row)));
         }
-
         out
     }
 
+    /// An empty quote is a lone `>`
     fn serialize_quote(blocks: &[Block]) -> String {
+        if blocks.is_empty() {
+            return ">\n".to_string();
+        }
         let mut out = String::new();
-        for b in blocks {
-            let lines = serialize_blocks(core::slice::from_ref(b))
-                .lines()
-                .map(|l| format!("> {l}"))
-                .collect::<Vec<_>>()
-                .join("\n");
-            out.push_str(&lines);
-            out.push('\n');
+        for line in serialize_blocks(blocks).lines() {
+            if line.is_empty() {
+                out.push_str(">\n");
+            } else {
+                writeln!(&mut out, "> {line}").unwrap();
+            }
         }
         out
     }
 
-    fn serialize_div(children: &[Block]) -> String {
-        children
-            .iter()
-            .map(|c| serialize_blocks(std::slice::from_ref(c)))
-            .collect()
+    /// An alert goes back to a quote opening with `[!KIND]`; other divs have
+    /// no Markdown syntax, so only their content is kept
+    ///
+    /// Anything but a paragraph goes after a blank line, where a `---` cannot
+    /// turn the `[!KIND]` line into a heading.
+    fn serialize_div(classes: &[String], children: &[Block]) -> String {
+        match alert_kind(classes) {
+            Some(kind) => {
+                let mut out = format!("> [!{}]\n", kind.to_uppercase());
+                match children.first() {
+                    None => {}
+                    Some(first) if first.is_paragraph() => {}
+                    Some(_) => out.push_str(">\n"),
+                }
+                if !children.is_empty() {
+                    out.push_str(&Self::serialize_quote(children));
+                }
+                out
+            }
+            None => serialize_blocks(children),
+        }
     }
 
     fn serialize_leaf(leaf: &LeafBlock) -> String {
         match leaf {
             LeafBlock::Heading { level, content } => {
-                format!(
-                    "{} {}\n",
-                    "#".repeat(*level as usize),
-                    serialize_inlines(content)
-                )
+                let mut content = serialize_inlines(content);
+                // `#`s after a space at the end would be a closing sequence
+                let text = content.trim_end_matches('#');
+                if text.len() < content.len() && text.ends_with([' ', '\t']) {
+                    content.insert(text.len(), '\\');
+                }
+                format!("{} {content}\n", "#".repeat(*level as usize))
             }
             LeafBlock::Paragraph { content } => format!("{}\n", serialize_inlines(content)),
             LeafBlock::Image { alt_text, src } => {
-                format!("![{}]({})\n", alt_text.clone().unwrap_or_default(), src)
+                format!(
+                    "![{}]({})\n",
+                    alt_text.clone().unwrap_or_default(),
+                    link_destination(src)
+                )
             }
             LeafBlock::CodeBlock { language, content } => {
                 let lang = language.as_deref().unwrap_or("");
@@ -207,8 +322,9 @@ impl MarkdownFormat {
             LeafBlock::MathBlock { content } => format!("$$\n{content}\n$$\n"),
             LeafBlock::Attachment {
                 attachment: Attachment { src, name, kind: _ },
-            } => format!("![{name}]({src})\n"),
+            } => format!("![{name}]({})\n", link_destination(src)),
             LeafBlock::HorizontalRule => String::from("---\n"),
+            LeafBlock::Html { content } => format!("{content}\n"),
         }
     }
 }
@@ -219,26 +335,31 @@ impl MarkdownFormat {
     #[must_use]
     #[uniffi::method]
     pub fn parse_note(&self, file_name: &str, content: &str) -> Note {
-        let yaml_title = Self::extract_yaml_title(content);
-        let title = yaml_title.unwrap_or_else(|| Self::strip_extension(file_name));
-        let body = Self::strip_yaml_frontmatter(content);
+        // Malformed front matter is kept out of the body but otherwise ignored
+        let (properties, body) = Self::parse_frontmatter(content).unwrap_or_else(|_| {
+            let body = frontmatter::split(content).map_or(content, |(_, body)| body);
+            (Properties::new(), trim_blank_lines(body))
+        });
+        let title = Self::frontmatter_title(&properties)
+            .unwrap_or_else(|| Self::strip_extension(file_name));
 
-        let blocks = parse_blocks(&body);
+        let blocks = blocks::parse(body, &self.gfm);
 
         Note {
             id: file_name.to_string(),
             title,
+            properties,
             blocks,
         }
     }
 }
 
 impl NoteSerialization for MarkdownFormat {
-    fn deserialize(&self, data: &[u8], id_hint: Option<&str>) -> Note {
-        let input = core::str::from_utf8(data).unwrap_or("");
+    fn deserialize(&self, data: &[u8], id_hint: Option<&str>) -> SerializationResult<Note> {
+        let input = core::str::from_utf8(data)?;
 
-        let yaml_title = Self::extract_yaml_title(input);
-        let body = Self::strip_yaml_frontmatter(input);
+        let (properties, body) = Self::parse_frontmatter(input)?;
+        let yaml_title = Self::frontmatter_title(&properties);
 
         let mut title = yaml_title.clone().unwrap_or_else(|| {
             let hint = id_hint.unwrap_or_default();
@@ -255,42 +376,137 @@ impl NoteSerialization for MarkdownFormat {
                 continue;
             }
 
+            // Only the first line can be the title
             if yaml_title.is_none()
                 && let Some(stripped) = trimmed.strip_prefix("# ")
             {
                 title = stripped.trim().to_string();
-                continue;
+            } else {
+                body_lines.push(line);
             }
-
-            body_lines.push(line);
             break;
         }
 
         body_lines.extend(lines);
         let clean_body = body_lines.join("\n");
 
-        let blocks = parse_blocks(&clean_body);
+        let blocks = blocks::parse(&clean_body, &self.gfm);
 
         let id = id_hint.map_or_else(|| uuid::Uuid::new_v4().to_string(), Self::filename_stem);
 
-        Note { id, title, blocks }
+        Ok(Note {
+            id,
+            title,
+            properties,
+            blocks,
+        })
     }
 
     fn serialize(&self, note: &Note) -> Vec<u8> {
         let mut output = String::new();
-        output.push_str("# ");
-        output.push_str(&note.title);
-        output.push('\n');
 
-        for block in &note.blocks {
-            output.push_str(&Self::serialize_block(block));
+        // A `title` property is kept in sync with the note's title and takes
+        // the place of the `# Title` line
+        let has_title_property = note.properties.get("title").is_some();
+        if !note.properties.is_empty() {
+            let mut properties = note.properties.clone();
+            if has_title_property {
+                properties.insert("title", PropertyValue::text(&note.title));
+            }
+            output.push_str("---\n");
+            output.push_str(&frontmatter::to_yaml(&properties));
+            output.push_str("---\n");
+        }
+        if !has_title_property {
+            output.push_str("# ");
+            output.push_str(&note.title);
+            output.push('\n');
+            if !note.blocks.is_empty() {
+                output.push('\n');
+            }
         }
 
+        output.push_str(&serialize_blocks(&note.blocks));
+
         output.into_bytes()
     }
+
+    /// Everything but attachments, definition lists, superscript and
+    /// subscript, with the GFM extensions on
+    ///
+    /// A div only survives as an alert (see [`GfmOptions::alerts`]), a table
+    /// loses its caption and comes back with alignments, an HTML block must
+    /// be one by CommonMark's rules while inline HTML cannot start a
+    /// paragraph, a `^block-id` must end its paragraph and headings hold no
+    /// `#tags`. Adjacent lists with the same
+    /// marker merge into one.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            blocks: vec![
+                BlockKind::Paragraph,
+                BlockKind::Heading,
+                BlockKind::Image,
+                BlockKind::CodeBlock,
+                BlockKind::MathBlock,
+                BlockKind::HorizontalRule,
+                BlockKind::Html,
+                BlockKind::Quote,
+                BlockKind::List,
+                BlockKind::Table,
+                BlockKind::Div,
+                BlockKind::FootnoteDefinition,
+            ],
+            inlines: vec![
+                InlineKind::Text,
+                InlineKind::Bold,
+                InlineKind::Italic,
+                InlineKind::Strikethrough,
+                InlineKind::Link,
+                InlineKind::Image,
+                InlineKind::Code,
+                InlineKind::Math,
+                InlineKind::LineBreak,
+                InlineKind::FootnoteReference,
+                InlineKind::BlockId,
+                InlineKind::Tag,
+                InlineKind::Html,
+            ],
+            properties: true,
+        }
+    }
 }
 
 impl NoteMetadata for MarkdownFormat {
+    /// Tags from front matter `tags:` and `#tag` inlines, in that order and
+    /// without duplicates
+    ///
+    /// Code, URLs and link targets never hold tags, and the `#` of a heading
+    /// is not one.
+    fn extract_tags(&self, content: &str) -> Vec<String> {
+        let Ok(note) = self.deserialize(content.as_bytes(), None) else {
+            return Vec::new();
+        };
+
+        let mut tags: Vec<String> = Vec::new();
+        let mut push = |tag: &str| {
+            let tag = tag.trim().trim_start_matches('#');
+            if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+                tags.push(tag.to_string());
+            }
+        };
+        match note.properties.get("tags") {
+            Some(PropertyValue::Text { value }) => {
+                value.split([',', ' ']).for_each(&mut push);
+            }
+            Some(value) => value.as_strings().into_iter().for_each(&mut push),
+            None => {}
+        }
+        visit_inlines(&note.blocks, |_, inlines| {
+            inline_tags(inlines, &mut push);
+        });
+        tags
+    }
+
     fn extract_links(&self, note: &Note, attachments: &[Attachment]) -> Vec<LinkTarget> {
         fn process_inlines(
             inlines: &[Inline],
@@ -336,10 +552,8 @@ impl NoteMetadata for MarkdownFormat {
                             content: inlines, ..
                         } => process_inlines(inlines, links, is_attachment),
 
-                        LeafBlock::Image { src, .. } => {
-                            if is_attachment(src) {
-                                links.push(LinkTarget::Attachment(src.clone()));
-                            }
+                        LeafBlock::Image { src, .. } if is_attachment(src) => {
+                            links.push(LinkTarget::Attachment(src.clone()));
                         }
                         _ => {}
                     },
@@ -352,7 +566,7 @@ impl NoteMetadata for MarkdownFormat {
                         } => process_blocks(inner_blocks, links, is_attachment),
                         ContainerBlock::List { items, .. } => {
                             for item in items {
-                                process_blocks(item, links, is_attachment);
+                                process_blocks(&item.blocks, links, is_attachment);
                             }
                         }
                         ContainerBlock::Table { headers, rows, .. } => {
@@ -376,267 +590,107 @@ impl NoteMetadata for MarkdownFormat {
         process_blocks(&note.blocks, &mut links, &is_attachment);
 
         links
+            .into_iter()
+            .map(|link| match link {
+                LinkTarget::Note(target) => LinkTarget::note_link(&target, &note.id),
+                other => other,
+            })
+            .collect()
     }
 }
 
-fn serialize_blocks(blocks: &[Block]) -> String {
-    let mut out = String::new();
-    for b in blocks {
-        match b {
-            Block::Container { container } => match container {
-                ContainerBlock::List { .. }
-                | ContainerBlock::Quote { .. }
-                | ContainerBlock::Table { .. } => {
-                    out.push_str(
-                        &String::from_utf8(MarkdownFormat.serialize(&Note {
-                            id: String::new(),
-                            title: String::new(),
-                            blocks: vec![b.clone()],
-                        }))
-                        .unwrap_or_default(),
-                    );
-                }
-                ContainerBlock::Div { children, .. } => {
-                    for child in children {
-                        out.push_str(&serialize_blocks(std::slice::from_ref(child)));
-                    }
-                }
-            },
-            Block::Leaf { leaf } => match leaf {
-                LeafBlock::Image { .. } => {
-                    out.push_str(
-                        &String::from_utf8(MarkdownFormat.serialize(&Note {
-                            id: String::new(),
-                            title: String::new(),
-                            blocks: vec![b.clone()],
-                        }))
-                        .unwrap_or_default(),
-                    );
-                }
-                LeafBlock::Paragraph { content: inlines } => {
-                    out.push_str(&serialize_inlines(inlines));
-                }
-                LeafBlock::Heading {
-                    level,
-                    content: inlines,
-                } => {
-                    out.push_str(&"#".repeat(*level as usize));
-                    out.push(' ');
-                    out.push_str(&serialize_inlines(inlines));
-                }
-
-                LeafBlock::HorizontalRule => out.push_str("---\n"),
-
-                _ => {}
-            },
-
-            Block::DefinitionList { items } => {
-                for item in items {
-                    out.push_str(&serialize_inlines(&item.term));
-                    out.push_str(":\n");
-                    for def_block in &item.definition {
-                        let def_serialized = serialize_blocks(std::slice::from_ref(def_block));
-                        for line in def_serialized.lines() {
-                            out.push_str("  ");
-                            out.push_str(line);
-                            out.push('\n');
-                        }
-                    }
-                }
-            }
-
-            Block::FootnoteDefinition { label, content } => {
-                write!(&mut out, "[^{label}]: ").unwrap();
-                let content_str = serialize_blocks(content);
-                write!(&mut out, "{}", content_str.replace('\n', "\n    ")).unwrap();
-                writeln!(&mut out).unwrap();
-            }
+/// Names of the `Inline::Tag`s among `inlines`, including formatted ones
+fn inline_tags(inlines: &[Inline], push: &mut impl FnMut(&str)) {
+    for inline in inlines {
+        match inline {
+            Inline::Tag { name } => push(name),
+            Inline::Bold { content }
+            | Inline::Italic { content }
+            | Inline::Strikethrough { content }
+            | Inline::Superscript { content }
+            | Inline::Subscript { content } => inline_tags(content, push),
+            _ => {}
         }
-        out.push('\n');
     }
-    out
 }
 
-#[uniffi::export]
-pub fn parse_blocks(input: &str) -> Vec<Block> {
-    let mut blocks = Vec::new();
-    let mut lines = input.lines().peekable();
-
-    while let Some(line) = lines.next() {
-        let trimmed = line.trim();
-        if trimmed.is_empty() {
-            continue;
-        }
-
-        if let Some(stripped) = trimmed.strip_prefix("```") {
-            let language = stripped.trim();
-            let mut content = String::new();
-            for next in lines.by_ref() {
-                if next.trim() == "```" {
-                    break;
-                }
-                content.push_str(next);
-                content.push('\n');
-            }
-
-            if content.ends_with('\n') {
-                content.pop();
-            }
-
-            blocks.push(Block::code_block(
-                if language.is_empty() {
-                    None
-                } else {
-                    Some(language.to_string())
-                },
-                content,
-            ));
-
-            continue;
-        }
-
-        if let Some(mut content) = trimmed.strip_prefix("$$") {
-            if content.ends_with("$$") {
-                content = &content[..content.len() - 2];
-                blocks.push(Block::math_block(content.to_string()));
-                continue;
-            }
-
-            let mut full_content = String::new();
-            if !content.is_empty() {
-                full_content.push_str(content);
-            }
-
-            for next in lines.by_ref() {
-                let next_trimmed = next.trim_end();
-                if !full_content.is_empty() {
-                    full_content.push('\n');
-                }
-                if let Some(stripped_end) = next_trimmed.strip_suffix("$$") {
-                    full_content.push_str(stripped_end);
-                    break;
-                }
-                full_content.push_str(next_trimmed);
-            }
-
-            if full_content.ends_with('\n') {
-                full_content.pop();
-            }
-
-            blocks.push(Block::math_block(full_content));
-            continue;
-        }
-
-        if let Some(header) = parse_markdown_header(trimmed) {
-            blocks.push(header);
-            continue;
-        }
-
-        if trimmed.starts_with('>') {
-            let mut quote_lines = vec![trimmed.trim_start_matches('>').trim_start().to_string()];
-
-            while let Some(next_line) = lines.peek() {
-                if next_line.trim_start().starts_with('>') {
-                    let clean_line = next_line
-                        .trim_start()
-                        .trim_start_matches('>')
-                        .trim_start()
-                        .to_string();
-                    quote_lines.push(clean_line);
-                    lines.next();
-                } else {
-                    break;
-                }
-            }
-
-            let inner = quote_lines.join("\n").trim().to_string();
-
-            if !inner.is_empty() {
-                blocks.push(Block::quote(parse_blocks(&inner)));
-            }
-
-            continue;
-        }
-
-        if trimmed.starts_with("- ")
-            || trimmed.starts_with("* ")
-            || trimmed.starts_with("+ ")
-            || trimmed.chars().next().is_some_and(|c| c.is_ascii_digit())
-        {
-            let mut list_lines = vec![line];
-            while let Some(&next_line) = lines.peek() {
-                let next_trimmed = next_line.trim_start();
-                if next_trimmed.starts_with("- ")
-                    || next_trimmed.starts_with("* ")
-                    || next_trimmed.starts_with("+ ")
-                    || next_trimmed
-                        .chars()
-                        .next()
-                        .is_some_and(|c| c.is_ascii_digit())
-                    || next_trimmed.is_empty()
-                {
-                    list_lines.push(lines.next().unwrap());
-                } else {
-                    break;
-                }
-            }
-
-            let list_text = list_lines.join("\n");
-            if let Some(list) = parse_list(&list_text) {
-                blocks.push(list);
-            }
-            continue;
-        }
-
-        if trimmed.contains('|')
-            && let Some(table) = parse_table(trimmed)
-        {
-            blocks.push(table);
-            continue;
-        }
-
-        if let Some((alt, src)) = parse_image(trimmed) {
-            let image_line = format!("![{}]({})", alt.unwrap_or(""), src);
-            if trimmed == image_line {
-                blocks.push(Block::image(
-                    alt.map(std::string::ToString::to_string),
-                    src.to_string(),
-                ));
-                continue;
-            }
-        }
-
-        blocks.push(Block::paragraph(parse_inlines(trimmed)));
+/// The kind of alert a div with these classes is, see [`GfmOptions::alerts`]
+fn alert_kind(classes: &[String]) -> Option<&str> {
+    match classes {
+        [alert, kind] if alert == "alert" && ALERT_KINDS.contains(&kind.as_str()) => Some(kind),
+        _ => None,
     }
+}
 
+/// Blocks separated by blank lines
+fn serialize_blocks(blocks: &[Block]) -> String {
     blocks
+        .iter()
+        .map(MarkdownFormat::serialize_block)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Parse a Markdown document into blocks, following CommonMark with the
+/// GFM extensions
+#[must_use]
+#[uniffi::export]
+pub fn parse_blocks(input: &str) -> Vec<Block> {
+    blocks::parse(input, &GfmOptions::default())
 }
 
+/// Inlines that are parsed on their own, as a paragraph, heading or cell
 fn serialize_inlines(inlines: &[Inline]) -> String {
+    write_inlines(inlines, false)
+}
+
+/// Inlines inside emphasis, a sub- or superscript or a link's text, where
+/// what comes after them is not known
+fn serialize_nested(inlines: &[Inline]) -> String {
+    write_inlines(inlines, true)
+}
+
+fn write_inlines(inlines: &[Inline], nested: bool) -> String {
     let mut output = String::new();
-    for inline in inlines {
+    for (i, inline) in inlines.iter().enumerate() {
         match inline {
-            Inline::Text { text } => output.push_str(text),
+            Inline::Text { text } => {
+                // What follows the text, which may close what it opens, with
+                // other texts as they are before escaping
+                let following = (!nested).then(|| {
+                    inlines[i + 1..]
+                        .iter()
+                        .map(|inline| match inline {
+                            Inline::Text { text } => text.clone(),
+                            inline => serialize_nested(std::slice::from_ref(inline)),
+                        })
+                        .collect::<String>()
+                });
+                let before = output.chars().next_back();
+                output.push_str(&escape_text(text, before, following.as_deref()));
+            }
             Inline::Bold { content } => {
                 output.push_str("**");
-                output.push_str(&serialize_inlines(content));
+                output.push_str(&serialize_nested(content));
                 output.push_str("**");
             }
             Inline::Italic { content } => {
                 output.push('*');
-                output.push_str(&serialize_inlines(content));
+                output.push_str(&serialize_nested(content));
                 output.push('*');
             }
             Inline::Strikethrough { content } => {
                 output.push_str("~~");
-                output.push_str(&serialize_inlines(content));
+                output.push_str(&serialize_nested(content));
                 output.push_str("~~");
             }
             Inline::Link { text, target } => {
-                output.push('[');
-                output.push_str(&serialize_inlines(text));
+                let text = serialize_nested(text);
+                // `[^` would start a footnote reference
+                output.push_str(if text.starts_with('^') { "[\\" } else { "[" });
+                output.push_str(&text);
                 output.push_str("](");
-                output.push_str(target);
+                output.push_str(&link_destination(target));
                 output.push(')');
             }
             Inline::Image { alt_text, src } => {
@@ -645,13 +699,23 @@ fn serialize_inlines(inlines: &[Inline]) -> String {
                     output.push_str(alt);
                 }
                 output.push_str("](");
-                output.push_str(src);
+                output.push_str(&link_destination(src));
                 output.push(')');
             }
             Inline::Code { code } => {
-                output.push('`');
-                output.push_str(code);
-                output.push('`');
+                // The fence is longer than any run of backticks in the code
+                let longest = code
+                    .split(|c| c != '`')
+                    .map(str::len)
+                    .max()
+                    .unwrap_or_default();
+                let fence = "`".repeat(longest + 1);
+                let padding = if code.starts_with('`') || code.ends_with('`') {
+                    " "
+                } else {
+                    ""
+                };
+                write!(output, "{fence}{padding}{code}{padding}{fence}").unwrap();
             }
             Inline::Math { content } => {
                 output.push('$');
@@ -661,361 +725,336 @@ fn serialize_inlines(inlines: &[Inline]) -> String {
             Inline::LineBreak => output.push_str("  \n"),
             Inline::Superscript { content } => {
                 output.push('^');
-                output.push_str(&serialize_inlines(content));
+                output.push_str(&serialize_nested(content));
             }
             Inline::Subscript { content } => {
                 output.push('_');
-                output.push_str(&serialize_inlines(content));
+                output.push_str(&serialize_nested(content));
             }
             Inline::FootnoteReference { label } => {
                 output.push_str("[^");
                 output.push_str(label);
                 output.push(']');
             }
+            Inline::BlockId { id } => {
+                output.push_str(" ^");
+                output.push_str(id);
+            }
+            Inline::Tag { name } => {
+                output.push('#');
+                output.push_str(name);
+            }
+            Inline::Html { content } => output.push_str(content),
         }
     }
     output
 }
 
-fn parse_markdown_header(line: &str) -> Option<Block> {
-    let trimmed = line.trim_start();
-    let mut chars = trimmed.chars().peekable();
-
-    let mut level = 0;
-    while matches!(chars.peek(), Some('#')) {
-        chars.next();
-        level += 1;
+/// A link or image destination that reads back as `target`, in `<...>` when
+/// it holds spaces, parentheses or angle brackets
+fn link_destination(target: &str) -> String {
+    let pointy = target.contains(|c: char| {
+        c.is_ascii_whitespace() || c.is_ascii_control() || matches!(c, '(' | ')' | '<' | '>')
+    });
+    let mut out = String::with_capacity(target.len() + 2);
+    if pointy {
+        out.push('<');
+    }
+    for (i, c) in target.char_indices() {
+        let rest = &target[i..];
+        let escape = match c {
+            '\\' => rest[1..]
+                .chars()
+                .next()
+                .is_none_or(|c| c.is_ascii_punctuation()),
+            '&' => inlines::decode_entity(rest).is_some(),
+            '<' | '>' => pointy,
+            _ => false,
+        };
+        if escape {
+            out.push('\\');
+        }
+        out.push(c);
     }
-
-    if level == 0 || chars.next() != Some(' ') {
-        return None;
+    if pointy {
+        out.push('>');
     }
-
-    let content: String = chars.collect();
-
-    Some(Block::heading(
-        level,
-        vec![Inline::Text {
-            text: content.trim().to_string(),
-        }],
-    ))
+    out
 }
 
-#[must_use]
-#[uniffi::export]
-pub fn parse_list(input: &str) -> Option<Block> {
-    let lines: Vec<&str> = input.lines().collect();
-    if lines.is_empty() {
-        return None;
-    }
+/// Text with a backslash or character reference in front of whatever would
+/// otherwise read back as markup, so that parsing it gives the same text
+///
+/// `before` is the last character written, if any, and `following` what
+/// comes after the text, or `None` when that is not known. Code, math,
+/// emphasis and links are only escaped where they could be closed.
+fn escape_text(text: &str, before: Option<char>, following: Option<&str>) -> String {
+    let mut out = String::with_capacity(text.len());
+    // Where the current line starts, if the text holds its start
+    let mut line = before.is_none_or(|before| before == '\n').then_some(0);
+    let mut before = before;
+    // Whether the run of `*`, `_` or `~` being written is escaped
+    let mut delimiters = false;
+    // The text and what follows it, with `&` for the whitespace written as
+    // character references, and whether anything unknown comes after that
+    let source = referenced_spaces(&format!("{text}{}", following.unwrap_or_default()));
+    let unknown = following.is_none();
+    for (i, c) in text.char_indices() {
+        let rest = &text[i + c.len_utf8()..];
+        let after = rest.chars().next();
+        let ahead = &source[i + c.len_utf8()..];
+        let next = ahead.chars().next();
+        let at_line_start = line == Some(i);
+        let ordered_marker = line.is_some_and(|start| {
+            (1..=9).contains(&(i - start)) && text[start..i].bytes().all(|b| b.is_ascii_digit())
+        });
+        let opens_tag = before.is_none_or(|b: char| b.is_whitespace() || "([{'\"".contains(b));
 
-    let mut items: Vec<Blocks> = Vec::new();
-    let mut i = 0;
-    let mut list_style: Option<ListStyle> = None;
+        if matches!(c, '*' | '_' | '~') && before != Some(c) {
+            delimiters = at_line_start || escape_delimiters(c, before, ahead, unknown);
+        }
 
-    while i < lines.len() {
-        if let Some((item_blocks, next_index, style)) = parse_list_item(&lines, i) {
-            if list_style.is_none() {
-                list_style = Some(style);
+        match c {
+            // Whitespace at either end of a line would be trimmed, and an
+            // empty line would end the paragraph
+            ' ' | '\t' | '\n' if at_line_start || (c != '\n' && ends_line(&text[i..])) => {
+                write!(out, "&#{};", u32::from(c)).unwrap();
+            }
+            '\r' => out.push_str("&#13;"),
+            // An escape or a hard line break, or a literal backslash at the end
+            '\\' if next.is_none_or(|c| c.is_ascii_punctuation() || c == '\n') => {
+                out.push_str("\\\\");
+            }
+            '*' | '_' | '~' if delimiters => {
+                out.push('\\');
+                out.push(c);
+            }
+            // A code span needs a closing backtick, and three open a fence
+            '`' if at_line_start || unknown || ahead.contains('`') => out.push_str("\\`"),
+            // A link or image needs a `](` to close it; a footnote, a wiki
+            // link or a reference definition does not
+            '[' if at_line_start
+                || unknown
+                || matches!(after, Some('[' | '^'))
+                || ahead.contains("](") =>
+            {
+                out.push_str("\\[");
             }
-            items.push(item_blocks);
-            i = next_index;
-        } else {
-            i += 1;
+            ']' if unknown => out.push_str("\\]"),
+            // Autolinks and HTML
+            '<' if next
+                .is_some_and(|c| c.is_ascii_alphabetic() || matches!(c, '/' | '!' | '?')) =>
+            {
+                out.push_str("\\<");
+            }
+            '$' if opens_math(ahead, unknown) || (at_line_start && after == Some('$')) => {
+                out.push_str("\\$");
+            }
+            '&' if inlines::decode_entity(&text[i..]).is_some() => out.push_str("\\&"),
+            // A heading, a `#tag` or a trailing `^block-id`
+            '#' if at_line_start || (opens_tag && tag_name(rest)) => {
+                out.push_str("\\#");
+            }
+            '^' if opens_tag && rest.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-') => {
+                out.push_str("\\^");
+            }
+            // `!` before a link would make it an image
+            '!' if rest.is_empty() && (unknown || ahead.starts_with('[')) => out.push_str("\\!"),
+            // List items, rules, setext underlines, quotes and the
+            // delimiter row of a table
+            '-' | '+' | '=' | '>' | '|' if at_line_start => {
+                out.push('\\');
+                out.push(c);
+            }
+            ':' if at_line_start && after == Some('-') => {
+                out.push('\\');
+                out.push(c);
+            }
+            '.' | ')' if ordered_marker => {
+                out.push('\\');
+                out.push(c);
+            }
+            // Bare URLs and email addresses would become links
+            ':' if text[..i].ends_with("http") || text[..i].ends_with("https") => {
+                out.push_str("\\:");
+            }
+            '.' if text[..i].ends_with("www") => out.push_str("\\."),
+            '@' if after.is_some_and(|a| a.is_ascii_alphanumeric() || a == '_' || a == '-') => {
+                out.push_str("\\@");
+            }
+            c => out.push(c),
+        }
+
+        if c == '\n' {
+            line = Some(i + 1);
         }
+        before = out.chars().next_back();
     }
+    out
+}
 
-    if items.is_empty() {
-        None
+/// Whether a run of `c` after `before` needs escaping, where `ahead` is
+/// what follows its first character: when it can open emphasis that the
+/// same character later could close, or when either is possible and what
+/// comes after is not known
+fn escape_delimiters(c: char, before: Option<char>, ahead: &str, unknown: bool) -> bool {
+    let rest = ahead.trim_start_matches(c);
+    let after = rest.chars().next().or(unknown.then_some(c));
+    let (can_open, can_close) = inlines::delimiter_sides(c, before, after);
+    if unknown {
+        can_open || can_close
     } else {
-        Some(Block::list(
-            list_style.unwrap_or(ListStyle::Unordered { bullet: b'-' }),
-            items,
-        ))
+        can_open && rest.contains(c)
     }
 }
 
-fn parse_list_item(lines: &[&str], start_index: usize) -> Option<(Blocks, usize, ListStyle)> {
-    if start_index >= lines.len() {
-        return None;
+/// Whether a `$` before `ahead` could open inline math: followed by
+/// something other than a space, with a later `$` that could close it
+fn opens_math(ahead: &str, unknown: bool) -> bool {
+    if ahead.chars().next().map_or(!unknown, char::is_whitespace) {
+        return false;
     }
+    unknown
+        || ahead.match_indices('$').any(|(i, _)| {
+            !ahead[..i].ends_with(char::is_whitespace)
+                && !ahead[i + 1..].starts_with(|c: char| c.is_ascii_digit())
+        })
+}
 
-    let line = lines[start_index];
-    let trimmed = line.trim_start();
-    let indent = line.len() - trimmed.len();
-
-    let (list_style, content) = if let Some(stripped) = trimmed.strip_prefix("- ") {
-        (ListStyle::Unordered { bullet: b'-' }, stripped)
-    } else if let Some(stripped) = trimmed.strip_prefix("* ") {
-        (ListStyle::Unordered { bullet: b'*' }, stripped)
-    } else if let Some(stripped) = trimmed.strip_prefix("+ ") {
-        (ListStyle::Unordered { bullet: b'+' }, stripped)
-    } else if let Some(dot_pos) = trimmed.find('.') {
-        if trimmed[..dot_pos].chars().all(|c| c.is_ascii_digit())
-            && trimmed[dot_pos + 1..].starts_with(' ')
-        {
-            (
-                ListStyle::Ordered {
-                    numbering: Numbering {
-                        kind: NumberingType::Decimal,
-                        style: NumberingStyle::Dot,
-                    },
-                },
-                &trimmed[dot_pos + 2..],
-            )
-        } else {
-            return None;
-        }
-    } else {
-        return None;
-    };
-
-    let mut item_blocks: Blocks = Vec::new();
-
-    item_blocks.push(Block::paragraph(parse_inlines(content.trim())));
+/// `text` with the whitespace [`escape_text`] writes as character
+/// references, at either end of a line, as `&`
+fn referenced_spaces(text: &str) -> String {
+    text.char_indices()
+        .map(|(i, c)| {
+            let referenced = match c {
+                ' ' | '\t' => text[..i].ends_with('\n') || ends_line(&text[i..]),
+                '\n' => text[..i].ends_with('\n'),
+                '\r' => true,
+                _ => false,
+            };
+            if referenced { '&' } else { c }
+        })
+        .collect()
+}
 
-    let mut nested_lines = Vec::new();
-    let mut i = start_index + 1;
+/// Whether only spaces and tabs are left before the next line ending
+fn ends_line(text: &str) -> bool {
+    text.find('\n')
+        .is_some_and(|end| text[..end].trim_matches([' ', '\t']).is_empty())
+}
 
-    while i < lines.len() {
-        let next_line = lines[i];
-        let next_trimmed = next_line.trim_start();
-        let next_indent = next_line.len() - next_trimmed.len();
+/// Whether the text after a `#` would make it a tag
+fn tag_name(text: &str) -> bool {
+    text.chars()
+        .take_while(|&c| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'))
+        .any(char::is_alphabetic)
+}
 
-        if next_trimmed.is_empty() {
-            i += 1;
-        } else if next_indent > indent {
-            nested_lines.push(next_line);
-            i += 1;
-        } else {
-            break;
-        }
-    }
+/// Parse the text of a paragraph or list item, turning a trailing
+/// `^block-id` marker into [`Inline::BlockId`]
+fn parse_block_inlines(text: &str, references: &References, gfm: &GfmOptions) -> Vec<Inline> {
+    let Some(marker) = BLOCK_ID.captures(text) else {
+        return inlines::parse(text, references, gfm, true);
+    };
 
-    if !nested_lines.is_empty() {
-        let nested_input = nested_lines.join("\n");
-        let nested_blocks = parse_blocks(&nested_input);
-        item_blocks.extend(nested_blocks);
-    }
+    let body = text[..marker.get(0).unwrap().start()].trim_end();
+    let mut inlines = if body.is_empty() {
+        Vec::new()
+    } else {
+        inlines::parse(body, references, gfm, true)
+    };
+    inlines.push(Inline::BlockId {
+        id: marker[1].to_string(),
+    });
+    inlines
+}
 
-    Some((item_blocks, i, list_style))
+/// The list at the start of the input, if it starts with one
+#[must_use]
+#[uniffi::export]
+pub fn parse_list(input: &str) -> Option<Block> {
+    parse_blocks(input)
+        .into_iter()
+        .next()
+        .filter(Block::is_list)
 }
 
-fn parse_table(input: &str) -> Option<Block> {
-    let mut lines: Vec<&str> = input.lines().filter(|l| l.contains('|')).collect();
+/// A pipe table: a header row, an optional delimiter row of `---`, `:---`,
+/// `---:` or `:---:` setting each column's alignment, and the body rows,
+/// which are cut or padded to the width of the header
+fn parse_table(input: &str, references: &References, gfm: &GfmOptions) -> Option<Block> {
+    let lines: Vec<&str> = input.lines().filter(|l| !l.trim().is_empty()).collect();
     if lines.len() < 2 {
         return None;
     }
+    let cells = |row: &str| {
+        table_cells(row)
+            .iter()
+            .map(|cell| inlines::parse(cell, references, gfm, true))
+            .collect::<Vec<_>>()
+    };
+    let headers = cells(lines[0]);
 
-    let headers = lines[0]
-        .split('|')
-        .map(|c| parse_inlines(c.trim()))
-        .collect::<Vec<_>>();
-
-    let alignments = if lines.len() >= 2
+    let delimiter = lines[1].contains('-')
         && lines[1]
-            .trim()
             .chars()
-            .all(|c| c == '-' || c == ':' || c == '|' || c.is_whitespace())
-    {
-        let alignment_line = lines.remove(1);
-        let detected = alignment_line
-            .split('|')
-            .map(|cell| {
-                let cell = cell.trim();
-                match (cell.starts_with(':'), cell.ends_with(':')) {
-                    (true, true) => Alignment::Center,
-                    (true, false) => Alignment::Left,
-                    (false, true) => Alignment::Right,
-                    _ => Alignment::default(),
-                }
+            .all(|c| matches!(c, '-' | ':' | '|') || c.is_whitespace());
+    let alignments = delimiter.then(|| {
+        let mut alignments: Vec<Alignment> = table_cells(lines[1])
+            .iter()
+            .map(|cell| match (cell.starts_with(':'), cell.ends_with(':')) {
+                (true, true) => Alignment::Center,
+                (false, true) => Alignment::Right,
+                _ => Alignment::Left,
             })
-            .collect::<Vec<_>>();
-        Some(detected)
-    } else {
-        None
-    };
+            .collect();
+        alignments.resize(headers.len(), Alignment::default());
+        alignments
+    });
 
-    let rows = lines[1..]
+    let body = if delimiter { &lines[2..] } else { &lines[1..] };
+    let rows = body
         .iter()
         .map(|row| {
-            row.split('|')
-                .map(|c| parse_inlines(c.trim()))
-                .collect::<Vec<_>>()
+            let mut row = cells(row);
+            row.resize(headers.len(), Vec::new());
+            row
         })
-        .collect::<Vec<_>>();
-
-    let caption = None;
-
-    Some(Block::table(headers, rows, alignments, caption))
-}
+        .collect();
 
-fn parse_image(line: &str) -> Option<(Option<&str>, &str)> {
-    let start = line.find("![")?;
-    let end_alt = line[start..].find(']')? + start;
-    let alt = &line[start + 2..end_alt];
-    let paren_start = line[end_alt..].find('(')? + end_alt + 1;
-    let paren_end = line[paren_start..].find(')')? + paren_start;
-    let src = &line[paren_start..paren_end];
-
-    Some((if alt.is_empty() { None } else { Some(alt) }, src))
+    Some(Block::table(headers, rows, alignments, None))
 }
 
-#[must_use]
-#[uniffi::export]
-pub fn parse_inlines(input: &str) -> Vec<Inline> {
-    let mut result = Vec::new();
-    let mut chars = input.chars().peekable();
+/// The trimmed cells of a table row, without the optional outer pipes and
+/// with `\|` as a literal pipe
+fn table_cells(row: &str) -> Vec<String> {
+    let row = row.trim();
+    let row = row.strip_prefix('|').unwrap_or(row);
+    let row = match row.strip_suffix('|') {
+        Some(inner) if !inner.ends_with('\\') => inner,
+        _ => row,
+    };
 
-    while let Some(c) = chars.peek().copied() {
+    let mut cells = vec![String::new()];
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
         match c {
-            '*' => {
+            '\\' if chars.peek() == Some(&'|') => {
                 chars.next();
-                if chars.peek().is_some_and(|&c| c == '*') {
-                    chars.next();
-                    let content = parse_until(&mut chars, "**");
-                    consume_delimiter(&mut chars, "**");
-                    result.push(Inline::Bold {
-                        content: parse_inlines(&content),
-                    });
-                } else {
-                    let content = parse_until(&mut chars, "*");
-                    consume_delimiter(&mut chars, "*");
-                    result.push(Inline::Italic {
-                        content: parse_inlines(&content),
-                    });
-                }
-            }
-
-            '~' => {
-                chars.next();
-                if chars.peek().is_some_and(|&c| c == '~') {
-                    chars.next();
-                    let content = parse_until(&mut chars, "~~");
-                    consume_delimiter(&mut chars, "~~");
-                    result.push(Inline::Strikethrough {
-                        content: parse_inlines(&content),
-                    });
-                } else {
-                    result.push(Inline::Text {
-                        text: "~".to_string(),
-                    });
-                }
-            }
-
-            '`' => {
-                chars.next();
-                let content = parse_until(&mut chars, "`");
-                consume_delimiter(&mut chars, "`");
-                result.push(Inline::Code { code: content });
-            }
-
-            '!' => {
-                let mut clone = chars.clone();
-                clone.next();
-                if clone.peek() == Some(&'[') {
-                    chars.next();
-                    chars.next();
-                    let alt_text = parse_until(&mut chars, "]");
-                    consume_delimiter(&mut chars, "]");
-                    if chars.peek() == Some(&'(') {
-                        chars.next();
-                        let src = parse_until(&mut chars, ")");
-                        consume_delimiter(&mut chars, ")");
-                        result.push(Inline::Image {
-                            alt_text: if alt_text.is_empty() {
-                                None
-                            } else {
-                                Some(alt_text)
-                            },
-                            src,
-                        });
-                        continue;
-                    }
-                }
-                chars.next();
-                result.push(Inline::Text {
-                    text: "!".to_string(),
-                });
-            }
-
-            '$' => {
-                chars.next();
-                let content = parse_until(&mut chars, "$");
-                consume_delimiter(&mut chars, "$");
-                result.push(Inline::Math { content });
-            }
-
-            '[' => {
-                chars.next();
-                if chars.peek() == Some(&'[') {
-                    chars.next();
-                    let text = parse_until(&mut chars, "]]");
-                    consume_delimiter(&mut chars, "]]");
-                    result.push(Inline::Link {
-                        text: vec![Inline::Text { text: text.clone() }],
-                        target: text,
-                    });
-                } else {
-                    let text = parse_until(&mut chars, "]");
-                    consume_delimiter(&mut chars, "]");
-                    if chars.peek() == Some(&'(') {
-                        chars.next();
-                        let target = parse_until(&mut chars, ")");
-                        consume_delimiter(&mut chars, ")");
-                        result.push(Inline::Link {
-                            text: parse_inlines(&text),
-                            target,
-                        });
-                    } else {
-                        result.push(Inline::Text {
-                            text: format!("[{text}]"),
-                        });
-                    }
-                }
-            }
-
-            _ => {
-                let mut text = String::new();
-                while let Some(&next) = chars.peek() {
-                    if next == '*'
-                        || next == '`'
-                        || next == '['
-                        || next == '!'
-                        || next == '$'
-                        || next == '~'
-                    {
-                        break;
-                    }
-                    text.push(chars.next().unwrap());
-                }
-                result.push(Inline::Text { text });
+                cells.last_mut().unwrap().push('|');
             }
+            '|' => cells.push(String::new()),
+            c => cells.last_mut().unwrap().push(c),
         }
     }
-
-    result
+    cells.iter().map(|cell| cell.trim().to_string()).collect()
 }
 
-fn parse_until<I>(chars: &mut core::iter::Peekable<I>, delimiter: &str) -> String
-where
-    I: Iterator<Item = char> + Clone,
-{
-    let mut buffer = String::new();
-    let delim_chars: Vec<char> = delimiter.chars().collect();
-    let delim_len = delim_chars.len();
-
-    while chars.peek().is_some() {
-        if delimiter_matches(chars.clone(), &delim_chars) {
-            for _ in 0..delim_len {
-                chars.next();
-            }
-            break;
-        }
-        buffer.push(chars.next().unwrap());
-    }
-
-    buffer
+/// Parse inline Markdown, with no link reference definitions to resolve
+#[must_use]
+#[uniffi::export]
+pub fn parse_inlines(input: &str) -> Vec<Inline> {
+    inlines::parse(input, &References::new(), &GfmOptions::default(), true)
 }
 
 #[must_use]
@@ -1060,8 +1099,8 @@ pub fn extract_attachments(blocks: &[Block]) -> Vec<Attachment> {
                 }
 
                 ContainerBlock::List { items, .. } => {
-                    for item_blocks in items {
-                        attachments.extend(extract_attachments(item_blocks));
+                    for item in items {
+                        attachments.extend(extract_attachments(&item.blocks));
                     }
                 }
 
@@ -1121,40 +1160,30 @@ pub fn extract_attachments(blocks: &[Block]) -> Vec<Attachment> {
     attachments
 }
 
-fn delimiter_matches<I>(mut chars: core::iter::Peekable<I>, delimiter: &[char]) -> bool
-where
-    I: Iterator<Item = char> + Clone,
-{
-    for &d in delimiter {
-        match chars.next() {
-            Some(c) if c == d => {}
-            _ => return false,
-        }
-    }
-    true
-}
-
-fn consume_delimiter<I>(chars: &mut core::iter::Peekable<I>, delimiter: &str)
-where
-    I: Iterator<Item = char>,
-{
-    for d in delimiter.chars() {
-        if chars.peek() == Some(&d) {
-            chars.next();
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
-    use crate::models::Inline;
+    use crate::{
+        error::SerializationError,
+        models::{Checkbox, Inline},
+    };
 
     use super::*;
 
+    /// The first image among the inlines of the line
+    fn parse_image(line: &str) -> Option<(Option<String>, String)> {
+        parse_inlines(line)
+            .into_iter()
+            .find_map(|inline| match inline {
+                Inline::Image { alt_text, src } => Some((alt_text, src)),
+                _ => None,
+            })
+    }
+
     fn sample_note() -> Note {
         Note {
             id: "test-id".to_string(),
             title: "Sample Note".to_string(),
+            properties: Properties::default(),
             blocks: vec![
                 Block::heading(
                     2,
@@ -1211,7 +1240,7 @@ mod tests {
         ];
 
         for (input, expected_level, expected_text) in cases {
-            let h = parse_markdown_header(input);
+            let h = parse_blocks(input).into_iter().next();
             assert!(h.is_some(), "Failed to parse header: {input:?}");
 
             let block = h.unwrap();
@@ -1242,9 +1271,9 @@ mod tests {
         if let Some(ContainerBlock::List { items, style }) = result.unwrap().as_list() {
             assert!(!style.is_ordered(), "Expected unordered list");
             assert_eq!(items.len(), 1);
-            assert_eq!(items[0].len(), 1);
+            assert_eq!(items[0].blocks.len(), 1);
 
-            match &items[0][0].as_paragraph() {
+            match &items[0].blocks[0].as_paragraph() {
                 Some(LeafBlock::Paragraph { content }) => {
                     assert_eq!(
                         content,
@@ -1269,9 +1298,9 @@ mod tests {
         if let Some(ContainerBlock::List { items, style }) = result.unwrap().as_list() {
             assert!(style.is_ordered(), "Expected ordered list");
             assert_eq!(items.len(), 1);
-            assert_eq!(items[0].len(), 1);
+            assert_eq!(items[0].blocks.len(), 1);
 
-            match &items[0][0].as_paragraph() {
+            match &items[0].blocks[0].as_paragraph() {
                 Some(LeafBlock::Paragraph { content }) => {
                     assert_eq!(
                         content,
@@ -1292,17 +1321,50 @@ mod tests {
         let line = "* Valid unordered list";
         assert!(parse_list(line).is_some());
 
-        let line2 = "2) Also not valid";
+        let line2 = "2.Not valid either";
         assert!(parse_list(line2).is_none());
     }
 
+    #[test]
+    fn test_parse_task_list() {
+        let input = "- [ ] Buy milk\n- [x] Call Bob\n- [-] Sell car\n- [ ]\n- [link] text";
+        let Some(ContainerBlock::List { items, .. }) = parse_list(input).unwrap().as_list() else {
+            panic!("Expected list");
+        };
+
+        let checkboxes: Vec<Option<Checkbox>> = items.iter().map(|item| item.checkbox).collect();
+        assert_eq!(
+            checkboxes,
+            vec![
+                Some(Checkbox::Unchecked),
+                Some(Checkbox::Checked),
+                Some(Checkbox::Partial),
+                Some(Checkbox::Unchecked),
+                None,
+            ]
+        );
+        assert_eq!(
+            items[0].blocks,
+            vec![Block::paragraph(vec![Inline::Text {
+                text: "Buy milk".into()
+            }])]
+        );
+
+        let serialized =
+            MarkdownFormat::serialize_list(&ListStyle::Unordered { bullet: b'-' }, &items);
+        assert_eq!(
+            serialized,
+            "- [ ] Buy milk\n- [x] Call Bob\n- [-] Sell car\n- [ ]\n- \\[link] text\n"
+        );
+    }
+
     #[test]
     fn test_parse_simple_table() {
         let input = "\
 Header 1 | Header 2
 Value 1  | Value 2";
 
-        let result = parse_table(input);
+        let result = parse_table(input, &References::new(), &GfmOptions::default());
         assert!(result.is_some());
 
         if let Some(ContainerBlock::Table { headers, rows, .. }) = result.unwrap().as_table() {
@@ -1338,7 +1400,7 @@ Value 1  | Value 2";
     #[test]
     fn test_parse_table_too_short() {
         let input = "Only one row | not enough";
-        let result = parse_table(input);
+        let result = parse_table(input, &References::new(), &GfmOptions::default());
         assert!(result.is_none(), "Expected None for short table input");
     }
 
@@ -1349,7 +1411,7 @@ Value 1  | Value 2";
         assert!(result.is_some());
 
         let (alt, src) = result.unwrap();
-        assert_eq!(alt, Some("alt text"));
+        assert_eq!(alt.as_deref(), Some("alt text"));
         assert_eq!(src, "image.png");
     }
 
@@ -1559,18 +1621,18 @@ Value 1  | Value 2";
 
             let first_item = &items[0];
             assert!(
-                first_item[0].is_paragraph(),
+                first_item.blocks[0].is_paragraph(),
                 "Expected first block in item 1 to be a paragraph"
             );
 
             if let Some(ContainerBlock::List {
                 items: inner_items,
                 style: inner_style,
-            }) = &first_item[1].as_list()
+            }) = &first_item.blocks[1].as_list()
             {
                 assert!(!inner_style.is_ordered());
                 assert_eq!(inner_items.len(), 2);
-                match &inner_items[0][0].as_paragraph() {
+                match &inner_items[0].blocks[0].as_paragraph() {
                     Some(LeafBlock::Paragraph { content }) => {
                         assert_eq!(
                             content,
@@ -1586,7 +1648,7 @@ Value 1  | Value 2";
             }
 
             let second_item = &items[1];
-            match &second_item[0].as_paragraph() {
+            match &second_item.blocks[0].as_paragraph() {
                 Some(LeafBlock::Paragraph { content }) => {
                     assert_eq!(
                         content,
@@ -1604,7 +1666,7 @@ Value 1  | Value 2";
 
     #[test]
     fn test_serialization() {
-        let format = MarkdownFormat;
+        let format = MarkdownFormat::default();
         let note = sample_note();
 
         let serialized = format.serialize(&note);
@@ -1621,16 +1683,22 @@ Value 1  | Value 2";
 
     #[test]
     fn test_deserialization() {
-        let format = MarkdownFormat;
+        let format = MarkdownFormat::default();
         let markdown = r"# Sample Note
+
 ## Heading Example
+
 This is a **bold** and *italic* text.
+
 ![Alt text](image.png)
+
 - Item 1
 - Item 2
 ";
 
-        let note = format.deserialize(markdown.as_bytes(), Some("test-id"));
+        let note = format
+            .deserialize(markdown.as_bytes(), Some("test-id"))
+            .unwrap();
 
         assert_eq!(note.id, "test-id");
         assert_eq!(note.title, "Sample Note");
@@ -1692,11 +1760,13 @@ This is a **bold** and *italic* text.
 
     #[test]
     fn test_round_trip() {
-        let format = MarkdownFormat;
+        let format = MarkdownFormat::default();
         let original_note = sample_note();
 
         let serialized = format.serialize(&original_note);
-        let deserialized = format.deserialize(&serialized, Some(&original_note.id));
+        let deserialized = format
+            .deserialize(&serialized, Some(&original_note.id))
+            .unwrap();
 
         assert_eq!(deserialized.id, original_note.id);
         assert_eq!(deserialized.title, original_note.title);
@@ -1705,11 +1775,12 @@ This is a **bold** and *italic* text.
 
     #[test]
     fn test_code_block_round_trip() {
-        let format = MarkdownFormat;
+        let format = MarkdownFormat::default();
 
         let note = Note {
             id: "test-id".into(),
             title: "Code Note".into(),
+            properties: Properties::default(),
             blocks: vec![Block::code_block(
                 Some("rust".into()),
                 "let x = 42;\nprintln!(\"{}\", x);\n".into(),
@@ -1717,7 +1788,7 @@ This is a **bold** and *italic* text.
         };
 
         let serialized = format.serialize(&note);
-        let deserialized = format.deserialize(&serialized, Some(&note.id));
+        let deserialized = format.deserialize(&serialized, Some(&note.id)).unwrap();
 
         assert_eq!(deserialized.id, note.id);
         assert_eq!(deserialized.title, note.title);
@@ -1734,16 +1805,17 @@ This is a **bold** and *italic* text.
 
     #[test]
     fn test_math_block_round_trip() {
-        let format = MarkdownFormat;
+        let format = MarkdownFormat::default();
 
         let note = Note {
             id: "test-id".into(),
             title: "Math Note".into(),
+            properties: Properties::default(),
             blocks: vec![Block::math_block("x^2 + y^2 = z^2\nx + y = z".into())],
         };
 
         let serialized = format.serialize(&note);
-        let deserialized = format.deserialize(&serialized, Some(&note.id));
+        let deserialized = format.deserialize(&serialized, Some(&note.id)).unwrap();
 
         assert_eq!(deserialized.id, note.id);
         assert_eq!(deserialized.title, note.title);
@@ -1792,13 +1864,172 @@ This is a **bold** and *italic* text.
         assert_eq!(inlines, expected);
     }
 
+    #[test]
+    fn test_parse_labelled_wiki_link() {
+        let inlines = parse_inlines("See [[Product Roadmap|the roadmap]].");
+
+        assert_eq!(
+            inlines[1],
+            Inline::Link {
+                text: vec![Inline::Text {
+                    text: "the roadmap".to_string(),
+                }],
+                target: "Product Roadmap".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_link_destinations_round_trip() {
+        let targets = [
+            "plans-2024",
+            "Product Roadmap",
+            "x#Q3 goals",
+            "my file.md",
+            "notes (draft).md",
+            "a<b>c",
+            "C:\\Users\\",
+            "fish &amp; chips",
+        ];
+        for target in targets {
+            let inlines = vec![
+                Inline::Link {
+                    text: vec![text("link")],
+                    target: target.to_string(),
+                },
+                Inline::Image {
+                    alt_text: Some("image".to_string()),
+                    src: target.to_string(),
+                },
+            ];
+            let serialized = serialize_inlines(&inlines);
+            assert_eq!(parse_inlines(&serialized), inlines, "{serialized:?}");
+        }
+        assert_eq!(
+            serialize_inlines(&parse_inlines("[[Product Roadmap]]")),
+            "[Product Roadmap](<Product Roadmap>)"
+        );
+    }
+
+    #[test]
+    fn test_block_ids_round_trip() {
+        let blocks = parse_blocks("Decided to ship ^decision\n- item ^item-1\n\n^own-line");
+
+        assert_eq!(
+            blocks[0],
+            Block::paragraph(vec![
+                Inline::Text {
+                    text: "Decided to ship".to_string(),
+                },
+                Inline::BlockId {
+                    id: "decision".to_string(),
+                },
+            ])
+        );
+        assert_eq!(
+            blocks[2],
+            Block::paragraph(vec![Inline::BlockId {
+                id: "own-line".to_string(),
+            }])
+        );
+        let note = Note {
+            id: "plan".to_string(),
+            title: "Plan".to_string(),
+            properties: Properties::default(),
+            blocks,
+        };
+        let round_trip = MarkdownFormat::default()
+            .deserialize(&MarkdownFormat::default().serialize(&note), Some("plan"))
+            .unwrap();
+        assert_eq!(round_trip.blocks, note.blocks);
+        assert_eq!(
+            parse_inlines("x^2 and [^1]"),
+            parse_block_inlines("x^2 and [^1]", &References::new(), &GfmOptions::default())
+        );
+    }
+
+    #[test]
+    fn test_extract_heading_and_block_links() {
+        let note = MarkdownFormat::default()
+            .deserialize(
+                b"# Plan\nSee [[Roadmap#Q3: Goals]], [[Roadmap#^decision]], [[#Plan]] and [site](https://example.com/#top)",
+                Some("plan"),
+            )
+            .unwrap();
+
+        assert_eq!(
+            MarkdownFormat::default().extract_links(&note, &[]),
+            vec![
+                LinkTarget::Heading {
+                    note: "Roadmap".to_string(),
+                    slug: "q3-goals".to_string(),
+                },
+                LinkTarget::Block {
+                    note: "Roadmap".to_string(),
+                    block_id: "decision".to_string(),
+                },
+                LinkTarget::Heading {
+                    note: "plan".to_string(),
+                    slug: "plan".to_string(),
+                },
+                LinkTarget::Note("https://example.com/#top".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_hashtags() {
+        let inlines = parse_inlines("(#Project/alpha) issue #42, a#b #todo/ ##x");
+
+        assert_eq!(
+            inlines,
+            vec![
+                Inline::Text {
+                    text: "(".to_string(),
+                },
+                Inline::Tag {
+                    name: "Project/alpha".to_string(),
+                },
+                Inline::Text {
+                    text: ") issue #42, a#b ".to_string(),
+                },
+                Inline::Tag {
+                    name: "todo".to_string(),
+                },
+                Inline::Text {
+                    text: "/ ##x".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            serialize_inlines(&inlines),
+            "(#Project/alpha) issue #42, a#b #todo/ ##x"
+        );
+    }
+
+    #[test]
+    fn test_extract_tags() {
+        let content = "---\ntags: [travel, \"#later\"]\n---\n# Trip #notatag\n\
+                       ## Plan #draft\n\
+                       Book *flights #travel/air* and see [site](https://example.com/#hotels)\n\
+                       Run `#code` https://example.com/page#anchor\n\
+                       ```\n#block\n```\n\
+                       - pack #later\n";
+
+        assert_eq!(
+            MarkdownFormat::default().extract_tags(content),
+            vec!["travel", "later", "travel/air"]
+        );
+    }
+
     #[test]
     fn test_extract_wiki_links() {
-        let format = MarkdownFormat;
+        let format = MarkdownFormat::default();
 
         let note = Note {
             id: "1".to_string(),
             title: "Wiki Links".to_string(),
+            properties: Properties::default(),
             blocks: vec![Block::paragraph(vec![
                 Inline::Text {
                     text: "Links: ".to_string(),
@@ -1876,4 +2107,316 @@ This is a **bold** and *italic* text.
                 .any(|a| a.src == "image.png" && a.name == "image.png")
         );
     }
+
+    #[test]
+    fn test_deserialize_reports_invalid_utf8() {
+        let result = MarkdownFormat::default().deserialize(b"# Title\n\xff broken", Some("bad.md"));
+
+        assert!(matches!(
+            result,
+            Err(SerializationError::InvalidUtf8 { offset: 8 })
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_keeps_leading_indentation() {
+        for markdown in [
+            "    let x = 1;\n",
+            "\n\n    let x = 1;\n",
+            "---\ntitle: Snippet\n---\n\n    let x = 1;\n",
+        ] {
+            let note = MarkdownFormat::default()
+                .deserialize(markdown.as_bytes(), Some("snippet.md"))
+                .unwrap();
+
+            assert_eq!(
+                note.blocks,
+                vec![Block::code_block(None, "let x = 1;".into())],
+                "{markdown:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_front_matter_round_trip() {
+        let markdown = "---\ntitle: Weekly review\ntags:\n  - work\n  - planning\ncreated: 2024-05-01\nrating: 4.5\n---\n\nBody text\n";
+
+        let note = MarkdownFormat::default()
+            .deserialize(markdown.as_bytes(), Some("weekly.md"))
+            .unwrap();
+        assert_eq!(note.title, "Weekly review");
+        assert_eq!(
+            note.properties.get("created"),
+            Some(&PropertyValue::Date {
+                value: "2024-05-01".into()
+            })
+        );
+        assert_eq!(
+            note.properties
+                .get("rating")
+                .and_then(PropertyValue::as_f64),
+            Some(4.5)
+        );
+        assert_eq!(note.blocks.len(), 1);
+
+        let mut renamed = note.clone();
+        renamed.title = "Monthly review".into();
+        let serialized = String::from_utf8(MarkdownFormat::default().serialize(&renamed)).unwrap();
+        assert!(serialized.starts_with("---\ntitle: Monthly review\ntags:\n  - work\n"));
+        assert!(!serialized.contains("# Monthly review"));
+
+        let reparsed = MarkdownFormat::default()
+            .deserialize(serialized.as_bytes(), Some("weekly.md"))
+            .unwrap();
+        renamed
+            .properties
+            .insert("title", PropertyValue::text("Monthly review"));
+        assert_eq!(reparsed, renamed);
+    }
+
+    #[test]
+    fn test_malformed_front_matter_is_an_error() {
+        let result =
+            MarkdownFormat::default().deserialize(b"---\ntags: [a, b\n---\nBody", Some("bad.md"));
+
+        assert!(matches!(
+            result,
+            Err(SerializationError::DeserializationFailed(message)) if message.contains("line 1")
+        ));
+    }
+
+    fn text(text: &str) -> Inline {
+        Inline::Text { text: text.into() }
+    }
+
+    fn link(text: &str, target: &str) -> Inline {
+        Inline::Link {
+            text: vec![self::text(text)],
+            target: target.into(),
+        }
+    }
+
+    #[test]
+    fn test_gfm_table_alignments() {
+        let input = "| a | b | c |\n| :-- | :-: | --: |\n| 1 | 2 \\| 3 |\n";
+        let blocks = parse_blocks(input);
+
+        let Some(ContainerBlock::Table {
+            headers,
+            rows,
+            alignments,
+            ..
+        }) = blocks[0].as_table()
+        else {
+            panic!("expected a table, got {blocks:?}");
+        };
+        assert_eq!(headers.len(), 3);
+        assert_eq!(
+            alignments,
+            Some(vec![Alignment::Left, Alignment::Center, Alignment::Right])
+        );
+        assert_eq!(
+            rows,
+            vec![vec![vec![text("1")], vec![text("2 | 3")], vec![]]]
+        );
+
+        let serialized = serialize_blocks(&blocks);
+        assert!(serialized.contains("| --- | :---: | ---: |\n"));
+        assert_eq!(parse_blocks(&serialized), blocks);
+    }
+
+    #[test]
+    fn test_gfm_autolinks() {
+        assert_eq!(
+            parse_inlines("See www.example.com/a_b, (https://example.org/x).\nOr me@example.com."),
+            vec![
+                text("See "),
+                link("www.example.com/a_b", "http://www.example.com/a_b"),
+                text(", ("),
+                link("https://example.org/x", "https://example.org/x"),
+                text(").\nOr "),
+                link("me@example.com", "mailto:me@example.com"),
+                text("."),
+            ]
+        );
+        assert_eq!(
+            parse_inlines("page#www.example.com and [www.a.com](b)"),
+            vec![text("page#www.example.com and "), link("www.a.com", "b")]
+        );
+    }
+
+    #[test]
+    fn test_gfm_strikethrough() {
+        let strike = |inner: &str| Inline::Strikethrough {
+            content: vec![text(inner)],
+        };
+        assert_eq!(
+            parse_inlines("~one~ ~~two~~ ~~~three~~~ ~~four~"),
+            vec![
+                strike("one"),
+                text(" "),
+                strike("two"),
+                text(" ~~~three~~~ ~~four~"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gfm_footnotes() {
+        let format = MarkdownFormat::default();
+        let note = format
+            .deserialize(
+                b"Claim[^1].\n\n[^1]: Source\n    page 4\n\n    Second\n",
+                Some("n"),
+            )
+            .unwrap();
+
+        assert_eq!(
+            note.blocks,
+            vec![
+                Block::paragraph(vec![
+                    text("Claim"),
+                    Inline::FootnoteReference { label: "1".into() },
+                    text("."),
+                ]),
+                Block::footnote_definition(
+                    "1".into(),
+                    vec![
+                        Block::paragraph(vec![text("Source\npage 4")]),
+                        Block::paragraph(vec![text("Second")]),
+                    ]
+                ),
+            ]
+        );
+        let round_trip = format
+            .deserialize(&format.serialize(&note), Some("n"))
+            .unwrap();
+        assert_eq!(round_trip, note);
+    }
+
+    #[test]
+    fn test_gfm_alerts() {
+        let input = "> [!WARNING]\n> Mind the gap\n>\n> - left\n";
+        let blocks = parse_blocks(input);
+
+        assert_eq!(
+            blocks,
+            vec![Block::div(
+                vec!["alert".into(), "warning".into()],
+                Vec::new(),
+                vec![
+                    Block::paragraph(vec![text("Mind the gap")]),
+                    Block::list(
+                        ListStyle::Unordered { bullet: b'-' },
+                        [ListItem::new(vec![Block::paragraph(vec![text("left")])])]
+                    ),
+                ]
+            )]
+        );
+        assert_eq!(serialize_blocks(&blocks), input);
+        assert!(parse_blocks("> [!NOTICE]\n> Not an alert")[0].is_quote());
+    }
+
+    #[test]
+    fn test_gfm_extensions_can_be_turned_off() {
+        let format = MarkdownFormat {
+            gfm: GfmOptions::none(),
+        };
+        let input = "a | b\n--- | ---\n\n- [x] done ~~no~~ www.example.com[^1]\n\n> [!NOTE]\n";
+        let note = format.deserialize(input.as_bytes(), Some("n")).unwrap();
+
+        assert_eq!(
+            note.blocks,
+            vec![
+                Block::paragraph(vec![text("a | b\n--- | ---")]),
+                Block::list(
+                    ListStyle::Unordered { bullet: b'-' },
+                    [ListItem::new(vec![Block::paragraph(vec![text(
+                        "[x] done ~~no~~ www.example.com[^1]"
+                    )])])]
+                ),
+                Block::quote(vec![Block::paragraph(vec![text("[!NOTE]")])]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dollar_amounts_are_not_math() {
+        assert_eq!(
+            parse_inlines("costs $5 and $10"),
+            vec![text("costs $5 and $10")]
+        );
+        assert_eq!(
+            parse_inlines("$x^2$ and $ y $ or $3$4"),
+            vec![
+                Inline::Math {
+                    content: "x^2".into()
+                },
+                text(" and $ y $ or $3$4"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unmatched_delimiters_are_text() {
+        assert_eq!(
+            parse_inlines("a * b *c and **d* \\*e\\* _f"),
+            vec![
+                text("a * b *c and *"),
+                Inline::Italic {
+                    content: vec![text("d")]
+                },
+                text(" *e* _f"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_text_is_only_escaped_where_it_would_be_markup() {
+        let plain = "snake_case, a_b, $5 or $10, 2*3, C:\\Users, 1 < 2, [sic] and wow!";
+        assert_eq!(serialize_inlines(&[text(plain)]), plain);
+
+        assert_eq!(
+            serialize_inlines(&[text("*not italic* `not code` $not math$ [not](a link)")]),
+            "\\*not italic* \\`not code` \\$not math$ \\[not](a link)"
+        );
+        assert_eq!(
+            serialize_inlines(&[
+                text("wow!"),
+                Inline::Link {
+                    text: vec![text("a ] and a *")],
+                    target: "x".to_string(),
+                },
+            ]),
+            "wow\\![a \\] and a \\*](x)"
+        );
+    }
+
+    #[test]
+    fn test_text_round_trips_through_escaping() {
+        let texts = [
+            "*not bold* and __not either__",
+            "snake_case \\path\\ `tick` ~~no~~",
+            "[x] not a task, [link](target) ![image](src) [^1] [[wiki]]",
+            "# not a heading",
+            "- not a list\n+ nor this\n1. nor this\n2) nor this",
+            "> not a quote\n---\n===\n***",
+            "costs $5, $x$ and $$",
+            "<b>html</b> &amp; &copy; & co",
+            "www.example.com, https://example.org and me@example.com",
+            "line one\n  indented\n\nafter a blank line\n    and code",
+            "trailing  \nspaces and a tab\t\nhere",
+            "a #tag, issue #42, C# and the end ^block-id",
+            "wow!",
+        ];
+        for text in texts {
+            let inlines = vec![self::text(text)];
+            let serialized = serialize_inlines(&inlines);
+            assert_eq!(parse_inlines(&serialized), inlines, "{serialized:?}");
+
+            let blocks = vec![Block::paragraph(inlines)];
+            let serialized = serialize_blocks(&blocks);
+            assert_eq!(parse_blocks(&serialized), blocks, "{serialized:?}");
+        }
+    }
 }
@@ -0,0 +1,904 @@
+//! CommonMark block structure
+//!
+//! Follows the two-phase strategy from the spec's appendix: lines are fed
+//! one at a time into a tree of open blocks, each line either continuing the
+//! open blocks, starting new ones or adding text to the innermost one, and
+//! the inline content of the finished leaves is parsed once every link
+//! reference definition is known.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::{
+    ALERT_KINDS, GfmOptions,
+    inlines::{self, HTML_TAG, References},
+    parse_block_inlines, parse_table, table_cells,
+};
+use crate::models::{
+    Block, Checkbox, Inline, ListItem, ListStyle, Numbering, NumberingStyle, NumberingType,
+};
+
+const TAB_STOP: usize = 4;
+const CODE_INDENT: usize = 4;
+
+static ATX_HEADING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^#{1,6}(?:[ \t]+|$)").unwrap());
+static CODE_FENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:`{3,}|~{3,})").unwrap());
+static CLOSING_FENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:`{3,}|~{3,})[ \t]*$").unwrap());
+static SETEXT_UNDERLINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(?:=+|-+)[ \t]*$").unwrap());
+static THEMATIC_BREAK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:(?:\*[ \t]*){3,}|(?:_[ \t]*){3,}|(?:-[ \t]*){3,})$").unwrap()
+});
+static ORDERED_MARKER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([0-9]{1,9})([.)])").unwrap());
+static FOOTNOTE_DEFINITION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\[\^([^\]\s]+)\]:").unwrap());
+static DELIMITER_ROW: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\|?(?:[ \t]*:?-+:?[ \t]*\|)*[ \t]*:?-+:?[ \t]*\|?[ \t]*$").unwrap()
+});
+
+/// How each of the seven kinds of HTML block starts
+static HTML_OPEN: LazyLock<[Regex; 7]> = LazyLock::new(|| {
+    [
+        r"(?i)^<(?:script|pre|textarea|style)(?:\s|>|$)".to_string(),
+        "^<!--".to_string(),
+        r"^<\?".to_string(),
+        "^<![A-Za-z]".to_string(),
+        r"^<!\[CDATA\[".to_string(),
+        r"(?i)^</?(?:address|article|aside|base|basefont|blockquote|body|caption|center|col|colgroup|dd|details|dialog|dir|div|dl|dt|fieldset|figcaption|figure|footer|form|frame|frameset|h[1-6]|head|header|hr|html|iframe|legend|li|link|main|menu|menuitem|nav|noframes|ol|optgroup|option|p|param|search|section|summary|table|tbody|td|tfoot|th|thead|title|tr|track|ul)(?:\s|/?>|$)".to_string(),
+        format!(r"^(?:{})[ \t]*$", *HTML_TAG),
+    ]
+    .map(|pattern| Regex::new(&pattern).unwrap())
+});
+
+/// How the first five kinds of HTML block end; the others end at a blank line
+static HTML_CLOSE: LazyLock<[Regex; 5]> = LazyLock::new(|| {
+    [
+        r"(?i)</(?:script|pre|textarea|style)>",
+        "-->",
+        r"\?>",
+        ">",
+        r"\]\]>",
+    ]
+    .map(|pattern| Regex::new(pattern).unwrap())
+});
+
+/// Parse a Markdown document into blocks, with the GFM extensions `gfm`
+/// turns on
+pub fn parse(input: &str, gfm: &GfmOptions) -> Vec<Block> {
+    let input = input.replace('\0', "\u{FFFD}");
+    let mut parser = BlockParser::new(gfm);
+    let mut lines = input.split_inclusive(['\n', '\r']).peekable();
+    while let Some(mut line) = lines.next() {
+        if line.ends_with('\r') && lines.peek() == Some(&"\n") {
+            lines.next();
+        }
+        line = line.trim_end_matches(['\n', '\r']);
+        parser.incorporate_line(line);
+    }
+    while parser.tip != DOCUMENT {
+        parser.finalize(parser.tip);
+    }
+    parser.finalize(DOCUMENT);
+    parser.blocks(DOCUMENT)
+}
+
+const DOCUMENT: usize = 0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+    /// `-`, `+` or `*`
+    Bullet(u8),
+    /// `.` or `)` after the number
+    Ordered(u8),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fence {
+    char: u8,
+    len: usize,
+    indent: usize,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Document,
+    Quote,
+    List {
+        marker: Marker,
+    },
+    /// `indent` is the column the item's content starts at
+    Item {
+        indent: usize,
+        checkbox: Option<Checkbox>,
+    },
+    /// `[^label]:`, whose content is indented by four columns
+    Footnote {
+        label: String,
+    },
+    Paragraph,
+    Heading {
+        level: u8,
+    },
+    CodeBlock {
+        fence: Option<Fence>,
+        info: String,
+    },
+    MathBlock,
+    Html {
+        kind: usize,
+    },
+    ThematicBreak,
+    Table,
+}
+
+impl Kind {
+    const fn accepts_lines(&self) -> bool {
+        matches!(
+            self,
+            Self::Paragraph
+                | Self::CodeBlock { .. }
+                | Self::MathBlock
+                | Self::Html { .. }
+                | Self::Table
+        )
+    }
+
+    const fn can_contain(&self, child: &Self) -> bool {
+        match self {
+            Self::Document | Self::Quote | Self::Item { .. } | Self::Footnote { .. } => {
+                !matches!(child, Self::Item { .. })
+            }
+            Self::List { .. } => matches!(child, Self::Item { .. }),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    kind: Kind,
+    parent: usize,
+    children: Vec<usize>,
+    open: bool,
+    content: String,
+}
+
+/// Whether an open block goes on through the current line
+enum Continuation {
+    Matched,
+    Failed,
+    /// The line closed the block and there is nothing left of it
+    Consumed,
+}
+
+/// What the start of the rest of the line opened
+enum Start {
+    Container,
+    Leaf,
+    /// The line opened a block and there is nothing left of it
+    Consumed,
+}
+
+#[derive(Debug)]
+struct BlockParser<'a> {
+    gfm: &'a GfmOptions,
+    nodes: Vec<Node>,
+    references: References,
+    /// The innermost open block
+    tip: usize,
+    /// The tip before the current line
+    old_tip: usize,
+    last_matched: usize,
+    all_closed: bool,
+
+    line: &'a str,
+    offset: usize,
+    column: usize,
+    partially_consumed_tab: bool,
+    next_nonspace: usize,
+    next_nonspace_column: usize,
+    indent: usize,
+    blank: bool,
+}
+
+impl<'a> BlockParser<'a> {
+    fn new(gfm: &'a GfmOptions) -> Self {
+        Self {
+            gfm,
+            nodes: vec![Node {
+                kind: Kind::Document,
+                parent: DOCUMENT,
+                children: Vec::new(),
+                open: true,
+                content: String::new(),
+            }],
+            references: References::new(),
+            tip: DOCUMENT,
+            old_tip: DOCUMENT,
+            last_matched: DOCUMENT,
+            all_closed: true,
+            line: "",
+            offset: 0,
+            column: 0,
+            partially_consumed_tab: false,
+            next_nonspace: 0,
+            next_nonspace_column: 0,
+            indent: 0,
+            blank: false,
+        }
+    }
+
+    fn incorporate_line(&mut self, line: &'a str) {
+        self.line = line;
+        self.offset = 0;
+        self.column = 0;
+        self.partially_consumed_tab = false;
+        self.old_tip = self.tip;
+
+        // Which open blocks does the line continue?
+        let mut container = DOCUMENT;
+        while let Some(&child) = self.nodes[container].children.last()
+            && self.nodes[child].open
+        {
+            self.find_next_nonspace();
+            match self.continues(child) {
+                Continuation::Matched => container = child,
+                Continuation::Failed => break,
+                Continuation::Consumed => return,
+            }
+        }
+        self.all_closed = container == self.old_tip;
+        self.last_matched = container;
+
+        // Does the rest of it start new blocks?
+        let kind = &self.nodes[container].kind;
+        let mut matched_leaf =
+            !matches!(kind, Kind::Paragraph | Kind::Table) && kind.accepts_lines();
+        while !matched_leaf {
+            self.find_next_nonspace();
+            match self.start(container) {
+                Some(Start::Container) => container = self.tip,
+                Some(Start::Leaf) => {
+                    container = self.tip;
+                    matched_leaf = true;
+                }
+                Some(Start::Consumed) => return,
+                None => {
+                    self.advance_next_nonspace();
+                    break;
+                }
+            }
+        }
+
+        // What's left is text, either a lazy continuation of a paragraph or
+        // content for the innermost block
+        if !self.all_closed && !self.blank && matches!(self.nodes[self.tip].kind, Kind::Paragraph) {
+            self.add_line();
+            return;
+        }
+        self.close_unmatched_blocks();
+        if self.nodes[container].kind.accepts_lines() {
+            self.add_line();
+            if let Kind::Html { kind: kind @ 1..=5 } = self.nodes[container].kind
+                && HTML_CLOSE[kind - 1].is_match(&self.line[self.offset..])
+            {
+                self.finalize(container);
+            }
+        } else if self.offset < self.line.len() && !self.blank {
+            self.add_child(Kind::Paragraph);
+            self.advance_next_nonspace();
+            self.add_line();
+        }
+    }
+
+    fn continues(&mut self, id: usize) -> Continuation {
+        let byte = self.line.as_bytes().get(self.next_nonspace).copied();
+        let matched = match self.nodes[id].kind {
+            Kind::Document | Kind::List { .. } => true,
+            Kind::Quote => {
+                let quoted = self.indent < CODE_INDENT && byte == Some(b'>');
+                if quoted {
+                    self.advance_next_nonspace();
+                    self.advance_offset(1, false);
+                    if self.at_space_or_tab() {
+                        self.advance_offset(1, true);
+                    }
+                }
+                quoted
+            }
+            Kind::Item { indent, .. } => {
+                if self.blank {
+                    // An item can start with at most one blank line
+                    let started = !self.nodes[id].children.is_empty();
+                    if started {
+                        self.advance_next_nonspace();
+                    }
+                    started
+                } else if self.indent >= indent {
+                    self.advance_offset(indent, true);
+                    true
+                } else {
+                    false
+                }
+            }
+            Kind::CodeBlock {
+                fence: Some(fence), ..
+            } => {
+                let rest = &self.line[self.next_nonspace..];
+                if self.indent < CODE_INDENT
+                    && byte == Some(fence.char)
+                    && CLOSING_FENCE.is_match(rest)
+                    && rest.trim_end().len() >= fence.len
+                    && rest.trim_end().bytes().all(|b| b == fence.char)
+                {
+                    self.finalize(id);
+                    return Continuation::Consumed;
+                }
+                for _ in 0..fence.indent {
+                    if !self.at_space_or_tab() {
+                        break;
+                    }
+                    self.advance_offset(1, true);
+                }
+                true
+            }
+            Kind::CodeBlock { fence: None, .. } => {
+                if self.indent >= CODE_INDENT {
+                    self.advance_offset(CODE_INDENT, true);
+                    true
+                } else if self.blank {
+                    self.advance_next_nonspace();
+                    true
+                } else {
+                    false
+                }
+            }
+            Kind::MathBlock => {
+                let rest = self.line[self.offset..].trim_end();
+                if let Some(last) = rest.strip_suffix("$$") {
+                    if !last.trim().is_empty() {
+                        let node = &mut self.nodes[id];
+                        node.content.push_str(last);
+                        node.content.push('\n');
+                    }
+                    self.finalize(id);
+                    return Continuation::Consumed;
+                }
+                true
+            }
+            Kind::Footnote { .. } => {
+                if self.blank {
+                    self.advance_next_nonspace();
+                    true
+                } else if self.indent >= CODE_INDENT {
+                    self.advance_offset(CODE_INDENT, true);
+                    true
+                } else {
+                    false
+                }
+            }
+            Kind::Html { kind } => !(self.blank && kind >= 6),
+            Kind::Paragraph | Kind::Table => !self.blank,
+            Kind::Heading { .. } | Kind::ThematicBreak => false,
+        };
+        if matched {
+            Continuation::Matched
+        } else {
+            Continuation::Failed
+        }
+    }
+
+    /// Try each kind of block start on the rest of the line, in order of
+    /// precedence
+    fn start(&mut self, container: usize) -> Option<Start> {
+        let rest = &self.line[self.next_nonspace..];
+        let indented = self.indent >= CODE_INDENT;
+        let in_paragraph = matches!(self.nodes[container].kind, Kind::Paragraph);
+
+        if !indented {
+            if self.gfm.footnotes
+                && let Some(captures) = FOOTNOTE_DEFINITION.captures(rest)
+            {
+                let label = captures[1].to_string();
+                self.advance_next_nonspace();
+                self.advance_offset(captures[0].len(), false);
+                self.close_unmatched_blocks();
+                self.add_child(Kind::Footnote { label });
+                return Some(Start::Container);
+            }
+
+            if rest.starts_with('>') {
+                self.advance_next_nonspace();
+                self.advance_offset(1, false);
+                if self.at_space_or_tab() {
+                    self.advance_offset(1, true);
+                }
+                self.close_unmatched_blocks();
+                self.add_child(Kind::Quote);
+                return Some(Start::Container);
+            }
+
+            if let Some(marker) = ATX_HEADING.find(rest) {
+                let level = rest.bytes().take_while(|&b| b == b'#').count();
+                self.advance_next_nonspace();
+                self.advance_offset(marker.len(), false);
+                self.close_unmatched_blocks();
+                let id = self.add_child(Kind::Heading {
+                    level: u8::try_from(level).unwrap(),
+                });
+                self.nodes[id].content = strip_closing_sequence(&self.line[self.offset..]).into();
+                self.offset = self.line.len();
+                return Some(Start::Leaf);
+            }
+
+            if let Some(fence) = CODE_FENCE.find(rest) {
+                let info = &rest[fence.end()..];
+                if !(rest.starts_with('`') && info.contains('`')) {
+                    let fence = Fence {
+                        char: rest.as_bytes()[0],
+                        len: fence.len(),
+                        indent: self.indent,
+                    };
+                    self.close_unmatched_blocks();
+                    self.add_child(Kind::CodeBlock {
+                        fence: Some(fence),
+                        info: inlines::unescape(info.trim()),
+                    });
+                    return Some(Start::Consumed);
+                }
+            }
+
+            if let Some(first) = rest.strip_prefix("$$") {
+                self.close_unmatched_blocks();
+                let id = self.add_child(Kind::MathBlock);
+                let first = first.trim_end();
+                if let Some(content) = first.strip_suffix("$$") {
+                    self.nodes[id].content = content.to_string();
+                    self.finalize(id);
+                } else if !first.trim().is_empty() {
+                    self.nodes[id].content = format!("{first}\n");
+                }
+                return Some(Start::Consumed);
+            }
+
+            if rest.starts_with('<') {
+                // An HTML block of the last kind may not interrupt a paragraph,
+                // not even lazily
+                let lazy = !self.all_closed
+                    && !self.blank
+                    && matches!(self.nodes[self.tip].kind, Kind::Paragraph);
+                let kind = (1..=7).find(|&kind| {
+                    HTML_OPEN[kind - 1].is_match(rest) && (kind < 7 || !(in_paragraph || lazy))
+                });
+                if let Some(kind) = kind {
+                    self.close_unmatched_blocks();
+                    self.add_child(Kind::Html { kind });
+                    return Some(Start::Leaf);
+                }
+            }
+
+            if in_paragraph && SETEXT_UNDERLINE.is_match(rest) {
+                self.close_unmatched_blocks();
+                self.extract_references(container);
+                if !self.nodes[container].content.is_empty() {
+                    let level = if rest.starts_with('=') { 1 } else { 2 };
+                    self.nodes[container].kind = Kind::Heading { level };
+                    self.offset = self.line.len();
+                    return Some(Start::Leaf);
+                }
+            }
+
+            if in_paragraph && self.starts_table(container, rest) {
+                self.close_unmatched_blocks();
+                self.nodes[container].kind = Kind::Table;
+                self.advance_next_nonspace();
+                self.add_line();
+                return Some(Start::Consumed);
+            }
+
+            if THEMATIC_BREAK.is_match(rest) {
+                self.close_unmatched_blocks();
+                self.add_child(Kind::ThematicBreak);
+                self.offset = self.line.len();
+                return Some(Start::Leaf);
+            }
+        }
+
+        if let Some((marker, indent, checkbox)) = self.list_marker(in_paragraph) {
+            self.close_unmatched_blocks();
+            let continues_list = matches!(
+                self.nodes[self.tip].kind,
+                Kind::List { marker: list } if list == marker
+            );
+            if !continues_list {
+                self.add_child(Kind::List { marker });
+            }
+            self.add_child(Kind::Item { indent, checkbox });
+            return Some(Start::Container);
+        }
+
+        if indented && !self.blank && !matches!(self.nodes[self.tip].kind, Kind::Paragraph) {
+            self.advance_offset(CODE_INDENT, true);
+            self.close_unmatched_blocks();
+            self.add_child(Kind::CodeBlock {
+                fence: None,
+                info: String::new(),
+            });
+            return Some(Start::Leaf);
+        }
+
+        None
+    }
+
+    /// A list marker at the next non-space character, with the column the
+    /// item's content starts at and its checkbox, if any
+    ///
+    /// Consumes the marker and the spaces after it.
+    fn list_marker(&mut self, in_paragraph: bool) -> Option<(Marker, usize, Option<Checkbox>)> {
+        if self.indent >= CODE_INDENT {
+            return None;
+        }
+        let rest = &self.line[self.next_nonspace..];
+        let (marker, len) = if let Some(&bullet @ (b'-' | b'+' | b'*')) = rest.as_bytes().first() {
+            (Marker::Bullet(bullet), 1)
+        } else {
+            let captures = ORDERED_MARKER.captures(rest)?;
+            // Only a list starting at 1 may interrupt a paragraph
+            if in_paragraph && &captures[1] != "1" {
+                return None;
+            }
+            (
+                Marker::Ordered(captures[2].as_bytes()[0]),
+                captures[0].len(),
+            )
+        };
+        if !matches!(rest.as_bytes().get(len), None | Some(b' ' | b'\t')) {
+            return None;
+        }
+        if in_paragraph && rest[len..].trim_matches([' ', '\t']).is_empty() {
+            return None;
+        }
+
+        let marker_offset = self.indent;
+        self.advance_next_nonspace();
+        self.advance_offset(len, true);
+        let spaces_start_column = self.column;
+        let spaces_start_offset = self.offset;
+        loop {
+            self.advance_offset(1, true);
+            if self.column - spaces_start_column >= 5 || !self.at_space_or_tab() {
+                break;
+            }
+        }
+        let spaces = self.column - spaces_start_column;
+        let blank_item = self.offset >= self.line.len();
+        let padding = if !(1..5).contains(&spaces) || blank_item {
+            // Content indented by five or more columns is indented code
+            // inside an item that starts one space after the marker
+            self.column = spaces_start_column;
+            self.offset = spaces_start_offset;
+            self.partially_consumed_tab = false;
+            if self.at_space_or_tab() {
+                self.advance_offset(1, true);
+            }
+            len + 1
+        } else {
+            len + spaces
+        };
+
+        let rest = &self.line[self.offset..];
+        let checkbox = Checkbox::strip_from(rest)
+            .filter(|_| self.gfm.task_lists)
+            .map(|(checkbox, after)| {
+                self.advance_offset(rest.len() - after.len(), false);
+                checkbox
+            });
+        Some((marker, marker_offset + padding, checkbox))
+    }
+
+    /// Whether the line is the delimiter row of a table whose header is the
+    /// one-line paragraph above it
+    fn starts_table(&self, paragraph: usize, rest: &str) -> bool {
+        let header = self.nodes[paragraph].content.trim_end_matches('\n');
+        self.gfm.tables
+            && !header.contains('\n')
+            && header.contains('|')
+            && rest.contains('|')
+            && DELIMITER_ROW.is_match(rest)
+            && table_cells(header).len() == table_cells(rest).len()
+    }
+
+    fn add_child(&mut self, kind: Kind) -> usize {
+        while !self.nodes[self.tip].kind.can_contain(&kind) {
+            self.finalize(self.tip);
+        }
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            kind,
+            parent: self.tip,
+            children: Vec::new(),
+            open: true,
+            content: String::new(),
+        });
+        self.nodes[self.tip].children.push(id);
+        self.tip = id;
+        id
+    }
+
+    /// Add the rest of the line to the innermost block
+    fn add_line(&mut self) {
+        let content = &mut self.nodes[self.tip].content;
+        if self.partially_consumed_tab {
+            self.offset += 1;
+            let columns = TAB_STOP - self.column % TAB_STOP;
+            content.push_str(&" ".repeat(columns));
+        }
+        content.push_str(&self.line[self.offset..]);
+        content.push('\n');
+    }
+
+    /// Close the blocks the line did not continue
+    fn close_unmatched_blocks(&mut self) {
+        if !self.all_closed {
+            while self.old_tip != self.last_matched {
+                let parent = self.nodes[self.old_tip].parent;
+                self.finalize(self.old_tip);
+                self.old_tip = parent;
+            }
+            self.all_closed = true;
+        }
+    }
+
+    fn finalize(&mut self, id: usize) {
+        let parent = self.nodes[id].parent;
+        self.nodes[id].open = false;
+        match self.nodes[id].kind {
+            Kind::Paragraph => {
+                self.extract_references(id);
+                if self.nodes[id].content.trim().is_empty() {
+                    self.nodes[parent].children.retain(|&child| child != id);
+                }
+            }
+            Kind::CodeBlock { fence: None, .. } => {
+                // Trailing blank lines are not part of the block
+                let content = &mut self.nodes[id].content;
+                let lines: Vec<&str> = content.split_inclusive('\n').collect();
+                let end = lines
+                    .iter()
+                    .rposition(|line| !line.trim_matches([' ', '\t', '\n']).is_empty())
+                    .map_or(0, |last| last + 1);
+                let len = lines[..end].iter().map(|line| line.len()).sum();
+                content.truncate(len);
+            }
+            _ => {}
+        }
+        self.tip = parent;
+    }
+
+    /// Move the link reference definitions at the start of a paragraph into
+    /// [`Self::references`]; the first definition of a label wins
+    fn extract_references(&mut self, paragraph: usize) {
+        let content = &self.nodes[paragraph].content;
+        let mut start = 0;
+        while let Some((label, destination, end)) = reference_definition(content, start) {
+            self.references.entry(label).or_insert(destination);
+            start = end;
+        }
+        if start > 0 {
+            let rest = content[start..].to_string();
+            self.nodes[paragraph].content = rest;
+        }
+    }
+
+    fn find_next_nonspace(&mut self) {
+        let mut i = self.offset;
+        let mut column = self.column;
+        for byte in self.line[self.offset..].bytes() {
+            match byte {
+                b' ' => column += 1,
+                b'\t' => column += TAB_STOP - column % TAB_STOP,
+                _ => break,
+            }
+            i += 1;
+        }
+        self.blank = i == self.line.len();
+        self.next_nonspace = i;
+        self.next_nonspace_column = column;
+        self.indent = column - self.column;
+    }
+
+    fn advance_next_nonspace(&mut self) {
+        self.offset = self.next_nonspace;
+        self.column = self.next_nonspace_column;
+        self.partially_consumed_tab = false;
+    }
+
+    /// Move `count` bytes along the line, or `count` columns with `columns`
+    /// set, in which case a tab may only be partially consumed
+    fn advance_offset(&mut self, count: usize, columns: bool) {
+        let mut count = count;
+        while count > 0
+            && let Some(&byte) = self.line.as_bytes().get(self.offset)
+        {
+            if byte == b'\t' {
+                let to_tab = TAB_STOP - self.column % TAB_STOP;
+                if columns {
+                    self.partially_consumed_tab = to_tab > count;
+                    let advance = to_tab.min(count);
+                    self.column += advance;
+                    if !self.partially_consumed_tab {
+                        self.offset += 1;
+                    }
+                    count -= advance;
+                } else {
+                    self.partially_consumed_tab = false;
+                    self.column += to_tab;
+                    self.offset += 1;
+                    count -= 1;
+                }
+            } else {
+                self.partially_consumed_tab = false;
+                self.offset += 1;
+                self.column += 1;
+                count -= 1;
+            }
+        }
+    }
+
+    fn at_space_or_tab(&self) -> bool {
+        matches!(self.line.as_bytes().get(self.offset), Some(b' ' | b'\t'))
+    }
+
+    fn blocks(&self, id: usize) -> Vec<Block> {
+        self.nodes[id]
+            .children
+            .iter()
+            .filter_map(|&child| self.block(child))
+            .collect()
+    }
+
+    fn block(&self, id: usize) -> Option<Block> {
+        let node = &self.nodes[id];
+        let block = match &node.kind {
+            Kind::Document | Kind::Item { .. } => return None,
+            Kind::Quote => self
+                .alert(id)
+                .unwrap_or_else(|| Block::quote(self.blocks(id))),
+            Kind::Footnote { label } => Block::footnote_definition(label.clone(), self.blocks(id)),
+            Kind::List { marker } => {
+                let style = match marker {
+                    Marker::Bullet(bullet) => ListStyle::Unordered { bullet: *bullet },
+                    Marker::Ordered(delimiter) => ListStyle::Ordered {
+                        numbering: Numbering {
+                            kind: NumberingType::Decimal,
+                            style: if *delimiter == b')' {
+                                NumberingStyle::Paren
+                            } else {
+                                NumberingStyle::Dot
+                            },
+                        },
+                    },
+                };
+                let items = node.children.iter().map(|&item| {
+                    let checkbox = match self.nodes[item].kind {
+                        Kind::Item { checkbox, .. } => checkbox,
+                        _ => None,
+                    };
+                    ListItem {
+                        checkbox,
+                        blocks: self.blocks(item),
+                    }
+                });
+                Block::list(style, items)
+            }
+            Kind::Paragraph => self.paragraph(&node.content),
+            Kind::Heading { level } => Block::heading(
+                *level,
+                inlines::parse(node.content.trim(), &self.references, self.gfm, false),
+            ),
+            Kind::CodeBlock { info, .. } => {
+                let language = info.split_whitespace().next().map(ToString::to_string);
+                let content = node.content.strip_suffix('\n').unwrap_or(&node.content);
+                Block::code_block(language, content.to_string())
+            }
+            Kind::MathBlock => Block::math_block(node.content.trim().to_string()),
+            Kind::Html { .. } => Block::html(node.content.trim_end().to_string()),
+            Kind::ThematicBreak => Block::horizontal_rule(),
+            Kind::Table => parse_table(&node.content, &self.references, self.gfm)?,
+        };
+        Some(block)
+    }
+
+    /// A paragraph, or an image block if the image is all there is to it
+    fn paragraph(&self, content: &str) -> Block {
+        let inlines = parse_block_inlines(content.trim(), &self.references, self.gfm);
+        match inlines.as_slice() {
+            [Inline::Image { alt_text, src }] => Block::image(alt_text.clone(), src.clone()),
+            _ => Block::paragraph(inlines),
+        }
+    }
+
+    /// The quote as an alert if its first line is `[!NOTE]`, `[!TIP]`,
+    /// `[!IMPORTANT]`, `[!WARNING]` or `[!CAUTION]`
+    fn alert(&self, quote: usize) -> Option<Block> {
+        if !self.gfm.alerts {
+            return None;
+        }
+        let (&first, rest) = self.nodes[quote].children.split_first()?;
+        let Kind::Paragraph = self.nodes[first].kind else {
+            return None;
+        };
+        let content = &self.nodes[first].content;
+        let (line, text) = content.split_once('\n').unwrap_or((content, ""));
+        let kind = line
+            .trim()
+            .strip_prefix("[!")?
+            .strip_suffix(']')?
+            .to_lowercase();
+        if !ALERT_KINDS.contains(&kind.as_str()) {
+            return None;
+        }
+
+        let mut children = Vec::new();
+        if !text.trim().is_empty() {
+            children.push(self.paragraph(text));
+        }
+        children.extend(rest.iter().filter_map(|&child| self.block(child)));
+        Some(Block::div(
+            vec!["alert".to_string(), kind],
+            Vec::new(),
+            children,
+        ))
+    }
+}
+
+/// The text of an ATX heading without its optional closing `#`s
+fn strip_closing_sequence(text: &str) -> &str {
+    let text = text.trim_end_matches([' ', '\t']);
+    let without = text.trim_end_matches('#');
+    if without.is_empty() {
+        ""
+    } else if without.ends_with([' ', '\t']) {
+        without.trim_end_matches([' ', '\t'])
+    } else {
+        text
+    }
+}
+
+/// A link reference definition, `[label]: destination "title"`, at `start`:
+/// the normalized label, the destination and where the definition ends
+fn reference_definition(text: &str, start: usize) -> Option<(String, String, usize)> {
+    let (label, end) = inlines::link_label(text, start)?;
+    let label = inlines::normalize_label(label);
+    if label.is_empty() || !text[end..].starts_with(':') {
+        return None;
+    }
+
+    let pos = inlines::skip_whitespace(text, end + 1);
+    let (destination, pos) = inlines::link_destination(text, pos)?;
+    let before_title = pos;
+    let title_start = inlines::skip_whitespace(text, pos);
+    if title_start > before_title
+        && let Some((_, after_title)) = inlines::link_title(text, title_start)
+        && let Some(end) = line_end(text, after_title)
+    {
+        return Some((label, destination, end));
+    }
+    let end = line_end(text, before_title)?;
+    Some((label, destination, end))
+}
+
+/// The offset after the line ending at `pos`, if only spaces are left on
+/// the line
+fn line_end(text: &str, pos: usize) -> Option<usize> {
+    let rest = &text[pos..];
+    let spaces = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    match rest[spaces..].chars().next() {
+        None => Some(text.len()),
+        Some('\n') => Some(pos + spaces + 1),
+        Some(_) => None,
+    }
+}
@@ -0,0 +1,966 @@
+//! CommonMark inline parsing
+//!
+//! The text is scanned once, left to right. Code spans, autolinks, raw HTML,
+//! escapes and entity references are resolved on the spot, while `*`, `_`
+//! and `~` runs and `[`/`![` brackets stay in place as markers until a
+//! closer shows up, following the spec's "An algorithm for parsing nested
+//! emphasis and links".
+
+use std::{collections::HashMap, sync::LazyLock};
+
+use regex::Regex;
+
+use super::GfmOptions;
+use crate::models::Inline;
+
+/// Link reference definitions, normalized label -> destination
+pub type References = HashMap<String, String>;
+
+const TAG_NAME: &str = "[A-Za-z][A-Za-z0-9-]*";
+const ATTRIBUTE: &str =
+    r#"(?:\s+[A-Za-z_:][A-Za-z0-9_.:-]*(?:\s*=\s*(?:[^\s"'=<>`]+|'[^']*'|"[^"]*"))?)"#;
+
+/// An open or closing HTML tag, see [`RAW_HTML`]
+pub static HTML_TAG: LazyLock<String> =
+    LazyLock::new(|| format!(r"<{TAG_NAME}{ATTRIBUTE}*\s*/?>|</{TAG_NAME}\s*>"));
+
+/// Inline raw HTML: a tag, comment, processing instruction, declaration or
+/// CDATA section
+static RAW_HTML: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"^(?:{}|<!-->|<!--->|<!--(?s:.*?)-->|<\?(?s:.*?)\?>|<![A-Za-z][^>]*>|<!\[CDATA\[(?s:.*?)\]\]>)",
+        *HTML_TAG
+    ))
+    .unwrap()
+});
+
+static URI_AUTOLINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^<([A-Za-z][A-Za-z0-9+.-]{1,31}:[^<>\x00-\x20]*)>").unwrap());
+
+static EMAIL_AUTOLINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"^<([a-zA-Z0-9.!#$%&'*+/=?^_`{|}~-]+@[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?(?:\.[a-zA-Z0-9](?:[a-zA-Z0-9-]{0,61}[a-zA-Z0-9])?)*)>",
+    )
+    .unwrap()
+});
+
+/// A bare URL, before its trailing punctuation is trimmed
+static URL_AUTOLINK: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(?:https?://|www\.)[A-Za-z0-9_-]+(?:\.[A-Za-z0-9_-]+)*[^\s<]*").unwrap()
+});
+
+/// Where a bare URL may start
+static URL_START: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"https?://|www\.").unwrap());
+
+/// The `@domain` of a bare email address
+static EMAIL_DOMAIN: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^@[A-Za-z0-9_-]+(?:\.[A-Za-z0-9_-]+)+").unwrap());
+
+static ENTITY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^&(?:#[xX]([0-9a-fA-F]{1,6})|#([0-9]{1,7})|([A-Za-z][A-Za-z0-9]{1,31}));").unwrap()
+});
+
+static NAMED_ENTITIES: LazyLock<HashMap<&'static str, &'static str>> = LazyLock::new(|| {
+    entities::ENTITIES
+        .iter()
+        .filter(|entity| entity.entity.ends_with(';'))
+        .map(|entity| (entity.entity, entity.characters))
+        .collect()
+});
+
+/// Characters that may start something other than plain text
+const SPECIAL: &[char] = &[
+    '\\', '`', '*', '_', '~', '[', '!', ']', '<', '&', '\n', '$', '#', '@',
+];
+
+/// Parse inline content, with the GFM extensions `gfm` turns on
+///
+/// `tags` turns `#tag` into [`Inline::Tag`]; headings leave it off so that
+/// `# Issue #42 and #next` keeps its text.
+pub fn parse(text: &str, references: &References, gfm: &GfmOptions, tags: bool) -> Vec<Inline> {
+    let mut parser = InlineParser {
+        text,
+        pos: 0,
+        references,
+        gfm,
+        tags,
+        nodes: Vec::new(),
+    };
+    parser.run();
+    process_emphasis(&mut parser.nodes);
+    finish(parser.nodes)
+}
+
+/// A piece of the parsed line: finished inline content, or a marker that
+/// may still become emphasis or a link
+enum Node {
+    Inline(Inline),
+    Delimiter(Delimiter),
+    /// `[` or `![`, with the offset of the link text
+    Bracket {
+        image: bool,
+        active: bool,
+        start: usize,
+    },
+}
+
+/// A run of `*`, `_` or `~`
+struct Delimiter {
+    char: char,
+    count: usize,
+    original: usize,
+    can_open: bool,
+    can_close: bool,
+}
+
+struct InlineParser<'a> {
+    text: &'a str,
+    pos: usize,
+    references: &'a References,
+    gfm: &'a GfmOptions,
+    tags: bool,
+    nodes: Vec<Node>,
+}
+
+impl InlineParser<'_> {
+    fn run(&mut self) {
+        while let Some(c) = self.peek() {
+            match c {
+                '\\' => self.backslash(),
+                '`' => self.code_span(),
+                '*' | '_' | '~' => self.delimiter_run(c),
+                '[' => self.open_bracket(),
+                '!' if self.text[self.pos + 1..].starts_with('[') => {
+                    self.pos += 2;
+                    self.nodes.push(Node::Bracket {
+                        image: true,
+                        active: true,
+                        start: self.pos,
+                    });
+                }
+                ']' => self.close_bracket(),
+                '<' => self.angle_bracket(),
+                '&' => self.entity(),
+                '\n' => self.line_ending(),
+                '$' => self.math(),
+                '#' if self.tags => self.tag(),
+                '@' if self.gfm.autolinks => self.email_autolink(),
+                'h' | 'w' if self.gfm.autolinks && self.url_autolink() => {}
+                _ => {
+                    let mut len = self.text[self.pos + c.len_utf8()..]
+                        .find(SPECIAL)
+                        .map_or(self.text.len() - self.pos, |i| i + c.len_utf8());
+                    // Stop where a bare URL may start
+                    if self.gfm.autolinks
+                        && let Some(start) = URL_START
+                            .find_iter(&self.text[self.pos..self.pos + len])
+                            .map(|found| found.start())
+                            .find(|&start| start > 0)
+                    {
+                        len = start;
+                    }
+                    self.push_text(&self.text[self.pos..self.pos + len]);
+                    self.pos += len;
+                }
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some(Node::Inline(Inline::Text { text: last })) = self.nodes.last_mut() {
+            last.push_str(text);
+        } else if !text.is_empty() {
+            self.nodes.push(Node::Inline(Inline::Text {
+                text: text.to_string(),
+            }));
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        let rest = &self.text[self.pos..];
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t']).len();
+    }
+
+    /// `\*` is a literal `*` and a backslash at the end of a line is a hard
+    /// line break
+    fn backslash(&mut self) {
+        match self.text[self.pos + 1..].chars().next() {
+            Some(c) if c.is_ascii_punctuation() => {
+                self.push_text(&self.text[self.pos + 1..self.pos + 2]);
+                self.pos += 2;
+            }
+            Some('\n') => {
+                self.nodes.push(Node::Inline(Inline::LineBreak));
+                self.pos += 2;
+                self.skip_spaces();
+            }
+            _ => {
+                self.push_text("\\");
+                self.pos += 1;
+            }
+        }
+    }
+
+    fn code_span(&mut self) {
+        let open = run_length(&self.text[self.pos..], '`');
+        let start = self.pos + open;
+        let mut search = start;
+        while let Some(found) = self.text[search..].find('`') {
+            let close = search + found;
+            let len = run_length(&self.text[close..], '`');
+            if len == open {
+                let mut code = self.text[start..close].replace('\n', " ");
+                if code.len() > 1
+                    && code.starts_with(' ')
+                    && code.ends_with(' ')
+                    && code.contains(|c| c != ' ')
+                {
+                    code = code[1..code.len() - 1].to_string();
+                }
+                self.nodes.push(Node::Inline(Inline::Code { code }));
+                self.pos = close + len;
+                return;
+            }
+            search = close + len;
+        }
+        self.push_text(&self.text[self.pos..start]);
+        self.pos = start;
+    }
+
+    fn delimiter_run(&mut self, c: char) {
+        let count = run_length(&self.text[self.pos..], c);
+        let before = self.text[..self.pos].chars().next_back();
+        let after = self.text[self.pos + count..].chars().next();
+        self.pos += count;
+        if c == '~' && !(self.gfm.strikethrough && count <= 2) {
+            self.push_text(&"~".repeat(count));
+            return;
+        }
+
+        let (can_open, can_close) = delimiter_sides(c, before, after);
+        self.nodes.push(Node::Delimiter(Delimiter {
+            char: c,
+            count,
+            original: count,
+            can_open,
+            can_close,
+        }));
+    }
+
+    /// `[[target]]` and `[[target|label]]` are links of their own, as is a
+    /// `[^label]` footnote reference; any other `[` may open link text
+    fn open_bracket(&mut self) {
+        let rest = &self.text[self.pos..];
+        if self.gfm.footnotes
+            && let Some(inner) = rest.strip_prefix("[^")
+            && let Some(end) = inner.find(']')
+            && end > 0
+            && !inner[..end].contains(|c: char| c.is_whitespace() || c == '[')
+        {
+            self.nodes.push(Node::Inline(Inline::FootnoteReference {
+                label: inner[..end].to_string(),
+            }));
+            self.pos += end + 3;
+            return;
+        }
+
+        if let Some(inner) = rest.strip_prefix("[[")
+            && let Some(end) = inner.find("]]")
+            && end > 0
+            && !inner[..end].contains(['\n', '[', ']'])
+        {
+            let inner = &inner[..end];
+            let (target, label) = inner.split_once('|').unwrap_or((inner, inner));
+            self.nodes.push(Node::Inline(Inline::Link {
+                text: vec![Inline::Text {
+                    text: label.to_string(),
+                }],
+                target: target.to_string(),
+            }));
+            self.pos += end + 4;
+            return;
+        }
+
+        self.pos += 1;
+        self.nodes.push(Node::Bracket {
+            image: false,
+            active: true,
+            start: self.pos,
+        });
+    }
+
+    fn close_bracket(&mut self) {
+        let close = self.pos;
+        self.pos += 1;
+        let Some(opener) = self
+            .nodes
+            .iter()
+            .rposition(|node| matches!(node, Node::Bracket { .. }))
+        else {
+            self.push_text("]");
+            return;
+        };
+        let Node::Bracket {
+            image,
+            active,
+            start,
+        } = self.nodes[opener]
+        else {
+            unreachable!()
+        };
+        if !active {
+            self.literal_bracket(opener, image);
+            return;
+        }
+
+        let Some((target, end)) = self.link_target(start, close) else {
+            self.literal_bracket(opener, image);
+            return;
+        };
+        self.pos = end;
+
+        let mut content = self.nodes.split_off(opener + 1);
+        self.nodes.pop();
+        process_emphasis(&mut content);
+        let content = finish(content);
+        if image {
+            let alt_text = alt_text(&content);
+            self.nodes.push(Node::Inline(Inline::Image {
+                alt_text: (!alt_text.is_empty()).then_some(alt_text),
+                src: target,
+            }));
+        } else {
+            self.nodes.push(Node::Inline(Inline::Link {
+                text: content,
+                target,
+            }));
+            // Links may not contain other links
+            for node in &mut self.nodes[..opener] {
+                if let Node::Bracket {
+                    image: false,
+                    active,
+                    ..
+                } = node
+                {
+                    *active = false;
+                }
+            }
+        }
+    }
+
+    /// Turn a bracket that opens nothing into text
+    fn literal_bracket(&mut self, opener: usize, image: bool) {
+        let text = if image { "![" } else { "[" };
+        self.nodes[opener] = Node::Inline(Inline::Text {
+            text: text.to_string(),
+        });
+        merge_text(&mut self.nodes, opener);
+        self.push_text("]");
+    }
+
+    /// The destination of the link whose text runs from `start` to the `]` at
+    /// `close`, and where the link ends: an inline `(destination "title")`,
+    /// a full `[label]`, a collapsed `[]` or a shortcut reference
+    fn link_target(&self, start: usize, close: usize) -> Option<(String, usize)> {
+        let after = close + 1;
+        if self.text[after..].starts_with('(')
+            && let Some(inline) = inline_link(self.text, after)
+        {
+            return Some(inline);
+        }
+
+        let (label, end) = match link_label(self.text, after) {
+            Some((label, end)) if !label.is_empty() => (label, end),
+            Some((_, end)) => (&self.text[start..close], end),
+            None => (&self.text[start..close], after),
+        };
+        if label.len() > 999 {
+            return None;
+        }
+        let target = self.references.get(&normalize_label(label))?;
+        Some((target.clone(), end))
+    }
+
+    /// Autolink, raw HTML or a literal `<`
+    fn angle_bracket(&mut self) {
+        let rest = &self.text[self.pos..];
+        let autolink = URI_AUTOLINK
+            .captures(rest)
+            .map(|captures| (captures[1].to_string(), captures[0].len()))
+            .or_else(|| {
+                EMAIL_AUTOLINK
+                    .captures(rest)
+                    .map(|captures| (format!("mailto:{}", &captures[1]), captures[0].len()))
+            });
+        if let Some((target, len)) = autolink {
+            let text = self.text[self.pos + 1..self.pos + len - 1].to_string();
+            self.nodes.push(Node::Inline(Inline::Link {
+                text: vec![Inline::Text { text }],
+                target,
+            }));
+            self.pos += len;
+        } else if let Some(html) = RAW_HTML.find(rest) {
+            self.nodes.push(Node::Inline(Inline::Html {
+                content: html.as_str().to_string(),
+            }));
+            self.pos += html.len();
+        } else {
+            self.push_text("<");
+            self.pos += 1;
+        }
+    }
+
+    fn entity(&mut self) {
+        if let Some((decoded, len)) = decode_entity(&self.text[self.pos..]) {
+            self.push_text(&decoded);
+            self.pos += len;
+        } else {
+            self.push_text("&");
+            self.pos += 1;
+        }
+    }
+
+    /// Two or more spaces before a line ending make a hard line break; any
+    /// other line ending stays in the text
+    ///
+    /// Only spaces in the source count, not `&#32;`.
+    fn line_ending(&mut self) {
+        let before = &self.text[..self.pos];
+        let spaces = before.len() - before.trim_end_matches(' ').len();
+        let hard = spaces >= 2;
+        if let Some(Node::Inline(Inline::Text { text })) = self.nodes.last_mut() {
+            let trimmed = text.trim_end_matches(' ').len().max(text.len() - spaces);
+            text.truncate(trimmed);
+            if text.is_empty() {
+                self.nodes.pop();
+            }
+        }
+        if hard {
+            self.nodes.push(Node::Inline(Inline::LineBreak));
+        } else {
+            self.push_text("\n");
+        }
+        self.pos += 1;
+        self.skip_spaces();
+    }
+
+    /// `$x^2$`
+    ///
+    /// The opening `$` must be followed by a non-space, and the closing one
+    /// must follow a non-space and not be followed by a digit, so that
+    /// prices such as `$5 and $10` stay text.
+    fn math(&mut self) {
+        let text = self.text;
+        let start = self.pos + 1;
+        let opens = text[start..]
+            .chars()
+            .next()
+            .is_some_and(|c| !c.is_whitespace() && c != '$');
+        let close = text[start..]
+            .match_indices('$')
+            .map(|(i, _)| start + i)
+            .find(|&i| {
+                !text[..i].ends_with(char::is_whitespace)
+                    && !text[i + 1..].starts_with(|c: char| c.is_ascii_digit())
+            });
+        if opens && let Some(close) = close {
+            self.nodes.push(Node::Inline(Inline::Math {
+                content: text[start..close].to_string(),
+            }));
+            self.pos = close + 1;
+        } else {
+            self.push_text("$");
+            self.pos += 1;
+        }
+    }
+
+    /// A bare `http://`, `https://` or `www.` URL
+    ///
+    /// It starts the text or follows whitespace, `*`, `_`, `~` or `(`, and
+    /// runs to the next whitespace or `<`, less any trailing punctuation,
+    /// unbalanced `)` or entity-like `&name;`.
+    fn url_autolink(&mut self) -> bool {
+        let opens = self.text[..self.pos]
+            .chars()
+            .next_back()
+            .is_none_or(|c| c.is_whitespace() || "*_~(".contains(c));
+        let Some(found) = URL_AUTOLINK.find(&self.text[self.pos..]).filter(|_| opens) else {
+            return false;
+        };
+        let url = trim_autolink(found.as_str());
+        if !url.contains('.') {
+            return false;
+        }
+        let target = if url.starts_with("www.") {
+            format!("http://{url}")
+        } else {
+            url.to_string()
+        };
+        self.nodes.push(Node::Inline(Inline::Link {
+            text: vec![Inline::Text {
+                text: url.to_string(),
+            }],
+            target,
+        }));
+        self.pos += url.len();
+        true
+    }
+
+    /// A bare `name@example.com`, whose name is the end of the text before
+    /// the `@`
+    fn email_autolink(&mut self) {
+        let text = self.text;
+        let before = &text[..self.pos];
+        let name = &before[before.trim_end_matches(is_email_char).len()..];
+        if !name.is_empty()
+            && let Some(domain) = EMAIL_DOMAIN.find(&text[self.pos..])
+            && !domain.as_str().ends_with(['-', '_'])
+            && let Some(Node::Inline(Inline::Text { text: last })) = self.nodes.last_mut()
+            && last.ends_with(name)
+        {
+            last.truncate(last.len() - name.len());
+            if last.is_empty() {
+                self.nodes.pop();
+            }
+            let address = format!("{name}{}", domain.as_str());
+            self.nodes.push(Node::Inline(Inline::Link {
+                text: vec![Inline::Text {
+                    text: address.clone(),
+                }],
+                target: format!("mailto:{address}"),
+            }));
+            self.pos += domain.len();
+        } else {
+            self.push_text("@");
+            self.pos += 1;
+        }
+    }
+
+    /// `#tag` and `#nested/tag`
+    ///
+    /// A tag starts at a `#` that opens the text or follows whitespace or an
+    /// opening bracket or quote, so URL fragments (`page#section`) and `##`
+    /// are left alone. It runs over letters, digits, `_`, `-` and `/`, must
+    /// contain a letter, and does not end in `/`. A `[#A]` priority cookie is
+    /// not a tag.
+    fn tag(&mut self) {
+        let before = self.text[..self.pos].chars().next_back();
+        let opens = before.is_none_or(|p| p.is_whitespace() || "([{'\"".contains(p));
+        let rest = &self.text[self.pos + 1..];
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '/')))
+            .unwrap_or(rest.len());
+        let name = rest[..len].trim_end_matches('/');
+        let priority = before == Some('[') && name.len() == 1 && rest[1..].starts_with(']');
+        if opens && !priority && name.chars().any(char::is_alphabetic) {
+            self.nodes.push(Node::Inline(Inline::Tag {
+                name: name.to_string(),
+            }));
+            self.pos += 1 + name.len();
+        } else {
+            self.push_text("#");
+            self.pos += 1;
+        }
+    }
+}
+
+/// Match closers to openers, innermost first, turning the runs between
+/// them into emphasis
+fn process_emphasis(nodes: &mut Vec<Node>) {
+    let mut i = 0;
+    while i < nodes.len() {
+        let Node::Delimiter(closer) = &nodes[i] else {
+            i += 1;
+            continue;
+        };
+        if !closer.can_close || closer.count == 0 {
+            i += 1;
+            continue;
+        }
+
+        let opener = (0..i).rev().find(|&j| match &nodes[j] {
+            Node::Delimiter(opener) => {
+                opener.char == closer.char
+                    && opener.can_open
+                    && opener.count > 0
+                    && pairs(opener, closer)
+            }
+            _ => false,
+        });
+        let Some(opener) = opener else {
+            if let Node::Delimiter(closer) = &mut nodes[i]
+                && !closer.can_open
+            {
+                closer.can_close = false;
+            }
+            i += 1;
+            continue;
+        };
+
+        let (Node::Delimiter(open), Node::Delimiter(close)) = (&nodes[opener], &nodes[i]) else {
+            unreachable!()
+        };
+        let used = if open.count >= 2 && close.count >= 2 {
+            2
+        } else {
+            1
+        };
+        let char = close.char;
+        let content = finish(nodes.drain(opener + 1..i).collect());
+        let inline = match (char, used) {
+            ('~', _) => Inline::Strikethrough { content },
+            (_, 2) => Inline::Bold { content },
+            _ => Inline::Italic { content },
+        };
+        nodes.insert(opener + 1, Node::Inline(inline));
+
+        let mut closer = opener + 2;
+        for index in [opener, closer] {
+            if let Node::Delimiter(delimiter) = &mut nodes[index] {
+                delimiter.count -= used;
+            }
+        }
+        if matches!(&nodes[closer], Node::Delimiter(d) if d.count == 0) {
+            nodes.remove(closer);
+        }
+        if matches!(&nodes[opener], Node::Delimiter(d) if d.count == 0) {
+            nodes.remove(opener);
+            closer -= 1;
+        }
+        i = closer;
+    }
+}
+
+/// Whether two runs may pair up: `~` and `~~` only with a run as long, and not when one of
+/// them could both open and close and their lengths add up to a multiple
+/// of three (the spec's "rule of 3")
+fn pairs(opener: &Delimiter, closer: &Delimiter) -> bool {
+    if opener.char == '~' {
+        return opener.count == closer.count;
+    }
+    !((opener.can_close || closer.can_open)
+        && (opener.original + closer.original).is_multiple_of(3)
+        && !(opener.original.is_multiple_of(3) && closer.original.is_multiple_of(3)))
+}
+
+/// The inlines for the nodes, with unmatched markers as text
+fn finish(nodes: Vec<Node>) -> Vec<Inline> {
+    let mut inlines: Vec<Inline> = Vec::new();
+    for node in nodes {
+        let inline = match node {
+            Node::Inline(inline) => inline,
+            Node::Delimiter(Delimiter { char, count, .. }) => Inline::Text {
+                text: char.to_string().repeat(count),
+            },
+            Node::Bracket { image, .. } => Inline::Text {
+                text: if image { "![" } else { "[" }.to_string(),
+            },
+        };
+        if let Inline::Text { text: next } = &inline {
+            if next.is_empty() {
+                continue;
+            }
+            if let Some(Inline::Text { text }) = inlines.last_mut() {
+                text.push_str(next);
+                continue;
+            }
+        }
+        inlines.push(inline);
+    }
+    inlines
+}
+
+/// A bare URL without the trailing characters that end its sentence rather
+/// than belong to it
+fn trim_autolink(url: &str) -> &str {
+    let mut url = url;
+    loop {
+        if let Some(trimmed) = url.strip_suffix(['?', '!', '.', ',', ':', '*', '_', '~', '\'', '"'])
+        {
+            url = trimmed;
+        } else if url.ends_with(')') && url.matches(')').count() > url.matches('(').count() {
+            url = &url[..url.len() - 1];
+        } else if let Some(name) = url.strip_suffix(';')
+            && let Some(amp) = name.rfind('&')
+            && amp + 1 < name.len()
+            && name[amp + 1..].chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            url = &url[..amp];
+        } else {
+            return url;
+        }
+    }
+}
+
+/// The text of an image description: its inlines without their formatting
+fn alt_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text { text: content }
+            | Inline::Code { code: content }
+            | Inline::Math { content }
+            | Inline::Html { content } => text.push_str(content),
+            Inline::Bold { content }
+            | Inline::Italic { content }
+            | Inline::Strikethrough { content }
+            | Inline::Superscript { content }
+            | Inline::Subscript { content }
+            | Inline::Link { text: content, .. } => text.push_str(&alt_text(content)),
+            Inline::Image { alt_text, .. } => {
+                text.push_str(alt_text.as_deref().unwrap_or_default());
+            }
+            Inline::Tag { name } => {
+                text.push('#');
+                text.push_str(name);
+            }
+            Inline::LineBreak => text.push('\n'),
+            Inline::FootnoteReference { .. } | Inline::BlockId { .. } => {}
+        }
+    }
+    text
+}
+
+/// Merge the text node at `index` into the text nodes around it
+fn merge_text(nodes: &mut Vec<Node>, index: usize) {
+    let mut index = index;
+    if index > 0
+        && let [
+            Node::Inline(Inline::Text { text: before }),
+            Node::Inline(Inline::Text { text }),
+        ] = &mut nodes[index - 1..=index]
+    {
+        before.push_str(text);
+        nodes.remove(index);
+        index -= 1;
+    }
+    if let [
+        Node::Inline(Inline::Text { text }),
+        Node::Inline(Inline::Text { text: after }),
+        ..,
+    ] = &mut nodes[index..]
+    {
+        text.push_str(after);
+        nodes.remove(index + 1);
+    }
+}
+
+fn is_email_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-')
+}
+
+fn run_length(text: &str, c: char) -> usize {
+    text.len() - text.trim_start_matches(c).len()
+}
+
+/// Whether a run of `c` between `before` and `after` can open and can close
+/// emphasis, where `_` inside a word does neither
+pub fn delimiter_sides(c: char, before: Option<char>, after: Option<char>) -> (bool, bool) {
+    let left = flanking(before, after);
+    let right = flanking(after, before);
+    if c == '_' {
+        (
+            left && (!right || before.is_some_and(is_punctuation)),
+            right && (!left || after.is_some_and(is_punctuation)),
+        )
+    } else {
+        (left, right)
+    }
+}
+
+/// Whether a run between `before` and `after` is left-flanking; swap them
+/// for right-flanking
+fn flanking(before: Option<char>, after: Option<char>) -> bool {
+    let Some(after) = after.filter(|c| !c.is_whitespace()) else {
+        return false;
+    };
+    !is_punctuation(after) || before.is_none_or(|c| c.is_whitespace() || is_punctuation(c))
+}
+
+/// ASCII punctuation, and anything outside ASCII that is neither a letter,
+/// digit, space nor control character, standing in for Unicode's P and S
+/// categories
+fn is_punctuation(c: char) -> bool {
+    if c.is_ascii() {
+        c.is_ascii_punctuation()
+    } else {
+        !(c.is_alphanumeric() || c.is_whitespace() || c.is_control())
+    }
+}
+
+/// An entity or numeric character reference at the start of `text`, decoded,
+/// and its length
+pub fn decode_entity(text: &str) -> Option<(String, usize)> {
+    let captures = ENTITY.captures(text)?;
+    let len = captures[0].len();
+    let code = if let Some(hex) = captures.get(1) {
+        u32::from_str_radix(hex.as_str(), 16).ok()?
+    } else if let Some(decimal) = captures.get(2) {
+        decimal.as_str().parse().ok()?
+    } else {
+        let characters = NAMED_ENTITIES.get(&captures[0])?;
+        return Some(((*characters).to_string(), len));
+    };
+    let c = char::from_u32(code)
+        .filter(|&c| c != '\0')
+        .unwrap_or(char::REPLACEMENT_CHARACTER);
+    Some((c.to_string(), len))
+}
+
+/// Resolve backslash escapes and entity references, as in link destinations,
+/// titles and code block info strings
+pub fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut i = 0;
+    while let Some(c) = text[i..].chars().next() {
+        match c {
+            '\\' if text[i + 1..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_punctuation()) =>
+            {
+                out.push_str(&text[i + 1..i + 2]);
+                i += 2;
+            }
+            '&' if let Some((decoded, len)) = decode_entity(&text[i..]) => {
+                out.push_str(&decoded);
+                i += len;
+            }
+            _ => {
+                out.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    out
+}
+
+/// Case-fold a link label and collapse its whitespace, so `[Foo  Bar]` and
+/// `[foo bar]` match
+pub fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .to_uppercase()
+}
+
+/// The content of the `[label]` at `pos` and the offset after it
+pub fn link_label(text: &str, pos: usize) -> Option<(&str, usize)> {
+    if !text[pos..].starts_with('[') {
+        return None;
+    }
+    let mut i = pos + 1;
+    while let Some(c) = text[i..].chars().next() {
+        match c {
+            '\\' => i += 1 + text[i + 1..].chars().next().map_or(0, char::len_utf8),
+            '[' => return None,
+            ']' => return Some((&text[pos + 1..i], i + 1)),
+            _ => i += c.len_utf8(),
+        }
+    }
+    None
+}
+
+/// Skip spaces, tabs and at most one line ending
+pub fn skip_whitespace(text: &str, pos: usize) -> usize {
+    let mut i = pos;
+    let mut newline = false;
+    for c in text[pos..].chars() {
+        match c {
+            ' ' | '\t' => {}
+            '\n' if !newline => newline = true,
+            _ => break,
+        }
+        i += 1;
+    }
+    i
+}
+
+/// The link destination at `pos`, unescaped, and the offset after it
+pub fn link_destination(text: &str, pos: usize) -> Option<(String, usize)> {
+    let rest = &text[pos..];
+    if let Some(inner) = rest.strip_prefix('<') {
+        let mut i = 0;
+        while let Some(c) = inner[i..].chars().next() {
+            match c {
+                '\\' => i += 1 + inner[i + 1..].chars().next().map_or(0, char::len_utf8),
+                '>' => return Some((unescape(&inner[..i]), pos + i + 2)),
+                '<' | '\n' => return None,
+                _ => i += c.len_utf8(),
+            }
+        }
+        return None;
+    }
+
+    let mut depth = 0;
+    let mut i = 0;
+    while let Some(c) = rest[i..].chars().next() {
+        match c {
+            '\\' if rest[i + 1..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_punctuation()) =>
+            {
+                i += 2;
+                continue;
+            }
+            '(' => depth += 1,
+            ')' if depth == 0 => break,
+            ')' => depth -= 1,
+            c if c.is_ascii_whitespace() || c.is_ascii_control() => break,
+            _ => {}
+        }
+        if depth > 32 {
+            return None;
+        }
+        i += c.len_utf8();
+    }
+    (i > 0 && depth == 0).then(|| (unescape(&rest[..i]), pos + i))
+}
+
+/// The link title at `pos`, in `"`, `'` or `(`, and the offset after it
+pub fn link_title(text: &str, pos: usize) -> Option<(String, usize)> {
+    let open = text[pos..].chars().next()?;
+    let close = match open {
+        '"' | '\'' => open,
+        '(' => ')',
+        _ => return None,
+    };
+    let mut i = pos + 1;
+    while let Some(c) = text[i..].chars().next() {
+        match c {
+            '\\' => i += 1 + text[i + 1..].chars().next().map_or(0, char::len_utf8),
+            c if c == close => return Some((unescape(&text[pos + 1..i]), i + 1)),
+            '(' if open == '(' => return None,
+            _ => i += c.len_utf8(),
+        }
+    }
+    None
+}
+
+/// `(destination "title")` starting at the `(` at `pos`: the destination and
+/// the offset after the `)`
+fn inline_link(text: &str, pos: usize) -> Option<(String, usize)> {
+    let mut i = skip_whitespace(text, pos + 1);
+    let mut target = String::new();
+    if !text[i..].starts_with(')')
+        && let Some((destination, end)) = link_destination(text, i)
+    {
+        target = destination;
+        i = end;
+    }
+    let before_title = i;
+    i = skip_whitespace(text, i);
+    if i > before_title
+        && let Some((_, end)) = link_title(text, i)
+    {
+        i = skip_whitespace(text, end);
+    }
+    text[i..].starts_with(')').then_some((target, i + 1))
+}
@@ -0,0 +1,275 @@
+//! The CommonMark spec examples, run against the block parser with the GFM
+//! extensions off
+//!
+//! Each example's Markdown is parsed, rendered to HTML the way the reference
+//! implementation does and compared to the spec's HTML. The block model
+//! does not keep everything the HTML shows, so both sides are normalized
+//! first: whether a list is tight (`<p>` inside `<li>`), the start number
+//! of an ordered list and link titles are left out, as are line breaks
+//! between tags.
+
+use std::{fmt::Write, sync::LazyLock};
+
+use regex::Regex;
+
+use super::{GfmOptions, blocks};
+use crate::models::{Block, ContainerBlock, Inline, LeafBlock, ListStyle};
+
+const SPEC: &str = include_str!("spec.txt");
+
+/// Examples that pass, so that regressions fail the build; raise it as
+/// conformance improves
+///
+/// The rest are `[[...]]`, which is a wiki link here rather than a link
+/// inside brackets.
+const PASSING: usize = 649;
+
+static FENCE: LazyLock<String> = LazyLock::new(|| "`".repeat(32));
+
+struct Example {
+    number: usize,
+    section: String,
+    markdown: String,
+    html: String,
+}
+
+fn examples() -> Vec<Example> {
+    let open = format!("{} example", *FENCE);
+    let mut examples = Vec::new();
+    let mut section = String::new();
+    let mut lines = SPEC.lines();
+    while let Some(line) = lines.next() {
+        if let Some(heading) = line.strip_prefix("## ") {
+            section = heading.to_string();
+        } else if line == open {
+            let mut markdown = String::new();
+            for line in lines.by_ref().take_while(|&line| line != ".") {
+                markdown.push_str(line);
+                markdown.push('\n');
+            }
+            let mut html = String::new();
+            for line in lines.by_ref().take_while(|&line| line != *FENCE) {
+                html.push_str(line);
+                html.push('\n');
+            }
+            examples.push(Example {
+                number: examples.len() + 1,
+                section: section.clone(),
+                markdown: markdown.replace('→', "\t"),
+                html: html.replace('→', "\t"),
+            });
+        }
+    }
+    examples
+}
+
+fn render_blocks(blocks: &[Block], out: &mut String) {
+    for block in blocks {
+        render_block(block, out);
+    }
+}
+
+fn render_block(block: &Block, out: &mut String) {
+    match block {
+        Block::Leaf { leaf } => match leaf {
+            LeafBlock::Paragraph { content } => {
+                writeln!(out, "<p>{}</p>", render_inlines(content)).unwrap();
+            }
+            LeafBlock::Heading { level, content } => {
+                writeln!(out, "<h{level}>{}</h{level}>", render_inlines(content)).unwrap();
+            }
+            LeafBlock::Image { alt_text, src } => {
+                let image = Inline::Image {
+                    alt_text: alt_text.clone(),
+                    src: src.clone(),
+                };
+                writeln!(out, "<p>{}</p>", render_inlines(&[image])).unwrap();
+            }
+            LeafBlock::CodeBlock { language, content } => {
+                out.push_str("<pre><code");
+                if let Some(language) = language {
+                    write!(out, " class=\"language-{}\"", escape_attribute(language)).unwrap();
+                }
+                out.push('>');
+                if !content.is_empty() {
+                    out.push_str(&escape(content));
+                    out.push('\n');
+                }
+                out.push_str("</code></pre>\n");
+            }
+            LeafBlock::MathBlock { content } => {
+                writeln!(out, "<p>$${}$$</p>", escape(content)).unwrap();
+            }
+            LeafBlock::HorizontalRule => out.push_str("<hr />\n"),
+            LeafBlock::Attachment { attachment } => {
+                writeln!(out, "<p>{}</p>", escape(&attachment.src)).unwrap();
+            }
+            LeafBlock::Html { content } => {
+                out.push_str(content);
+                out.push('\n');
+            }
+        },
+        Block::Container { container } => match container {
+            ContainerBlock::Quote { blocks } => {
+                out.push_str("<blockquote>\n");
+                render_blocks(blocks, out);
+                out.push_str("</blockquote>\n");
+            }
+            ContainerBlock::List { style, items } => {
+                let tag = match style {
+                    ListStyle::Ordered { .. } => "ol",
+                    ListStyle::Unordered { .. } => "ul",
+                };
+                writeln!(out, "<{tag}>").unwrap();
+                for item in items {
+                    out.push_str("<li>");
+                    render_blocks(&item.blocks, out);
+                    out.push_str("</li>\n");
+                }
+                writeln!(out, "</{tag}>").unwrap();
+            }
+            ContainerBlock::Table { .. } | ContainerBlock::Div { .. } => {}
+        },
+        Block::DefinitionList { .. } | Block::FootnoteDefinition { .. } => {}
+    }
+}
+
+fn render_inlines(inlines: &[Inline]) -> String {
+    let mut out = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text { text } => out.push_str(&escape(text)),
+            Inline::Bold { content } => {
+                write!(out, "<strong>{}</strong>", render_inlines(content)).unwrap();
+            }
+            Inline::Italic { content } => {
+                write!(out, "<em>{}</em>", render_inlines(content)).unwrap();
+            }
+            Inline::Strikethrough { content } => {
+                write!(out, "<del>{}</del>", render_inlines(content)).unwrap();
+            }
+            Inline::Superscript { content } => {
+                write!(out, "<sup>{}</sup>", render_inlines(content)).unwrap();
+            }
+            Inline::Subscript { content } => {
+                write!(out, "<sub>{}</sub>", render_inlines(content)).unwrap();
+            }
+            Inline::Link { text, target } => {
+                let text = render_inlines(text);
+                write!(out, "<a href=\"{}\">{text}</a>", escape_href(target)).unwrap();
+            }
+            Inline::Image { alt_text, src } => {
+                let alt = escape_attribute(alt_text.as_deref().unwrap_or_default());
+                write!(out, "<img src=\"{}\" alt=\"{alt}\" />", escape_href(src)).unwrap();
+            }
+            Inline::Code { code } => write!(out, "<code>{}</code>", escape(code)).unwrap(),
+            Inline::Math { content } => write!(out, "${}$", escape(content)).unwrap(),
+            Inline::LineBreak => out.push_str("<br />\n"),
+            Inline::FootnoteReference { label } => write!(out, "[^{}]", escape(label)).unwrap(),
+            Inline::BlockId { id } => write!(out, " ^{id}").unwrap(),
+            Inline::Tag { name } => write!(out, "#{}", escape(name)).unwrap(),
+            Inline::Html { content } => out.push_str(content),
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn escape_attribute(text: &str) -> String {
+    escape(text).replace('"', "&quot;")
+}
+
+/// Percent-encode a URL except for the characters that are safe in one or
+/// reserved, as the reference implementation does
+fn escape_href(url: &str) -> String {
+    let mut out = String::new();
+    for byte in url.bytes() {
+        match byte {
+            b'&' => out.push_str("&amp;"),
+            b'\'' => out.push_str("&#x27;"),
+            b if b.is_ascii_alphanumeric() || b"-_.+!*(),%#@?=;:/$~".contains(&b) => {
+                out.push(char::from(b));
+            }
+            b => write!(out, "%{b:02X}").unwrap(),
+        }
+    }
+    out
+}
+
+static TOKEN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>|[^<]+|<").unwrap());
+static IGNORED_ATTRIBUTES: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#" (?:title|start)="[^"]*""#).unwrap());
+
+/// Drop what the block model cannot tell apart, see the module docs
+fn normalize(html: &str) -> String {
+    let mut out = String::new();
+    let mut items = 0_usize;
+    let mut preformatted = false;
+    for token in TOKEN.find_iter(html).map(|token| token.as_str()) {
+        if token.starts_with("<pre") {
+            preformatted = true;
+        } else if token == "</pre>" {
+            preformatted = false;
+        } else if token.starts_with("<li") {
+            items += 1;
+        } else if token == "</li>" {
+            items = items.saturating_sub(1);
+        }
+
+        if preformatted || token.starts_with("</pre") {
+            out.push_str(token);
+        } else if token.starts_with('<') && token.len() > 1 {
+            if items == 0 || !matches!(token, "<p>" | "</p>") {
+                out.push_str(&IGNORED_ATTRIBUTES.replace_all(token, ""));
+            }
+        } else {
+            out.push_str(token.trim_matches('\n'));
+        }
+    }
+    out
+}
+
+#[test]
+fn spec_examples() {
+    // Section name, passing and total examples, in spec order
+    let mut sections: Vec<(String, usize, usize)> = Vec::new();
+    let mut failing = Vec::new();
+    for example in examples() {
+        let mut html = String::new();
+        render_blocks(
+            &blocks::parse(&example.markdown, &GfmOptions::none()),
+            &mut html,
+        );
+        let passed = normalize(&html) == normalize(&example.html);
+
+        if sections
+            .last()
+            .is_none_or(|(name, ..)| *name != example.section)
+        {
+            sections.push((example.section.clone(), 0, 0));
+        }
+        let section = sections.last_mut().unwrap();
+        section.2 += 1;
+        if passed {
+            section.1 += 1;
+        } else {
+            failing.push(example.number);
+        }
+    }
+
+    for (name, passed, total) in &sections {
+        println!("{name}: {passed}/{total}");
+    }
+    let total: usize = sections.iter().map(|(_, _, total)| total).sum();
+    let passing = total - failing.len();
+    println!("CommonMark {passing}/{total}, failing: {failing:?}");
+    assert!(
+        passing >= PASSING,
+        "{passing} spec examples pass, expected at least {PASSING}"
+    );
+}
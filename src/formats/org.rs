@@ -7,23 +7,76 @@
 
 // formats/org/mod.rs
 
-use crate::formats::{NoteMetadata, NoteSerialization};
-use crate::models::{Block, ContainerBlock, Inline, LeafBlock, LinkTarget, Note};
+use crate::error::SerializationResult;
+use crate::formats::{Capabilities, NoteMetadata, NoteSerialization};
+use crate::models::{
+    Block, BlockKind, ContainerBlock, Inline, InlineKind, LeafBlock, LinkTarget, Note,
+};
 
 /// Entry point for Org serialization/deserialization
 #[derive(Debug, uniffi::Object)]
 pub struct OrgFormat;
 
 impl NoteSerialization for OrgFormat {
-    fn deserialize(&self, data: &[u8], id_hint: Option<&str>) -> Note {
-        let text = std::str::from_utf8(data).unwrap_or("");
+    fn deserialize(&self, data: &[u8], id_hint: Option<&str>) -> SerializationResult<Note> {
+        let text = std::str::from_utf8(data)?;
         let doc = parser::parse_org(text);
-        lower::org_to_note(doc, id_hint)
+        Ok(lower::org_to_note(doc, id_hint))
     }
 
     fn serialize(&self, note: &Note) -> Vec<u8> {
         serializer::note_to_org(note).into_bytes()
     }
+
+    /// Everything but attachments, with tags only at the end of headings
+    ///
+    /// Headings cannot sit inside other blocks. A div needs a class to name its special block,
+    /// which comes back lowercased, and a lone inline image is an image
+    /// block; images need an image extension to tell them from links, and
+    /// lose their alt text inline. Table cells are one line with no `|` in
+    /// their code or math, a paragraph cannot start like a numbered list
+    /// item or with a footnote reference, and a list item's text cannot hold
+    /// ` :: `. Emphasis and code cannot start or end with whitespace and need
+    /// a space or punctuation either side. Adjacent lists of the same kind,
+    /// or tables, merge into one, `*` bullets become `-` and the title only
+    /// survives as the `title` property.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            blocks: vec![
+                BlockKind::Paragraph,
+                BlockKind::Heading,
+                BlockKind::Image,
+                BlockKind::CodeBlock,
+                BlockKind::MathBlock,
+                BlockKind::HorizontalRule,
+                BlockKind::Html,
+                BlockKind::Quote,
+                BlockKind::List,
+                BlockKind::Table,
+                BlockKind::Div,
+                BlockKind::DefinitionList,
+                BlockKind::FootnoteDefinition,
+            ],
+            inlines: vec![
+                InlineKind::Text,
+                InlineKind::Bold,
+                InlineKind::Italic,
+                InlineKind::Strikethrough,
+                InlineKind::Link,
+                InlineKind::Image,
+                InlineKind::Code,
+                InlineKind::Math,
+                InlineKind::LineBreak,
+                InlineKind::Superscript,
+                InlineKind::Subscript,
+                InlineKind::FootnoteReference,
+                InlineKind::BlockId,
+                InlineKind::Tag,
+                InlineKind::Html,
+            ],
+            properties: true,
+        }
+    }
 }
 
 impl NoteMetadata for OrgFormat {
@@ -55,7 +108,7 @@ impl NoteMetadata for OrgFormat {
                         }
                         ContainerBlock::List { items, .. } => {
                             for item in items {
-                                walk_blocks(item, out);
+                                walk_blocks(&item.blocks, out);
                             }
                         }
                         ContainerBlock::Table { rows, .. } => {
@@ -83,11 +136,17 @@ impl NoteMetadata for OrgFormat {
 }
 
 pub mod model {
-    use crate::models::Inline;
+    use crate::models::{Block, Inline};
     use std::collections::HashMap;
 
     #[derive(Debug, Clone, PartialEq, Eq)]
     pub struct OrgDocument {
+        /// `#+KEY: value` lines before the first heading
+        pub keywords: Vec<(String, String)>,
+        /// The property drawer before the first heading
+        pub properties: Vec<(String, String)>,
+        /// Blocks after the keywords and drawer but before the first heading
+        pub blocks: Vec<Block>,
         pub nodes: Vec<OrgNode>,
     }
 
@@ -103,6 +162,51 @@ pub mod model {
         pub children: Vec<Self>,
     }
 
+    /// Class of the div a heading's property drawer is lowered to, with the
+    /// properties as its attributes
+    pub const DRAWER_CLASS: &str = "properties";
+
+    impl OrgNode {
+        /// The property drawer, `:ID:` first and then the rest sorted so the
+        /// output is stable
+        #[must_use]
+        pub fn drawer(&self) -> Vec<(&str, &str)> {
+            let mut properties: Vec<(&str, &str)> = self
+                .properties
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str()))
+                .collect();
+            properties.sort_unstable_by_key(|&(key, value)| (key != "ID", key, value));
+            properties
+        }
+    }
+
+    /// Characters written as `\name{}` entities where Org would otherwise
+    /// read them as markup
+    pub const ENTITIES: [(&str, char); 13] = [
+        ("backslash", '\\'),
+        ("lbrack", '['),
+        ("rbrack", ']'),
+        ("lbrace", '{'),
+        ("rbrace", '}'),
+        ("vert", '|'),
+        ("lt", '<'),
+        ("ast", '*'),
+        ("slash", '/'),
+        ("plus", '+'),
+        ("tilde", '~'),
+        ("minus", '-'),
+        ("colon", ':'),
+    ];
+
+    /// What may come before the opening marker of emphasis or code, besides
+    /// whitespace
+    pub const PRE: &str = "-('\"{";
+
+    /// What may come after the closing marker of emphasis or code, besides
+    /// whitespace
+    pub const POST: &str = "-.,;:!?')}]\"\\[";
+
     #[derive(Debug, Clone, PartialEq, Eq)]
     pub struct OrgTable {
         pub headers: Vec<Vec<Inline>>,
@@ -119,153 +223,823 @@ pub mod model {
 
 /// --- parser.rs ---
 pub mod parser {
-    use super::model::{OrgDocument, OrgNode};
+    use std::collections::{HashMap, HashSet};
+
+    use super::model::{ENTITIES, OrgDocument, OrgNode, POST, PRE};
     use crate::{
-        formats::org::model::TableFormula,
-        models::{Block, Inline},
+        formats::{markdown::ALERT_KINDS, org::model::TableFormula},
+        models::{
+            Alignment, Attribute, Block, Checkbox, DefinitionItem, Inline, ListItem, ListStyle,
+            Numbering, NumberingStyle, NumberingType,
+        },
     };
-    use std::collections::HashMap;
+
+    /// Link targets read as images when a link has no description
+    const IMAGE_EXTENSIONS: [&str; 7] = ["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp"];
 
     #[must_use]
     pub fn parse_org(input: &str) -> OrgDocument {
+        let lines: Vec<&str> = input.lines().collect();
         let mut root: Vec<OrgNode> = Vec::new();
         let mut stack: Vec<OrgNode> = Vec::new();
-        let mut lines = input.lines().peekable();
-
-        while let Some(line) = lines.next() {
-            if let Some((level, todo, title, tags)) = parse_heading(line) {
-                let node = OrgNode {
-                    id: None,
-                    level,
-                    title,
-                    todo,
-                    tags,
-                    properties: HashMap::new(),
-                    body: Vec::new(),
-                    children: Vec::new(),
-                };
+        let mut keywords = Vec::new();
+        let mut properties = Vec::new();
 
-                while let Some(top) = stack.last() {
-                    if top.level < level {
+        // Keywords and the file's drawer open the document, and whatever
+        // follows them before the first heading is read as blocks
+        let mut i = 0;
+        while let Some(line) = lines.get(i) {
+            if line.trim().is_empty() {
+                i += 1;
+            } else if line.trim().eq_ignore_ascii_case(":PROPERTIES:") {
+                i += 1;
+                while let Some(line) = lines.get(i) {
+                    i += 1;
+                    if line.trim().eq_ignore_ascii_case(":END:") {
                         break;
                     }
-                    let finished = stack.pop().unwrap();
-                    attach_node(&mut root, &mut stack, finished);
+                    properties.extend(parse_property(line));
                 }
+            } else if let Some(keyword) = parse_keyword(line).filter(|(key, _)| !is_affiliated(key))
+            {
+                keywords.push(keyword);
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        let start = i;
+        while lines
+            .get(i)
+            .is_some_and(|line| heading_level(line).is_none())
+        {
+            i += 1;
+        }
+        let blocks = parse_blocks(&lines[start..i]);
 
-                stack.push(node);
-            } else if let Some((k, v)) = parse_property(line) {
-                if let Some(last) = stack.last_mut() {
-                    last.properties.insert(k, v);
+        while let Some((level, todo, title, tags)) =
+            lines.get(i).and_then(|line| parse_heading(line))
+        {
+            i += 1;
+            let mut node = OrgNode {
+                id: None,
+                level,
+                title,
+                todo,
+                tags,
+                properties: HashMap::new(),
+                body: Vec::new(),
+                children: Vec::new(),
+            };
+
+            // A property drawer belongs to the heading right above it
+            if lines
+                .get(i)
+                .is_some_and(|line| line.trim().eq_ignore_ascii_case(":PROPERTIES:"))
+            {
+                i += 1;
+                while let Some(line) = lines.get(i) {
+                    i += 1;
+                    if line.trim().eq_ignore_ascii_case(":END:") {
+                        break;
+                    }
+                    if let Some((key, value)) = parse_property(line) {
+                        node.properties.insert(key, value);
+                    }
                 }
-            } else if let Some(node) = stack.last_mut() {
-                node.body.push(parse_block(line, &mut lines));
             }
+
+            let body = i;
+            while lines
+                .get(i)
+                .is_some_and(|line| heading_level(line).is_none())
+            {
+                i += 1;
+            }
+            node.body = parse_blocks(&lines[body..i]);
+
+            while let Some(top) = stack.last() {
+                if top.level < level {
+                    break;
+                }
+                let finished = stack.pop().unwrap();
+                attach_node(&mut root, &mut stack, finished);
+            }
+
+            stack.push(node);
         }
 
         while let Some(node) = stack.pop() {
             attach_node(&mut root, &mut stack, node);
         }
 
-        OrgDocument { nodes: root }
+        OrgDocument {
+            keywords,
+            properties,
+            blocks,
+            nodes: root,
+        }
     }
 
-    fn parse_heading(line: &str) -> Option<(u8, Option<String>, Vec<Inline>, Vec<String>)> {
-        let trimmed = line.trim_start();
-        if !trimmed.starts_with('*') {
+    /// `#+CAPTION:` and `#+ATTR_...:` belong to the block below them, not
+    /// the file
+    fn is_affiliated(key: &str) -> bool {
+        let key = key.to_uppercase();
+        key == "CAPTION" || key.starts_with("ATTR_")
+    }
+
+    /// `#+KEY: value`, excluding `#+BEGIN_...` style block markers
+    fn parse_keyword(line: &str) -> Option<(String, String)> {
+        let (key, value) = line.trim().strip_prefix("#+")?.split_once(':')?;
+        if key.is_empty()
+            || !key
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+        {
             return None;
         }
+        Some((key.to_string(), value.trim().to_string()))
+    }
 
-        let level = trimmed.chars().take_while(|c| *c == '*').count();
-        let rest = trimmed[level..].trim();
+    /// The value of a `#+KEY: value` line for `key`, in any case
+    fn keyword_value<'a>(line: &'a str, key: &str) -> Option<&'a str> {
+        let (name, value) = line.strip_prefix("#+")?.split_once(':')?;
+        name.eq_ignore_ascii_case(key).then(|| value.trim())
+    }
+
+    /// The number of stars starting a heading line, which are followed by a
+    /// space unless nothing else is
+    fn heading_level(line: &str) -> Option<usize> {
+        let level = line.chars().take_while(|c| *c == '*').count();
+        let rest = &line[level..];
+        (level > 0 && (rest.is_empty() || rest.starts_with(' '))).then_some(level)
+    }
+
+    /// `** TODO Title :tag:other:`, where an upper case first word is the
+    /// TODO keyword
+    fn parse_heading(line: &str) -> Option<(u8, Option<String>, Vec<Inline>, Vec<String>)> {
+        let level = heading_level(line)?;
+        let mut rest = line[level..].trim();
 
-        let mut todo = None;
-        let mut title = Vec::new();
         let mut tags = Vec::new();
+        let last = rest.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        if is_tags(&rest[last..]) {
+            tags = rest[last..]
+                .trim_matches(':')
+                .split(':')
+                .map(ToString::to_string)
+                .collect();
+            rest = rest[..last].trim_end();
+        }
+
+        let mut todo = None;
+        if let Some(word) = rest.split_whitespace().next()
+            && word.chars().all(char::is_uppercase)
+        {
+            todo = Some(word.to_string());
+            rest = rest[word.len()..].trim_start();
+        }
+
+        Some((level as u8, todo, parse_inlines(rest), tags))
+    }
 
-        let parts: Vec<&str> = rest.split_whitespace().collect();
+    /// `:tag:other:`
+    fn is_tags(word: &str) -> bool {
+        word.len() > 2
+            && word.starts_with(':')
+            && word.ends_with(':')
+            && word[1..word.len() - 1]
+                .split(':')
+                .all(|tag| !tag.is_empty())
+    }
+
+    fn parse_property(line: &str) -> Option<(String, String)> {
+        let (key, value) = line.trim().strip_prefix(':')?.split_once(':')?;
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return None;
+        }
+        Some((key.to_string(), value.trim().to_string()))
+    }
+
+    /// The blocks in the lines under a heading, or inside a greater block or
+    /// list item; `#+CAPTION:` and `#+ATTR_HTML:` lines go with the block
+    /// after them
+    fn parse_blocks(lines: &[&str]) -> Vec<Block> {
+        let mut blocks = Vec::new();
+        let mut caption = None;
+        let mut attributes = Vec::new();
         let mut i = 0;
+        while let Some(line) = lines.get(i) {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                i += 1;
+            } else if let Some(value) = keyword_value(trimmed, "CAPTION") {
+                caption = Some(parse_inlines(value));
+                i += 1;
+            } else if let Some(value) = keyword_value(trimmed, "ATTR_HTML") {
+                attributes = parse_attributes(value);
+                i += 1;
+            } else {
+                let (block, next) =
+                    parse_block(lines, i, caption.take(), std::mem::take(&mut attributes));
+                blocks.push(block);
+                i = next;
+            }
+        }
+        blocks
+    }
+
+    /// The block starting at line `start`, and the line after it
+    fn parse_block(
+        lines: &[&str],
+        start: usize,
+        caption: Option<Vec<Inline>>,
+        attributes: Vec<Attribute>,
+    ) -> (Block, usize) {
+        let line = lines[start];
+        let trimmed = line.trim();
 
-        if parts
-            .first()
-            .is_some_and(|s| s.chars().all(char::is_uppercase))
+        if let Some((name, arguments)) = begin_line(trimmed)
+            && let Some(end) = find_end(lines, start, name)
         {
-            todo = Some(parts[0].to_string());
-            i += 1;
+            let inner = &lines[start + 1..end];
+            let block = match name.to_uppercase().as_str() {
+                "EXPORT" if arguments.eq_ignore_ascii_case("html") => Block::html(verbatim(inner)),
+                "SRC" | "EXPORT" => Block::code_block(first_word(arguments), verbatim(inner)),
+                "EXAMPLE" => Block::code_block(None, verbatim(inner)),
+                "QUOTE" => Block::quote(parse_blocks(inner)),
+                _ => {
+                    let name = name.to_lowercase();
+                    let classes = if ALERT_KINDS.contains(&name.as_str()) {
+                        vec!["alert".to_string(), name]
+                    } else {
+                        vec![name]
+                    };
+                    Block::div(classes, attributes, parse_blocks(inner))
+                }
+            };
+            return (block, end + 1);
         }
 
-        while i < parts.len() {
-            let part = parts[i];
-            if part.starts_with(':') && part.ends_with(':') {
-                tags = part
-                    .trim_matches(':')
-                    .split(':')
-                    .map(ToString::to_string)
-                    .collect();
-                break;
+        if trimmed == "\\["
+            && let Some(end) = (start + 1..lines.len()).find(|&j| lines[j].trim() == "\\]")
+        {
+            return (Block::math_block(verbatim(&lines[start + 1..end])), end + 1);
+        }
+
+        if trimmed.len() >= 5 && trimmed.chars().all(|c| c == '-') {
+            return (Block::horizontal_rule(), start + 1);
+        }
+
+        if trimmed.starts_with('|') {
+            let end = (start..lines.len())
+                .find(|&j| !lines[j].trim_start().starts_with('|'))
+                .unwrap_or(lines.len());
+            return (parse_table(&lines[start..end], caption), end);
+        }
+
+        if parse_item(line).is_some() {
+            return parse_list(lines, start);
+        }
+
+        if let Some((label, first)) = footnote_definition(line) {
+            let (body, end) = item_body(lines, start, first);
+            let content = parse_blocks(&body);
+            return (Block::footnote_definition(label.to_string(), content), end);
+        }
+
+        parse_paragraph(lines, start, &attributes)
+    }
+
+    /// The name and arguments of a `#+BEGIN_NAME arguments` line
+    fn begin_line(line: &str) -> Option<(&str, &str)> {
+        let rest = line.strip_prefix("#+")?;
+        let rest = rest
+            .get(6..)
+            .filter(|_| rest[..6].eq_ignore_ascii_case("BEGIN_"))?;
+        let (name, arguments) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        (!name.is_empty()).then(|| (name, arguments.trim()))
+    }
+
+    /// The `#+END_NAME` line closing the block opened at `start`, past any
+    /// nested block of the same name
+    fn find_end(lines: &[&str], start: usize, name: &str) -> Option<usize> {
+        let mut depth = 0;
+        for (i, line) in lines.iter().enumerate().skip(start + 1) {
+            let line = line.trim();
+            if begin_line(line).is_some_and(|(inner, _)| inner.eq_ignore_ascii_case(name)) {
+                depth += 1;
+            } else if line
+                .strip_prefix("#+")
+                .and_then(|rest| rest.get(..4).zip(rest.get(4..)))
+                .is_some_and(|(end, rest)| {
+                    end.eq_ignore_ascii_case("END_") && rest.eq_ignore_ascii_case(name)
+                })
+            {
+                if depth == 0 {
+                    return Some(i);
+                }
+                depth -= 1;
             }
-            title.push(Inline::Text {
-                text: part.to_string(),
-            });
-            i += 1;
         }
+        None
+    }
 
-        Some((level as u8, todo, title, tags))
+    fn first_word(text: &str) -> Option<String> {
+        text.split_whitespace().next().map(ToString::to_string)
     }
 
-    fn parse_property(line: &str) -> Option<(String, String)> {
-        if !line.starts_with(':') {
-            return None;
+    /// The lines of a source, export or math block, less the comma that
+    /// keeps Org from reading one as a heading or keyword
+    fn verbatim(lines: &[&str]) -> String {
+        lines
+            .iter()
+            .map(|line| match line.strip_prefix(',') {
+                Some(rest) if is_escaped(rest) => rest,
+                _ => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn is_escaped(line: &str) -> bool {
+        let line = line.trim_start().trim_start_matches(',');
+        line.starts_with('*') || line.starts_with("#+")
+    }
+
+    /// `:key value :other value` pairs of an `#+ATTR_HTML:` line
+    fn parse_attributes(text: &str) -> Vec<Attribute> {
+        let mut attributes: Vec<Attribute> = Vec::new();
+        for word in text.split_whitespace() {
+            match (word.strip_prefix(':'), attributes.last_mut()) {
+                (Some(key), _) if !key.is_empty() => attributes.push(Attribute {
+                    key: key.to_string(),
+                    value: String::new(),
+                }),
+                (_, Some(last)) => {
+                    if !last.value.is_empty() {
+                        last.value.push(' ');
+                    }
+                    last.value.push_str(word);
+                }
+                (_, None) => {}
+            }
+        }
+        attributes
+    }
+
+    /// The first row as the headers, then an optional row of `<l>`, `<c>`
+    /// and `<r>` cookies and the other rows, skipping `|---+---|` rules
+    fn parse_table(lines: &[&str], caption: Option<Vec<Inline>>) -> Block {
+        let mut rows = lines
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.starts_with("|-"))
+            .map(split_row)
+            .peekable();
+        let headers = rows.next().unwrap_or_default();
+        let alignments = rows
+            .next_if(|cells| cells.iter().all(|cell| cookie(cell).is_some()))
+            .map(|cells| cells.iter().filter_map(|cell| cookie(cell)).collect());
+        let cells = |cells: Vec<&str>| cells.into_iter().map(parse_inlines).collect::<Vec<_>>();
+        Block::table(
+            cells(headers),
+            rows.map(cells).collect(),
+            alignments,
+            caption,
+        )
+    }
+
+    fn split_row(line: &str) -> Vec<&str> {
+        let line = line.strip_prefix('|').unwrap_or(line);
+        let line = line.strip_suffix('|').unwrap_or(line);
+        line.split('|').map(str::trim).collect()
+    }
+
+    fn cookie(cell: &str) -> Option<Alignment> {
+        match cell {
+            "<l>" => Some(Alignment::Left),
+            "<c>" => Some(Alignment::Center),
+            "<r>" => Some(Alignment::Right),
+            _ => None,
         }
-        let parts: Vec<&str> = line.trim_matches(':').splitn(2, ':').collect();
-        if parts.len() == 2 {
-            Some((parts[0].trim().to_string(), parts[1].trim().to_string()))
+    }
+
+    /// The start of a list item, with what follows its bullet
+    enum Item<'a> {
+        List(ListStyle, Option<Checkbox>, &'a str),
+        /// `- term :: definition`
+        Definition(&'a str, &'a str),
+    }
+
+    /// `- text`, `+ text`, `1. text` or `1) text`, optionally with a
+    /// `[ ]`, `[X]` or `[-]` checkbox after the bullet, or `- term :: text`
+    fn parse_item(line: &str) -> Option<Item<'_>> {
+        let trimmed = line.trim_start();
+        let (style, rest) = if let Some(rest) = after_bullet(trimmed, "-") {
+            (ListStyle::Unordered { bullet: b'-' }, rest)
+        } else if let Some(rest) = after_bullet(trimmed, "+") {
+            (ListStyle::Unordered { bullet: b'+' }, rest)
         } else {
-            None
+            let digits = trimmed.chars().take_while(char::is_ascii_digit).count();
+            if digits == 0 {
+                return None;
+            }
+            let (style, delimiter) = match trimmed[digits..].chars().next()? {
+                '.' => (NumberingStyle::Dot, "."),
+                ')' => (NumberingStyle::Paren, ")"),
+                _ => return None,
+            };
+            let numbering = Numbering {
+                kind: NumberingType::Decimal,
+                style,
+            };
+            let rest = after_bullet(&trimmed[digits..], delimiter)?;
+            (ListStyle::Ordered { numbering }, rest)
+        };
+
+        if matches!(style, ListStyle::Unordered { .. })
+            && let Some((term, definition)) = split_definition(rest)
+        {
+            return Some(Item::Definition(term, definition));
         }
+        Some(match Checkbox::strip_from(rest) {
+            Some((checkbox, text)) => Item::List(style, Some(checkbox), text),
+            None => Item::List(style, None, rest),
+        })
     }
 
-    fn parse_block(line: &str, lines_iter: &mut std::iter::Peekable<std::str::Lines>) -> Block {
-        if line.trim().starts_with('|') {
-            let mut rows = Vec::new();
-            while let Some(&next_line) = lines_iter.peek() {
-                if next_line.trim().starts_with('|') {
-                    rows.push(parse_table_row(next_line));
-                    lines_iter.next();
-                } else {
-                    break;
+    /// What follows `bullet` and a space, or a bullet on its own
+    fn after_bullet<'a>(line: &'a str, bullet: &str) -> Option<&'a str> {
+        let rest = line.strip_prefix(bullet)?;
+        if rest.is_empty() {
+            Some(rest)
+        } else {
+            rest.strip_prefix(' ')
+        }
+    }
+
+    /// The term and definition either side of ` :: `
+    fn split_definition(text: &str) -> Option<(&str, &str)> {
+        text.match_indices(" ::").find_map(|(i, _)| {
+            let definition = &text[i + 3..];
+            if definition.is_empty() {
+                Some((&text[..i], definition))
+            } else {
+                definition
+                    .strip_prefix(' ')
+                    .map(|definition| (&text[..i], definition))
+            }
+        })
+    }
+
+    /// Items of the same kind as the one at `start`, as a list or a
+    /// definition list
+    fn parse_list(lines: &[&str], start: usize) -> (Block, usize) {
+        let style = match parse_item(lines[start]) {
+            Some(Item::List(style, ..)) => Some(style),
+            _ => None,
+        };
+        let mut items = Vec::new();
+        let mut definitions = Vec::new();
+        let mut i = start;
+        while let Some(item) = lines.get(i).and_then(|line| parse_item(line)) {
+            match item {
+                Item::List(item_style, checkbox, first) if style.as_ref() == Some(&item_style) => {
+                    let (body, next) = item_body(lines, i, first);
+                    items.push(ListItem {
+                        checkbox,
+                        blocks: parse_blocks(&body),
+                    });
+                    i = next;
                 }
+                Item::Definition(term, first) if style.is_none() => {
+                    let (body, next) = item_body(lines, i, first);
+                    definitions.push(DefinitionItem {
+                        term: parse_inlines(term),
+                        definition: parse_blocks(&body),
+                    });
+                    i = next;
+                }
+                _ => break,
             }
+        }
 
-            return Block::Container {
-                container: crate::models::ContainerBlock::Table {
-                    headers: rows.first().cloned().unwrap_or_default(),
-                    rows,
-                    alignments: None,
-                    caption: None,
-                },
+        let block = match style {
+            Some(style) => Block::list(style, items),
+            None => Block::definition_list(definitions),
+        };
+        (block, i)
+    }
+
+    /// An item's text after its bullet, then the lines indented beneath it
+    /// without that indentation
+    fn item_body<'a>(lines: &[&'a str], start: usize, first: &'a str) -> (Vec<&'a str>, usize) {
+        let mut body = vec![first];
+        let mut i = start + 1;
+        while let Some(line) = lines.get(i).and_then(|line| line.strip_prefix("  ")) {
+            body.push(line);
+            i += 1;
+        }
+        (body, i)
+    }
+
+    /// `[fn:label] text` at the start of a line
+    fn footnote_definition(line: &str) -> Option<(&str, &str)> {
+        let (label, rest) = line.strip_prefix("[fn:")?.split_once(']')?;
+        let rest = after_bullet(rest, "")?;
+        is_label(label).then_some((label, rest))
+    }
+
+    fn is_label(label: &str) -> bool {
+        !label.is_empty()
+            && label
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    }
+
+    /// Lines joined by `\\` line breaks, or a lone image link as an image
+    /// with the `:alt` of its `#+ATTR_HTML:` line
+    fn parse_paragraph(lines: &[&str], start: usize, attributes: &[Attribute]) -> (Block, usize) {
+        let mut end = start + 1;
+        while lines[end - 1].ends_with("\\\\")
+            && lines.get(end).is_some_and(|line| !line.trim().is_empty())
+        {
+            end += 1;
+        }
+
+        let content = parse_inlines(&lines[start..end].join("\n"));
+        let block = match content.as_slice() {
+            [Inline::Image { src, .. }] => {
+                let alt = attributes
+                    .iter()
+                    .find(|attribute| attribute.key == "alt")
+                    .map(|attribute| attribute.value.clone());
+                Block::image(alt, src.clone())
+            }
+            _ => Block::paragraph(content),
+        };
+        (block, end)
+    }
+
+    /// Emphasis, code, math, links, footnote references, targets, export
+    /// snippets, sub- and superscripts, line breaks and entities in a run of
+    /// Org text
+    fn parse_inlines(text: &str) -> Vec<Inline> {
+        let mut parser = InlineParser {
+            chars: text.chars().collect(),
+            unclosed: HashSet::new(),
+        };
+        parser
+            .parse(0, Stop::End)
+            .map_or_default(|(inlines, _)| inlines)
+    }
+
+    /// What ends a run of objects
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    enum Stop {
+        End,
+        /// The closing marker of bold, italic or struck-through text
+        Marker(char),
+        /// The `}` of a sub- or superscript
+        Brace,
+        /// The `]]` of a link's description
+        Link,
+    }
+
+    enum Object {
+        Inline(Inline),
+        /// A character written as an entity
+        Char(char),
+    }
+
+    struct InlineParser {
+        chars: Vec<char>,
+        /// Runs already found to have no end, so a stray marker is only
+        /// followed once
+        unclosed: HashSet<(usize, Stop)>,
+    }
+
+    impl InlineParser {
+        /// The objects from `start` up to `stop` and the position past it,
+        /// or `None` when the text ends first
+        fn parse(&mut self, start: usize, stop: Stop) -> Option<(Vec<Inline>, usize)> {
+            if self.unclosed.contains(&(start, stop)) {
+                return None;
+            }
+            let mut inlines = Vec::new();
+            let mut text = String::new();
+            let mut i = start;
+            loop {
+                let end = match stop {
+                    Stop::End => (i == self.chars.len()).then_some(i),
+                    Stop::Marker(marker) => {
+                        (i > start && self.at(i) == Some(marker) && self.closes(i)).then_some(i + 1)
+                    }
+                    Stop::Brace => (self.at(i) == Some('}')).then_some(i + 1),
+                    Stop::Link => self.starts_with(i, "]]").then_some(i + 2),
+                };
+                if let Some(end) = end {
+                    flush(&mut text, &mut inlines);
+                    return Some((inlines, end));
+                }
+                let Some(c) = self.at(i) else {
+                    self.unclosed.insert((start, stop));
+                    return None;
+                };
+
+                match self.object(i, start) {
+                    Some((Object::Char(c), next)) => {
+                        text.push(c);
+                        i = next;
+                    }
+                    Some((Object::Inline(inline), next)) => {
+                        // The space the serializer puts before a target
+                        if matches!(inline, Inline::BlockId { .. }) && text.ends_with(' ') {
+                            text.pop();
+                        }
+                        flush(&mut text, &mut inlines);
+                        inlines.push(inline);
+                        i = next;
+                    }
+                    None => {
+                        text.push(c);
+                        i += 1;
+                    }
+                }
+            }
+        }
+
+        /// The object at `i` and the position past it, where `start` begins
+        /// the current run and counts as the start of a line
+        fn object(&mut self, i: usize, start: usize) -> Option<(Object, usize)> {
+            let next = self.at(i + 1);
+            let (inline, end) = match self.chars[i] {
+                '\\' if next == Some('\\') && matches!(self.at(i + 2), None | Some('\n')) => {
+                    (Inline::LineBreak, (i + 3).min(self.chars.len()))
+                }
+                '\\' if next == Some('(') => {
+                    let end = self.find(i + 2, "\\)")?;
+                    let content = self.slice(i + 2, end);
+                    (Inline::Math { content }, end + 2)
+                }
+                '\\' => return self.entity(i),
+                '[' if next == Some('[') => return self.link(i),
+                '[' if self.starts_with(i, "[fn:") => {
+                    let end = self.find(i + 4, "]")?;
+                    let label = self.slice(i + 4, end);
+                    if !is_label(&label) {
+                        return None;
+                    }
+                    (Inline::FootnoteReference { label }, end + 1)
+                }
+                '<' if next == Some('<') => {
+                    let end = self.find(i + 2, ">>")?;
+                    let id = self.slice(i + 2, end);
+                    if id.is_empty() || id.contains(['<', '\n']) {
+                        return None;
+                    }
+                    (Inline::BlockId { id }, end + 2)
+                }
+                '@' if self.starts_with(i, "@@html:") => {
+                    let end = self.find(i + 7, "@@")?;
+                    let content = self.slice(i + 7, end);
+                    (Inline::Html { content }, end + 2)
+                }
+                marker @ ('^' | '_') if next == Some('{') => {
+                    let (content, end) = self.parse(i + 2, Stop::Brace)?;
+                    if marker == '^' {
+                        (Inline::Superscript { content }, end)
+                    } else {
+                        (Inline::Subscript { content }, end)
+                    }
+                }
+                '~' if self.opens(i, start) => {
+                    let end = (i + 2..self.chars.len())
+                        .find(|&j| self.chars[j] == '~' && self.closes(j))?;
+                    let code = self.slice(i + 1, end);
+                    (Inline::Code { code }, end + 1)
+                }
+                marker @ ('*' | '/' | '+') if self.opens(i, start) => {
+                    let (content, end) = self.parse(i + 1, Stop::Marker(marker))?;
+                    let inline = match marker {
+                        '*' => Inline::Bold { content },
+                        '/' => Inline::Italic { content },
+                        _ => Inline::Strikethrough { content },
+                    };
+                    (inline, end)
+                }
+                _ => return None,
             };
+            Some((Object::Inline(inline), end))
         }
 
-        Block::Leaf {
-            leaf: crate::models::LeafBlock::Paragraph {
-                content: vec![crate::models::Inline::Text {
-                    text: line.to_string(),
-                }],
-            },
+        /// `[[target]]` or `[[target][description]]`, with `\`, `[` and `]`
+        /// escaped in the target; a target alone that names an image is one
+        fn link(&mut self, i: usize) -> Option<(Object, usize)> {
+            let mut target = String::new();
+            let mut j = i + 2;
+            loop {
+                match self.at(j)? {
+                    '\\' if matches!(self.at(j + 1), Some('\\' | '[' | ']')) => {
+                        target.push(self.chars[j + 1]);
+                        j += 2;
+                    }
+                    ']' => break,
+                    '[' | '\n' => return None,
+                    c => {
+                        target.push(c);
+                        j += 1;
+                    }
+                }
+            }
+
+            let (inline, end) = match self.at(j + 1)? {
+                ']' if is_image(&target) => (
+                    Inline::Image {
+                        alt_text: None,
+                        src: target,
+                    },
+                    j + 2,
+                ),
+                ']' => (
+                    Inline::Link {
+                        text: Vec::new(),
+                        target,
+                    },
+                    j + 2,
+                ),
+                '[' => {
+                    let (text, end) = self.parse(j + 2, Stop::Link)?;
+                    (Inline::Link { text, target }, end)
+                }
+                _ => return None,
+            };
+            Some((Object::Inline(inline), end))
+        }
+
+        /// `\name{}` for one of the entities the serializer writes
+        fn entity(&self, i: usize) -> Option<(Object, usize)> {
+            let end = (i + 1..self.chars.len()).find(|&j| !self.chars[j].is_ascii_alphabetic())?;
+            if !self.starts_with(end, "{}") {
+                return None;
+            }
+            let name = self.slice(i + 1, end);
+            let (_, c) = ENTITIES.iter().find(|(entity, _)| *entity == name)?;
+            Some((Object::Char(*c), end + 2))
+        }
+
+        /// Whether a marker at `i` can open emphasis or code: at the start
+        /// of the run or after a space or opening punctuation, and before
+        /// something other than a space
+        fn opens(&self, i: usize, start: usize) -> bool {
+            let before = if i == start { None } else { self.at(i - 1) };
+            before.is_none_or(|c| c.is_whitespace() || PRE.contains(c))
+                && self.at(i + 1).is_some_and(|c| !c.is_whitespace())
+        }
+
+        /// Whether a marker at `i` can close emphasis or code: after
+        /// something other than a space, and at the end or before a space or
+        /// closing punctuation
+        fn closes(&self, i: usize) -> bool {
+            i > 0
+                && !self.chars[i - 1].is_whitespace()
+                && self
+                    .at(i + 1)
+                    .is_none_or(|c| c.is_whitespace() || POST.contains(c))
+        }
+
+        fn at(&self, i: usize) -> Option<char> {
+            self.chars.get(i).copied()
+        }
+
+        fn starts_with(&self, i: usize, text: &str) -> bool {
+            let mut chars = self.chars.iter().skip(i);
+            text.chars().all(|c| chars.next() == Some(&c))
+        }
+
+        /// Where `text` next occurs from `i`
+        fn find(&self, i: usize, text: &str) -> Option<usize> {
+            (i..self.chars.len()).find(|&j| self.starts_with(j, text))
+        }
+
+        fn slice(&self, start: usize, end: usize) -> String {
+            self.chars[start..end].iter().collect()
         }
     }
 
-    fn parse_table_row(line: &str) -> Vec<Vec<Inline>> {
-        line.trim()
-            .trim_matches('|')
-            .split('|')
-            .map(|cell| {
-                vec![Inline::Text {
-                    text: cell.trim().to_string(),
-                }]
-            })
-            .collect()
+    fn flush(text: &mut String, inlines: &mut Vec<Inline>) {
+        if !text.is_empty() {
+            inlines.push(Inline::Text {
+                text: std::mem::take(text),
+            });
+        }
+    }
+
+    fn is_image(target: &str) -> bool {
+        target.rsplit_once('.').is_some_and(|(_, extension)| {
+            IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str())
+        })
     }
 
     #[allow(dead_code)]
@@ -290,134 +1064,728 @@ pub mod parser {
 
 /// --- lower.rs ---
 pub mod lower {
-    use super::model::OrgDocument;
-    use crate::models::{Block, Note};
+    use super::{
+        model::{DRAWER_CLASS, OrgDocument},
+        properties,
+    };
+    use crate::models::{Attribute, Block, Note, Properties, PropertyValue};
 
     #[must_use]
     pub fn org_to_note(doc: OrgDocument, id_hint: Option<&str>) -> Note {
         let mut blocks = Vec::new();
+        let properties = lower_properties(&doc);
+        let title = properties
+            .get("title")
+            .and_then(PropertyValue::as_str)
+            .map_or_else(|| extract_title(&doc.nodes), ToString::to_string);
 
+        blocks.extend(doc.blocks);
         for node in doc.nodes {
             lower_node(&node, &mut blocks);
         }
 
         Note {
             id: id_hint.unwrap_or("org").to_string(),
-            title: extract_title(&blocks),
+            title,
+            properties,
             blocks,
         }
     }
 
+    /// File keywords (lowercased) followed by the file property drawer
+    fn lower_properties(doc: &OrgDocument) -> Properties {
+        let keywords = doc.keywords.iter().map(|(key, value)| {
+            let key = key.to_lowercase();
+            let value = match key.as_str() {
+                "title" => PropertyValue::text(value),
+                "filetags" => PropertyValue::List {
+                    items: value
+                        .split(':')
+                        .filter(|tag| !tag.trim().is_empty())
+                        .map(|tag| PropertyValue::text(tag.trim()))
+                        .collect(),
+                },
+                _ => properties::read_value(value),
+            };
+            let key = if key == "filetags" {
+                "tags".to_string()
+            } else {
+                key
+            };
+            (key, value)
+        });
+        let drawer = doc
+            .properties
+            .iter()
+            .map(|(key, value)| (key.clone(), properties::read_value(value)));
+
+        keywords.chain(drawer).collect()
+    }
+
     fn lower_node(node: &super::model::OrgNode, out: &mut Vec<Block>) {
-        out.push(Block::heading(node.level, node.title.clone()));
+        // Keep the TODO keyword as the first word of the heading so the state
+        // survives lowering into the format-agnostic model
+        let mut title = node.title.clone();
+        if let Some(todo) = &node.todo {
+            match title.first_mut() {
+                Some(crate::models::Inline::Text { text }) => *text = format!("{todo} {text}"),
+                Some(_) => title.insert(
+                    0,
+                    crate::models::Inline::Text {
+                        text: format!("{todo} "),
+                    },
+                ),
+                None => title.push(crate::models::Inline::Text { text: todo.clone() }),
+            }
+        }
+        title.extend(
+            node.tags
+                .iter()
+                .map(|tag| crate::models::Inline::Tag { name: tag.clone() }),
+        );
+        out.push(Block::heading(node.level, title));
+        if !node.properties.is_empty() {
+            let attributes = node
+                .drawer()
+                .into_iter()
+                .map(|(key, value)| Attribute {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                })
+                .collect();
+            out.push(Block::div(
+                vec![DRAWER_CLASS.to_string()],
+                attributes,
+                Vec::new(),
+            ));
+        }
         out.extend(node.body.clone());
         for child in &node.children {
             lower_node(child, out);
         }
     }
 
-    fn extract_title(blocks: &[Block]) -> String {
-        for block in blocks {
-            if let Block::Leaf {
-                leaf: crate::models::LeafBlock::Heading { content, .. },
-            } = block
-            {
-                return content
-                    .iter()
-                    .filter_map(|i| {
-                        if let crate::models::Inline::Text { text } = i {
-                            Some(text.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect::<Vec<_>>()
-                    .join(" ");
-            }
+    fn extract_title(nodes: &[super::model::OrgNode]) -> String {
+        if let Some(node) = nodes.first() {
+            return crate::document::plain_text(&node.title);
         }
         "Untitled".to_string()
     }
 }
 
 pub mod serializer {
-    use crate::models::{Block, ContainerBlock, Inline, LeafBlock, Note};
+    use std::fmt::Write;
+
+    use super::{
+        model::{DRAWER_CLASS, ENTITIES, OrgDocument, OrgNode, POST, PRE},
+        parser::parse_org,
+        properties,
+    };
+    use crate::models::{
+        Alignment, Attribute, Block, Checkbox, ContainerBlock, Inline, LeafBlock, ListItem,
+        ListStyle, Note, NumberingStyle, PropertyValue,
+    };
+
+    /// Keywords written as `#+KEY:` lines; other properties go in a drawer
+    const KEYWORDS: [&str; 5] = ["author", "date", "email", "language", "description"];
 
     #[must_use]
     pub fn note_to_org(note: &Note) -> String {
+        let body = blocks(&note.blocks);
+
         let mut out = String::new();
-        for block in &note.blocks {
-            write_block(block, &mut out);
-        }
+        write_properties(note, &body, &mut out);
+        out.push_str(&body);
         out
     }
 
+    /// File keywords and property drawer for the note's properties
+    fn write_properties(note: &Note, body: &str, out: &mut String) {
+        // The title is only written when the first heading would not yield it
+        let derived_title = super::lower::org_to_note(parse_org(body), None).title;
+        if note.properties.get("title").is_some() || note.title != derived_title {
+            writeln!(out, "#+TITLE: {}", note.title).unwrap();
+        }
+
+        let mut drawer = Vec::new();
+        for (key, value) in note.properties.iter() {
+            match key {
+                "title" => {}
+                "tags" if is_filetags(value) => {
+                    let tags = value.as_strings().join(":");
+                    writeln!(out, "#+FILETAGS: :{tags}:").unwrap();
+                }
+                key if KEYWORDS.contains(&key) && !matches!(value, PropertyValue::Null) => {
+                    let value = properties::write_value(value);
+                    writeln!(out, "#+{}: {value}", key.to_uppercase()).unwrap();
+                }
+                key => drawer.push((key, properties::write_value(value))),
+            }
+        }
+        write_drawer(
+            drawer.iter().map(|(key, value)| (*key, value.as_str())),
+            out,
+        );
+    }
+
+    /// Tags that fit `#+FILETAGS: :a:b:`
+    fn is_filetags(value: &PropertyValue) -> bool {
+        let PropertyValue::List { items } = value else {
+            return false;
+        };
+        !items.is_empty()
+            && items.iter().all(|item| {
+                matches!(item, PropertyValue::Text { value }
+                    if !value.is_empty() && !value.contains([':', ' ', '\t', '\n']))
+            })
+    }
+
     fn write_block(block: &Block, out: &mut String) {
         match block {
-            Block::Leaf { leaf } => match leaf {
-                LeafBlock::Paragraph { content } => {
-                    for inline in content {
-                        write_inline(inline, out);
-                    }
-                    out.push('\n');
+            Block::Leaf { leaf } => write_leaf(leaf, out),
+            Block::Container { container } => match container {
+                ContainerBlock::Quote { blocks } => write_greater_block("QUOTE", blocks, out),
+                ContainerBlock::List { style, items } => write_list(style, items, out),
+                ContainerBlock::Table {
+                    headers,
+                    rows,
+                    alignments,
+                    caption,
+                } => write_table(
+                    headers,
+                    rows,
+                    alignments.as_deref(),
+                    caption.as_deref(),
+                    out,
+                ),
+                ContainerBlock::Div {
+                    classes,
+                    attributes,
+                    children,
+                } => write_div(classes, attributes, children, out),
+            },
+            Block::DefinitionList { items } => {
+                for item in items {
+                    let body = blocks(&item.definition);
+                    write_item(&format!("- {} ::", inlines(&item.term)), &body, out);
                 }
-                LeafBlock::Heading { level, content } => {
-                    out.push_str(&"*".repeat(*level as usize));
-                    out.push(' ');
-                    for inline in content {
-                        write_inline(inline, out);
-                    }
-                    out.push('\n');
+            }
+            Block::FootnoteDefinition { label, content } => {
+                let body = blocks(content);
+                write_item(&format!("[fn:{label}]"), &body, out);
+            }
+        }
+    }
+
+    fn write_leaf(leaf: &LeafBlock, out: &mut String) {
+        match leaf {
+            LeafBlock::Paragraph { content } => {
+                out.push_str(&inlines(content));
+                out.push('\n');
+            }
+            LeafBlock::Heading { level, content } => {
+                let tags: Vec<String> = content
+                    .iter()
+                    .filter_map(|inline| match inline {
+                        Inline::Tag { name } => Some(name.clone()),
+                        _ => None,
+                    })
+                    .collect();
+                let title: Vec<Inline> = content
+                    .iter()
+                    .filter(|inline| !matches!(inline, Inline::Tag { .. }))
+                    .cloned()
+                    .collect();
+                write_heading(*level, None, &title, &tags, out);
+            }
+            LeafBlock::Image { alt_text, src } => {
+                if let Some(alt) = alt_text {
+                    writeln!(out, "#+ATTR_HTML: :alt {alt}").unwrap();
                 }
-                _ => {}
-            },
-            Block::Container {
-                container: ContainerBlock::Quote { blocks },
-            } => {
-                out.push_str("#+begin_quote\n");
-                for b in blocks {
-                    write_block(b, out);
+                writeln!(out, "[[{}]]", link_path(src)).unwrap();
+            }
+            LeafBlock::CodeBlock { language, content } => {
+                match language {
+                    Some(language) => writeln!(out, "#+BEGIN_SRC {language}").unwrap(),
+                    None => out.push_str("#+BEGIN_SRC\n"),
                 }
-                out.push_str("#+end_quote\n");
+                write_verbatim(content, out);
+                out.push_str("#+END_SRC\n");
+            }
+            LeafBlock::MathBlock { content } => {
+                out.push_str("\\[\n");
+                write_verbatim(content, out);
+                out.push_str("\\]\n");
+            }
+            LeafBlock::HorizontalRule => out.push_str("-----\n"),
+            LeafBlock::Attachment { attachment } => {
+                writeln!(
+                    out,
+                    "[[{}][{}]]",
+                    link_path(&attachment.src),
+                    attachment.name
+                )
+                .unwrap();
+            }
+            LeafBlock::Html { content } => {
+                out.push_str("#+BEGIN_EXPORT html\n");
+                write_verbatim(content, out);
+                out.push_str("#+END_EXPORT\n");
             }
-            _ => {}
         }
     }
 
-    fn write_inline(inline: &Inline, out: &mut String) {
-        match inline {
-            Inline::Text { text } => out.push_str(text),
-            Inline::Bold { content } => {
-                out.push('*');
-                for c in content {
-                    write_inline(c, out);
+    /// `* TODO Title :tag:other:`
+    fn write_heading(
+        level: u8,
+        todo: Option<&str>,
+        title: &[Inline],
+        tags: &[String],
+        out: &mut String,
+    ) {
+        out.push_str(&"*".repeat(usize::from(level.max(1))));
+        if let Some(todo) = todo {
+            write!(out, " {todo}").unwrap();
+        }
+        let title = inlines(title);
+        if !title.is_empty() {
+            write!(out, " {title}").unwrap();
+        }
+        if !tags.is_empty() {
+            write!(out, " :{}:", tags.join(":")).unwrap();
+        }
+        out.push('\n');
+    }
+
+    /// `#+BEGIN_NAME` ... `#+END_NAME` around blocks
+    fn write_greater_block(name: &str, blocks: &[Block], out: &mut String) {
+        writeln!(out, "#+BEGIN_{name}").unwrap();
+        for block in blocks {
+            write_block(block, out);
+        }
+        writeln!(out, "#+END_{name}").unwrap();
+    }
+
+    /// A div becomes a special block named after its first class, or the
+    /// kind of an alert; one without classes leaves only its content. An
+    /// empty `properties` div is a heading's property drawer.
+    fn write_div(
+        classes: &[String],
+        attributes: &[Attribute],
+        children: &[Block],
+        out: &mut String,
+    ) {
+        if children.is_empty() && matches!(classes, [class] if class == DRAWER_CLASS) {
+            let properties = attributes
+                .iter()
+                .map(|Attribute { key, value }| (key.as_str(), value.as_str()));
+            write_drawer(properties, out);
+            return;
+        }
+
+        let name = match classes {
+            [alert, kind] if alert == "alert" => Some(kind),
+            classes => classes.first(),
+        };
+        let Some(name) = name else {
+            for block in children {
+                write_block(block, out);
+            }
+            return;
+        };
+
+        if !attributes.is_empty() {
+            out.push_str("#+ATTR_HTML:");
+            for Attribute { key, value } in attributes {
+                write!(out, " :{key} {value}").unwrap();
+            }
+            out.push('\n');
+        }
+        write_greater_block(&name.to_uppercase(), children, out);
+    }
+
+    /// Lines of a source, export or math block, with a comma in front of
+    /// those Org would otherwise read as a heading or keyword
+    fn write_verbatim(content: &str, out: &mut String) {
+        for line in content.lines() {
+            let trimmed = line.trim_start().trim_start_matches(',');
+            if trimmed.starts_with('*') || trimmed.starts_with("#+") {
+                out.push(',');
+            }
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    fn write_list(style: &ListStyle, items: &[ListItem], out: &mut String) {
+        for (i, item) in items.iter().enumerate() {
+            let mut bullet = match style {
+                ListStyle::Unordered { bullet: b'+' } => "+".to_string(),
+                ListStyle::Unordered { .. } => "-".to_string(),
+                ListStyle::Ordered { numbering } => {
+                    let delimiter = if numbering.style == NumberingStyle::Paren {
+                        ')'
+                    } else {
+                        '.'
+                    };
+                    format!("{}{delimiter}", i + 1)
                 }
-                out.push('*');
+            };
+            match item.checkbox {
+                Some(Checkbox::Checked) => bullet.push_str(" [X]"),
+                Some(checkbox) => write!(bullet, " {}", checkbox.marker()).unwrap(),
+                None => {}
             }
-            Inline::Italic { content } => {
-                out.push('/');
-                for c in content {
-                    write_inline(c, out);
+
+            let body = blocks(&item.blocks);
+            write_item(&bullet, &body, out);
+        }
+    }
+
+    /// A list item's first line after its bullet, and the rest indented
+    /// beneath it; a block or table cannot start on the bullet's line, so
+    /// its first line goes beneath too
+    fn write_item(bullet: &str, body: &str, out: &mut String) {
+        out.push_str(bullet);
+        let mut lines = body.lines().peekable();
+        if let Some(first) = lines.next_if(|line| {
+            !(line.is_empty() || line.starts_with("#+") || line.starts_with('|') || *line == "\\[")
+        }) {
+            out.push(' ');
+            out.push_str(first);
+        }
+        out.push('\n');
+        for line in lines {
+            out.push_str("  ");
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+
+    /// `| a | b |` rows with a `|---+---|` rule under the headers, and a row
+    /// of `<l>`, `<c>` and `<r>` cookies when the table has alignments
+    fn write_table(
+        headers: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+        alignments: Option<&[Alignment]>,
+        caption: Option<&[Inline]>,
+        out: &mut String,
+    ) {
+        if let Some(caption) = caption {
+            writeln!(out, "#+CAPTION: {}", inlines(caption)).unwrap();
+        }
+        write_row(headers.iter().map(|cell| table_cell(cell)), out);
+        writeln!(out, "|{}|", vec!["---"; headers.len()].join("+")).unwrap();
+        if let Some(alignments) = alignments {
+            let cookies = alignments.iter().map(|alignment| match alignment {
+                Alignment::Left => "<l>".to_string(),
+                Alignment::Center => "<c>".to_string(),
+                Alignment::Right => "<r>".to_string(),
+            });
+            write_row(cookies, out);
+        }
+        for cells in rows {
+            write_row(cells.iter().map(|cell| table_cell(cell)), out);
+        }
+    }
+
+    fn write_row(cells: impl Iterator<Item = String>, out: &mut String) {
+        let cells: Vec<String> = cells.collect();
+        writeln!(out, "| {} |", cells.join(" | ")).unwrap();
+    }
+
+    /// A cell's content, with `|` written as the `\vert` entity
+    fn table_cell(cell: &[Inline]) -> String {
+        inlines(cell).replace('|', "\\vert{}")
+    }
+
+    fn blocks(blocks: &[Block]) -> String {
+        let mut out = String::new();
+        for block in blocks {
+            write_block(block, &mut out);
+        }
+        out
+    }
+
+    /// A paragraph, table cell, title or term, which Org parses on its own
+    fn inlines(inlines: &[Inline]) -> String {
+        let mut out = String::new();
+        for (i, inline) in inlines.iter().enumerate() {
+            // What follows a text, for the markers that could close its
+            // emphasis, with other texts as they are before escaping
+            let mut following = String::new();
+            if matches!(inline, Inline::Text { .. }) {
+                for inline in &inlines[i + 1..] {
+                    match inline {
+                        Inline::Text { text } => following.push_str(text),
+                        inline => write_inline(inline, Run::Top(""), &mut following),
+                    }
                 }
-                out.push('/');
             }
+            write_inline(inline, Run::Top(&following), &mut out);
+        }
+        out
+    }
+
+    /// The content of emphasis, a sub- or superscript or a link's
+    /// description, which `close` ends
+    fn nested(inlines: &[Inline], close: char) -> String {
+        let mut out = String::new();
+        for inline in inlines {
+            write_inline(inline, Run::Nested(close), &mut out);
+        }
+        out
+    }
+
+    /// Where a text is written
+    #[derive(Clone, Copy)]
+    enum Run<'a> {
+        /// Not inside any other object, before what the rest of the run
+        /// writes
+        Top(&'a str),
+        /// Inside an object that `char` closes
+        Nested(char),
+    }
+
+    /// A link path with its brackets and backslashes escaped
+    fn link_path(target: &str) -> String {
+        target
+            .replace('\\', "\\\\")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+    }
+
+    fn write_inline(inline: &Inline, run: Run, out: &mut String) {
+        match inline {
+            Inline::Text { text } => write_text(text, run, out),
+            Inline::Bold { content } => write!(out, "*{}*", nested(content, '*')).unwrap(),
+            Inline::Italic { content } => write!(out, "/{}/", nested(content, '/')).unwrap(),
             Inline::Strikethrough { content } => {
-                out.push('+');
-                for c in content {
-                    write_inline(c, out);
+                write!(out, "+{}+", nested(content, '+')).unwrap();
+            }
+            Inline::Superscript { content } => {
+                write!(out, "^{{{}}}", nested(content, '}')).unwrap();
+            }
+            Inline::Subscript { content } => write!(out, "_{{{}}}", nested(content, '}')).unwrap(),
+            Inline::Link { text, target } => {
+                if text.is_empty() {
+                    write!(out, "[[{}]]", link_path(target)).unwrap();
+                } else {
+                    write!(out, "[[{}][{}]]", link_path(target), nested(text, ']')).unwrap();
                 }
-                out.push('+');
             }
-            Inline::Code { code } => {
-                out.push('~');
-                out.push_str(code);
-                out.push('~');
+            Inline::Image { src, .. } => write!(out, "[[{}]]", link_path(src)).unwrap(),
+            Inline::Code { code } => write!(out, "~{code}~").unwrap(),
+            Inline::Math { content } => write!(out, "\\({content}\\)").unwrap(),
+            Inline::LineBreak => out.push_str("\\\\\n"),
+            Inline::FootnoteReference { label } => write!(out, "[fn:{label}]").unwrap(),
+            // A dedicated target, which `[[id]]` links to
+            Inline::BlockId { id } => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push(' ');
+                }
+                write!(out, "<<{id}>>").unwrap();
             }
-            Inline::Math { content } => {
-                out.push_str("\\(");
-                out.push_str(content);
-                out.push_str("\\)");
+            Inline::Tag { name } => write!(out, "#{name}").unwrap(),
+            Inline::Html { content } => write!(out, "@@html:{content}@@").unwrap(),
+        }
+    }
+
+    /// Text with what Org would read as markup written as entities
+    fn write_text(text: &str, run: Run, out: &mut String) {
+        for (i, c) in text.char_indices() {
+            match entity(c, out, &text[i + c.len_utf8()..], run) {
+                Some(name) => write!(out, "\\{name}{{}}").unwrap(),
+                None => out.push(c),
             }
-            _ => {}
+        }
+    }
+
+    /// The entity `c` is written as after `written` and before `rest`,
+    /// the remainder of its text, or `None` when Org reads it as it is
+    fn entity(c: char, written: &str, rest: &str, run: Run) -> Option<&'static str> {
+        let before = written.chars().next_back();
+        let after = rest.chars().next();
+        let line = written.rsplit('\n').next().unwrap_or_default();
+        let line_start = line.trim().is_empty();
+        let opens = before.is_none_or(|before| before.is_whitespace() || PRE.contains(before))
+            && after.is_none_or(|after| !after.is_whitespace());
+        let closes =
+            before.is_none_or(|before| !before.is_whitespace()) && after.is_none_or(closing);
+        // Emphasis and code only need escaping when they could be closed,
+        // which inside another object may be past its end
+        let emphasis = match run {
+            Run::Top(following) => opens && closed_later(c, rest, following),
+            Run::Nested(close) => opens || (c == close && closes),
+        };
+        let bullet = line_start && after.is_none_or(|after| after == ' ' || after == '\n');
+        let escape = match c {
+            // Line breaks, math, entities and math blocks
+            '\\' => {
+                rest.starts_with('(')
+                    || rest
+                        .strip_prefix('\\')
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('\n'))
+                    || is_entity(rest)
+                    || (line_start && rest.starts_with('['))
+            }
+            // Links and footnotes, and the end of a link's description
+            '[' => rest.starts_with('[') || rest.starts_with("fn:"),
+            ']' => matches!(run, Run::Nested(']')) && (rest.is_empty() || rest.starts_with(']')),
+            // Sub- and superscripts
+            '{' => matches!(before, Some('^' | '_')),
+            '}' => matches!(run, Run::Nested('}')),
+            // Targets
+            '<' => rest.starts_with('<'),
+            // Headings
+            '*' => {
+                emphasis
+                    || (line_start
+                        && rest
+                            .trim_start_matches('*')
+                            .chars()
+                            .next()
+                            .is_none_or(|c| c == ' ' || c == '\n'))
+            }
+            // List items and keywords
+            '+' => emphasis || bullet || (before == Some('#') && line.trim() == "#"),
+            '/' | '~' => emphasis,
+            // List items and rules
+            '-' => bullet || (line_start && is_rule(rest)),
+            // Table rows and drawers
+            '|' | ':' => line_start,
+            _ => false,
+        };
+        ENTITIES
+            .iter()
+            .find(|(_, entity)| escape && *entity == c)
+            .map(|(name, _)| *name)
+    }
+
+    /// Whether `c` may come after a closing marker, counting an entity,
+    /// which starts with `\`
+    fn closing(c: char) -> bool {
+        c.is_whitespace() || POST.contains(c) || ENTITIES.iter().any(|(_, entity)| *entity == c)
+    }
+
+    /// Whether `marker` occurs where it could close emphasis or code opened
+    /// just before `rest`, which `following` continues
+    fn closed_later(marker: char, rest: &str, following: &str) -> bool {
+        let chars: Vec<char> = rest.chars().chain(following.chars()).collect();
+        (1..chars.len()).any(|i| {
+            chars[i] == marker
+                && !chars[i - 1].is_whitespace()
+                && chars.get(i + 1).is_none_or(|c| closing(*c))
+        })
+    }
+
+    /// Whether `rest` starts with the name of an entity and `{}`
+    fn is_entity(rest: &str) -> bool {
+        let name = rest
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .next()
+            .unwrap_or_default();
+        rest[name.len()..].starts_with("{}") && ENTITIES.iter().any(|(entity, _)| *entity == name)
+    }
+
+    /// Whether a `-` and `rest` make a line of five or more dashes
+    fn is_rule(rest: &str) -> bool {
+        let line = rest.split('\n').next().unwrap_or_default().trim_end();
+        line.len() >= 4 && line.chars().all(|c| c == '-')
+    }
+
+    /// Write an Org document back out: its keywords and property drawer,
+    /// then each heading with its TODO keyword, tags, property drawer, body
+    /// and subheadings
+    #[must_use]
+    pub fn document_to_org(doc: &OrgDocument) -> String {
+        let mut out = String::new();
+        for (key, value) in &doc.keywords {
+            writeln!(out, "#+{key}: {value}").unwrap();
+        }
+        write_drawer(
+            doc.properties.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+            &mut out,
+        );
+        for block in &doc.blocks {
+            write_block(block, &mut out);
+        }
+        for node in &doc.nodes {
+            write_node(node, &mut out);
+        }
+        out
+    }
+
+    fn write_node(node: &OrgNode, out: &mut String) {
+        write_heading(
+            node.level,
+            node.todo.as_deref(),
+            &node.title,
+            &node.tags,
+            out,
+        );
+
+        let mut properties = node.drawer();
+        if let Some(id) = &node.id
+            && !node.properties.contains_key("ID")
+        {
+            properties.insert(0, ("ID", id));
+        }
+        write_drawer(properties.into_iter(), out);
+
+        for block in &node.body {
+            write_block(block, out);
+        }
+        for child in &node.children {
+            write_node(child, out);
+        }
+    }
+
+    /// `:PROPERTIES:` ... `:END:`, unless there are none
+    fn write_drawer<'a>(properties: impl Iterator<Item = (&'a str, &'a str)>, out: &mut String) {
+        let mut properties = properties.peekable();
+        if properties.peek().is_none() {
+            return;
+        }
+        out.push_str(":PROPERTIES:\n");
+        for (key, value) in properties {
+            writeln!(out, ":{key}: {value}").unwrap();
+        }
+        out.push_str(":END:\n");
+    }
+}
+
+/// Typed property values from Org's plain-text keyword and drawer values
+pub mod properties {
+    use std::sync::LazyLock;
+
+    use regex::Regex;
+
+    use crate::{formats::frontmatter, models::PropertyValue};
+
+    static TIMESTAMP: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"^[\[<][0-9]{4}-[0-9]{2}-[0-9]{2}[^\]>]*[\]>]$").unwrap());
+
+    /// Type a value as YAML would, keeping Org timestamps as text
+    #[must_use]
+    pub fn read_value(value: &str) -> PropertyValue {
+        if TIMESTAMP.is_match(value) {
+            return PropertyValue::text(value);
+        }
+        frontmatter::parse_value(value).unwrap_or_else(|_| PropertyValue::text(value))
+    }
+
+    /// Write a value so that [`read_value`] returns it unchanged
+    #[must_use]
+    pub fn write_value(value: &PropertyValue) -> String {
+        match value {
+            PropertyValue::Null => String::new(),
+            PropertyValue::Text { value: text }
+                if !text.contains('\n') && read_value(text) == *value =>
+            {
+                text.clone()
+            }
+            value => frontmatter::inline_value(value),
         }
     }
 }
@@ -439,3 +1807,486 @@ pub mod metadata {
         tags
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::SerializationError,
+        formats::markdown::MarkdownFormat,
+        models::{
+            Alignment, Attribute, Checkbox, DefinitionItem, ListItem, ListStyle, Properties,
+            PropertyValue,
+        },
+    };
+
+    fn text(text: &str) -> Inline {
+        Inline::Text {
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_deserialize_reports_invalid_utf8() {
+        let result = OrgFormat.deserialize(b"* Title\n\xff broken", Some("bad.org"));
+
+        assert!(matches!(
+            result,
+            Err(SerializationError::InvalidUtf8 { offset: 8 })
+        ));
+    }
+
+    #[test]
+    fn file_keywords_and_drawer_become_properties() {
+        let org = "#+TITLE: Reading list\n#+FILETAGS: :books:later:\n:PROPERTIES:\n:ID: 42\n:CREATED: [2024-05-01 Wed]\n:END:\n* Dune\n";
+
+        let note = OrgFormat
+            .deserialize(org.as_bytes(), Some("reading.org"))
+            .unwrap();
+        assert_eq!(note.title, "Reading list");
+
+        let expected: Properties = [
+            ("title", PropertyValue::text("Reading list")),
+            (
+                "tags",
+                PropertyValue::List {
+                    items: vec![PropertyValue::text("books"), PropertyValue::text("later")],
+                },
+            ),
+            ("ID", PropertyValue::Number { value: "42".into() }),
+            ("CREATED", PropertyValue::text("[2024-05-01 Wed]")),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value))
+        .collect();
+        assert_eq!(note.properties, expected);
+
+        let serialized = String::from_utf8(OrgFormat.serialize(&note)).unwrap();
+        assert_eq!(serialized, org);
+    }
+
+    #[test]
+    fn content_before_the_first_heading_is_kept() {
+        let org = "#+TITLE: Work\nContext for the week.\n- Inbox zero\n- [ ] Expenses\n* TODO Call the bank\n";
+
+        let note = OrgFormat
+            .deserialize(org.as_bytes(), Some("work.org"))
+            .unwrap();
+        let kinds: Vec<BlockKind> = note.blocks.iter().map(Block::kind).collect();
+        assert_eq!(
+            kinds,
+            [BlockKind::Paragraph, BlockKind::List, BlockKind::Heading]
+        );
+
+        let serialized = String::from_utf8(OrgFormat.serialize(&note)).unwrap();
+        assert_eq!(serialized, org);
+    }
+
+    #[test]
+    fn text_is_only_escaped_where_it_would_read_as_markup() {
+        let org = "* TODO [#A] Call Alice about {budget} :work:\nLook in /usr/bin and C:\\Users for 2*3 C++ files, a_b and x^2.\n";
+
+        let note = OrgFormat
+            .deserialize(org.as_bytes(), Some("work.org"))
+            .unwrap();
+        assert_eq!(
+            note.blocks[1],
+            Block::paragraph(vec![text(
+                "Look in /usr/bin and C:\\Users for 2*3 C++ files, a_b and x^2."
+            )])
+        );
+        let serialized = String::from_utf8(OrgFormat.serialize(&note)).unwrap();
+        assert_eq!(serialized, org);
+
+        let markup = Note {
+            blocks: vec![Block::paragraph(vec![text(
+                "* not a heading, /not italic/ and [[not a link]]",
+            )])],
+            ..note
+        };
+        let serialized = String::from_utf8(OrgFormat.serialize(&markup)).unwrap();
+        assert!(serialized.ends_with(
+            "\\ast{} not a heading, \\slash{}not italic/ and \\lbrack{}[not a link]]\n"
+        ));
+        let read = OrgFormat.deserialize(serialized.as_bytes(), None).unwrap();
+        assert_eq!(read.blocks, markup.blocks);
+    }
+
+    #[test]
+    fn properties_survive_markdown_to_org_and_back() {
+        let markdown = "---\ntitle: Trip\ntags:\n  - travel\naliases:\n  - Holiday\n  - Vacation\ncreated: 2024-05-01\nbudget:\n  total: 1200\n---\n";
+        let original = MarkdownFormat::default()
+            .deserialize(markdown.as_bytes(), Some("trip.md"))
+            .unwrap();
+
+        let org = OrgFormat.serialize(&original);
+        let via_org = OrgFormat.deserialize(&org, Some("trip.org")).unwrap();
+        assert_eq!(via_org.title, "Trip");
+        assert_eq!(via_org.properties, original.properties);
+
+        let back = MarkdownFormat::default().serialize(&via_org);
+        assert_eq!(String::from_utf8(back).unwrap(), markdown);
+    }
+
+    #[test]
+    fn checkboxes_map_to_list_items() {
+        let org = "* Errands\n- [ ] Buy milk\n- [X] Call Bob\n- [-] Sell car\n- Not a task\n";
+        let note = OrgFormat
+            .deserialize(org.as_bytes(), Some("errands.org"))
+            .unwrap();
+
+        let Some(ContainerBlock::List { items, .. }) = note.blocks[1].as_list() else {
+            panic!("Expected list");
+        };
+        let checkboxes: Vec<_> = items.iter().map(|item| item.checkbox).collect();
+        assert_eq!(
+            checkboxes,
+            vec![
+                Some(Checkbox::Unchecked),
+                Some(Checkbox::Checked),
+                Some(Checkbox::Partial),
+                None,
+            ]
+        );
+        assert_eq!(String::from_utf8(OrgFormat.serialize(&note)).unwrap(), org);
+
+        let markdown = "- [ ] Buy milk\n- [x] Call Bob\n- [-] Sell car\n- Not a task";
+        assert_eq!(
+            crate::formats::markdown::parse_list(markdown).as_ref(),
+            Some(&note.blocks[1])
+        );
+    }
+
+    /// One of every block and most inlines
+    fn kitchen() -> Note {
+        Note {
+            id: "kitchen".into(),
+            title: "Kitchen".into(),
+            properties: Properties::new(),
+            blocks: vec![
+                Block::heading(1, vec![text("Kitchen")]),
+                Block::paragraph(vec![
+                    text("See "),
+                    Inline::Link {
+                        text: vec![text("the manual")],
+                        target: "https://example.com".into(),
+                    },
+                    text(", "),
+                    Inline::Link {
+                        text: vec![],
+                        target: "Oven".into(),
+                    },
+                    text(" and x"),
+                    Inline::Superscript {
+                        content: vec![text("2")],
+                    },
+                    Inline::FootnoteReference { label: "1".into() },
+                    Inline::LineBreak,
+                    text("Done"),
+                ]),
+                Block::code_block(Some("rust".into()), "* not a heading\nfn main() {}".into()),
+                Block::math_block("e^{i\\pi} = -1".into()),
+                Block::horizontal_rule(),
+                Block::html("<video src=\"a.mp4\"></video>".into()),
+                Block::table(
+                    vec![vec![text("Item")], vec![text("Price")]],
+                    vec![vec![vec![text("a | b")], vec![text("3")]]],
+                    Some(vec![Alignment::Left, Alignment::Right]),
+                    Some(vec![text("Costs")]),
+                ),
+                Block::div(
+                    vec!["alert".into(), "warning".into()],
+                    vec![],
+                    vec![Block::paragraph(vec![text("Hot")])],
+                ),
+                Block::div(
+                    vec!["aside".into()],
+                    vec![Attribute {
+                        key: "id".into(),
+                        value: "tip".into(),
+                    }],
+                    vec![Block::paragraph(vec![text("Aside")])],
+                ),
+                Block::definition_list(vec![DefinitionItem {
+                    term: vec![text("Roux")],
+                    definition: vec![
+                        Block::paragraph(vec![text("Flour and fat")]),
+                        Block::paragraph(vec![text("Cooked")]),
+                    ],
+                }]),
+                Block::footnote_definition("1".into(), vec![Block::paragraph(vec![text("Ours")])]),
+            ],
+        }
+    }
+
+    /// `note` comes back from Org as it went in, and is written the same
+    /// way again
+    fn assert_round_trips(note: &Note) {
+        let org = OrgFormat.serialize(note);
+        let parsed = OrgFormat.deserialize(&org, Some(&note.id)).unwrap();
+        assert_eq!(parsed, *note, "{}", String::from_utf8_lossy(&org));
+        assert_eq!(OrgFormat.serialize(&parsed), org);
+    }
+
+    #[test]
+    fn every_block_and_inline_is_written() {
+        let note = kitchen();
+        let expected = "* Kitchen
+See [[https://example.com][the manual]], [[Oven]] and x^{2}[fn:1]\\\\
+Done
+#+BEGIN_SRC rust
+,* not a heading
+fn main() {}
+#+END_SRC
+\\[
+e^{i\\pi} = -1
+\\]
+-----
+#+BEGIN_EXPORT html
+<video src=\"a.mp4\"></video>
+#+END_EXPORT
+#+CAPTION: Costs
+| Item | Price |
+|---+---|
+| <l> | <r> |
+| a \\vert{} b | 3 |
+#+BEGIN_WARNING
+Hot
+#+END_WARNING
+#+ATTR_HTML: :id tip
+#+BEGIN_ASIDE
+Aside
+#+END_ASIDE
+- Roux :: Flour and fat
+  Cooked
+[fn:1] Ours
+";
+        assert_eq!(
+            String::from_utf8(OrgFormat.serialize(&note)).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn every_block_and_inline_round_trips() {
+        assert_round_trips(&kitchen());
+
+        let note = Note {
+            id: "trip.org".into(),
+            title: "Trip".into(),
+            properties: Properties::new(),
+            blocks: vec![
+                Block::heading(
+                    1,
+                    vec![
+                        text("TODO Trip"),
+                        Inline::Tag {
+                            name: "travel".into(),
+                        },
+                    ],
+                ),
+                Block::div(
+                    vec!["properties".into()],
+                    vec![Attribute {
+                        key: "ID".into(),
+                        value: "7".into(),
+                    }],
+                    vec![],
+                ),
+                Block::paragraph(vec![
+                    Inline::Bold {
+                        content: vec![text("Pack")],
+                    },
+                    text(" "),
+                    Inline::Italic {
+                        content: vec![text("light")],
+                    },
+                    text(" "),
+                    Inline::Strikethrough {
+                        content: vec![text("heavy")],
+                    },
+                    text(" H"),
+                    Inline::Subscript {
+                        content: vec![text("2")],
+                    },
+                    text("O "),
+                    Inline::Code {
+                        code: "ls -a".into(),
+                    },
+                    text(" "),
+                    Inline::Math {
+                        content: "x^2".into(),
+                    },
+                    text(" "),
+                    Inline::Image {
+                        alt_text: None,
+                        src: "map.png".into(),
+                    },
+                    text(" "),
+                    Inline::Html {
+                        content: "<kbd>".into(),
+                    },
+                    Inline::BlockId { id: "pack".into() },
+                ]),
+                Block::paragraph(vec![text(
+                    "*not bold* /nor/ ~code~, [[no link]] a\\b x^{2} <<no>> \\ast{}",
+                )]),
+                Block::quote(vec![Block::paragraph(vec![text("Go")])]),
+                Block::list(
+                    ListStyle::Ordered {
+                        numbering: crate::models::Numbering {
+                            kind: crate::models::NumberingType::Decimal,
+                            style: crate::models::NumberingStyle::Paren,
+                        },
+                    },
+                    vec![ListItem::task(
+                        Checkbox::Partial,
+                        vec![
+                            Block::paragraph(vec![text("Bags")]),
+                            Block::list(
+                                ListStyle::Unordered { bullet: b'+' },
+                                vec![vec![Block::code_block(None, "zip".into())]],
+                            ),
+                        ],
+                    )],
+                ),
+                Block::image(Some("The route".into()), "route.svg".into()),
+                Block::heading(2, vec![text("Hotel")]),
+            ],
+        };
+        assert_round_trips(&note);
+    }
+
+    #[test]
+    fn tables_take_their_first_row_as_headers() {
+        let org =
+            "* Prices\n| Item | Price |\n|------+-------|\n| Tea  |     3 |\n| Cake |     4 |\n";
+        let note = OrgFormat
+            .deserialize(org.as_bytes(), Some("prices.org"))
+            .unwrap();
+
+        assert_eq!(
+            note.blocks[1],
+            Block::table(
+                vec![vec![text("Item")], vec![text("Price")]],
+                vec![
+                    vec![vec![text("Tea")], vec![text("3")]],
+                    vec![vec![text("Cake")], vec![text("4")]],
+                ],
+                None,
+                None,
+            )
+        );
+    }
+
+    #[test]
+    fn blocks_written_by_hand_are_read() {
+        let org = "* Notes
+#+begin_src python
+print(1)
+#+end_src
+#+BEGIN_QUOTE
+Quoted /words/
+#+END_QUOTE
+#+BEGIN_EXAMPLE
+raw
+#+END_EXAMPLE
+-----
+- Roux :: Flour and fat
+See [[https://example.com][the site]][fn:1]
+[fn:1] The note
+";
+        let note = OrgFormat
+            .deserialize(org.as_bytes(), Some("notes.org"))
+            .unwrap();
+
+        assert_eq!(
+            note.blocks[1..],
+            [
+                Block::code_block(Some("python".into()), "print(1)".into()),
+                Block::quote(vec![Block::paragraph(vec![
+                    text("Quoted "),
+                    Inline::Italic {
+                        content: vec![text("words")],
+                    },
+                ])]),
+                Block::code_block(None, "raw".into()),
+                Block::horizontal_rule(),
+                Block::definition_list(vec![DefinitionItem {
+                    term: vec![text("Roux")],
+                    definition: vec![Block::paragraph(vec![text("Flour and fat")])],
+                }]),
+                Block::paragraph(vec![
+                    text("See "),
+                    Inline::Link {
+                        text: vec![text("the site")],
+                        target: "https://example.com".into(),
+                    },
+                    Inline::FootnoteReference { label: "1".into() },
+                ]),
+                Block::footnote_definition(
+                    "1".into(),
+                    vec![Block::paragraph(vec![text("The note")])]
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn documents_are_written_back_as_parsed() {
+        let org = "#+TITLE: Projects
+:PROPERTIES:
+:CATEGORY: work
+:END:
+* TODO Ship the release :work:urgent:
+:PROPERTIES:
+:ID: 7
+:EFFORT: 2h
+:OWNER: Sam
+:END:
+Check the changelog
+** DONE Write notes
+- [X] Draft
+- [ ] Review
+* Ideas
+";
+        let doc = parser::parse_org(org);
+        assert_eq!(serializer::document_to_org(&doc), org);
+    }
+
+    #[test]
+    fn heading_drawers_survive_a_round_trip() {
+        let org = "* Trip
+:PROPERTIES:
+:ID: 5f1c-22
+:CUSTOM_ID: trip
+:END:
+Pack light
+** Hotel
+:PROPERTIES:
+:CUSTOM_ID: hotel
+:END:
+";
+        let note = OrgFormat
+            .deserialize(org.as_bytes(), Some("trip.org"))
+            .unwrap();
+        assert_eq!(
+            note.blocks[1],
+            Block::div(
+                vec!["properties".into()],
+                vec![
+                    Attribute {
+                        key: "ID".into(),
+                        value: "5f1c-22".into(),
+                    },
+                    Attribute {
+                        key: "CUSTOM_ID".into(),
+                        value: "trip".into(),
+                    },
+                ],
+                vec![],
+            )
+        );
+        assert_eq!(String::from_utf8(OrgFormat.serialize(&note)).unwrap(), org);
+    }
+}
@@ -0,0 +1,616 @@
+//! Property tests that `deserialize(serialize(note)) == note` for arbitrary
+//! notes within each format's [`Capabilities`](super::Capabilities)
+//!
+//! The generators stay inside what a format can tell apart: pieces of text
+//! never start or end with whitespace, which the parsers trim, and markup is
+//! separated from its neighbours by a space so that two runs of `*` cannot
+//! run together. Everything else, including text full of Markdown or Org
+//! syntax, is left to the serializers to escape.
+
+use proptest::{collection::vec, option, prelude::*, string::string_regex};
+
+use super::{NoteSerialization, markdown::MarkdownFormat, org::OrgFormat};
+use crate::models::{
+    Alignment, Attribute, Block, BlockKind, Checkbox, DefinitionItem, Inline, ListItem, ListStyle,
+    Note, Numbering, NumberingStyle, NumberingType, Properties, PropertyValue,
+};
+
+/// Punctuation that means something in Markdown or Org
+const PUNCTUATION: &str = r#".,;:!?'"()#&@^$*_~\[\]<>\\/|%+=-"#;
+
+/// Text with no whitespace at either end, and line breaks inside when
+/// `multiline`
+fn word(multiline: bool) -> BoxedStrategy<String> {
+    let edge = format!("[A-Za-z0-9{PUNCTUATION}]");
+    let inner = format!(
+        "[A-Za-z0-9 {}{PUNCTUATION}]",
+        if multiline { "\\n" } else { "" }
+    );
+    regex(&format!("{edge}({inner}{{0,12}}{edge})?"))
+}
+
+/// Letters and digits, for names, targets and plain text
+fn plain(max: usize) -> BoxedStrategy<String> {
+    regex(&format!("[a-z][a-z0-9]{{0,{max}}}"))
+}
+
+/// Strings matching `pattern`, which unlike a `&str` strategy is compiled
+/// once rather than for every value
+fn regex(pattern: &str) -> BoxedStrategy<String> {
+    string_regex(pattern).unwrap().boxed()
+}
+
+/// Words separated by single spaces
+fn words() -> impl Strategy<Value = String> {
+    vec(plain(6), 1..4).prop_map(|words| words.join(" "))
+}
+
+fn text(text: impl Into<String>) -> Inline {
+    Inline::Text { text: text.into() }
+}
+
+/// Join pieces of text and other inlines with a space between each, merging
+/// neighbouring text; a line break joins its neighbours directly
+fn join(pieces: Vec<Inline>) -> Vec<Inline> {
+    let mut out: Vec<Inline> = Vec::new();
+    let mut glued = true;
+    for piece in pieces {
+        match (out.last_mut(), piece) {
+            (_, Inline::LineBreak) => {
+                if !glued {
+                    out.push(Inline::LineBreak);
+                    glued = true;
+                }
+                continue;
+            }
+            (Some(Inline::Text { text: last }), Inline::Text { text }) => {
+                if !glued {
+                    last.push(' ');
+                }
+                last.push_str(&text);
+            }
+            (Some(Inline::Text { text: last }), piece) => {
+                if !glued {
+                    last.push(' ');
+                }
+                out.push(piece);
+            }
+            (_, Inline::Text { text }) if !glued => out.push(self::text(format!(" {text}"))),
+            (_, piece) => {
+                if !glued {
+                    out.push(self::text(" "));
+                }
+                out.push(piece);
+            }
+        }
+        glued = false;
+    }
+    if out.last() == Some(&Inline::LineBreak) {
+        out.pop();
+    }
+    out
+}
+
+/// Text, code, math or formatting around them
+fn formatted(multiline: bool) -> BoxedStrategy<Inline> {
+    formatted_with(word(multiline), code().boxed())
+}
+
+/// [`formatted`] with the given text and code
+fn formatted_with(
+    word: BoxedStrategy<String>,
+    code: BoxedStrategy<Inline>,
+) -> BoxedStrategy<Inline> {
+    let leaf = prop_oneof![
+        4 => word.clone().prop_map(text),
+        1 => code,
+        1 => math(),
+    ];
+    // Called for every value, so the word's regex is built outside
+    leaf.prop_recursive(2, 8, 3, move |inner| {
+        let content = emphasized(word.clone(), inner);
+        prop_oneof![
+            content.clone().prop_map(|content| Inline::Bold { content }),
+            content
+                .clone()
+                .prop_map(|content| Inline::Italic { content }),
+            content.prop_map(|content| Inline::Strikethrough { content }),
+        ]
+    })
+    .boxed()
+}
+
+/// The content of bold, italic or struck-through text, which starts and
+/// ends with text so that its delimiters cannot run into those of a nested
+/// `inner`
+fn emphasized(
+    word: BoxedStrategy<String>,
+    inner: BoxedStrategy<Inline>,
+) -> BoxedStrategy<Vec<Inline>> {
+    (word.clone(), vec(inner, 0..3), word)
+        .prop_map(|(first, middle, last)| {
+            let mut pieces = vec![text(first)];
+            pieces.extend(middle);
+            pieces.push(text(last));
+            join(pieces)
+        })
+        .boxed()
+}
+
+fn code() -> impl Strategy<Value = Inline> {
+    regex("[a-z `|*\\\\]{0,8}[a-z`|*\\\\]").prop_map(|code| Inline::Code { code })
+}
+
+fn math() -> impl Strategy<Value = Inline> {
+    math_line().prop_map(|content| Inline::Math { content })
+}
+
+fn math_line() -> BoxedStrategy<String> {
+    regex("[a-z0-9+=^{}\\\\]([a-z0-9 +=^{}\\\\]{0,8}[a-z0-9+=^{}\\\\])?")
+}
+
+/// Link targets as notes write them: paths, or titles with spaces and
+/// punctuation, either with a heading or block anchor
+fn target() -> BoxedStrategy<String> {
+    let name = prop_oneof![
+        regex("[a-z][a-z0-9_/.-]{0,10}[a-z0-9]"),
+        regex("[A-Z][A-Za-z0-9 ()<>&;:'!?,.\\\\-]{0,14}[A-Za-z0-9)]"),
+    ];
+    let anchor = prop_oneof![
+        regex("#[A-Z]([A-Za-z0-9 ]{0,8}[a-z0-9])?"),
+        regex("#\\^[a-z0-9-]{1,6}"),
+    ];
+    (name, option::weighted(0.3, anchor))
+        .prop_map(|(name, anchor)| name + anchor.as_deref().unwrap_or_default())
+        .boxed()
+}
+
+/// Inlines of any kind the Markdown format keeps, with line breaks when
+/// `multiline` and `#tags` when `tags`
+fn markdown_inlines(multiline: bool, tags: bool) -> impl Strategy<Value = Vec<Inline>> {
+    let piece = prop_oneof![
+        8 => word(multiline).prop_map(text),
+        2 => emphasized(word(multiline), formatted(multiline))
+            .prop_map(|content| Inline::Bold { content }),
+        2 => emphasized(word(multiline), formatted(multiline))
+            .prop_map(|content| Inline::Italic { content }),
+        1 => emphasized(word(multiline), formatted(multiline))
+            .prop_map(|content| Inline::Strikethrough { content }),
+        2 => (vec(formatted(false), 1..4).prop_map(join), target())
+            .prop_map(|(text, target)| Inline::Link { text, target }),
+        1 => (option::of(words()), target())
+            .prop_map(|(alt_text, src)| Inline::Image { alt_text, src }),
+        2 => code(),
+        1 => math(),
+        1 => plain(5).prop_map(|label| Inline::FootnoteReference { label }),
+        1 => regex("[A-Za-z][A-Za-z0-9_-]{0,6}").prop_map(|name| Inline::Tag { name }),
+        1 => prop::sample::select(vec!["<kbd>", "</kbd>", "<b>", "</i>", "<span class=\"x\">"])
+            .prop_map(|content| Inline::Html { content: content.to_string() }),
+        2 => Just(Inline::LineBreak),
+    ];
+    vec(piece, 1..6).prop_map(move |mut pieces| {
+        pieces.retain(|piece| match piece {
+            Inline::LineBreak => multiline,
+            Inline::Tag { .. } => tags,
+            _ => true,
+        });
+        join(pieces)
+    })
+}
+
+fn markdown_paragraph() -> BoxedStrategy<Block> {
+    (
+        markdown_inlines(true, true),
+        option::weighted(0.2, plain(6)),
+    )
+        .prop_filter(
+            "an empty paragraph is nothing, and a lone image or opening tag a block",
+            |(inlines, _)| {
+                !matches!(
+                    inlines.as_slice(),
+                    [] | [Inline::Image { .. }] | [Inline::Html { .. }, ..]
+                )
+            },
+        )
+        .prop_map(|(mut inlines, id)| {
+            if let Some(id) = id {
+                inlines.push(Inline::BlockId { id });
+            }
+            Block::paragraph(inlines)
+        })
+        .boxed()
+}
+
+fn list_style() -> impl Strategy<Value = ListStyle> {
+    prop_oneof![
+        prop::sample::select(vec![b'-', b'+', b'*'])
+            .prop_map(|bullet| ListStyle::Unordered { bullet }),
+        prop::sample::select(vec![NumberingStyle::Dot, NumberingStyle::Paren]).prop_map(|style| {
+            ListStyle::Ordered {
+                numbering: Numbering {
+                    kind: NumberingType::Decimal,
+                    style,
+                },
+            }
+        }),
+    ]
+}
+
+fn checkbox() -> impl Strategy<Value = Option<Checkbox>> {
+    option::of(prop::sample::select(vec![
+        Checkbox::Unchecked,
+        Checkbox::Checked,
+        Checkbox::Partial,
+    ]))
+}
+
+/// Drop a block that would merge into the one before it, such as a second
+/// list or table right after another
+fn separate(blocks: Vec<Block>) -> Vec<Block> {
+    let mut out: Vec<Block> = Vec::new();
+    for block in blocks {
+        let merges = matches!(
+            block.kind(),
+            BlockKind::List | BlockKind::Table | BlockKind::DefinitionList
+        ) && out.last().is_some_and(|last| last.kind() == block.kind());
+        if !merges {
+            out.push(block);
+        }
+    }
+    out
+}
+
+fn markdown_blocks() -> impl Strategy<Value = Vec<Block>> {
+    let paragraph = markdown_paragraph();
+    let leaf = prop_oneof![
+        6 => paragraph.clone(),
+        2 => (1..=6_u8, markdown_inlines(false, false))
+            .prop_map(|(level, content)| Block::heading(level, content)),
+        1 => (option::of(words()), target()).prop_map(|(alt, src)| Block::image(alt, src)),
+        // A line of only spaces is blank, and loses them inside a list
+        1 => (option::of(plain(6)), regex("(([ -~]{0,12}[!-~])?\n){0,3}([ -~]{0,12}[!-~])?"))
+            .prop_map(|(language, content)| Block::code_block(language, content)),
+        1 => vec(math_line(), 1..3).prop_map(|lines| Block::math_block(lines.join("\n"))),
+        1 => Just(Block::horizontal_rule()),
+        1 => words().prop_map(|words| Block::html(format!("<div>\n{words}\n</div>"))),
+    ];
+    // Called for every value, so the paragraph's regexes are built outside
+    let first = paragraph.clone();
+    let block = leaf.prop_recursive(3, 24, 4, move |inner| {
+        let blocks = vec(inner.clone(), 0..4).prop_map(separate).boxed();
+        let item =
+            (checkbox(), first.clone(), vec(inner, 0..3)).prop_map(|(checkbox, first, rest)| {
+                ListItem {
+                    checkbox,
+                    blocks: separate([vec![first], rest].concat()),
+                }
+            });
+        prop_oneof![
+            blocks.clone().prop_map(Block::quote),
+            (list_style(), vec(item, 1..4)).prop_map(|(style, items)| Block::list(style, items)),
+            (
+                prop::sample::select(vec!["note", "tip", "important", "warning", "caution"]),
+                blocks,
+            )
+                .prop_map(|(kind, children)| {
+                    Block::div(
+                        vec!["alert".to_string(), kind.to_string()],
+                        Vec::new(),
+                        children,
+                    )
+                }),
+        ]
+    });
+    let top = prop_oneof![
+        8 => block,
+        1 => table(),
+        1 => (plain(4), vec(paragraph, 1..3))
+            .prop_map(|(label, content)| Block::footnote_definition(label, content)),
+    ];
+    vec(top, 0..6).prop_map(separate)
+}
+
+fn table() -> impl Strategy<Value = Block> {
+    let alignment =
+        prop::sample::select(vec![Alignment::Left, Alignment::Center, Alignment::Right]);
+    let cell = option::weighted(0.8, markdown_inlines(false, true))
+        .prop_map(Option::unwrap_or_default)
+        .boxed();
+    (1..4_usize)
+        .prop_flat_map(move |columns| {
+            (
+                vec(cell.clone(), columns),
+                vec(vec(cell.clone(), columns), 0..3),
+                vec(alignment.clone(), columns),
+            )
+        })
+        .prop_map(|(headers, rows, alignments)| Block::table(headers, rows, Some(alignments), None))
+}
+
+/// Front matter values that survive YAML
+fn property_value() -> impl Strategy<Value = PropertyValue> {
+    prop_oneof![
+        word(false).prop_map(PropertyValue::text),
+        any::<bool>().prop_map(|value| PropertyValue::Bool { value }),
+        (0..1000_u32).prop_map(|value| PropertyValue::Number {
+            value: value.to_string()
+        }),
+        vec(words().prop_map(PropertyValue::text), 1..3)
+            .prop_map(|items| PropertyValue::List { items }),
+    ]
+}
+
+/// Unique keys other than `title`, which follows the note's title
+fn properties(value: impl Strategy<Value = PropertyValue>) -> impl Strategy<Value = Properties> {
+    vec((regex("[a-z][a-z_]{0,8}"), value), 0..4).prop_map(|properties| {
+        let mut out = Properties::new();
+        for (key, value) in properties {
+            if key != "title" && out.get(&key).is_none() {
+                out.insert(key, value);
+            }
+        }
+        out
+    })
+}
+
+fn markdown_note() -> impl Strategy<Value = Note> {
+    (
+        words(),
+        any::<bool>(),
+        properties(property_value()),
+        markdown_blocks(),
+    )
+        .prop_map(|(title, title_property, rest, blocks)| {
+            let mut properties = Properties::new();
+            if title_property {
+                properties.insert("title", PropertyValue::text(&title));
+            }
+            for (key, value) in rest.iter() {
+                properties.insert(key, value.clone());
+            }
+            Note {
+                id: "note".to_string(),
+                title,
+                properties,
+                blocks,
+            }
+        })
+}
+
+/// Text with no whitespace at either end that starts with a letter, so
+/// that a line of it cannot read as a numbered list item, and has no `:`,
+/// so that a list item cannot read as a definition
+fn org_word() -> BoxedStrategy<String> {
+    let punctuation = format!("{{}}{}", PUNCTUATION.replace(':', ""));
+    regex(&format!(
+        "[A-Za-z]([A-Za-z0-9 {punctuation}]{{0,12}}[A-Za-z0-9{punctuation}])?"
+    ))
+}
+
+/// Code that neither starts nor ends with a space, and has no `|` to split
+/// a table cell
+fn org_code() -> BoxedStrategy<Inline> {
+    regex("[a-z`*\\\\]([a-z `*\\\\]{0,6}[a-z`*\\\\])?")
+        .prop_map(|code| Inline::Code { code })
+        .boxed()
+}
+
+/// Inlines of any kind the Org format keeps in text, with line breaks when
+/// `multiline`
+fn org_inlines(multiline: bool) -> impl Strategy<Value = Vec<Inline>> {
+    let formatted = formatted_with(org_word(), org_code());
+    let content = emphasized(org_word(), formatted.clone());
+    let piece = prop_oneof![
+        8 => org_word().prop_map(text),
+        2 => content.clone().prop_map(|content| Inline::Bold { content }),
+        2 => content.clone().prop_map(|content| Inline::Italic { content }),
+        1 => content.clone().prop_map(|content| Inline::Strikethrough { content }),
+        1 => content.clone().prop_map(|content| Inline::Superscript { content }),
+        1 => content.prop_map(|content| Inline::Subscript { content }),
+        2 => (vec(formatted, 1..4).prop_map(join), target())
+            .prop_map(|(text, target)| Inline::Link { text, target }),
+        1 => target().prop_map(|src| Inline::Image { alt_text: None, src: format!("{src}.png") }),
+        2 => org_code(),
+        1 => math(),
+        1 => plain(5).prop_map(|label| Inline::FootnoteReference { label }),
+        1 => prop::sample::select(vec!["<kbd>", "</kbd>", "<b>", "</i>", "<span class=\"x\">"])
+            .prop_map(|content| Inline::Html { content: content.to_string() }),
+        2 => Just(Inline::LineBreak),
+    ];
+    vec(piece, 1..6).prop_map(move |mut pieces| {
+        pieces.retain(|piece| multiline || *piece != Inline::LineBreak);
+        join(pieces)
+    })
+}
+
+fn org_paragraph() -> BoxedStrategy<Block> {
+    (org_inlines(true), option::weighted(0.2, plain(6)))
+        .prop_filter(
+            "an empty paragraph is nothing, a lone image a block and a footnote reference \
+             starting a line a definition",
+            |(inlines, _)| {
+                !matches!(
+                    inlines.as_slice(),
+                    [] | [Inline::Image { .. }] | [Inline::FootnoteReference { .. }, ..]
+                )
+            },
+        )
+        .prop_map(|(mut inlines, id)| {
+            if let Some(id) = id {
+                inlines.push(Inline::BlockId { id });
+            }
+            Block::paragraph(inlines)
+        })
+        .boxed()
+}
+
+fn org_blocks() -> impl Strategy<Value = Vec<Block>> {
+    let heading = (
+        1..=4_u8,
+        option::of(prop::sample::select(vec!["TODO", "DONE", "WAITING"])),
+        option::of(words()),
+        vec(regex("[A-Za-z0-9_@]{1,6}"), 0..3),
+    )
+        .prop_map(|(level, todo, title, tags)| {
+            let title = match (todo, title) {
+                (Some(todo), Some(title)) => Some(format!("{todo} {title}")),
+                (todo, title) => title.or(todo.map(ToString::to_string)),
+            };
+            let mut content: Vec<Inline> = title.into_iter().map(text).collect();
+            content.extend(tags.into_iter().map(|name| Inline::Tag { name }));
+            Block::heading(level, content)
+        });
+    let list_style = prop_oneof![
+        prop::sample::select(vec![b'-', b'+']).prop_map(|bullet| ListStyle::Unordered { bullet }),
+        prop::sample::select(vec![NumberingStyle::Dot, NumberingStyle::Paren]).prop_map(|style| {
+            ListStyle::Ordered {
+                numbering: Numbering {
+                    kind: NumberingType::Decimal,
+                    style,
+                },
+            }
+        }),
+    ];
+    let paragraph = org_paragraph();
+    // Lines are kept as they are, even blank ones, but not a newline at the end
+    let code_lines = vec(regex("([ -~]{0,12}[!-~])?"), 0..4)
+        .prop_map(|lines| lines.join("\n"))
+        .prop_filter("a trailing newline", |content| !content.ends_with('\n'));
+    let leaf = prop_oneof![
+        6 => paragraph.clone(),
+        1 => (option::of(words()), target())
+            .prop_map(|(alt, src)| Block::image(alt, format!("{src}.png"))),
+        1 => (option::of(plain(6)), code_lines)
+            .prop_map(|(language, content)| Block::code_block(language, content)),
+        1 => vec(math_line(), 1..3).prop_map(|lines| Block::math_block(lines.join("\n"))),
+        1 => Just(Block::horizontal_rule()),
+        1 => words().prop_map(|words| Block::html(format!("<div>\n{words}\n</div>"))),
+    ];
+    // Called for every value, so the paragraph's regexes are built outside
+    let first = paragraph.clone();
+    let block = leaf.prop_recursive(3, 24, 4, move |inner| {
+        let blocks = vec(inner.clone(), 0..4).prop_map(separate).boxed();
+        let item = (checkbox(), first.clone(), vec(inner.clone(), 0..3)).prop_map(
+            |(checkbox, first, rest)| ListItem {
+                checkbox,
+                blocks: separate([vec![first], rest].concat()),
+            },
+        );
+        let definition = (words(), blocks.clone()).prop_map(|(term, definition)| DefinitionItem {
+            term: vec![text(term)],
+            definition,
+        });
+        let attribute =
+            (regex("[a-z]{1,6}"), words()).prop_map(|(key, value)| Attribute { key, value });
+        prop_oneof![
+            blocks.clone().prop_map(Block::quote),
+            (list_style.clone(), vec(item, 1..4))
+                .prop_map(|(style, items)| Block::list(style, items)),
+            vec(definition, 1..3).prop_map(Block::definition_list),
+            (
+                prop::sample::select(vec!["note", "tip", "important", "warning", "caution"]),
+                blocks.clone(),
+            )
+                .prop_map(|(kind, children)| {
+                    Block::div(
+                        vec!["alert".to_string(), kind.to_string()],
+                        Vec::new(),
+                        children,
+                    )
+                }),
+            (
+                prop::sample::select(vec!["aside", "details", "sidebar"]),
+                vec(attribute, 0..3),
+                blocks,
+            )
+                .prop_map(|(class, attributes, children)| {
+                    Block::div(vec![class.to_string()], attributes, children)
+                }),
+        ]
+    });
+    let top = prop_oneof![
+        8 => block,
+        1 => org_table(),
+        1 => (plain(4), vec(paragraph, 1..3))
+            .prop_map(|(label, content)| Block::footnote_definition(label, content)),
+    ];
+    let section = (heading, vec(top.clone(), 0..4)).prop_map(|(heading, body)| {
+        let mut blocks = vec![heading];
+        blocks.extend(separate(body));
+        blocks
+    });
+    (vec(top, 0..3), vec(section, 0..4)).prop_map(|(preamble, sections)| {
+        let mut blocks = separate(preamble);
+        blocks.extend(sections.concat());
+        blocks
+    })
+}
+
+fn org_table() -> impl Strategy<Value = Block> {
+    let alignment =
+        prop::sample::select(vec![Alignment::Left, Alignment::Center, Alignment::Right]);
+    let cell = option::weighted(0.8, org_inlines(false))
+        .prop_map(Option::unwrap_or_default)
+        .boxed();
+    (1..4_usize, option::of(words()))
+        .prop_flat_map(move |(columns, caption)| {
+            (
+                vec(cell.clone(), columns),
+                vec(vec(cell.clone(), columns), 0..3),
+                vec(alignment.clone(), columns),
+                Just(caption),
+            )
+        })
+        .prop_map(|(headers, rows, alignments, caption)| {
+            Block::table(
+                headers,
+                rows,
+                Some(alignments),
+                caption.map(|caption| vec![text(caption)]),
+            )
+        })
+}
+
+fn org_note() -> impl Strategy<Value = Note> {
+    (
+        words(),
+        properties(words().prop_map(PropertyValue::text)),
+        org_blocks(),
+    )
+        .prop_map(|(title, drawer, blocks)| {
+            let mut properties = Properties::new();
+            properties.insert("title", PropertyValue::text(&title));
+            // Upper case, as drawer keys usually are, so that none is
+            // written as a `#+KEY:` line ahead of the drawer
+            for (key, value) in drawer.iter() {
+                properties.insert(key.to_uppercase(), value.clone());
+            }
+            Note {
+                id: "note.org".to_string(),
+                title,
+                properties,
+                blocks,
+            }
+        })
+}
+
+proptest! {
+    #[test]
+    fn markdown_round_trips(note in markdown_note()) {
+        let format = MarkdownFormat::default();
+        prop_assert!(format.capabilities().supports(&note));
+
+        let serialized = format.serialize(&note);
+        let parsed = format.deserialize(&serialized, Some(&note.id)).unwrap();
+        prop_assert_eq!(parsed, note, "{}", String::from_utf8_lossy(&serialized));
+    }
+
+    #[test]
+    fn org_round_trips(note in org_note()) {
+        prop_assert!(OrgFormat.capabilities().supports(&note));
+
+        let serialized = OrgFormat.serialize(&note);
+        let parsed = OrgFormat.deserialize(&serialized, Some(&note.id)).unwrap();
+        prop_assert_eq!(parsed, note, "{}", String::from_utf8_lossy(&serialized));
+    }
+}
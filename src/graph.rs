@@ -0,0 +1,294 @@
+//! Directed graph of the links between notes
+//!
+//! Nodes are note IDs and there is an edge from `a` to `b` when note `a`
+//! links to note `b`, or to a heading or block inside it. Links that do not
+//! resolve to a known note, links to attachments and links from a note to
+//! itself are left out.
+
+pub mod export;
+pub mod rank;
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+pub use rank::NoteRank;
+
+/// A link between two notes
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, uniffi::Record)]
+pub struct GraphEdge {
+    pub source: String,
+    pub target: String,
+}
+
+/// A subgraph, such as the notes around one note
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Subgraph {
+    /// Note IDs, sorted
+    pub nodes: Vec<String>,
+    /// Links between those notes, sorted
+    pub edges: Vec<GraphEdge>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LinkGraph {
+    /// `note_id` -> IDs of the notes it links to
+    outbound: BTreeMap<String, BTreeSet<String>>,
+    /// `note_id` -> IDs of the notes linking to it
+    inbound: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl LinkGraph {
+    /// Build a graph over `nodes`, keeping only edges between them
+    pub fn new(
+        nodes: impl IntoIterator<Item = String>,
+        edges: impl IntoIterator<Item = (String, String)>,
+    ) -> Self {
+        let mut graph = Self::default();
+        for node in nodes {
+            graph.inbound.entry(node.clone()).or_default();
+            graph.outbound.entry(node).or_default();
+        }
+        for (source, target) in edges {
+            if source == target || !graph.outbound.contains_key(&target) {
+                continue;
+            }
+            if let Some(targets) = graph.outbound.get_mut(&source) {
+                targets.insert(target.clone());
+                graph.inbound.entry(target).or_default().insert(source);
+            }
+        }
+        graph
+    }
+
+    /// Every note ID, sorted
+    pub fn nodes(&self) -> impl Iterator<Item = &str> {
+        self.outbound.keys().map(String::as_str)
+    }
+
+    /// Every edge, sorted by source then target
+    pub fn edges(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.outbound.iter().flat_map(|(source, targets)| {
+            targets
+                .iter()
+                .map(move |target| (source.as_str(), target.as_str()))
+        })
+    }
+
+    /// IDs of the notes `note_id` links to, sorted
+    #[must_use]
+    pub fn outbound(&self, note_id: &str) -> Vec<String> {
+        self.outbound
+            .get(note_id)
+            .map_or_else(Vec::new, |ids| ids.iter().cloned().collect())
+    }
+
+    /// IDs of the notes linking to `note_id`, sorted
+    #[must_use]
+    pub fn inbound(&self, note_id: &str) -> Vec<String> {
+        self.inbound
+            .get(note_id)
+            .map_or_else(Vec::new, |ids| ids.iter().cloned().collect())
+    }
+
+    /// Notes linked to or from each note, in either direction
+    fn neighbors<'a>(&'a self, note_id: &str) -> impl Iterator<Item = &'a String> {
+        self.outbound
+            .get(note_id)
+            .into_iter()
+            .chain(self.inbound.get(note_id))
+            .flatten()
+    }
+
+    /// The notes within `depth` links of `note_id`, following links in
+    /// either direction, and the links between them
+    ///
+    /// Empty if the note is not in the graph.
+    #[must_use]
+    pub fn local_graph(&self, note_id: &str, depth: u32) -> Subgraph {
+        let mut nodes = BTreeSet::new();
+        if self.outbound.contains_key(note_id) {
+            let distances = self.distances(note_id, Some(depth));
+            nodes.extend(distances.into_keys());
+        }
+
+        let edges = nodes
+            .iter()
+            .flat_map(|source| {
+                self.outbound[*source]
+                    .iter()
+                    .filter(|target| nodes.contains(target.as_str()))
+                    .map(|target| GraphEdge {
+                        source: (*source).to_string(),
+                        target: target.clone(),
+                    })
+            })
+            .collect();
+
+        Subgraph {
+            nodes: nodes.into_iter().map(ToString::to_string).collect(),
+            edges,
+        }
+    }
+
+    /// Groups of notes connected by links in either direction
+    ///
+    /// Each group is sorted; the largest groups come first, ties broken by
+    /// their first ID.
+    #[must_use]
+    pub fn components(&self) -> Vec<Vec<String>> {
+        let mut seen = BTreeSet::new();
+        let mut components = Vec::new();
+        for node in self.outbound.keys() {
+            if seen.contains(node.as_str()) {
+                continue;
+            }
+            let mut component: Vec<String> = self
+                .distances(node, None)
+                .into_keys()
+                .map(ToString::to_string)
+                .collect();
+            component.sort();
+            seen.extend(component.iter().cloned());
+            components.push(component);
+        }
+        components.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        components
+    }
+
+    /// A shortest chain of notes from `from` to `to`, both included
+    ///
+    /// With `directed` set only links from one note to the next are followed;
+    /// otherwise a link in either direction connects two notes. Among equally
+    /// short paths the one through the smallest IDs is chosen.
+    #[must_use]
+    pub fn shortest_path(&self, from: &str, to: &str, directed: bool) -> Option<Vec<String>> {
+        let start = self.outbound.get_key_value(from)?.0;
+        self.outbound.get(to)?;
+
+        let mut previous: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([start.as_str()]);
+        previous.insert(start, start);
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![to.to_string()];
+                let mut current = to;
+                while current != from {
+                    current = previous[current];
+                    path.push(current.to_string());
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let next: BTreeSet<&String> = if directed {
+                self.outbound[node].iter().collect()
+            } else {
+                self.neighbors(node).collect()
+            };
+            for neighbor in next {
+                if !previous.contains_key(neighbor.as_str()) {
+                    previous.insert(neighbor, node);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        None
+    }
+
+    /// Breadth-first distances from `start`, ignoring link direction
+    fn distances(&self, start: &str, max_depth: Option<u32>) -> HashMap<&str, u32> {
+        let mut distances = HashMap::new();
+        let Some((start, _)) = self.outbound.get_key_value(start) else {
+            return distances;
+        };
+
+        distances.insert(start.as_str(), 0);
+        let mut queue = VecDeque::from([start.as_str()]);
+        while let Some(node) = queue.pop_front() {
+            let distance = distances[node];
+            if max_depth.is_some_and(|max| distance >= max) {
+                continue;
+            }
+            for neighbor in self.neighbors(node) {
+                if !distances.contains_key(neighbor.as_str()) {
+                    distances.insert(neighbor, distance + 1);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        distances
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// a -> b -> c -> a, c -> d, e alone, f -> g
+    fn graph() -> LinkGraph {
+        let nodes = ["a", "b", "c", "d", "e", "f", "g"].map(String::from);
+        let edges = [
+            ("a", "b"),
+            ("b", "c"),
+            ("c", "a"),
+            ("c", "d"),
+            ("f", "g"),
+            ("a", "a"),
+            ("a", "missing"),
+        ]
+        .map(|(s, t)| (s.to_string(), t.to_string()));
+        LinkGraph::new(nodes, edges)
+    }
+
+    #[test]
+    fn neighbors_skip_self_links_and_unknown_notes() {
+        let graph = graph();
+
+        assert_eq!(graph.outbound("a"), vec!["b"]);
+        assert_eq!(graph.inbound("a"), vec!["c"]);
+        assert_eq!(graph.outbound("missing"), Vec::<String>::new());
+        assert_eq!(graph.edges().count(), 5);
+    }
+
+    #[test]
+    fn local_graph_follows_links_both_ways() {
+        let graph = graph();
+
+        let local = graph.local_graph("d", 1);
+        assert_eq!(local.nodes, vec!["c", "d"]);
+        assert_eq!(
+            local.edges,
+            vec![GraphEdge {
+                source: "c".into(),
+                target: "d".into(),
+            }]
+        );
+        assert_eq!(graph.local_graph("d", 2).nodes, vec!["a", "b", "c", "d"]);
+        assert_eq!(graph.local_graph("d", 0).nodes, vec!["d"]);
+        assert_eq!(graph.local_graph("missing", 2).nodes, Vec::<String>::new());
+    }
+
+    #[test]
+    fn components_are_largest_first() {
+        assert_eq!(
+            graph().components(),
+            vec![vec!["a", "b", "c", "d"], vec!["f", "g"], vec!["e"]]
+        );
+    }
+
+    #[test]
+    fn shortest_paths_respect_direction_when_asked() {
+        let graph = graph();
+
+        assert_eq!(
+            graph.shortest_path("a", "d", true),
+            Some(vec!["a".into(), "b".into(), "c".into(), "d".into()])
+        );
+        assert_eq!(
+            graph.shortest_path("a", "d", false),
+            Some(vec!["a".into(), "c".into(), "d".into()])
+        );
+        assert_eq!(graph.shortest_path("d", "a", true), None);
+        assert_eq!(graph.shortest_path("a", "e", false), None);
+        assert_eq!(graph.shortest_path("a", "a", true), Some(vec!["a".into()]));
+    }
+}
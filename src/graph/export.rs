@@ -0,0 +1,92 @@
+use std::fmt::Write;
+
+use crate::graph::LinkGraph;
+
+impl LinkGraph {
+    /// Graphviz DOT source for the graph
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph vault {\n");
+        for node in self.nodes() {
+            writeln!(out, "  {};", dot_id(node)).unwrap();
+        }
+        for (source, target) in self.edges() {
+            writeln!(out, "  {} -> {};", dot_id(source), dot_id(target)).unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// `GraphML` document for the graph
+    #[must_use]
+    pub fn to_graphml(&self) -> String {
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n  \
+             <graph id=\"vault\" edgedefault=\"directed\">\n",
+        );
+        for node in self.nodes() {
+            writeln!(out, "    <node id=\"{}\"/>", xml_escape(node)).unwrap();
+        }
+        for (source, target) in self.edges() {
+            writeln!(
+                out,
+                "    <edge source=\"{}\" target=\"{}\"/>",
+                xml_escape(source),
+                xml_escape(target)
+            )
+            .unwrap();
+        }
+        out.push_str("  </graph>\n</graphml>\n");
+        out
+    }
+}
+
+/// A quoted DOT identifier
+fn dot_id(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> LinkGraph {
+        let nodes = ["plan", "notes/\"q3\" & more"].map(String::from);
+        let edges = [("plan".to_string(), "notes/\"q3\" & more".to_string())];
+        LinkGraph::new(nodes, edges)
+    }
+
+    #[test]
+    fn dot_quotes_ids() {
+        assert_eq!(
+            graph().to_dot(),
+            "digraph vault {\n  \"notes/\\\"q3\\\" & more\";\n  \"plan\";\n  \
+             \"plan\" -> \"notes/\\\"q3\\\" & more\";\n}\n"
+        );
+    }
+
+    #[test]
+    fn graphml_escapes_ids() {
+        let xml = graph().to_graphml();
+
+        assert!(xml.contains("<node id=\"plan\"/>"));
+        assert!(xml.contains("<edge source=\"plan\" target=\"notes/&quot;q3&quot; &amp; more\"/>"));
+        assert!(xml.ends_with("</graph>\n</graphml>\n"));
+    }
+}
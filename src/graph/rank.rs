@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use crate::graph::LinkGraph;
+
+/// How often a random walk along links would visit a note
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct NoteRank {
+    pub note_id: String,
+    /// Share of the walk spent on this note; scores add up to 1
+    pub score: f64,
+}
+
+/// Chance of following a link rather than jumping to a random note
+const DAMPING: f64 = 0.85;
+const MAX_ITERATIONS: usize = 100;
+/// Stop once the scores move less than this in total
+const TOLERANCE: f64 = 1e-9;
+
+impl LinkGraph {
+    /// `PageRank` of every note, highest first
+    ///
+    /// Notes without outbound links spread their score over every note, so
+    /// scores always add up to 1. Ties are sorted by ID.
+    #[must_use]
+    pub fn page_rank(&self) -> Vec<NoteRank> {
+        let ids: Vec<&str> = self.nodes().collect();
+        if ids.is_empty() {
+            return Vec::new();
+        }
+        let index: HashMap<&str, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let outbound: Vec<Vec<usize>> = ids
+            .iter()
+            .map(|id| {
+                self.outbound[*id]
+                    .iter()
+                    .map(|t| index[t.as_str()])
+                    .collect()
+            })
+            .collect();
+
+        #[allow(clippy::cast_precision_loss)]
+        let n = ids.len() as f64;
+        let mut scores = vec![1.0 / n; ids.len()];
+        for _ in 0..MAX_ITERATIONS {
+            let dangling: f64 = outbound
+                .iter()
+                .zip(&scores)
+                .filter(|(targets, _)| targets.is_empty())
+                .map(|(_, score)| score)
+                .sum();
+            let mut next = vec![(1.0 - DAMPING) / n + DAMPING * dangling / n; ids.len()];
+            for (targets, score) in outbound.iter().zip(&scores) {
+                #[allow(clippy::cast_precision_loss)]
+                let share = DAMPING * score / targets.len() as f64;
+                for &target in targets {
+                    next[target] += share;
+                }
+            }
+
+            let change: f64 = next.iter().zip(&scores).map(|(a, b)| (a - b).abs()).sum();
+            scores = next;
+            if change < TOLERANCE {
+                break;
+            }
+        }
+
+        let mut ranks: Vec<NoteRank> = ids
+            .into_iter()
+            .zip(scores)
+            .map(|(id, score)| NoteRank {
+                note_id: id.to_string(),
+                score,
+            })
+            .collect();
+        ranks.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.note_id.cmp(&b.note_id))
+        });
+        ranks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heavily_linked_notes_rank_highest() {
+        let nodes = ["hub", "a", "b", "c", "lonely"].map(String::from);
+        let edges = [("a", "hub"), ("b", "hub"), ("c", "hub"), ("hub", "a")]
+            .map(|(s, t)| (s.to_string(), t.to_string()));
+        let ranks = LinkGraph::new(nodes, edges).page_rank();
+
+        let order: Vec<&str> = ranks.iter().map(|r| r.note_id.as_str()).collect();
+        assert_eq!(order, vec!["hub", "a", "b", "c", "lonely"]);
+        assert_eq!(ranks[2].score, ranks[3].score);
+        let total: f64 = ranks.iter().map(|r| r.score).sum();
+        assert!((total - 1.0).abs() < 1e-6, "{total}");
+    }
+}
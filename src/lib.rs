@@ -6,10 +6,14 @@ uniffi::setup_scaffolding!();
 pub mod document;
 pub mod error;
 pub mod formats;
+pub mod graph;
 pub mod managers;
 pub mod models;
 pub mod parser;
+pub mod query;
 pub mod repo;
+pub mod search;
+pub mod tasks;
 pub mod vault;
 
 // Re-export common error types for convenience
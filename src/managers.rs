@@ -1,2 +1,3 @@
 pub mod backlinks;
+pub mod links;
 pub mod tags;
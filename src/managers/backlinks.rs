@@ -1,15 +1,30 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, hash_map::Entry};
 
 use crate::{
     formats::NoteMetadata,
     models::{Attachment, LinkTarget, Note},
 };
 
+/// A link from one note into another, or into one of its sections
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, uniffi::Record)]
+pub struct Backlink {
+    /// ID of the linking note
+    pub source: String,
+    /// What was linked: the note itself, a heading or a block
+    pub target: LinkTarget,
+}
+
 /// Manages a mapping of note → backlinks (who links to this note)
 #[derive(Default)]
 pub struct BacklinkManager {
     /// `note_id` -> set of `note_ids` that link to it
     backlinks: HashMap<LinkTarget, HashSet<String>>,
+    /// `note_id` -> set of targets it links to, so a note's edges can be
+    /// dropped without scanning the whole index
+    outbound: HashMap<String, HashSet<LinkTarget>>,
+    /// `note_id` -> the note, heading and block targets inside it that have
+    /// backlinks
+    sections: HashMap<String, HashSet<LinkTarget>>,
 }
 
 impl BacklinkManager {
@@ -17,6 +32,8 @@ impl BacklinkManager {
     pub fn new() -> Self {
         Self {
             backlinks: HashMap::new(),
+            outbound: HashMap::new(),
+            sections: HashMap::new(),
         }
     }
 
@@ -27,14 +44,81 @@ impl BacklinkManager {
         attachments: &[Attachment],
         metadata: &dyn NoteMetadata,
     ) {
-        self.backlinks.clear();
+        self.clear();
         for note in notes {
-            let targets = metadata.extract_links(note, attachments);
-            for target in targets {
-                self.backlinks
-                    .entry(target)
+            self.index_note(note, attachments, metadata);
+        }
+    }
+
+    /// Index a single note, replacing any links previously recorded for it
+    pub fn index_note(
+        &mut self,
+        note: &Note,
+        attachments: &[Attachment],
+        metadata: &dyn NoteMetadata,
+    ) {
+        self.index_links(&note.id, metadata.extract_links(note, attachments));
+    }
+
+    /// Record the links of a single note, replacing any recorded before
+    ///
+    /// Used when the targets have already been extracted, e.g. after
+    /// resolving them to note IDs.
+    pub fn index_links(&mut self, source: &str, targets: impl IntoIterator<Item = LinkTarget>) {
+        self.remove_note(source);
+
+        let targets: HashSet<LinkTarget> = targets.into_iter().collect();
+        if targets.is_empty() {
+            return;
+        }
+
+        for target in &targets {
+            self.backlinks
+                .entry(target.clone())
+                .or_default()
+                .insert(source.to_string());
+            if let Some(note_id) = target.note_id() {
+                self.sections
+                    .entry(note_id.to_string())
                     .or_default()
-                    .insert(note.id.clone());
+                    .insert(target.clone());
+            }
+        }
+        self.outbound.insert(source.to_string(), targets);
+    }
+
+    /// Drop every recorded link
+    pub fn clear(&mut self) {
+        self.backlinks.clear();
+        self.outbound.clear();
+        self.sections.clear();
+    }
+
+    /// Drop every link originating from the given note
+    ///
+    /// Links pointing *to* the note are left alone; they belong to other notes.
+    pub fn remove_note(&mut self, note_id: &str) {
+        let Some(targets) = self.outbound.remove(note_id) else {
+            return;
+        };
+
+        for target in targets {
+            let Entry::Occupied(mut sources) = self.backlinks.entry(target) else {
+                continue;
+            };
+            sources.get_mut().remove(note_id);
+            if !sources.get().is_empty() {
+                continue;
+            }
+
+            let (target, _) = sources.remove_entry();
+            if let Some(note) = target.note_id()
+                && let Entry::Occupied(mut sections) = self.sections.entry(note.to_string())
+            {
+                sections.get_mut().remove(&target);
+                if sections.get().is_empty() {
+                    sections.remove();
+                }
             }
         }
     }
@@ -44,8 +128,57 @@ impl BacklinkManager {
     pub fn backlinks_for(&self, target: &LinkTarget) -> Vec<String> {
         self.backlinks
             .get(target)
-            .map(|s| s.iter().cloned().collect())
-            .unwrap_or_default()
+            .map_or_else(Vec::new, |s| s.iter().cloned().collect())
+    }
+
+    /// Every link into the given note, its headings or its blocks, sorted by
+    /// source
+    #[must_use]
+    pub fn references_to(&self, note_id: &str) -> Vec<Backlink> {
+        let mut references: Vec<Backlink> = self
+            .sections
+            .get(note_id)
+            .into_iter()
+            .flatten()
+            .flat_map(|target| {
+                self.backlinks[target].iter().map(|source| Backlink {
+                    source: source.clone(),
+                    target: target.clone(),
+                })
+            })
+            .collect();
+        references.sort();
+        references
+    }
+
+    /// IDs of the notes linking to the given note or any section of it, sorted
+    #[must_use]
+    pub fn sources_for_note(&self, note_id: &str) -> Vec<String> {
+        let mut sources: Vec<String> = self
+            .references_to(note_id)
+            .into_iter()
+            .map(|backlink| backlink.source)
+            .collect();
+        sources.dedup();
+        sources
+    }
+
+    /// Whether `source` links to the given note or any section of it
+    #[must_use]
+    pub fn links_to_note(&self, source: &str, note_id: &str) -> bool {
+        self.sections
+            .get(note_id)
+            .into_iter()
+            .flatten()
+            .any(|target| self.links_to(source, target))
+    }
+
+    /// Whether the note `source` links to `target`
+    #[must_use]
+    pub fn links_to(&self, source: &str, target: &LinkTarget) -> bool {
+        self.backlinks
+            .get(target)
+            .is_some_and(|sources| sources.contains(source))
     }
 
     /// Optionally: get all outbound links from a specific note
@@ -69,6 +202,7 @@ mod tests {
 
     use super::*;
 
+    #[allow(clippy::similar_names)]
     fn make_markdown_notes() -> (Vec<Note>, Vec<Attachment>) {
         let att1 = Attachment {
             src: "img1".into(),
@@ -93,19 +227,48 @@ This links to [Note B](b) and to an attachment ![Image](img1)
 This links to [Note C](c)
 ";
 
-        let format = MarkdownFormat;
+        let format = MarkdownFormat::default();
 
         let notes = vec![
-            format.deserialize(note_a_md.as_bytes(), Some("a")),
-            format.deserialize(note_b_md.as_bytes(), Some("b")),
+            format.deserialize(note_a_md.as_bytes(), Some("a")).unwrap(),
+            format.deserialize(note_b_md.as_bytes(), Some("b")).unwrap(),
         ];
 
         (notes, vec![att1, att2])
     }
 
+    #[test]
+    fn test_reindexing_a_note_replaces_its_links() {
+        let format = MarkdownFormat::default();
+        let (mut notes, attachments) = make_markdown_notes();
+        let mut manager = BacklinkManager::new();
+        manager.index_all(&notes, &attachments, &format);
+
+        notes[0] = format
+            .deserialize(b"# Note A\n\nNow links to [Note C](c)", Some("a"))
+            .unwrap();
+        manager.index_note(&notes[0], &attachments, &format);
+
+        let none: Vec<String> = Vec::new();
+        assert_eq!(manager.backlinks_for(&LinkTarget::Note("b".into())), none);
+        assert_eq!(
+            manager.backlinks_for(&LinkTarget::Attachment("img1".into())),
+            none
+        );
+        let mut backlinks_c = manager.backlinks_for(&LinkTarget::Note("c".into()));
+        backlinks_c.sort();
+        assert_eq!(backlinks_c, vec!["a", "b"]);
+
+        manager.remove_note("b");
+        assert_eq!(
+            manager.backlinks_for(&LinkTarget::Note("c".into())),
+            vec!["a"]
+        );
+    }
+
     #[test]
     fn test_backlinks_with_markdown_format() {
-        let format = MarkdownFormat;
+        let format = MarkdownFormat::default();
         let (notes, attachments) = make_markdown_notes();
         let mut manager = BacklinkManager::new();
 
@@ -121,4 +284,49 @@ This links to [Note C](c)
         assert!(outbound.contains(&LinkTarget::Note("b".into())));
         assert!(outbound.contains(&LinkTarget::Attachment("img1".into())));
     }
+
+    #[test]
+    fn test_references_report_the_linked_section() {
+        let format = MarkdownFormat::default();
+        let notes: Vec<Note> = [
+            ("plan", "# Plan\n## Q3 Goals\nShip it ^ship"),
+            ("a", "# A\nSee [[plan#Q3 Goals]] and [[plan#^ship]]"),
+            ("b", "# B\nSee [[plan]]"),
+        ]
+        .into_iter()
+        .map(|(id, text)| format.deserialize(text.as_bytes(), Some(id)).unwrap())
+        .collect();
+        let mut manager = BacklinkManager::new();
+        manager.index_all(&notes, &[], &format);
+
+        assert_eq!(
+            manager.references_to("plan"),
+            vec![
+                Backlink {
+                    source: "a".into(),
+                    target: LinkTarget::Heading {
+                        note: "plan".into(),
+                        slug: "q3-goals".into(),
+                    },
+                },
+                Backlink {
+                    source: "a".into(),
+                    target: LinkTarget::Block {
+                        note: "plan".into(),
+                        block_id: "ship".into(),
+                    },
+                },
+                Backlink {
+                    source: "b".into(),
+                    target: LinkTarget::Note("plan".into()),
+                },
+            ]
+        );
+        assert_eq!(manager.sources_for_note("plan"), vec!["a", "b"]);
+        assert!(manager.links_to_note("a", "plan"));
+
+        manager.remove_note("a");
+        assert_eq!(manager.sources_for_note("plan"), vec!["b"]);
+        assert!(!manager.links_to_note("a", "plan"));
+    }
 }
@@ -0,0 +1,300 @@
+use std::collections::{BTreeSet, HashMap, hash_map::Entry};
+
+use crate::{
+    document::TextMatch,
+    models::{LinkTarget, Note},
+};
+
+/// Extensions stripped from link targets and IDs to get a filename stem
+const NOTE_EXTENSIONS: [&str; 3] = ["md", "markdown", "org"];
+
+/// Outcome of resolving a link target to a note
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Enum)]
+pub enum LinkResolution {
+    /// Exactly one note matches
+    Resolved { note_id: String },
+    /// Several notes match equally well, sorted by ID
+    Ambiguous { candidates: Vec<String> },
+    /// No note matches
+    Unresolved,
+}
+
+/// A note's title or alias appearing as plain text in another note
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Mention {
+    /// ID of the mentioned note
+    pub target: String,
+    /// ID of the note the text appears in
+    pub source: String,
+    /// Where in the source note the text is
+    pub location: TextMatch,
+}
+
+/// Ways a note can be named in a link, strongest first
+const ID: usize = 0;
+const STEM: usize = 1;
+const TITLE: usize = 2;
+const ALIAS: usize = 3;
+
+/// Maps link targets such as `[[Some Title]]` to note IDs
+///
+/// A target matches a note by its ID, filename stem, title or one of the
+/// `aliases` in its front matter, ignoring case. Matches are ranked in that
+/// order, so a note whose ID is `plan` wins over another note titled "Plan";
+/// only when several notes match at the same rank is the link ambiguous.
+#[derive(Debug, Clone, Default)]
+pub struct LinkResolver {
+    /// One map per rank: lowercased name -> IDs of the notes using it
+    names: [HashMap<String, BTreeSet<String>>; 4],
+    /// `note_id` -> the names it was registered under, so they can be dropped
+    note_names: HashMap<String, Vec<(usize, String)>>,
+}
+
+impl LinkResolver {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index the names of every note, replacing anything indexed before
+    pub fn index_all(&mut self, notes: &[Note]) {
+        self.names = Default::default();
+        self.note_names.clear();
+        for note in notes {
+            self.index_note(note);
+        }
+    }
+
+    /// Index a single note's names
+    ///
+    /// Returns whether they changed, in which case links elsewhere in the
+    /// vault may now resolve differently.
+    pub fn index_note(&mut self, note: &Note) -> bool {
+        let names = note_names(note);
+        if self.note_names.get(&note.id) == Some(&names) {
+            return false;
+        }
+
+        self.remove_note(&note.id);
+        for (rank, name) in &names {
+            self.names[*rank]
+                .entry(name.clone())
+                .or_default()
+                .insert(note.id.clone());
+        }
+        self.note_names.insert(note.id.clone(), names);
+        true
+    }
+
+    /// Forget a note's names, returning whether it was indexed
+    pub fn remove_note(&mut self, note_id: &str) -> bool {
+        let Some(names) = self.note_names.remove(note_id) else {
+            return false;
+        };
+
+        for (rank, name) in names {
+            if let Entry::Occupied(mut ids) = self.names[rank].entry(name) {
+                ids.get_mut().remove(note_id);
+                if ids.get().is_empty() {
+                    ids.remove();
+                }
+            }
+        }
+        true
+    }
+
+    /// Whether `target` is one of the note's names, so links to it may
+    /// resolve to the note
+    #[must_use]
+    pub fn answers_to(&self, note_id: &str, target: &str) -> bool {
+        let target = target.trim().to_lowercase();
+        let stem = stem(&target);
+        self.note_names.get(note_id).is_some_and(|names| {
+            names
+                .iter()
+                .any(|(rank, name)| *name == target || (*rank == STEM && name == stem))
+        })
+    }
+
+    /// Resolve a link target to the note it names
+    #[must_use]
+    pub fn resolve(&self, target: &str) -> LinkResolution {
+        let target = target.trim().to_lowercase();
+        if target.is_empty() {
+            return LinkResolution::Unresolved;
+        }
+        let stem = stem(&target);
+
+        for (rank, names) in self.names.iter().enumerate() {
+            let mut ids: BTreeSet<&String> = names.get(&target).into_iter().flatten().collect();
+            if rank == STEM {
+                ids.extend(names.get(stem).into_iter().flatten());
+            }
+
+            let ids: Vec<String> = ids.into_iter().cloned().collect();
+            match ids.as_slice() {
+                [] => {}
+                [id] => {
+                    return LinkResolution::Resolved {
+                        note_id: id.clone(),
+                    };
+                }
+                _ => return LinkResolution::Ambiguous { candidates: ids },
+            }
+        }
+
+        LinkResolution::Unresolved
+    }
+
+    /// Rewrite a link into a note, heading or block to use the resolved note
+    /// ID
+    ///
+    /// Ambiguous and unresolved links, and attachment links, are returned
+    /// unchanged.
+    #[must_use]
+    pub fn resolve_target(&self, target: &LinkTarget) -> LinkTarget {
+        match target.note_id().map(|name| self.resolve(name)) {
+            Some(LinkResolution::Resolved { note_id }) => target.with_note(note_id),
+            _ => target.clone(),
+        }
+    }
+}
+
+/// Every lowercased name a note answers to, with its rank
+fn note_names(note: &Note) -> Vec<(usize, String)> {
+    let id = note.id.to_lowercase();
+    let mut names = vec![(STEM, stem(&id).to_string()), (ID, id)];
+
+    let title = note.title.trim();
+    if !title.is_empty() {
+        names.push((TITLE, title.to_lowercase()));
+    }
+    if let Some(aliases) = note.properties.get("aliases") {
+        names.extend(
+            aliases
+                .as_strings()
+                .into_iter()
+                .map(str::trim)
+                .filter(|alias| !alias.is_empty())
+                .map(|alias| (ALIAS, alias.to_lowercase())),
+        );
+    }
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// The file name of a path without a note extension
+fn stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && NOTE_EXTENSIONS.contains(&ext) => stem,
+        _ => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Properties, PropertyValue};
+
+    fn note(id: &str, title: &str, aliases: &[&str]) -> Note {
+        let mut properties = Properties::new();
+        if !aliases.is_empty() {
+            properties.insert(
+                "aliases",
+                PropertyValue::List {
+                    items: aliases.iter().map(|a| PropertyValue::text(*a)).collect(),
+                },
+            );
+        }
+        Note {
+            id: id.into(),
+            title: title.into(),
+            properties,
+            blocks: vec![],
+        }
+    }
+
+    fn resolved(id: &str) -> LinkResolution {
+        LinkResolution::Resolved { note_id: id.into() }
+    }
+
+    #[test]
+    fn resolves_by_id_stem_title_and_alias_ignoring_case() {
+        let mut resolver = LinkResolver::new();
+        resolver.index_all(&[
+            note("projects/roadmap.md", "Product Roadmap", &["Plan", "Q3"]),
+            note("inbox", "Inbox", &[]),
+        ]);
+
+        for target in [
+            "projects/roadmap.md",
+            "ROADMAP",
+            "roadmap.md",
+            "product roadmap",
+            "plan",
+            " q3 ",
+        ] {
+            assert_eq!(
+                resolver.resolve(target),
+                resolved("projects/roadmap.md"),
+                "{target:?}"
+            );
+        }
+        assert_eq!(resolver.resolve("Inbox"), resolved("inbox"));
+        assert_eq!(resolver.resolve("Someday"), LinkResolution::Unresolved);
+        assert_eq!(resolver.resolve(""), LinkResolution::Unresolved);
+    }
+
+    #[test]
+    fn answers_to_every_name_of_a_note() {
+        let mut resolver = LinkResolver::new();
+        resolver.index_all(&[note("projects/roadmap.md", "Product Roadmap", &["Plan"])]);
+
+        for target in ["projects/roadmap.md", "Roadmap", "roadmap.org", " plan "] {
+            assert!(
+                resolver.answers_to("projects/roadmap.md", target),
+                "{target:?}"
+            );
+        }
+        assert!(!resolver.answers_to("projects/roadmap.md", "Inbox"));
+        assert!(!resolver.answers_to("inbox", "Roadmap"));
+    }
+
+    #[test]
+    fn stronger_matches_win_and_ties_are_ambiguous() {
+        let mut resolver = LinkResolver::new();
+        resolver.index_all(&[
+            note("plan", "Plan", &[]),
+            note("q3", "Plan", &["Goals"]),
+            note("q4", "Later", &["Goals"]),
+        ]);
+
+        assert_eq!(resolver.resolve("Plan"), resolved("plan"));
+        assert_eq!(
+            resolver.resolve("goals"),
+            LinkResolution::Ambiguous {
+                candidates: vec!["q3".into(), "q4".into()],
+            }
+        );
+        assert_eq!(
+            resolver.resolve_target(&LinkTarget::Note("Goals".into())),
+            LinkTarget::Note("Goals".into())
+        );
+    }
+
+    #[test]
+    fn reindexing_a_note_reports_name_changes() {
+        let mut resolver = LinkResolver::new();
+        assert!(resolver.index_note(&note("a", "Alpha", &[])));
+        assert!(!resolver.index_note(&note("a", "Alpha", &[])));
+        assert!(resolver.index_note(&note("a", "Alpha", &["First"])));
+        assert_eq!(resolver.resolve("first"), resolved("a"));
+
+        assert!(resolver.remove_note("a"));
+        assert!(!resolver.remove_note("a"));
+        assert_eq!(resolver.resolve("alpha"), LinkResolution::Unresolved);
+    }
+}
@@ -1,16 +1,75 @@
+pub mod store;
+
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, hash_map::Entry},
     sync::{Arc, RwLock},
 };
 
 use uniffi::export;
 
-use crate::models::Note;
+pub use store::{FileTagStore, TagStore};
+
+use crate::{error::RepositoryResult, models::Note};
+
+/// How many notes carry a tag, counting notes tagged with its subtags
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: u64,
+}
+
+/// Whether `tag` is `query` or one of its subtags, so `project/alpha`
+/// matches `project` but `projects` does not
+#[must_use]
+pub fn tag_matches(tag: &str, query: &str) -> bool {
+    tag.strip_prefix(query)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// `tag` with `old` (or the `old/` prefix of a subtag) replaced by `new`, or
+/// `None` if it does not match `old`
+#[must_use]
+pub fn rename_tag(tag: &str, old: &str, new: &str) -> Option<String> {
+    tag_matches(tag, old).then(|| format!("{new}{}", &tag[old.len()..]))
+}
+
+/// Count the notes under every tag and its parent tags
+///
+/// `tags` is each note's tags; a note tagged `project/alpha` is counted
+/// once under `project/alpha` and once under `project`. Sorted by tag.
+#[must_use]
+pub fn count_tags<'a>(tags: impl IntoIterator<Item = &'a BTreeSet<String>>) -> Vec<TagCount> {
+    let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+    for note_tags in tags {
+        let mut seen = BTreeSet::new();
+        for tag in note_tags {
+            seen.extend(
+                tag.match_indices('/')
+                    .map(|(i, _)| &tag[..i])
+                    .chain([tag.as_str()]),
+            );
+        }
+        for tag in seen {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+    counts
+        .into_iter()
+        .map(|(tag, count)| TagCount {
+            tag: tag.to_string(),
+            count,
+        })
+        .collect()
+}
 
 /// Global tags assigned externally
+///
+/// Each note's tags form a set. With a [`TagStore`] every change is saved
+/// before it takes effect, so a failed save leaves the tags unchanged.
 #[derive(Debug, Default, uniffi::Object)]
 pub struct GlobalTagManager {
-    global_tags: RwLock<HashMap<String, Vec<String>>>,
+    global_tags: RwLock<BTreeMap<String, BTreeSet<String>>>,
+    store: Option<Arc<dyn TagStore>>,
 }
 
 #[export]
@@ -18,36 +77,204 @@ impl GlobalTagManager {
     #[must_use]
     #[uniffi::constructor]
     pub fn new() -> Arc<Self> {
-        Arc::new(Self {
-            global_tags: RwLock::new(HashMap::new()),
+        Arc::new(Self::default())
+    }
+
+    /// Load the assignments saved in `store` and keep saving changes to it
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the store cannot be read
+    #[uniffi::constructor]
+    pub fn with_store(store: Arc<dyn TagStore>) -> RepositoryResult<Arc<Self>> {
+        let global_tags = store
+            .load()?
+            .into_iter()
+            .map(|(note_id, tags)| (note_id, tags.into_iter().collect::<BTreeSet<_>>()))
+            .filter(|(_, tags)| !tags.is_empty())
+            .collect();
+        Ok(Arc::new(Self {
+            global_tags: RwLock::new(global_tags),
+            store: Some(store),
+        }))
+    }
+
+    /// Add a tag to a note; assigning a tag twice has no effect
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if saving to the store fails
+    #[uniffi::method]
+    pub fn assign_tag(self: Arc<Self>, note_id: &str, tag: String) -> RepositoryResult<()> {
+        self.update(|tags| tags.entry(note_id.to_string()).or_default().insert(tag))
+    }
+
+    /// Take a tag off a note
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if saving to the store fails
+    #[uniffi::method]
+    pub fn remove_tag(self: Arc<Self>, note_id: &str, tag: &str) -> RepositoryResult<()> {
+        self.update(|tags| {
+            let Some(note_tags) = tags.get_mut(note_id) else {
+                return false;
+            };
+            let removed = note_tags.remove(tag);
+            if note_tags.is_empty() {
+                tags.remove(note_id);
+            }
+            removed
         })
     }
 
+    /// Remove every tag assigned to a note
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if saving to the store fails
     #[uniffi::method]
-    pub fn assign_tag(self: Arc<Self>, note_id: &str, tag: String) {
-        self.global_tags
-            .write()
-            .unwrap()
-            .entry(note_id.to_string())
-            .or_default()
-            .push(tag);
+    pub fn clear_tags(self: Arc<Self>, note_id: &str) -> RepositoryResult<()> {
+        self.update(|tags| tags.remove(note_id).is_some())
     }
 
+    /// Move every tag of one note to another, as when a note is renamed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if saving to the store fails
+    #[uniffi::method]
+    pub fn move_tags(self: Arc<Self>, from: &str, to: &str) -> RepositoryResult<()> {
+        self.update(|tags| {
+            let Some(moved) = tags.remove(from) else {
+                return false;
+            };
+            tags.entry(to.to_string()).or_default().extend(moved);
+            true
+        })
+    }
+
+    /// Rename a tag and its subtags on every note
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if saving to the store fails
+    #[uniffi::method]
+    pub fn rename_tag(self: Arc<Self>, old: &str, new: &str) -> RepositoryResult<()> {
+        self.update(|tags| {
+            let mut changed = false;
+            for note_tags in tags.values_mut() {
+                *note_tags = std::mem::take(note_tags)
+                    .into_iter()
+                    .map(|tag| match rename_tag(&tag, old, new) {
+                        Some(renamed) => {
+                            changed = true;
+                            renamed
+                        }
+                        None => tag,
+                    })
+                    .collect();
+            }
+            changed
+        })
+    }
+
+    /// Tags of a note, sorted
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag lock is poisoned
     #[must_use]
     #[uniffi::method]
     pub fn get_tags_for(self: Arc<Self>, note_id: &str) -> Vec<String> {
         self.global_tags
-            .write()
+            .read()
             .unwrap()
             .get(note_id)
-            .cloned()
-            .unwrap_or_default()
+            .map_or_else(Vec::new, |tags| tags.iter().cloned().collect())
+    }
+
+    /// IDs of the notes with the tag or one of its subtags, sorted
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag lock is poisoned
+    #[must_use]
+    #[uniffi::method]
+    pub fn notes_with_tag(self: Arc<Self>, tag: &str) -> Vec<String> {
+        self.global_tags
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, tags)| tags.iter().any(|t| tag_matches(t, tag)))
+            .map(|(note_id, _)| note_id.clone())
+            .collect()
+    }
+
+    /// Every tag assigned to at least one note, sorted
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag lock is poisoned
+    #[must_use]
+    #[uniffi::method]
+    pub fn all_tags(self: Arc<Self>) -> Vec<String> {
+        let global_tags = self.global_tags.read().unwrap();
+        let tags: BTreeSet<&String> = global_tags.values().flatten().collect();
+        tags.into_iter().cloned().collect()
+    }
+}
+
+impl GlobalTagManager {
+    /// `note_id` -> tags, for every note with global tags
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag lock is poisoned
+    #[must_use]
+    pub fn assignments(&self) -> HashMap<String, BTreeSet<String>> {
+        self.global_tags
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(note_id, tags)| (note_id.clone(), tags.clone()))
+            .collect()
+    }
+
+    /// Apply `change` to a copy of the assignments and, if it reports a
+    /// change, save the copy and keep it
+    fn update(
+        &self,
+        change: impl FnOnce(&mut BTreeMap<String, BTreeSet<String>>) -> bool,
+    ) -> RepositoryResult<()> {
+        let mut global_tags = self.global_tags.write().unwrap();
+        let mut updated = global_tags.clone();
+        if !change(&mut updated) {
+            return Ok(());
+        }
+
+        if let Some(store) = &self.store {
+            store.save(
+                updated
+                    .iter()
+                    .map(|(note_id, tags)| (note_id.clone(), tags.iter().cloned().collect()))
+                    .collect(),
+            )?;
+        }
+        *global_tags = updated;
+        Ok(())
     }
 }
 
 /// Tags extracted from content (if supported by format)
+///
+/// Tags are hierarchical: `project/alpha` is a subtag of `project`, and
+/// lookups by `project` include notes tagged with its subtags.
 pub struct ScopedTagManager {
+    /// tag -> IDs of the notes tagged with exactly that tag
     pub tag_index: HashMap<String, HashSet<String>>,
+    /// `note_id` -> tags indexed for it
+    note_tags: HashMap<String, BTreeSet<String>>,
 }
 
 impl Default for ScopedTagManager {
@@ -61,33 +288,96 @@ impl ScopedTagManager {
     pub fn new() -> Self {
         Self {
             tag_index: HashMap::new(),
+            note_tags: HashMap::new(),
+        }
+    }
+
+    /// Rebuild the index from scratch for the given notes
+    pub fn index_all(&mut self, notes: &[Note], extract_tags: impl Fn(&Note) -> Vec<String>) {
+        self.tag_index.clear();
+        self.note_tags.clear();
+        for note in notes {
+            self.index_note(note, &extract_tags);
         }
     }
 
+    /// Index a single note, replacing any tags previously recorded for it
     pub fn index_note(&mut self, note: &Note, extract_tags: impl Fn(&Note) -> Vec<String>) {
-        for tag in extract_tags(note) {
+        self.remove_note(&note.id);
+
+        let tags: BTreeSet<String> = extract_tags(note).into_iter().collect();
+        if tags.is_empty() {
+            return;
+        }
+
+        for tag in &tags {
             self.tag_index
-                .entry(tag)
+                .entry(tag.clone())
                 .or_default()
                 .insert(note.id.clone());
         }
+        self.note_tags.insert(note.id.clone(), tags);
+    }
+
+    /// Drop every tag recorded for the given note
+    pub fn remove_note(&mut self, note_id: &str) {
+        let Some(tags) = self.note_tags.remove(note_id) else {
+            return;
+        };
+
+        for tag in tags {
+            if let Entry::Occupied(mut notes) = self.tag_index.entry(tag) {
+                notes.get_mut().remove(note_id);
+                if notes.get().is_empty() {
+                    notes.remove();
+                }
+            }
+        }
     }
 
+    /// Whether the note was indexed with the given tag or one of its subtags
+    #[must_use]
+    pub fn has_tag(&self, note_id: &str, tag: &str) -> bool {
+        self.note_tags
+            .get(note_id)
+            .is_some_and(|tags| tags.iter().any(|t| tag_matches(t, tag)))
+    }
+
+    /// IDs of the notes tagged with the tag or one of its subtags, sorted
     #[must_use]
     pub fn notes_with_tag(&self, tag: &str) -> Vec<String> {
-        self.tag_index
-            .get(tag)
-            .map(|s| s.iter().cloned().collect())
-            .unwrap_or_default()
+        let notes: BTreeSet<&String> = self
+            .tag_index
+            .iter()
+            .filter(|(t, _)| tag_matches(t, tag))
+            .flat_map(|(_, notes)| notes)
+            .collect();
+        notes.into_iter().cloned().collect()
+    }
+
+    /// Tags indexed for the note, sorted
+    #[must_use]
+    pub fn tags_for_note(&self, note_id: &str) -> Vec<String> {
+        self.note_tags
+            .get(note_id)
+            .map_or_else(Vec::new, |tags| tags.iter().cloned().collect())
+    }
+
+    /// Notes per tag, see [`count_tags`]
+    #[must_use]
+    pub fn tag_counts(&self) -> Vec<TagCount> {
+        count_tags(self.note_tags.values())
     }
 }
 
 #[cfg(test)]
 mod tag_tests {
 
+    use std::collections::HashMap;
+
     use crate::{
         managers::tags::{GlobalTagManager, ScopedTagManager},
-        models::Note,
+        models::{Note, Properties},
     };
 
     #[test]
@@ -96,11 +386,13 @@ mod tag_tests {
             Note {
                 id: "a".into(),
                 title: "A".into(),
+                properties: Properties::default(),
                 blocks: vec![],
             },
             Note {
                 id: "b".into(),
                 title: "B".into(),
+                properties: Properties::default(),
                 blocks: vec![],
             },
         ];
@@ -111,9 +403,104 @@ mod tag_tests {
         }
 
         let global = GlobalTagManager::new();
-        global.clone().assign_tag("a", "global".into());
+        global.clone().assign_tag("a", "global".into()).unwrap();
 
         assert_eq!(scoped.notes_with_tag("scoped").len(), 2);
         assert_eq!(global.get_tags_for("a"), vec!["global"]);
     }
+
+    #[test]
+    fn test_reindexing_a_note_replaces_its_tags() {
+        let note = Note {
+            id: "a".into(),
+            title: "A".into(),
+            properties: Properties::default(),
+            blocks: vec![],
+        };
+
+        let mut scoped = ScopedTagManager::new();
+        scoped.index_note(&note, |_note| vec!["draft".into(), "work".into()]);
+        scoped.index_note(&note, |_note| vec!["work".into()]);
+
+        assert!(!scoped.tag_index.contains_key("draft"));
+        assert_eq!(scoped.notes_with_tag("work"), vec!["a"]);
+
+        scoped.remove_note("a");
+        assert_eq!(scoped.tag_index, HashMap::new());
+
+        let global = GlobalTagManager::new();
+        global.clone().assign_tag("a", "pinned".into()).unwrap();
+        global.clone().clear_tags("a").unwrap();
+        assert_eq!(global.get_tags_for("a"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_subtags_match_their_parents() {
+        let note = |id: &str| Note {
+            id: id.into(),
+            title: id.into(),
+            properties: Properties::default(),
+            blocks: vec![],
+        };
+
+        let mut scoped = ScopedTagManager::new();
+        scoped.index_note(&note("a"), |_note| {
+            vec!["project/alpha".into(), "project".into()]
+        });
+        scoped.index_note(&note("b"), |_note| vec!["project/beta".into()]);
+        scoped.index_note(&note("c"), |_note| vec!["projects".into()]);
+
+        assert_eq!(scoped.notes_with_tag("project"), vec!["a", "b"]);
+        assert_eq!(scoped.notes_with_tag("project/beta"), vec!["b"]);
+        assert!(scoped.has_tag("b", "project"));
+        assert!(!scoped.has_tag("c", "project"));
+        assert_eq!(scoped.tags_for_note("a"), vec!["project", "project/alpha"]);
+
+        let counts: Vec<(String, u64)> = scoped
+            .tag_counts()
+            .into_iter()
+            .map(|count| (count.tag, count.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("project".into(), 2),
+                ("project/alpha".into(), 1),
+                ("project/beta".into(), 1),
+                ("projects".into(), 1),
+            ]
+        );
+
+        let global = GlobalTagManager::new();
+        global
+            .clone()
+            .assign_tag("a", "project/alpha".into())
+            .unwrap();
+        global.clone().assign_tag("a", "work".into()).unwrap();
+        global.clone().assign_tag("a", "work/alpha".into()).unwrap();
+        global.clone().rename_tag("project", "work").unwrap();
+        assert_eq!(global.get_tags_for("a"), vec!["work", "work/alpha"]);
+    }
+
+    #[test]
+    fn test_global_tags_are_sets() {
+        let global = GlobalTagManager::new();
+        for (note, tag) in [
+            ("a", "work"),
+            ("a", "work"),
+            ("a", "later"),
+            ("b", "work/x"),
+        ] {
+            global.clone().assign_tag(note, tag.into()).unwrap();
+        }
+
+        assert_eq!(global.clone().get_tags_for("a"), vec!["later", "work"]);
+        assert_eq!(global.clone().notes_with_tag("work"), vec!["a", "b"]);
+        assert_eq!(global.clone().all_tags(), vec!["later", "work", "work/x"]);
+
+        global.clone().remove_tag("a", "work").unwrap();
+        global.clone().move_tags("a", "b").unwrap();
+        assert_eq!(global.clone().get_tags_for("a"), Vec::<String>::new());
+        assert_eq!(global.clone().get_tags_for("b"), vec!["later", "work/x"]);
+    }
 }
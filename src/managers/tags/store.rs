@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    error::{RepositoryError, RepositoryResult, SerializationError},
+    formats::frontmatter,
+    models::{Properties, PropertyValue},
+    repo::{file::FileProvider, local::LocalFsProvider},
+};
+
+/// Where [`crate::managers::tags::GlobalTagManager`] keeps its assignments
+/// between runs
+#[uniffi::export]
+pub trait TagStore: Send + Sync + Debug {
+    /// Read every saved assignment, `note_id` -> tags
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the saved assignments exist but cannot be read
+    fn load(&self) -> RepositoryResult<HashMap<String, Vec<String>>>;
+
+    /// Replace the saved assignments
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails
+    fn save(&self, assignments: HashMap<String, Vec<String>>) -> RepositoryResult<()>;
+}
+
+/// Keeps global tags in a YAML sidecar file, one `note_id: [tags]` entry per
+/// note, sorted by note ID
+#[derive(Debug, uniffi::Object)]
+pub struct FileTagStore {
+    provider: RwLock<Box<dyn FileProvider>>,
+    path: String,
+}
+
+#[uniffi::export]
+impl FileTagStore {
+    /// Keep the sidecar file at `path` inside a local folder
+    #[uniffi::constructor]
+    #[must_use]
+    pub fn local(provider: Arc<LocalFsProvider>, path: &str) -> Self {
+        Self::new(Box::new(Arc::unwrap_or_clone(provider)), path)
+    }
+
+    /// This store as a [`TagStore`], such as for
+    /// [`crate::managers::tags::GlobalTagManager::with_store`]
+    #[must_use]
+    #[uniffi::method]
+    pub fn as_tag_store(self: Arc<Self>) -> Arc<dyn TagStore> {
+        self
+    }
+}
+
+impl FileTagStore {
+    #[must_use]
+    pub fn new(provider: Box<dyn FileProvider>, path: &str) -> Self {
+        Self {
+            provider: RwLock::new(provider),
+            path: path.to_string(),
+        }
+    }
+}
+
+#[uniffi::export]
+impl TagStore for FileTagStore {
+    /// A missing file holds no assignments
+    fn load(&self) -> RepositoryResult<HashMap<String, Vec<String>>> {
+        let Some(data) = self.provider.read().unwrap().read(&self.path) else {
+            return Ok(HashMap::new());
+        };
+        let yaml = std::str::from_utf8(&data).map_err(SerializationError::from)?;
+        let properties = frontmatter::parse(yaml)?;
+
+        properties
+            .iter()
+            .map(|(note_id, tags)| match tags {
+                PropertyValue::List { .. } | PropertyValue::Null => Ok((
+                    note_id.to_string(),
+                    tags.as_strings().into_iter().map(String::from).collect(),
+                )),
+                _ => Err(RepositoryError::other(format!(
+                    "{}: tags of {note_id} are not a list",
+                    self.path
+                ))),
+            })
+            .collect()
+    }
+
+    fn save(&self, assignments: HashMap<String, Vec<String>>) -> RepositoryResult<()> {
+        let mut assignments: Vec<(String, Vec<String>)> = assignments
+            .into_iter()
+            .filter(|(_, tags)| !tags.is_empty())
+            .collect();
+        assignments.sort();
+
+        let properties: Properties = assignments
+            .into_iter()
+            .map(|(note_id, tags)| {
+                let items = tags.into_iter().map(PropertyValue::text).collect();
+                (note_id, PropertyValue::List { items })
+            })
+            .collect();
+        self.provider
+            .write()
+            .unwrap()
+            .write(&self.path, frontmatter::to_yaml(&properties).as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::managers::tags::GlobalTagManager;
+
+    #[derive(Debug, Default)]
+    struct MemoryFiles(Arc<RwLock<HashMap<String, Vec<u8>>>>);
+
+    impl FileProvider for MemoryFiles {
+        fn read(&self, path: &str) -> Option<Vec<u8>> {
+            self.0.read().unwrap().get(path).cloned()
+        }
+
+        fn write(&mut self, path: &str, data: &[u8]) -> RepositoryResult<()> {
+            self.0
+                .write()
+                .unwrap()
+                .insert(path.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn delete(&mut self, path: &str) -> RepositoryResult<()> {
+            self.0
+                .write()
+                .unwrap()
+                .remove(path)
+                .map(|_| ())
+                .ok_or_else(|| RepositoryError::not_found(path))
+        }
+
+        fn list(&self) -> Vec<String> {
+            self.0.read().unwrap().keys().cloned().collect()
+        }
+    }
+
+    #[test]
+    fn assignments_survive_a_restart() {
+        let files = Arc::new(RwLock::new(HashMap::new()));
+        let open = || {
+            let store = FileTagStore::new(Box::new(MemoryFiles(files.clone())), ".tags.yml");
+            GlobalTagManager::with_store(Arc::new(store)).unwrap()
+        };
+
+        let tags = open();
+        tags.clone()
+            .assign_tag("notes/plan", "2024".into())
+            .unwrap();
+        tags.clone()
+            .assign_tag("notes/plan", "work: q3".into())
+            .unwrap();
+        tags.clone().assign_tag("idea", "later".into()).unwrap();
+        tags.clear_tags("idea").unwrap();
+
+        assert_eq!(
+            String::from_utf8(files.read().unwrap()[".tags.yml"].clone()).unwrap(),
+            "notes/plan:\n  - \"2024\"\n  - \"work: q3\"\n"
+        );
+        let reopened = open();
+        assert_eq!(
+            reopened.clone().get_tags_for("notes/plan"),
+            vec!["2024", "work: q3"]
+        );
+        assert_eq!(reopened.all_tags(), vec!["2024", "work: q3"]);
+    }
+
+    #[test]
+    fn malformed_files_are_an_error() {
+        let files = Arc::new(RwLock::new(HashMap::from([(
+            ".tags.yml".to_string(),
+            b"plan: work".to_vec(),
+        )])));
+        let store = FileTagStore::new(Box::new(MemoryFiles(files)), ".tags.yml");
+
+        assert!(store.load().is_err());
+    }
+}
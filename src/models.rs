@@ -21,13 +21,216 @@ pub struct DefinitionItem {
     pub definition: Blocks,
 }
 
+/// A list item, optionally a task with a checkbox
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, uniffi::Record)]
+pub struct ListItem {
+    #[serde(default)]
+    pub checkbox: Option<Checkbox>,
+    pub blocks: Blocks,
+}
+
+impl ListItem {
+    #[must_use]
+    pub const fn new(blocks: Blocks) -> Self {
+        Self {
+            checkbox: None,
+            blocks,
+        }
+    }
+
+    #[must_use]
+    pub const fn task(checkbox: Checkbox, blocks: Blocks) -> Self {
+        Self {
+            checkbox: Some(checkbox),
+            blocks,
+        }
+    }
+}
+
+impl From<Blocks> for ListItem {
+    fn from(blocks: Blocks) -> Self {
+        Self::new(blocks)
+    }
+}
+
+/// State of a task list item: `[ ]`, `[x]` or `[-]`
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash, uniffi::Enum)]
+pub enum Checkbox {
+    Unchecked,
+    Checked,
+    /// `[-]`: partly done, so still open
+    Partial,
+}
+
+impl Checkbox {
+    /// Split a leading `[ ]`, `[x]`, `[X]` or `[-]` followed by a space (or
+    /// nothing) off the text of a list item
+    #[must_use]
+    pub fn strip_from(text: &str) -> Option<(Self, &str)> {
+        let checkbox = match text.get(..3)? {
+            "[ ]" => Self::Unchecked,
+            "[x]" | "[X]" => Self::Checked,
+            "[-]" => Self::Partial,
+            _ => return None,
+        };
+        let rest = &text[3..];
+        if rest.is_empty() {
+            Some((checkbox, rest))
+        } else {
+            rest.strip_prefix(' ').map(|rest| (checkbox, rest))
+        }
+    }
+
+    /// Markdown spelling, `[ ]`, `[x]` or `[-]`
+    #[must_use]
+    pub const fn marker(self) -> &'static str {
+        match self {
+            Self::Unchecked => "[ ]",
+            Self::Checked => "[x]",
+            Self::Partial => "[-]",
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, uniffi::Record)]
 pub struct Note {
     pub id: String,
     pub title: String,
+    /// Front matter (Markdown) or keywords and property drawer (Org)
+    #[serde(default)]
+    pub properties: Properties,
     pub blocks: Blocks,
 }
 
+/// A typed metadata value, as found in YAML front matter
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, uniffi::Enum)]
+pub enum PropertyValue {
+    Null,
+    Bool {
+        value: bool,
+    },
+    /// A number kept as written (`3`, `-1.5e3`) so it round-trips exactly
+    Number {
+        value: String,
+    },
+    Text {
+        value: String,
+    },
+    /// An ISO 8601 date or date-time (`2024-05-01`, `2024-05-01T09:30:00Z`)
+    Date {
+        value: String,
+    },
+    List {
+        items: Vec<PropertyValue>,
+    },
+    Map {
+        entries: Properties,
+    },
+}
+
+impl PropertyValue {
+    #[must_use]
+    pub fn text(value: impl Into<String>) -> Self {
+        Self::Text {
+            value: value.into(),
+        }
+    }
+
+    /// The value as a string, for scalars
+    #[must_use]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Text { value } | Self::Number { value } | Self::Date { value } => Some(value),
+            Self::Bool { value: true } => Some("true"),
+            Self::Bool { value: false } => Some("false"),
+            _ => None,
+        }
+    }
+
+    /// The value as a number, if it is one
+    #[must_use]
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Number { value } => value.parse().ok(),
+            _ => None,
+        }
+    }
+
+    /// The scalar items of a list, or a single scalar as a one-item list
+    #[must_use]
+    pub fn as_strings(&self) -> Vec<&str> {
+        match self {
+            Self::List { items } => items.iter().filter_map(Self::as_str).collect(),
+            other => other.as_str().into_iter().collect(),
+        }
+    }
+}
+
+/// A single named property
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, uniffi::Record)]
+pub struct Property {
+    pub key: String,
+    pub value: PropertyValue,
+}
+
+/// Ordered property map; keys keep the order they were written in
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq, uniffi::Record)]
+pub struct Properties {
+    pub entries: Vec<Property>,
+}
+
+impl Properties {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&PropertyValue> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| &entry.value)
+    }
+
+    /// Set a property, replacing an existing value in place
+    pub fn insert(&mut self, key: impl Into<String>, value: PropertyValue) {
+        let key = key.into();
+        match self.entries.iter_mut().find(|entry| entry.key == key) {
+            Some(entry) => entry.value = value,
+            None => self.entries.push(Property { key, value }),
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<PropertyValue> {
+        let index = self.entries.iter().position(|entry| entry.key == key)?;
+        Some(self.entries.remove(index).value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &PropertyValue)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.key.as_str(), &entry.value))
+    }
+}
+
+impl FromIterator<(String, PropertyValue)> for Properties {
+    fn from_iter<I: IntoIterator<Item = (String, PropertyValue)>>(iter: I) -> Self {
+        let mut properties = Self::new();
+        for (key, value) in iter {
+            properties.insert(key, value);
+        }
+        properties
+    }
+}
+
 /// A note that preserves syntax type information and supports hybrid markup
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct HybridNote {
@@ -122,7 +325,7 @@ pub enum ContainerBlock {
     },
     List {
         style: ListStyle,
-        items: Vec<Blocks>,
+        items: Vec<ListItem>,
     },
     Table {
         headers: Vec<Vec<Inline>>,
@@ -161,6 +364,10 @@ pub enum LeafBlock {
     Attachment {
         attachment: Attachment,
     },
+    /// Raw HTML, kept verbatim
+    Html {
+        content: String,
+    },
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, uniffi::Enum)]
@@ -221,6 +428,13 @@ impl Block {
         }
     }
 
+    #[must_use]
+    pub const fn html(content: String) -> Self {
+        Self::Leaf {
+            leaf: LeafBlock::Html { content },
+        }
+    }
+
     #[must_use]
     pub const fn quote(blocks: Blocks) -> Self {
         Self::Container {
@@ -229,9 +443,12 @@ impl Block {
     }
 
     #[must_use]
-    pub const fn list(style: ListStyle, items: Vec<Blocks>) -> Self {
+    pub fn list(style: ListStyle, items: impl IntoIterator<Item = impl Into<ListItem>>) -> Self {
         Self::Container {
-            container: ContainerBlock::List { style, items },
+            container: ContainerBlock::List {
+                style,
+                items: items.into_iter().map(Into::into).collect(),
+            },
         }
     }
 
@@ -366,7 +583,8 @@ impl_leaf_helpers!(
     Image { alt_text, src },
     CodeBlock { language, content },
     MathBlock { content },
-    Attachment { attachment }
+    Attachment { attachment },
+    Html { content }
 );
 
 impl_container_helpers!(
@@ -385,6 +603,51 @@ impl_container_helpers!(
     }
 );
 
+/// What a [`Block`] is, without its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, uniffi::Enum)]
+pub enum BlockKind {
+    Paragraph,
+    Heading,
+    Image,
+    CodeBlock,
+    MathBlock,
+    HorizontalRule,
+    Attachment,
+    Html,
+    Quote,
+    List,
+    Table,
+    Div,
+    DefinitionList,
+    FootnoteDefinition,
+}
+
+impl Block {
+    #[must_use]
+    pub const fn kind(&self) -> BlockKind {
+        match self {
+            Self::Leaf { leaf } => match leaf {
+                LeafBlock::Paragraph { .. } => BlockKind::Paragraph,
+                LeafBlock::Heading { .. } => BlockKind::Heading,
+                LeafBlock::Image { .. } => BlockKind::Image,
+                LeafBlock::CodeBlock { .. } => BlockKind::CodeBlock,
+                LeafBlock::MathBlock { .. } => BlockKind::MathBlock,
+                LeafBlock::HorizontalRule => BlockKind::HorizontalRule,
+                LeafBlock::Attachment { .. } => BlockKind::Attachment,
+                LeafBlock::Html { .. } => BlockKind::Html,
+            },
+            Self::Container { container } => match container {
+                ContainerBlock::Quote { .. } => BlockKind::Quote,
+                ContainerBlock::List { .. } => BlockKind::List,
+                ContainerBlock::Table { .. } => BlockKind::Table,
+                ContainerBlock::Div { .. } => BlockKind::Div,
+            },
+            Self::DefinitionList { .. } => BlockKind::DefinitionList,
+            Self::FootnoteDefinition { .. } => BlockKind::FootnoteDefinition,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq, uniffi::Enum)]
 pub enum ListStyle {
     Unordered { bullet: u8 },
@@ -467,6 +730,64 @@ pub enum Inline {
     FootnoteReference {
         label: String,
     },
+
+    /// `^block-id` marker naming the block it ends, for `[[note#^block-id]]`
+    BlockId {
+        id: String,
+    },
+
+    /// `#tag` or `#nested/tag` in running text; `name` has no `#`
+    Tag {
+        name: String,
+    },
+
+    /// Raw inline HTML such as `<kbd>`, kept verbatim
+    Html {
+        content: String,
+    },
+}
+
+/// What an [`Inline`] is, without its content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, uniffi::Enum)]
+pub enum InlineKind {
+    Text,
+    Bold,
+    Italic,
+    Strikethrough,
+    Link,
+    Image,
+    Code,
+    Math,
+    LineBreak,
+    Superscript,
+    Subscript,
+    FootnoteReference,
+    BlockId,
+    Tag,
+    Html,
+}
+
+impl Inline {
+    #[must_use]
+    pub const fn kind(&self) -> InlineKind {
+        match self {
+            Self::Text { .. } => InlineKind::Text,
+            Self::Bold { .. } => InlineKind::Bold,
+            Self::Italic { .. } => InlineKind::Italic,
+            Self::Strikethrough { .. } => InlineKind::Strikethrough,
+            Self::Link { .. } => InlineKind::Link,
+            Self::Image { .. } => InlineKind::Image,
+            Self::Code { .. } => InlineKind::Code,
+            Self::Math { .. } => InlineKind::Math,
+            Self::LineBreak => InlineKind::LineBreak,
+            Self::Superscript { .. } => InlineKind::Superscript,
+            Self::Subscript { .. } => InlineKind::Subscript,
+            Self::FootnoteReference { .. } => InlineKind::FootnoteReference,
+            Self::BlockId { .. } => InlineKind::BlockId,
+            Self::Tag { .. } => InlineKind::Tag,
+            Self::Html { .. } => InlineKind::Html,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, uniffi::Enum)]
@@ -513,10 +834,75 @@ pub enum DecimalStyle {
     ZeroPadded,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, uniffi::Enum)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, uniffi::Enum)]
 pub enum LinkTarget {
     Note(String),
     Attachment(String),
+    /// A heading inside a note, by its [`crate::document::slugify`] slug
+    Heading {
+        note: String,
+        slug: String,
+    },
+    /// A block inside a note marked with `^block-id`
+    Block {
+        note: String,
+        block_id: String,
+    },
+}
+
+impl LinkTarget {
+    /// Parse the target of a link to a note
+    ///
+    /// `note#Heading` points at a heading and `note#^block-id` at a block; an
+    /// empty note part (`#Heading`) refers to `current_note`. URLs are kept
+    /// whole.
+    #[must_use]
+    pub fn note_link(target: &str, current_note: &str) -> Self {
+        let Some((note, anchor)) = target.split_once('#') else {
+            return Self::Note(target.to_string());
+        };
+        if target.contains("://") || anchor.is_empty() {
+            return Self::Note(target.to_string());
+        }
+
+        let note = if note.is_empty() { current_note } else { note }.to_string();
+        match anchor.strip_prefix('^') {
+            Some(block_id) => Self::Block {
+                note,
+                block_id: block_id.to_string(),
+            },
+            None => Self::Heading {
+                note,
+                slug: crate::document::slugify(anchor),
+            },
+        }
+    }
+
+    /// The note this target points into, if it is not an attachment
+    #[must_use]
+    pub fn note_id(&self) -> Option<&str> {
+        match self {
+            Self::Note(note) | Self::Heading { note, .. } | Self::Block { note, .. } => Some(note),
+            Self::Attachment(_) => None,
+        }
+    }
+
+    /// The same target pointing into another note
+    #[must_use]
+    pub fn with_note(&self, note_id: String) -> Self {
+        match self {
+            Self::Note(_) => Self::Note(note_id),
+            Self::Heading { slug, .. } => Self::Heading {
+                note: note_id,
+                slug: slug.clone(),
+            },
+            Self::Block { block_id, .. } => Self::Block {
+                note: note_id,
+                block_id: block_id.clone(),
+            },
+            Self::Attachment(_) => self.clone(),
+        }
+    }
 }
 
 /// Document format preference
@@ -544,6 +930,7 @@ impl Document {
         Self::Standard(Note {
             id,
             title,
+            properties: Properties::new(),
             blocks: Vec::new(),
         })
     }
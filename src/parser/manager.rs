@@ -223,7 +223,7 @@ mod tests {
     fn test_block_manager_initialization() {
         let manager = BlockManager::default();
         assert_eq!(manager.block_count(), 0);
-        assert!(manager.dirty_blocks().is_empty());
+        assert_eq!(manager.dirty_blocks(), Vec::<usize>::new());
     }
 
     #[test]
@@ -237,8 +237,8 @@ mod tests {
         );
         manager.insert_block(0, block);
 
-        assert!(!manager.dirty_blocks().is_empty());
+        assert_eq!(manager.dirty_blocks(), vec![0]);
         manager.clear_dirty();
-        assert!(manager.dirty_blocks().is_empty());
+        assert_eq!(manager.dirty_blocks(), Vec::<usize>::new());
     }
 }
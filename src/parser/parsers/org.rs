@@ -73,8 +73,7 @@ impl Parser for OrgParser {
                 let todo_prefix = metadata
                     .todo_state
                     .as_ref()
-                    .map(|s| format!("{s} "))
-                    .unwrap_or_default();
+                    .map_or_else(String::new, |s| format!("{s} "));
                 format!("{stars} {todo_prefix}{text}")
             }
             Block::Leaf {
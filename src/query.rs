@@ -0,0 +1,59 @@
+//! Structured query language for filtering notes
+//!
+//! A query is a boolean combination of predicates:
+//!
+//! ```text
+//! tag:project AND links-to:roadmap AND todo:TODO AND heading:"Q3"
+//! (tag:work OR tag:home) NOT todo:DONE
+//! ```
+//!
+//! - `tag:NAME`: the note carries the tag (scoped or global)
+//! - `links-to:ID`: the note links to the note `ID`
+//! - `linked-from:ID`: the note `ID` links to this note
+//! - `todo:STATE`: a heading starts with the TODO keyword `STATE`
+//! - `heading:TEXT`: a heading contains `TEXT` (case-insensitive)
+//! - `title:TEXT`: the title contains `TEXT` (case-insensitive)
+//! - a bare word or `"quoted phrase"`: the title or text contains it; words
+//!   such as `http://example.com` whose `name:` prefix is not a field above
+//!   are searched as text too
+//!
+//! Adjacent terms are combined with `AND`; `AND` binds tighter than `OR`, and
+//! `NOT` (or a `-` in front of a term) negates the term that follows it. A `-`
+//! in front of a number or inside a field's value, as in `tag:-x`, is part of
+//! the text.
+
+pub mod eval;
+pub mod parser;
+
+pub use eval::QueryContext;
+pub use parser::parse_query;
+
+/// A parsed query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    /// Every sub-query matches
+    And(Vec<Query>),
+    /// At least one sub-query matches
+    Or(Vec<Query>),
+    /// The sub-query does not match
+    Not(Box<Query>),
+    Tag(String),
+    LinksTo(String),
+    LinkedFrom(String),
+    Todo(String),
+    Heading(String),
+    Title(String),
+    Text(String),
+}
+
+/// Order in which query results are returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, uniffi::Enum)]
+pub enum QuerySort {
+    /// By note ID
+    #[default]
+    Id,
+    /// By note title, case-insensitive
+    Title,
+    /// Most linked-to notes first
+    Backlinks,
+}
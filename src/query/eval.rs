@@ -0,0 +1,142 @@
+use crate::{
+    document::{plain_text, text_runs},
+    models::{Block, ContainerBlock, Inline, LeafBlock, Note},
+    query::Query,
+};
+
+/// Index lookups a [`Query`] needs beyond the note itself
+pub trait QueryContext {
+    /// Whether the note carries the given tag
+    fn has_tag(&self, note_id: &str, tag: &str) -> bool;
+
+    /// Whether the note `source` links to the note `target`, each named as a
+    /// link would name it
+    fn links_to(&self, source: &str, target: &str) -> bool;
+}
+
+impl Query {
+    /// Evaluate the query against a single note
+    #[must_use]
+    pub fn matches(&self, note: &Note, context: &dyn QueryContext) -> bool {
+        match self {
+            Self::And(terms) => terms.iter().all(|q| q.matches(note, context)),
+            Self::Or(terms) => terms.iter().any(|q| q.matches(note, context)),
+            Self::Not(term) => !term.matches(note, context),
+            Self::Tag(tag) => context.has_tag(&note.id, tag),
+            Self::LinksTo(target) => context.links_to(&note.id, target),
+            Self::LinkedFrom(source) => context.links_to(source, &note.id),
+            Self::Todo(state) => headings(&note.blocks)
+                .iter()
+                .any(|content| todo_keyword(content) == Some(state.as_str())),
+            Self::Heading(text) => {
+                let needle = text.to_lowercase();
+                headings(&note.blocks)
+                    .iter()
+                    .any(|content| plain_text(content).to_lowercase().contains(&needle))
+            }
+            Self::Title(text) => note.title.to_lowercase().contains(&text.to_lowercase()),
+            Self::Text(text) => {
+                let needle = text.to_lowercase();
+                note.title.to_lowercase().contains(&needle)
+                    || text_runs(&note.blocks)
+                        .iter()
+                        .any(|run| run.text.to_lowercase().contains(&needle))
+            }
+        }
+    }
+}
+
+/// Heading content at any depth of the block tree
+fn headings(blocks: &[Block]) -> Vec<&[Inline]> {
+    fn collect<'a>(blocks: &'a [Block], out: &mut Vec<&'a [Inline]>) {
+        for block in blocks {
+            match block {
+                Block::Leaf {
+                    leaf: LeafBlock::Heading { content, .. },
+                } => out.push(content),
+                Block::Container {
+                    container:
+                        ContainerBlock::Quote { blocks }
+                        | ContainerBlock::Div {
+                            children: blocks, ..
+                        },
+                } => collect(blocks, out),
+                Block::Container {
+                    container: ContainerBlock::List { items, .. },
+                } => {
+                    for item in items {
+                        collect(&item.blocks, out);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    collect(blocks, &mut out);
+    out
+}
+
+/// The first word of a heading, which Org uses for its TODO keyword
+fn todo_keyword(content: &[Inline]) -> Option<&str> {
+    content.iter().find_map(|inline| match inline {
+        Inline::Text { text } => text.split_whitespace().next(),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{models::Properties, query::parse_query};
+
+    struct Links;
+
+    impl QueryContext for Links {
+        fn has_tag(&self, note_id: &str, tag: &str) -> bool {
+            note_id == "plan" && tag == "project"
+        }
+
+        fn links_to(&self, source: &str, target: &str) -> bool {
+            source == "plan" && target == "roadmap"
+        }
+    }
+
+    fn text(value: &str) -> Inline {
+        Inline::Text {
+            text: value.to_string(),
+        }
+    }
+
+    fn plan() -> Note {
+        Note {
+            id: "plan".into(),
+            title: "Quarterly plan".into(),
+            properties: Properties::default(),
+            blocks: vec![
+                Block::heading(1, vec![text("TODO Q3 goals")]),
+                Block::paragraph(vec![text("Ship the sync engine")]),
+            ],
+        }
+    }
+
+    fn check(query: &str) -> bool {
+        parse_query(query).unwrap().matches(&plan(), &Links)
+    }
+
+    #[test]
+    fn evaluates_field_predicates() {
+        assert!(check(
+            r#"tag:project AND links-to:roadmap AND todo:TODO AND heading:"Q3 goals""#
+        ));
+        assert!(check("linked-from:other OR title:quarterly"));
+        assert!(check("sync -todo:DONE"));
+
+        assert!(!check("tag:home"));
+        assert!(!check("links-to:elsewhere"));
+        assert!(!check("todo:DONE"));
+        assert!(!check("heading:Q4"));
+        assert!(!check("NOT engine"));
+    }
+}
@@ -0,0 +1,371 @@
+use crate::{
+    error::{ParseError, ParseResult},
+    query::Query,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TokenKind {
+    Word(String),
+    Quoted(String),
+    Field(String),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    line: u64,
+    column: u64,
+}
+
+/// Parse a query string into a [`Query`]
+///
+/// # Errors
+///
+/// Returns [`ParseError::SyntaxError`] with the offending line when the query
+/// is empty, has unbalanced parentheses or quotes, or is missing an operand
+/// or a field's value.
+pub fn parse_query(input: &str) -> ParseResult<Query> {
+    let tokens = lex(input)?;
+    let mut parser = QueryParser {
+        tokens,
+        position: 0,
+        last_line: input.lines().count().max(1) as u64,
+    };
+
+    let query = parser.parse_or()?;
+    match parser.peek() {
+        None => Ok(query),
+        Some(token) => Err(token_error(token, "unexpected ')'")),
+    }
+}
+
+/// Names accepted before `:`; other `name:` prefixes are part of a word
+const FIELDS: [&str; 6] = ["tag", "links-to", "linked-from", "todo", "heading", "title"];
+
+fn lex(input: &str) -> ParseResult<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut line = 1;
+    let mut column = 0;
+
+    while let Some(c) = chars.next() {
+        column += 1;
+        let (start_line, start_column) = (line, column);
+        // A field's value is taken as written, so `tag:-x` is not negated
+        let after_field = matches!(
+            tokens.last(),
+            Some(Token {
+                kind: TokenKind::Field(_),
+                ..
+            })
+        );
+        let kind = match c {
+            '\n' => {
+                line += 1;
+                column = 0;
+                continue;
+            }
+            c if c.is_whitespace() => continue,
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            // Only at the start of a term, and not in front of a number
+            '-' if !after_field
+                && chars
+                    .peek()
+                    .is_some_and(|next| !next.is_whitespace() && !next.is_ascii_digit()) =>
+            {
+                TokenKind::Not
+            }
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    let Some(c) = chars.next() else {
+                        return Err(ParseError::syntax_error(
+                            start_line,
+                            format!("unterminated quote starting at column {start_column}"),
+                        ));
+                    };
+                    column += 1;
+                    match c {
+                        '"' => break,
+                        '\\' if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            column += 1;
+                            text.push('"');
+                        }
+                        '\n' => {
+                            line += 1;
+                            column = 0;
+                            text.push(c);
+                        }
+                        _ => text.push(c),
+                    }
+                }
+                TokenKind::Quoted(text)
+            }
+            _ => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || matches!(next, '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                    column += 1;
+                    if next == ':' && FIELDS.contains(&&word[..word.len() - 1]) {
+                        break;
+                    }
+                }
+                match word.as_str() {
+                    "AND" => TokenKind::And,
+                    "OR" => TokenKind::Or,
+                    "NOT" => TokenKind::Not,
+                    _ => match word.strip_suffix(':') {
+                        Some(field) if FIELDS.contains(&field) => {
+                            TokenKind::Field(field.to_string())
+                        }
+                        _ => TokenKind::Word(word),
+                    },
+                }
+            }
+        };
+        tokens.push(Token {
+            kind,
+            line: start_line,
+            column: start_column,
+        });
+    }
+
+    Ok(tokens)
+}
+
+struct QueryParser {
+    tokens: Vec<Token>,
+    position: usize,
+    last_line: u64,
+}
+
+impl QueryParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eof_error(&self, message: &str) -> ParseError {
+        ParseError::syntax_error(self.last_line, format!("{message} at end of query"))
+    }
+
+    fn parse_or(&mut self) -> ParseResult<Query> {
+        let mut terms = vec![self.parse_and()?];
+        while self.peek().is_some_and(|t| t.kind == TokenKind::Or) {
+            self.next();
+            terms.push(self.parse_and()?);
+        }
+        Ok(flatten(terms, Query::Or))
+    }
+
+    fn parse_and(&mut self) -> ParseResult<Query> {
+        let mut terms = vec![self.parse_not()?];
+        loop {
+            match self.peek().map(|t| &t.kind) {
+                Some(TokenKind::And) => {
+                    self.next();
+                    terms.push(self.parse_not()?);
+                }
+                Some(TokenKind::Or | TokenKind::RParen) | None => break,
+                Some(_) => terms.push(self.parse_not()?),
+            }
+        }
+        Ok(flatten(terms, Query::And))
+    }
+
+    fn parse_not(&mut self) -> ParseResult<Query> {
+        if self.peek().is_some_and(|t| t.kind == TokenKind::Not) {
+            self.next();
+            return Ok(Query::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> ParseResult<Query> {
+        let Some(token) = self.next() else {
+            return Err(self.eof_error("expected a term"));
+        };
+
+        match token.kind {
+            TokenKind::LParen => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Some(Token {
+                        kind: TokenKind::RParen,
+                        ..
+                    }) => Ok(query),
+                    Some(other) => Err(token_error(&other, "expected ')'")),
+                    None => Err(self.eof_error("missing ')'")),
+                }
+            }
+            TokenKind::Word(text) | TokenKind::Quoted(text) => Ok(Query::Text(text)),
+            TokenKind::Field(ref field) => {
+                let value = match self.next() {
+                    Some(Token {
+                        kind: TokenKind::Word(value) | TokenKind::Quoted(value),
+                        ..
+                    }) => value,
+                    Some(other) => {
+                        return Err(token_error(
+                            &other,
+                            &format!("expected a value for '{field}:'"),
+                        ));
+                    }
+                    None => return Err(self.eof_error(&format!("expected a value for '{field}:'"))),
+                };
+                field_query(field, value)
+                    .ok_or_else(|| token_error(&token, &format!("unknown field '{field}'")))
+            }
+            TokenKind::RParen => Err(token_error(&token, "unexpected ')'")),
+            TokenKind::And | TokenKind::Or | TokenKind::Not => {
+                Err(token_error(&token, "expected a term before operator"))
+            }
+        }
+    }
+}
+
+fn field_query(field: &str, value: String) -> Option<Query> {
+    Some(match field {
+        "tag" => Query::Tag(value.trim_start_matches('#').to_string()),
+        "links-to" => Query::LinksTo(value),
+        "linked-from" => Query::LinkedFrom(value),
+        "todo" => Query::Todo(value),
+        "heading" => Query::Heading(value),
+        "title" => Query::Title(value),
+        _ => return None,
+    })
+}
+
+fn flatten(mut terms: Vec<Query>, combine: fn(Vec<Query>) -> Query) -> Query {
+    if terms.len() == 1 {
+        terms.pop().unwrap()
+    } else {
+        combine(terms)
+    }
+}
+
+fn token_error(token: &Token, message: &str) -> ParseError {
+    ParseError::syntax_error(token.line, format!("{message} at column {}", token.column))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fields_joined_by_and() {
+        let query =
+            parse_query(r#"tag:project AND links-to:roadmap todo:TODO heading:"Q3 plan""#).unwrap();
+
+        assert_eq!(
+            query,
+            Query::And(vec![
+                Query::Tag("project".into()),
+                Query::LinksTo("roadmap".into()),
+                Query::Todo("TODO".into()),
+                Query::Heading("Q3 plan".into()),
+            ])
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let query = parse_query("tag:a OR tag:b -todo:DONE").unwrap();
+
+        assert_eq!(
+            query,
+            Query::Or(vec![
+                Query::Tag("a".into()),
+                Query::And(vec![
+                    Query::Tag("b".into()),
+                    Query::Not(Box::new(Query::Todo("DONE".into()))),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn parentheses_group_terms() {
+        let query = parse_query("NOT (tag:a OR roadmap)").unwrap();
+
+        assert_eq!(
+            query,
+            Query::Not(Box::new(Query::Or(vec![
+                Query::Tag("a".into()),
+                Query::Text("roadmap".into()),
+            ])))
+        );
+    }
+
+    #[test]
+    fn reports_syntax_errors_with_location() {
+        let cases = [
+            ("", "expected a term"),
+            ("tag:a AND", "expected a term"),
+            ("(tag:a", "missing ')'"),
+            ("tag:a)", "unexpected ')'"),
+            ("heading:\"Q3", "unterminated quote"),
+            ("tag: OR", "expected a value for 'tag:'"),
+        ];
+
+        for (input, expected) in cases {
+            match parse_query(input) {
+                Err(ParseError::SyntaxError { line, message }) => {
+                    assert_eq!(line, 1, "{input:?}");
+                    assert!(message.contains(expected), "{input:?}: {message}");
+                }
+                other => panic!("expected syntax error for {input:?}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn reports_the_line_of_multi_line_errors() {
+        let err = parse_query("tag:a\nAND\ntag:)").unwrap_err();
+
+        assert!(matches!(err, ParseError::SyntaxError { line: 3, .. }));
+    }
+
+    #[test]
+    fn dashes_negate_only_at_the_start_of_a_term() {
+        let query = parse_query("tag:-x -5 -(tag:a)").unwrap();
+
+        assert_eq!(
+            query,
+            Query::And(vec![
+                Query::Tag("-x".into()),
+                Query::Text("-5".into()),
+                Query::Not(Box::new(Query::Tag("a".into()))),
+            ])
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_searched_as_text() {
+        let query = parse_query("http://example.com color:red").unwrap();
+
+        assert_eq!(
+            query,
+            Query::And(vec![
+                Query::Text("http://example.com".into()),
+                Query::Text("color:red".into()),
+            ])
+        );
+    }
+}
@@ -2,13 +2,15 @@ use std::fmt::Debug;
 
 use uniffi::trait_interface;
 
-use crate::models::Note;
+use crate::{
+    error::{RepositoryError, RepositoryResult},
+    models::Note,
+};
 
 pub mod file;
+pub mod local;
 pub mod memory;
 
-pub type RepoResult<T> = Result<T, Box<dyn core::error::Error>>;
-
 #[trait_interface]
 pub trait NotesRepository: Send + Sync + Debug {
     /// List all notes
@@ -16,28 +18,55 @@ pub trait NotesRepository: Send + Sync + Debug {
     /// # Errors
     ///
     /// Returns an error if reading from the repository fails
-    fn list_notes(&self) -> RepoResult<Vec<Note>>;
+    fn list_notes(&self) -> RepositoryResult<Vec<Note>>;
 
     /// Get a note by ID
     ///
     /// # Errors
     ///
     /// Returns an error if reading from the repository fails
-    fn get_note(&self, id: &str) -> RepoResult<Option<Note>>;
+    fn get_note(&self, id: &str) -> RepositoryResult<Option<Note>>;
 
     /// Save a note
     ///
     /// # Errors
     ///
     /// Returns an error if writing to the repository fails
-    fn save_note(&mut self, note: &Note) -> RepoResult<()>;
+    fn save_note(&self, note: &Note) -> RepositoryResult<()>;
 
     /// Delete a note
     ///
     /// # Errors
     ///
     /// Returns an error if deleting from the repository fails
-    fn delete_note(&mut self, id: &str) -> RepoResult<()>;
+    fn delete_note(&self, id: &str) -> RepositoryResult<()>;
+
+    /// Give a note a new ID, returning the note as stored under it
+    ///
+    /// The default implementation saves a copy under the new ID and deletes
+    /// the old one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RepositoryError::NotFound`] if there is no note `old_id`,
+    /// [`RepositoryError::AlreadyExists`] if `new_id` is taken, or an error if
+    /// writing to the repository fails
+    fn rename_note(&self, old_id: &str, new_id: &str) -> RepositoryResult<Note> {
+        let Some(mut note) = self.get_note(old_id)? else {
+            return Err(RepositoryError::not_found(old_id));
+        };
+        if old_id == new_id {
+            return Ok(note);
+        }
+        if self.get_note(new_id)?.is_some() {
+            return Err(RepositoryError::already_exists(new_id));
+        }
+
+        note.id = new_id.to_string();
+        self.save_note(&note)?;
+        self.delete_note(old_id)?;
+        Ok(note)
+    }
 }
 
 #[cfg(test)]
@@ -46,8 +75,9 @@ mod tests {
 
     use super::*;
     use crate::{
+        error::RepositoryError,
         formats::markdown::MarkdownFormat,
-        models::{Block, Inline, Note},
+        models::{Block, Inline, Note, Properties},
         repo::{
             file::{FileNotesRepository, FileProvider},
             memory::MemoryNotesRepository,
@@ -72,13 +102,16 @@ mod tests {
             self.files.get(id).cloned()
         }
 
-        fn write(&mut self, id: &str, data: &[u8]) -> bool {
+        fn write(&mut self, id: &str, data: &[u8]) -> RepositoryResult<()> {
             self.files.insert(id.to_string(), data.to_vec());
-            true
+            Ok(())
         }
 
-        fn delete(&mut self, id: &str) -> bool {
-            self.files.remove(id).is_some()
+        fn delete(&mut self, id: &str) -> RepositoryResult<()> {
+            self.files
+                .remove(id)
+                .map(|_| ())
+                .ok_or_else(|| RepositoryError::not_found(id))
         }
 
         fn list(&self) -> Vec<String> {
@@ -88,12 +121,13 @@ mod tests {
 
     #[test]
     fn memory_repo_basic_operations() {
-        let format = Arc::new(MarkdownFormat);
-        let mut repo = MemoryNotesRepository::new(format);
+        let format = Arc::new(MarkdownFormat::default());
+        let repo = MemoryNotesRepository::new(format);
 
         let note = Note {
             id: "1".to_string(),
             title: "Hello".to_string(),
+            properties: Properties::default(),
             blocks: vec![Block::paragraph(vec![Inline::Text {
                 text: "World".to_string(),
             }])],
@@ -114,12 +148,13 @@ mod tests {
     #[test]
     fn file_repo_basic_operations() {
         let provider = Box::new(MockFileProvider::new());
-        let format = Arc::new(MarkdownFormat);
-        let mut repo = FileNotesRepository::new(provider, format);
+        let format = Arc::new(MarkdownFormat::default());
+        let repo = FileNotesRepository::new(provider, format);
 
         let note = Note {
             id: "note1".to_string(),
             title: "File Note".to_string(),
+            properties: Properties::default(),
             blocks: vec![Block::paragraph(vec![Inline::Text {
                 text: "Content".to_string(),
             }])],
@@ -137,4 +172,23 @@ mod tests {
         repo.delete_note("note1").unwrap();
         assert!(repo.get_note("note1").unwrap().is_none());
     }
+
+    #[test]
+    fn deleting_a_missing_note_is_not_found() {
+        let format = Arc::new(MarkdownFormat::default());
+        let repos: [Box<dyn NotesRepository>; 2] = [
+            Box::new(MemoryNotesRepository::new(format.clone())),
+            Box::new(FileNotesRepository::new(
+                Box::new(MockFileProvider::new()),
+                format,
+            )),
+        ];
+
+        for repo in repos {
+            assert!(matches!(
+                repo.delete_note("missing"),
+                Err(RepositoryError::NotFound(id)) if id == "missing"
+            ));
+        }
+    }
 }
@@ -1,9 +1,18 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, RwLock},
+};
 
 use crate::{
-    formats::{NoteSerialization, markdown::extract_attachments},
+    error::{RepositoryError, RepositoryResult, SerializationError},
+    formats::{
+        NoteSerialization,
+        markdown::{MarkdownFormat, extract_attachments},
+        org::OrgFormat,
+    },
     models::{Attachment, Note},
-    repo::{NotesRepository, RepoResult},
+    repo::{NotesRepository, local::LocalFsProvider},
 };
 
 #[uniffi::trait_interface]
@@ -12,26 +21,102 @@ pub trait FileProvider: Send + Sync + Debug {
     fn read(&self, path: &str) -> Option<Vec<u8>>;
 
     /// Write raw bytes to a file relative to the vault
-    fn write(&mut self, path: &str, data: &[u8]) -> bool;
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RepositoryError::InvalidId`] for paths the provider refuses
+    /// and [`RepositoryError::IoError`] if the write itself fails
+    fn write(&mut self, path: &str, data: &[u8]) -> RepositoryResult<()>;
 
     /// Delete a file by path
-    fn delete(&mut self, path: &str) -> bool;
+    ///
+    /// # Errors
+    ///
+    /// Returns [`RepositoryError::NotFound`] if there is no such file and
+    /// [`RepositoryError::IoError`] if it cannot be removed
+    fn delete(&mut self, path: &str) -> RepositoryResult<()>;
 
     /// List all note IDs (or file names) in the provider
     fn list(&self) -> Vec<String>;
 }
 
 /// File-based repository
+///
+/// Each file is parsed with the format registered for its extension, falling
+/// back to the default format. A note's ID is its file's path without the
+/// registered extension (`notes/plan.md` is `notes/plan`), whatever the
+/// format. The file a note was loaded from is remembered, so saving writes it
+/// back to the same path and format.
 #[derive(Debug, uniffi::Object)]
 pub struct FileNotesRepository {
-    provider: Box<dyn FileProvider>,
-    formats: Arc<dyn NoteSerialization>,
+    provider: RwLock<Box<dyn FileProvider>>,
+    default_format: Arc<dyn NoteSerialization>,
+    /// Lowercase extension (without the dot) -> format
+    formats: Vec<(String, Arc<dyn NoteSerialization>)>,
+    /// Note ID -> path of the file it was loaded from or saved to
+    paths: RwLock<HashMap<String, String>>,
+    /// Files the last [`NotesRepository::list_notes`] could not read
+    failures: RwLock<Vec<FileFailure>>,
+}
+
+/// A file that was skipped because it could not be read as a note
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct FileFailure {
+    pub path: String,
+    pub reason: String,
 }
 
+#[uniffi::export]
 impl FileNotesRepository {
+    /// Notes kept as files in a local folder
+    ///
+    /// `.md` files are read as Markdown and `.org` files as Org. New notes
+    /// are written as Markdown unless their ID ends in `.org`; either way
+    /// they are listed back under their ID without the extension.
     #[uniffi::constructor]
+    #[must_use]
+    pub fn local(provider: Arc<LocalFsProvider>) -> Self {
+        Self::new(
+            Box::new(Arc::unwrap_or_clone(provider)),
+            Arc::new(MarkdownFormat::default()),
+        )
+        .with_format("md", Arc::new(MarkdownFormat::default()))
+        .with_format("org", Arc::new(OrgFormat))
+    }
+
+    /// Files skipped by the last [`NotesRepository::list_notes`]
+    ///
+    /// # Panics
+    ///
+    /// Panics if a lock is poisoned
+    #[must_use]
+    #[uniffi::method]
+    pub fn failed_files(&self) -> Vec<FileFailure> {
+        self.failures.read().unwrap().clone()
+    }
+}
+
+impl FileNotesRepository {
     pub fn new(provider: Box<dyn FileProvider>, formats: Arc<dyn NoteSerialization>) -> Self {
-        Self { provider, formats }
+        Self {
+            provider: RwLock::new(provider),
+            default_format: formats,
+            formats: Vec::new(),
+            paths: RwLock::new(HashMap::new()),
+            failures: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Use `format` for files ending in `.extension`
+    ///
+    /// The first registered extension is also given to new notes whose ID
+    /// has no registered extension.
+    #[must_use]
+    pub fn with_format(mut self, extension: &str, format: Arc<dyn NoteSerialization>) -> Self {
+        let extension = extension.trim_start_matches('.').to_lowercase();
+        self.formats.retain(|(ext, _)| *ext != extension);
+        self.formats.push((extension, format));
+        self
     }
 
     /// Extract all attachments from a note using the format's parser.
@@ -39,58 +124,280 @@ impl FileNotesRepository {
     /// # Errors
     ///
     /// Returns an error if reading the note from the repository fails
-    pub fn get_attachments(&self, note_id: &str) -> RepoResult<Vec<Attachment>> {
+    pub fn get_attachments(&self, note_id: &str) -> RepositoryResult<Vec<Attachment>> {
         if let Some(note) = self.get_note(note_id)? {
             Ok(extract_attachments(&note.blocks))
         } else {
             Ok(Vec::new())
         }
     }
+
+    /// Path of the file backing a note, if it exists
+    ///
+    /// # Panics
+    ///
+    /// Panics if a lock is poisoned
+    #[must_use]
+    pub fn path_for(&self, note_id: &str) -> Option<String> {
+        if let Some(path) = self.paths.read().unwrap().get(note_id) {
+            return Some(path.clone());
+        }
+
+        let provider = self.provider.read().unwrap();
+        std::iter::once(note_id.to_string())
+            .chain(
+                self.formats
+                    .iter()
+                    .map(|(ext, _)| format!("{note_id}.{ext}")),
+            )
+            .find(|path| provider.read(path).is_some())
+    }
+
+    /// Rewrite a note in the format registered for `extension`
+    ///
+    /// The note is written next to its current file with the new extension
+    /// and the old file is removed. Nothing is written unless the new file
+    /// reads back as the same title, properties and blocks. Returns the note
+    /// as read back from the new file, which keeps its ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note does not exist, no format is registered
+    /// for `extension`, the note would not read back the same from the new
+    /// format, or writing or deleting a file fails
+    ///
+    /// # Panics
+    ///
+    /// Panics if a lock is poisoned
+    pub fn convert_note(&self, note_id: &str, extension: &str) -> RepositoryResult<Note> {
+        let extension = extension.trim_start_matches('.').to_lowercase();
+        let Some(format) = self.registered_format(&extension) else {
+            return Err(SerializationError::unsupported_format(extension).into());
+        };
+        let Some(old_path) = self.path_for(note_id) else {
+            return Err(RepositoryError::not_found(note_id));
+        };
+        let Some(note) = self.get_note(note_id)? else {
+            return Err(RepositoryError::not_found(note_id));
+        };
+
+        let new_path = format!("{}.{extension}", self.note_id(&old_path));
+        let data = format.serialize(&note);
+        let converted = self.read_note(&new_path, &data)?;
+        if !same_content(&converted, &note) {
+            return Err(SerializationError::serialization_failed(format!(
+                "{note_id} would not read back the same from .{extension}"
+            ))
+            .into());
+        }
+
+        {
+            let mut provider = self.provider.write().unwrap();
+            provider.write(&new_path, &data)?;
+            if new_path != old_path {
+                provider.delete(&old_path)?;
+            }
+        }
+
+        let mut paths = self.paths.write().unwrap();
+        paths.remove(note_id);
+        paths.insert(converted.id.clone(), new_path);
+        Ok(converted)
+    }
+
+    /// Path of a note renamed to `new_id`
+    ///
+    /// `new_id` keeps the old file's extension unless it has a registered one
+    /// of its own, and stays in the old file's folder unless it names one.
+    fn rename_path(&self, old_path: &str, new_id: &str) -> String {
+        let has_extension = new_id
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| self.registered_format(ext).is_some());
+        let mut path = match old_path.rsplit_once('/') {
+            Some((folder, _)) if !new_id.contains('/') => format!("{folder}/{new_id}"),
+            _ => new_id.to_string(),
+        };
+        if !has_extension
+            && let Some((_, ext)) = old_path
+                .rsplit_once('.')
+                .filter(|(_, ext)| self.registered_format(ext).is_some())
+        {
+            path.push('.');
+            path.push_str(ext);
+        }
+        path
+    }
+
+    /// ID of the note kept at `path`: the path without a registered extension
+    fn note_id<'a>(&self, path: &'a str) -> &'a str {
+        path.rsplit_once('.')
+            .filter(|(_, ext)| self.registered_format(ext).is_some())
+            .map_or(path, |(stem, _)| stem)
+    }
+
+    /// Parse the file at `path` with its format and give it its path's ID
+    fn read_note(&self, path: &str, bytes: &[u8]) -> RepositoryResult<Note> {
+        let mut note = self.format_for(path).deserialize(bytes, Some(path))?;
+        note.id = self.note_id(path).to_string();
+        Ok(note)
+    }
+
+    fn registered_format(&self, extension: &str) -> Option<&Arc<dyn NoteSerialization>> {
+        let extension = extension.to_lowercase();
+        self.formats
+            .iter()
+            .find(|(ext, _)| *ext == extension)
+            .map(|(_, format)| format)
+    }
+
+    fn format_for(&self, path: &str) -> &Arc<dyn NoteSerialization> {
+        path.rsplit_once('.')
+            .and_then(|(_, ext)| self.registered_format(ext))
+            .unwrap_or(&self.default_format)
+    }
+
+    /// Path a note should be saved to: where it was loaded from, else its ID,
+    /// with the default extension added if the ID has no registered one
+    fn save_path(&self, note: &Note) -> String {
+        if let Some(path) = self.path_for(&note.id) {
+            return path;
+        }
+
+        let has_extension = note
+            .id
+            .rsplit_once('.')
+            .is_some_and(|(_, ext)| self.registered_format(ext).is_some());
+        match self.formats.first() {
+            Some((ext, _)) if !has_extension => format!("{}.{ext}", note.id),
+            _ => note.id.clone(),
+        }
+    }
 }
 
 impl NotesRepository for FileNotesRepository {
-    fn list_notes(&self) -> RepoResult<Vec<Note>> {
+    /// List every note that can be read
+    ///
+    /// A file that fails to parse does not hide the rest; it is skipped and
+    /// reported by [`FileNotesRepository::failed_files`]. So is a file whose
+    /// ID an earlier file already has, such as `plan.org` next to `plan.md`.
+    fn list_notes(&self) -> RepositoryResult<Vec<Note>> {
+        let provider = self.provider.read().unwrap();
+        let mut paths = self.paths.write().unwrap();
+        let mut listed = HashMap::new();
         let mut notes = Vec::new();
-        for id in self.provider.list() {
-            if let Some(bytes) = self.provider.read(&id) {
-                notes.push(self.formats.deserialize(&bytes, Some(&id)));
+        let mut failures = Vec::new();
+        for path in provider.list() {
+            let id = self.note_id(&path);
+            if let Some(other) = listed.get(id) {
+                let reason = RepositoryError::already_exists(format!("{id} (read from {other})"));
+                failures.push(FileFailure {
+                    reason: reason.to_string(),
+                    path,
+                });
+                continue;
+            }
+            let Some(bytes) = provider.read(&path) else {
+                continue;
+            };
+            match self.read_note(&path, &bytes) {
+                Ok(note) => {
+                    listed.insert(note.id.clone(), path.clone());
+                    paths.insert(note.id.clone(), path);
+                    notes.push(note);
+                }
+                Err(err) => failures.push(FileFailure {
+                    reason: err.to_string(),
+                    path,
+                }),
             }
         }
+        *self.failures.write().unwrap() = failures;
         Ok(notes)
     }
 
-    fn get_note(&self, id: &str) -> RepoResult<Option<Note>> {
-        self.provider.read(id).map_or_else(
-            || Ok(None),
-            |bytes| Ok(Some(self.formats.deserialize(&bytes, Some(id)))),
-        )
+    fn get_note(&self, id: &str) -> RepositoryResult<Option<Note>> {
+        let Some(path) = self.path_for(id) else {
+            return Ok(None);
+        };
+        let Some(bytes) = self.provider.read().unwrap().read(&path) else {
+            return Ok(None);
+        };
+        let note = self.read_note(&path, &bytes)?;
+        self.paths.write().unwrap().insert(note.id.clone(), path);
+        Ok(Some(note))
     }
 
-    fn save_note(&mut self, note: &Note) -> RepoResult<()> {
-        let data = self.formats.serialize(note);
-        if self.provider.write(&note.id, &data) {
-            Ok(())
-        } else {
-            Err(Box::from("Failed to write note"))
-        }
+    fn save_note(&self, note: &Note) -> RepositoryResult<()> {
+        let path = self.save_path(note);
+        let data = self.format_for(&path).serialize(note);
+        self.provider.write().unwrap().write(&path, &data)?;
+        self.paths.write().unwrap().insert(note.id.clone(), path);
+        Ok(())
     }
 
-    fn delete_note(&mut self, id: &str) -> RepoResult<()> {
-        if self.provider.delete(id) {
-            Ok(())
-        } else {
-            Err(Box::from("Failed to delete note"))
+    fn delete_note(&self, id: &str) -> RepositoryResult<()> {
+        let path = self.path_for(id).unwrap_or_else(|| id.to_string());
+        self.provider.write().unwrap().delete(&path)?;
+        self.paths.write().unwrap().remove(id);
+        Ok(())
+    }
+
+    /// Move the note's file to match `new_id`, see [`Self::rename_path`]
+    fn rename_note(&self, old_id: &str, new_id: &str) -> RepositoryResult<Note> {
+        let Some(old_path) = self.path_for(old_id) else {
+            return Err(RepositoryError::not_found(old_id));
+        };
+        let Some(mut note) = self.get_note(old_id)? else {
+            return Err(RepositoryError::not_found(old_id));
+        };
+        let new_path = self.rename_path(&old_path, new_id);
+        if new_path == old_path {
+            return Ok(note);
+        }
+        if self.path_for(new_id).is_some()
+            || self.provider.read().unwrap().read(&new_path).is_some()
+        {
+            return Err(RepositoryError::already_exists(new_id));
         }
+
+        note.id = new_id.to_string();
+        let format = self.format_for(&new_path);
+        let data = format.serialize(&note);
+        {
+            let mut provider = self.provider.write().unwrap();
+            provider.write(&new_path, &data)?;
+            provider.delete(&old_path)?;
+        }
+
+        let renamed = self.read_note(&new_path, &data)?;
+        let mut paths = self.paths.write().unwrap();
+        paths.remove(old_id);
+        paths.insert(renamed.id.clone(), new_path);
+        Ok(renamed)
     }
 }
 
+/// Whether two notes have the same title, properties and blocks
+///
+/// A `title` property is ignored, as formats keep the title either there or
+/// in the content.
+fn same_content(a: &Note, b: &Note) -> bool {
+    let properties = |note: &Note| {
+        let mut properties = note.properties.clone();
+        properties.remove("title");
+        properties
+    };
+    a.title == b.title && a.blocks == b.blocks && properties(a) == properties(b)
+}
+
 #[cfg(test)]
 mod tests {
-
-    use std::collections::HashMap;
-
     use super::*;
-    use crate::models::{Block, Inline, Note};
+    use crate::{
+        error::SerializationResult,
+        formats::{markdown::MarkdownFormat, org::OrgFormat},
+        models::{Block, Inline, Note, Properties, PropertyValue},
+    };
 
     #[derive(Debug)]
     struct MockProvider {
@@ -110,13 +417,16 @@ mod tests {
             self.files.get(path).cloned()
         }
 
-        fn write(&mut self, path: &str, data: &[u8]) -> bool {
+        fn write(&mut self, path: &str, data: &[u8]) -> RepositoryResult<()> {
             self.files.insert(path.to_string(), data.to_vec());
-            true
+            Ok(())
         }
 
-        fn delete(&mut self, path: &str) -> bool {
-            self.files.remove(path).is_some()
+        fn delete(&mut self, path: &str) -> RepositoryResult<()> {
+            self.files
+                .remove(path)
+                .map(|_| ())
+                .ok_or_else(|| RepositoryError::not_found(path))
         }
 
         fn list(&self) -> Vec<String> {
@@ -132,12 +442,13 @@ mod tests {
             serde_cbor::to_vec(note).unwrap()
         }
 
-        fn deserialize(&self, data: &[u8], id: Option<&str>) -> Note {
-            let mut note: Note = serde_cbor::from_slice(data).unwrap();
+        fn deserialize(&self, data: &[u8], id: Option<&str>) -> SerializationResult<Note> {
+            let mut note: Note = serde_cbor::from_slice(data)
+                .map_err(|e| SerializationError::deserialization_failed(e.to_string()))?;
             if let Some(id) = id {
                 note.id = id.to_string();
             }
-            note
+            Ok(note)
         }
     }
 
@@ -145,11 +456,12 @@ mod tests {
     fn test_save_and_get_note() {
         let provider = Box::new(MockProvider::new());
         let format = Arc::new(MockFormat);
-        let mut repo = FileNotesRepository::new(provider, format);
+        let repo = FileNotesRepository::new(provider, format);
 
         let note = Note {
             id: "note1".to_string(),
             title: "Test Note".to_string(),
+            properties: Properties::default(),
             blocks: vec![Block::paragraph(vec![Inline::Text {
                 text: "Hello World".into(),
             }])],
@@ -164,19 +476,22 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::similar_names)]
     fn test_list_notes() {
         let provider = Box::new(MockProvider::new());
         let format = Arc::new(MockFormat);
-        let mut repo = FileNotesRepository::new(provider, format);
+        let repo = FileNotesRepository::new(provider, format);
 
         let note1 = Note {
             id: "n1".to_string(),
             title: "A".to_string(),
+            properties: Properties::default(),
             blocks: vec![],
         };
         let note2 = Note {
             id: "n2".to_string(),
             title: "B".to_string(),
+            properties: Properties::default(),
             blocks: vec![],
         };
 
@@ -193,11 +508,12 @@ mod tests {
     fn test_delete_note() {
         let provider = Box::new(MockProvider::new());
         let formats = Arc::new(MockFormat);
-        let mut repo = FileNotesRepository::new(provider, formats);
+        let repo = FileNotesRepository::new(provider, formats);
 
         let note = Note {
             id: "n1".to_string(),
             title: "Test".to_string(),
+            properties: Properties::default(),
             blocks: vec![],
         };
         repo.save_note(&note).unwrap();
@@ -212,11 +528,12 @@ mod tests {
     fn test_get_attachments() {
         let provider = Box::new(MockProvider::new());
         let format = Arc::new(MockFormat);
-        let mut repo = FileNotesRepository::new(provider, format);
+        let repo = FileNotesRepository::new(provider, format);
 
         let note = Note {
             id: "n1".to_string(),
             title: "Attachments".to_string(),
+            properties: Properties::default(),
             blocks: vec![
                 Block::paragraph(vec![Inline::Image {
                     alt_text: Some("img1".into()),
@@ -234,4 +551,220 @@ mod tests {
         assert!(srcs.contains(&"file1.png".to_string()));
         assert!(srcs.contains(&"file2.png".to_string()));
     }
+
+    fn mixed_repo() -> FileNotesRepository {
+        let mut provider = MockProvider::new();
+        provider
+            .write("notes/alpha.md", b"# Alpha\n\nFrom markdown\n")
+            .unwrap();
+        provider
+            .write("notes/beta.org", b"* Beta\nFrom org\n")
+            .unwrap();
+
+        let markdown = Arc::new(MarkdownFormat::default());
+        FileNotesRepository::new(Box::new(provider), markdown.clone())
+            .with_format("md", markdown)
+            .with_format("org", Arc::new(OrgFormat))
+    }
+
+    fn file(repo: &FileNotesRepository, path: &str) -> Option<String> {
+        let bytes = repo.provider.read().unwrap().read(path)?;
+        Some(String::from_utf8(bytes).unwrap())
+    }
+
+    #[test]
+    fn test_formats_are_chosen_by_extension() {
+        let repo = mixed_repo();
+
+        let mut notes = repo.list_notes().unwrap();
+        notes.sort_by(|a, b| a.id.cmp(&b.id));
+        let titles: Vec<_> = notes.iter().map(|n| n.title.as_str()).collect();
+        assert_eq!(titles, vec!["Alpha", "Beta"]);
+
+        for note in &notes {
+            repo.save_note(note).unwrap();
+        }
+        assert!(
+            file(&repo, "notes/alpha.md")
+                .unwrap()
+                .starts_with("# Alpha")
+        );
+        assert!(file(&repo, "notes/beta.org").unwrap().starts_with("* Beta"));
+        assert_eq!(repo.provider.read().unwrap().list().len(), 2);
+    }
+
+    #[test]
+    fn test_new_notes_get_the_first_registered_extension() {
+        let repo = mixed_repo();
+
+        let note = Note {
+            id: "gamma".to_string(),
+            title: "Gamma".to_string(),
+            properties: Properties::default(),
+            blocks: vec![],
+        };
+        repo.save_note(&note).unwrap();
+
+        assert_eq!(repo.path_for("gamma").as_deref(), Some("gamma.md"));
+        assert!(repo.get_note("gamma").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_convert_note_changes_format_and_path() {
+        let repo = mixed_repo();
+        repo.list_notes().unwrap();
+
+        let converted = repo.convert_note("notes/beta", "md").unwrap();
+
+        assert_eq!(converted.id, "notes/beta");
+        assert_eq!(
+            repo.path_for("notes/beta").as_deref(),
+            Some("notes/beta.md")
+        );
+        assert_eq!(file(&repo, "notes/beta.org"), None);
+        assert!(file(&repo, "notes/beta.md").unwrap().contains("Beta"));
+
+        assert!(repo.convert_note("notes/beta", "txt").is_err());
+    }
+
+    #[test]
+    fn test_convert_note_keeps_the_body() {
+        let repo = mixed_repo();
+        repo.provider
+            .write()
+            .unwrap()
+            .write(
+                "plan.md",
+                b"---\nstatus: draft\n---\n# Plan\n\nShip the *first* draft.\n\n- Write\n- Review\n",
+            )
+            .unwrap();
+        let original = repo.get_note("plan").unwrap().unwrap();
+        assert_eq!(original.blocks.len(), 2);
+
+        let converted = repo.convert_note("plan", "org").unwrap();
+
+        assert_eq!(converted.id, "plan");
+        assert_eq!(converted.title, "Plan");
+        assert_eq!(converted.blocks, original.blocks);
+        assert_eq!(
+            converted.properties.get("status"),
+            Some(&PropertyValue::text("draft"))
+        );
+        assert_eq!(repo.get_note("plan").unwrap(), Some(converted));
+        assert_eq!(file(&repo, "plan.md"), None);
+        assert!(file(&repo, "plan.org").is_some());
+    }
+
+    #[test]
+    fn test_convert_note_refuses_notes_that_would_change() {
+        let repo = mixed_repo();
+        repo.provider
+            .write()
+            .unwrap()
+            .write("plan.md", b"# Plan\n\nTerm\n: Definition\n")
+            .unwrap();
+
+        assert!(matches!(
+            repo.convert_note("plan", "org"),
+            Err(RepositoryError::Serialization(
+                SerializationError::SerializationFailed(_)
+            ))
+        ));
+        assert_eq!(
+            file(&repo, "plan.md").as_deref(),
+            Some("# Plan\n\nTerm\n: Definition\n")
+        );
+        assert_eq!(file(&repo, "plan.org"), None);
+    }
+
+    #[test]
+    fn test_rename_note_moves_the_file() {
+        let repo = mixed_repo();
+        repo.list_notes().unwrap();
+
+        let renamed = repo.rename_note("notes/alpha", "gamma").unwrap();
+        assert_eq!(renamed.id, "notes/gamma");
+        assert_eq!(renamed.title, "Alpha");
+        assert_eq!(
+            repo.path_for("notes/gamma").as_deref(),
+            Some("notes/gamma.md")
+        );
+        assert_eq!(file(&repo, "notes/alpha.md"), None);
+
+        let moved = repo.rename_note("notes/beta", "archive/beta").unwrap();
+        assert_eq!(moved.id, "archive/beta");
+        assert!(file(&repo, "archive/beta.org").unwrap().contains("Beta"));
+
+        assert!(matches!(
+            repo.rename_note("notes/gamma", "archive/beta"),
+            Err(RepositoryError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            repo.rename_note("notes/alpha", "delta"),
+            Err(RepositoryError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_corrupt_files_are_skipped_when_listing() {
+        let repo = mixed_repo();
+        repo.provider
+            .write()
+            .unwrap()
+            .write("notes/corrupt.org", b"* Heading\n\xc3\x28")
+            .unwrap();
+
+        assert!(matches!(
+            repo.get_note("notes/corrupt.org"),
+            Err(RepositoryError::Serialization(
+                SerializationError::InvalidUtf8 { offset: 10 }
+            ))
+        ));
+
+        let notes = repo.list_notes().unwrap();
+        let mut ids: Vec<&str> = notes.iter().map(|note| note.id.as_str()).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, vec!["notes/alpha", "notes/beta"]);
+        assert_eq!(
+            repo.failed_files(),
+            vec![FileFailure {
+                path: "notes/corrupt.org".to_string(),
+                reason: "Invalid UTF-8 encoding at byte 10".to_string(),
+            }]
+        );
+
+        repo.provider
+            .write()
+            .unwrap()
+            .delete("notes/corrupt.org")
+            .unwrap();
+        assert_eq!(repo.list_notes().unwrap().len(), 2);
+        assert_eq!(repo.failed_files(), Vec::new());
+    }
+
+    #[test]
+    fn test_ids_are_paths_without_the_extension() {
+        let repo = mixed_repo();
+        {
+            let mut provider = repo.provider.write().unwrap();
+            provider.write("work/alpha.md", b"# Work alpha\n").unwrap();
+            provider.write("notes/alpha.org", b"* Org alpha\n").unwrap();
+        }
+
+        let notes = repo.list_notes().unwrap();
+        let ids: Vec<&str> = notes.iter().map(|note| note.id.as_str()).collect();
+        assert_eq!(ids.len(), 3);
+        assert!(ids.contains(&"notes/beta") && ids.contains(&"work/alpha"));
+        assert_eq!(
+            repo.get_note("work/alpha").unwrap().unwrap().title,
+            "Work alpha"
+        );
+
+        let failures = repo.failed_files();
+        assert_eq!(failures.len(), 1);
+        let listed = repo.path_for("notes/alpha").unwrap();
+        assert!(listed.starts_with("notes/alpha."));
+        assert_ne!(failures[0].path, listed);
+        assert!(failures[0].reason.contains("already exists: notes/alpha"));
+    }
 }
@@ -0,0 +1,428 @@
+use std::{
+    collections::HashSet,
+    fs,
+    io::Write,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    error::{RepositoryError, RepositoryResult},
+    repo::file::FileProvider,
+};
+
+/// Extensions listed by default: Markdown and Org notes
+const NOTE_EXTENSIONS: [&str; 2] = ["md", "org"];
+
+/// [`FileProvider`] backed by a directory on the local filesystem
+///
+/// IDs are `/`-separated paths relative to the root. Paths that would leave
+/// the root, whether through `..`, an absolute path or a symlink, are refused.
+#[derive(Debug, Clone, uniffi::Object)]
+pub struct LocalFsProvider {
+    root: PathBuf,
+    extensions: Vec<String>,
+}
+
+#[uniffi::export]
+impl LocalFsProvider {
+    /// Serve Markdown and Org notes from the given directory
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `root` does not exist or is not a directory
+    #[uniffi::constructor]
+    pub fn new(root: &str) -> RepositoryResult<Self> {
+        let extensions = NOTE_EXTENSIONS.iter().map(ToString::to_string).collect();
+        Self::with_extensions(root, extensions)
+    }
+
+    /// Serve files with the given extensions (without the leading dot), or
+    /// every file if `extensions` is empty
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `root` does not exist or is not a directory
+    #[uniffi::constructor]
+    pub fn with_extensions(root: &str, extensions: Vec<String>) -> RepositoryResult<Self> {
+        let root = fs::canonicalize(root)
+            .map_err(|e| RepositoryError::io_error(format!("{root}: {e}")))?;
+        if !root.is_dir() {
+            return Err(RepositoryError::io_error(format!(
+                "{} is not a directory",
+                root.display()
+            )));
+        }
+
+        Ok(Self {
+            root,
+            extensions: extensions
+                .into_iter()
+                .map(|ext| ext.trim_start_matches('.').to_lowercase())
+                .collect(),
+        })
+    }
+
+    /// Read a file, or `None` if it does not exist or lies outside the root
+    #[must_use]
+    #[uniffi::method]
+    pub fn read_file(&self, id: &str) -> Option<Vec<u8>> {
+        let path = self.existing_path(id).ok()?;
+        fs::read(path).ok()
+    }
+
+    /// Atomically replace a file's contents, creating parent folders as needed
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ID escapes the root or the write fails
+    #[uniffi::method]
+    pub fn write_file(&self, id: &str, data: &[u8]) -> RepositoryResult<()> {
+        let io_error = |e: std::io::Error| RepositoryError::io_error(format!("{id}: {e}"));
+
+        let path = self.join(id)?;
+        let parent = path
+            .parent()
+            .ok_or_else(|| RepositoryError::invalid_id(id))?;
+        let name = path
+            .file_name()
+            .ok_or_else(|| RepositoryError::invalid_id(id))?
+            .to_string_lossy()
+            .into_owned();
+
+        // Check the deepest existing ancestor before creating anything, so a
+        // symlinked folder cannot redirect `create_dir_all` outside the root
+        let existing = parent
+            .ancestors()
+            .find(|ancestor| ancestor.exists())
+            .ok_or_else(|| RepositoryError::invalid_id(id))?;
+        if !fs::canonicalize(existing)
+            .map_err(io_error)?
+            .starts_with(&self.root)
+        {
+            return Err(RepositoryError::invalid_id(id));
+        }
+        fs::create_dir_all(parent).map_err(io_error)?;
+        let parent = fs::canonicalize(parent).map_err(io_error)?;
+        if !parent.starts_with(&self.root) {
+            return Err(RepositoryError::invalid_id(id));
+        }
+
+        let temp = parent.join(format!(".{name}.{}.tmp", uuid::Uuid::new_v4()));
+        let result = fs::File::create(&temp)
+            .and_then(|mut file| {
+                file.write_all(data)?;
+                file.sync_all()
+            })
+            .and_then(|()| fs::rename(&temp, parent.join(&name)));
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        result.map_err(io_error)
+    }
+
+    /// Delete a file
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the ID escapes the root or the file cannot be removed
+    #[uniffi::method]
+    pub fn delete_file(&self, id: &str) -> RepositoryResult<()> {
+        let path = self.existing_path(id)?;
+        fs::remove_file(&path).map_err(|e| RepositoryError::io_error(format!("{id}: {e}")))
+    }
+
+    /// IDs of every file under the root with an accepted extension, sorted
+    ///
+    /// Hidden files and folders (starting with `.`) are skipped.
+    #[must_use]
+    #[uniffi::method]
+    pub fn list_files(&self) -> Vec<String> {
+        let mut ids = Vec::new();
+        let mut visited = HashSet::new();
+        self.list_dir(&self.root, &mut ids, &mut visited);
+        ids.sort();
+        ids
+    }
+}
+
+impl LocalFsProvider {
+    /// The same folder, listing every file whatever its extension
+    #[must_use]
+    pub fn all_files(self) -> Self {
+        Self {
+            extensions: Vec::new(),
+            ..self
+        }
+    }
+
+    /// Lexically validate an ID and join it onto the root
+    fn join(&self, id: &str) -> RepositoryResult<PathBuf> {
+        let relative = Path::new(id);
+        let mut path = self.root.clone();
+        let mut has_name = false;
+
+        for component in relative.components() {
+            match component {
+                Component::Normal(part) => {
+                    path.push(part);
+                    has_name = true;
+                }
+                Component::CurDir => {}
+                Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                    return Err(RepositoryError::invalid_id(id));
+                }
+            }
+        }
+
+        if has_name {
+            Ok(path)
+        } else {
+            Err(RepositoryError::invalid_id(id))
+        }
+    }
+
+    /// Resolve an ID to an existing path whose real location is inside the root
+    fn existing_path(&self, id: &str) -> RepositoryResult<PathBuf> {
+        let path = self.join(id)?;
+        let resolved = fs::canonicalize(&path).map_err(|_| RepositoryError::not_found(id))?;
+        if resolved.starts_with(&self.root) && resolved.is_file() {
+            Ok(resolved)
+        } else {
+            Err(RepositoryError::invalid_id(id))
+        }
+    }
+
+    fn list_dir(&self, dir: &Path, ids: &mut Vec<String>, visited: &mut HashSet<PathBuf>) {
+        let Ok(canonical) = fs::canonicalize(dir) else {
+            return;
+        };
+        if !canonical.starts_with(&self.root) || !visited.insert(canonical.clone()) {
+            return;
+        }
+
+        let Ok(entries) = fs::read_dir(&canonical) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            let path = entry.path();
+            // `metadata` follows symlinks; where they lead is checked below
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            if metadata.is_dir() {
+                self.list_dir(&path, ids, visited);
+            } else if metadata.is_file()
+                && self.accepts(&path)
+                && fs::canonicalize(&path).is_ok_and(|real| real.starts_with(&self.root))
+                && let Some(id) = self.relative_id(&path)
+            {
+                ids.push(id);
+            }
+        }
+    }
+
+    fn accepts(&self, path: &Path) -> bool {
+        self.extensions.is_empty()
+            || path
+                .extension()
+                .map(|ext| ext.to_string_lossy().to_lowercase())
+                .is_some_and(|ext| self.extensions.contains(&ext))
+    }
+
+    fn relative_id(&self, path: &Path) -> Option<String> {
+        let relative = path.strip_prefix(&self.root).ok()?;
+        let parts: Vec<String> = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        Some(parts.join("/"))
+    }
+}
+
+impl FileProvider for LocalFsProvider {
+    fn read(&self, path: &str) -> Option<Vec<u8>> {
+        self.read_file(path)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8]) -> RepositoryResult<()> {
+        self.write_file(path, data)
+    }
+
+    fn delete(&mut self, path: &str) -> RepositoryResult<()> {
+        self.delete_file(path)
+    }
+
+    fn list(&self) -> Vec<String> {
+        self.list_files()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        managers::tags::{FileTagStore, GlobalTagManager},
+        repo::{NotesRepository, file::FileNotesRepository},
+        vault::Vault,
+    };
+
+    /// Temporary directory removed when dropped
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("libnote-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&path).unwrap();
+            Self(fs::canonicalize(path).unwrap())
+        }
+
+        fn provider(&self) -> LocalFsProvider {
+            LocalFsProvider::new(&self.0.to_string_lossy()).unwrap()
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn write_read_list_and_delete() {
+        let dir = TempDir::new();
+        let mut provider = dir.provider();
+
+        provider.write("daily/2024-01-01.md", b"# Day").unwrap();
+        provider.write("projects/plan.org", b"* Plan").unwrap();
+        provider.write("projects/diagram.png", b"png").unwrap();
+
+        assert_eq!(
+            provider.read("daily/2024-01-01.md"),
+            Some(b"# Day".to_vec())
+        );
+        assert_eq!(
+            provider.list(),
+            vec!["daily/2024-01-01.md", "projects/plan.org"]
+        );
+
+        provider.write("projects/plan.org", b"* Revised").unwrap();
+        assert_eq!(
+            provider.read("projects/plan.org"),
+            Some(b"* Revised".to_vec())
+        );
+
+        provider.delete("daily/2024-01-01.md").unwrap();
+        assert_eq!(provider.read("daily/2024-01-01.md"), None);
+        assert!(matches!(
+            provider.delete("daily/2024-01-01.md"),
+            Err(RepositoryError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn writes_leave_no_temp_files() {
+        let dir = TempDir::new();
+        let provider = dir.provider();
+
+        provider.write_file("note.md", b"one").unwrap();
+        provider.write_file("note.md", b"two").unwrap();
+
+        let names: Vec<String> = fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, vec!["note.md"]);
+    }
+
+    #[test]
+    fn refuses_paths_outside_the_root() {
+        let dir = TempDir::new();
+        let provider = dir.provider();
+
+        for id in ["../escape.md", "a/../../escape.md", "/etc/passwd", "", "."] {
+            assert!(
+                matches!(
+                    provider.write_file(id, b"x"),
+                    Err(RepositoryError::InvalidId(_))
+                ),
+                "{id:?}"
+            );
+            assert_eq!(provider.read_file(id), None, "{id:?}");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_symlinks_pointing_outside_the_root() {
+        let dir = TempDir::new();
+        let outside = TempDir::new();
+        fs::write(outside.0.join("secret.md"), b"secret").unwrap();
+        std::os::unix::fs::symlink(outside.0.join("secret.md"), dir.0.join("link.md")).unwrap();
+        std::os::unix::fs::symlink(&outside.0, dir.0.join("linked")).unwrap();
+
+        let provider = dir.provider();
+
+        assert_eq!(provider.read_file("link.md"), None);
+        assert_eq!(provider.read_file("linked/secret.md"), None);
+        assert!(provider.write_file("linked/new.md", b"x").is_err());
+        assert!(!outside.0.join("new.md").exists());
+        assert_eq!(provider.list_files(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn local_repositories_read_markdown_and_org_notes() {
+        let dir = TempDir::new();
+        fs::write(dir.0.join("plan.md"), "# Plan\nSee [[Ideas]]").unwrap();
+        fs::write(dir.0.join("ideas.org"), "* Ideas\nSomeday").unwrap();
+
+        let repo = Arc::new(FileNotesRepository::local(Arc::new(dir.provider())));
+        let vault = Vault::from_repository(Arc::clone(&repo)).unwrap();
+
+        let mut ids: Vec<String> = repo
+            .list_notes()
+            .unwrap()
+            .into_iter()
+            .map(|n| n.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["ideas", "plan"]);
+        assert_eq!(vault.backlinks_for_note("ideas"), vec!["plan"]);
+    }
+
+    #[test]
+    fn attachment_folders_list_every_file() {
+        let notes = TempDir::new();
+        let attachments = TempDir::new();
+        fs::write(notes.0.join("trip.md"), "# Trip\n![Map](map.png)").unwrap();
+        fs::write(attachments.0.join("map.png"), b"png").unwrap();
+        fs::write(attachments.0.join("ticket.pdf"), b"pdf").unwrap();
+        fs::write(attachments.0.join("packing.md"), "# Packing").unwrap();
+
+        let repo = Arc::new(FileNotesRepository::local(Arc::new(notes.provider())));
+        let vault = Vault::from_repository(repo).unwrap();
+        vault.set_attachment_folder(Arc::new(attachments.provider()));
+
+        assert_eq!(vault.unused_attachments(), vec!["packing.md", "ticket.pdf"]);
+        assert_eq!(vault.missing_attachments(), Vec::new());
+        assert_eq!(attachments.provider().list_files(), vec!["packing.md"]);
+    }
+
+    #[test]
+    fn global_tags_are_kept_in_a_local_folder() {
+        let dir = TempDir::new();
+        let store = Arc::new(FileTagStore::local(Arc::new(dir.provider()), "tags.yaml"));
+        let tags = GlobalTagManager::with_store(Arc::clone(&store).as_tag_store()).unwrap();
+        tags.assign_tag("trip", "travel".into()).unwrap();
+
+        let reloaded = GlobalTagManager::with_store(store.as_tag_store()).unwrap();
+        assert_eq!(reloaded.get_tags_for("trip"), vec!["travel"]);
+        assert!(dir.0.join("tags.yaml").is_file());
+    }
+}
@@ -4,9 +4,10 @@ use std::{
 };
 
 use crate::{
+    error::{RepositoryError, RepositoryResult},
     formats::NoteSerialization,
     models::Note,
-    repo::{NotesRepository, RepoResult},
+    repo::NotesRepository,
 };
 
 /// In-memory repository for notes
@@ -29,8 +30,12 @@ impl MemoryNotesRepository {
     /// # Errors
     ///
     /// Returns an error if deserialization fails
-    pub fn insert_raw(&self, raw_data: &[u8], id_hint: Option<&str>) -> RepoResult<String> {
-        let note = self.format.deserialize(raw_data, id_hint);
+    ///
+    /// # Panics
+    ///
+    /// Panics if the note lock is poisoned
+    pub fn insert_raw(&self, raw_data: &[u8], id_hint: Option<&str>) -> RepositoryResult<String> {
+        let note = self.format.deserialize(raw_data, id_hint)?;
         let id = note.id.clone();
         self.notes.write().unwrap().insert(id.clone(), note);
         Ok(id)
@@ -41,7 +46,10 @@ impl MemoryNotesRepository {
     /// # Errors
     ///
     /// Returns an error if reading the note fails
-    pub fn get_attachments(&self, note_id: &str) -> RepoResult<Vec<crate::models::Attachment>> {
+    pub fn get_attachments(
+        &self,
+        note_id: &str,
+    ) -> RepositoryResult<Vec<crate::models::Attachment>> {
         if let Some(note) = self.get_note(note_id)? {
             Ok(crate::formats::markdown::extract_attachments(&note.blocks))
         } else {
@@ -51,15 +59,15 @@ impl MemoryNotesRepository {
 }
 
 impl NotesRepository for MemoryNotesRepository {
-    fn list_notes(&self) -> RepoResult<Vec<Note>> {
+    fn list_notes(&self) -> RepositoryResult<Vec<Note>> {
         Ok(self.notes.read().unwrap().values().cloned().collect())
     }
 
-    fn get_note(&self, id: &str) -> RepoResult<Option<Note>> {
+    fn get_note(&self, id: &str) -> RepositoryResult<Option<Note>> {
         Ok(self.notes.read().unwrap().get(id).cloned())
     }
 
-    fn save_note(&mut self, note: &Note) -> RepoResult<()> {
+    fn save_note(&self, note: &Note) -> RepositoryResult<()> {
         self.notes
             .write()
             .unwrap()
@@ -67,9 +75,11 @@ impl NotesRepository for MemoryNotesRepository {
         Ok(())
     }
 
-    fn delete_note(&mut self, id: &str) -> RepoResult<()> {
-        self.notes.write().unwrap().remove(id);
-        Ok(())
+    fn delete_note(&self, id: &str) -> RepositoryResult<()> {
+        match self.notes.write().unwrap().remove(id) {
+            Some(_) => Ok(()),
+            None => Err(RepositoryError::not_found(id)),
+        }
     }
 }
 
@@ -80,13 +90,13 @@ mod tests {
     use super::*;
     use crate::{
         formats::markdown::MarkdownFormat,
-        models::{Attachment, Block, Inline, Note},
+        models::{Attachment, Block, Inline, Note, Properties},
     };
 
     #[test]
     fn memory_repo_insert_raw_and_basic_ops() {
-        let format = Arc::new(MarkdownFormat);
-        let mut repo = MemoryNotesRepository::new(format);
+        let format = Arc::new(MarkdownFormat::default());
+        let repo = MemoryNotesRepository::new(format);
 
         let md_data = b"# My Title\nThis is a paragraph.\n![[image.png]]";
         let note_id = repo.insert_raw(md_data, None).unwrap();
@@ -98,6 +108,7 @@ mod tests {
         let note2 = Note {
             id: "manual".to_string(),
             title: "Manual Note".to_string(),
+            properties: Properties::default(),
             blocks: vec![Block::paragraph(vec![Inline::Text {
                 text: "Hello".to_string(),
             }])],
@@ -113,7 +124,7 @@ mod tests {
 
     #[test]
     fn memory_repo_extract_attachments() {
-        let format = Arc::new(MarkdownFormat);
+        let format = Arc::new(MarkdownFormat::default());
         let repo = MemoryNotesRepository::new(format);
 
         let md_data = b"# Title\nParagraph with ![](file1.png) and ![](file2.jpg)";
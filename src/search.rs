@@ -0,0 +1,6 @@
+//! Full-text search over the text content of notes
+
+pub mod index;
+pub mod tokenizer;
+
+pub use index::{SearchHit, SearchIndex, SearchMatch};
@@ -0,0 +1,280 @@
+use std::collections::{HashMap, HashSet, hash_map::Entry};
+
+use crate::{
+    document::{TextRun, TextSpan, text_runs},
+    models::Note,
+    search::tokenizer::tokenize,
+};
+
+/// BM25 term frequency saturation
+const K1: f64 = 1.2;
+/// BM25 document length normalization
+const B: f64 = 0.75;
+/// How many occurrences in the body one occurrence in the title counts as
+const TITLE_BOOST: f64 = 3.0;
+
+/// Where a query term matched inside a note
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct SearchMatch {
+    /// Block path of the matching text, see [`crate::document::TextRun`];
+    /// empty for a match in the note's title
+    pub path: Vec<u64>,
+    /// Character span of the term within that block's plain text, or within
+    /// the title
+    pub span: TextSpan,
+}
+
+/// A note matching a search query
+#[derive(Debug, Clone, PartialEq, uniffi::Record)]
+pub struct SearchHit {
+    pub note_id: String,
+    /// BM25 relevance; higher is better
+    pub score: f64,
+    /// Every occurrence of a query term, title first and then in document
+    /// order
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Inverted index over the titles and text content of notes, ranked with
+/// BM25 with title matches boosted
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    /// term -> `note_id` -> occurrences of the term in that note
+    postings: HashMap<String, HashMap<String, Vec<SearchMatch>>>,
+    /// `note_id` -> distinct terms, so a note can be removed without a scan
+    note_terms: HashMap<String, HashSet<String>>,
+    /// `note_id` -> number of tokens in the note, title included
+    note_lengths: HashMap<String, usize>,
+    total_length: usize,
+}
+
+impl SearchIndex {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Rebuild the index from scratch for the given notes
+    pub fn index_all(&mut self, notes: &[Note]) {
+        self.postings.clear();
+        self.note_terms.clear();
+        self.note_lengths.clear();
+        self.total_length = 0;
+        for note in notes {
+            self.index_note(note);
+        }
+    }
+
+    /// Index a single note, replacing anything previously recorded for it
+    pub fn index_note(&mut self, note: &Note) {
+        self.remove_note(&note.id);
+
+        let mut terms = HashSet::new();
+        let mut length = 0;
+        let title = TextRun {
+            path: Vec::new(),
+            text: note.title.clone(),
+        };
+        for run in std::iter::once(title).chain(text_runs(&note.blocks)) {
+            for token in tokenize(&run.text) {
+                length += 1;
+                terms.insert(token.term.clone());
+                self.postings
+                    .entry(token.term)
+                    .or_default()
+                    .entry(note.id.clone())
+                    .or_default()
+                    .push(SearchMatch {
+                        path: run.path.clone(),
+                        span: TextSpan {
+                            start: token.start as u64,
+                            end: token.end as u64,
+                        },
+                    });
+            }
+        }
+
+        if length == 0 {
+            return;
+        }
+        self.total_length += length;
+        self.note_lengths.insert(note.id.clone(), length);
+        self.note_terms.insert(note.id.clone(), terms);
+    }
+
+    /// Drop a note from the index
+    pub fn remove_note(&mut self, note_id: &str) {
+        let Some(terms) = self.note_terms.remove(note_id) else {
+            return;
+        };
+        self.total_length -= self.note_lengths.remove(note_id).unwrap_or_default();
+
+        for term in terms {
+            if let Entry::Occupied(mut notes) = self.postings.entry(term) {
+                notes.get_mut().remove(note_id);
+                if notes.get().is_empty() {
+                    notes.remove();
+                }
+            }
+        }
+    }
+
+    /// Find notes containing any of the query's terms, best match first
+    ///
+    /// Ties are broken by note ID so results are stable.
+    #[must_use]
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        let terms: HashSet<String> = tokenize(query).into_iter().map(|t| t.term).collect();
+        if terms.is_empty() || self.note_lengths.is_empty() {
+            return Vec::new();
+        }
+
+        let note_count = float(self.note_lengths.len());
+        let average_length = float(self.total_length) / note_count;
+        let mut hits: HashMap<&str, SearchHit> = HashMap::new();
+
+        for term in &terms {
+            let Some(notes) = self.postings.get(term) else {
+                continue;
+            };
+
+            let matching = float(notes.len());
+            let idf = ((note_count - matching + 0.5) / (matching + 0.5)).ln_1p();
+
+            for (note_id, occurrences) in notes {
+                let in_title = occurrences.iter().filter(|m| m.path.is_empty()).count();
+                let frequency = float(occurrences.len() - in_title) + TITLE_BOOST * float(in_title);
+                let length = float(self.note_lengths[note_id]);
+                let score = idf * frequency * (K1 + 1.0)
+                    / (frequency + K1 * (1.0 - B + B * length / average_length));
+
+                let hit = hits.entry(note_id).or_insert_with(|| SearchHit {
+                    note_id: note_id.clone(),
+                    score: 0.0,
+                    matches: Vec::new(),
+                });
+                hit.score += score;
+                hit.matches.extend(occurrences.iter().cloned());
+            }
+        }
+
+        let mut hits: Vec<SearchHit> = hits.into_values().collect();
+        for hit in &mut hits {
+            hit.matches
+                .sort_by(|a, b| (&a.path, a.span.start).cmp(&(&b.path, b.span.start)));
+        }
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.note_id.cmp(&b.note_id))
+        });
+        hits
+    }
+}
+
+/// A count as a float for scoring
+///
+/// Counts of notes and tokens stay far below 2^52, where `f64` would start
+/// to round them.
+#[allow(clippy::cast_precision_loss)]
+fn float(count: usize) -> f64 {
+    count as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Block, Inline, Properties};
+
+    fn note(id: &str, paragraphs: &[&str]) -> Note {
+        Note {
+            id: id.to_string(),
+            title: id.to_string(),
+            properties: Properties::default(),
+            blocks: paragraphs
+                .iter()
+                .map(|text| {
+                    Block::paragraph(vec![Inline::Text {
+                        text: (*text).to_string(),
+                    }])
+                })
+                .collect(),
+        }
+    }
+
+    fn ids(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.note_id.as_str()).collect()
+    }
+
+    #[test]
+    fn ranks_by_term_frequency_and_rarity() {
+        let mut index = SearchIndex::new();
+        index.index_all(&[
+            note("a", &["rust rust rust and more"]),
+            note("b", &["rust once"]),
+            note("c", &["nothing relevant here"]),
+        ]);
+
+        let hits = index.search("Rust");
+        assert_eq!(ids(&hits), vec!["a", "b"]);
+        assert!(hits[0].score > hits[1].score);
+    }
+
+    #[test]
+    fn title_matches_are_boosted() {
+        let mut titled = note("roadmap", &["the plan for the year ahead"]);
+        titled.title = "Product Roadmap".to_string();
+        let mut index = SearchIndex::new();
+        index.index_all(&[
+            note("a", &["roadmap roadmap and a few other words"]),
+            titled,
+            note("c", &["nothing relevant here"]),
+        ]);
+
+        let hits = index.search("roadmap");
+        assert_eq!(ids(&hits), vec!["roadmap", "a"]);
+        assert_eq!(
+            hits[0].matches,
+            vec![SearchMatch {
+                path: Vec::new(),
+                span: TextSpan { start: 8, end: 15 },
+            }]
+        );
+    }
+
+    #[test]
+    fn reports_match_spans() {
+        let mut index = SearchIndex::new();
+        index.index_note(&note("a", &["intro", "the Roadmap for Q3"]));
+
+        let hits = index.search("roadmap q3");
+        assert_eq!(
+            hits[0].matches,
+            vec![
+                SearchMatch {
+                    path: vec![1],
+                    span: TextSpan { start: 4, end: 11 },
+                },
+                SearchMatch {
+                    path: vec![1],
+                    span: TextSpan { start: 16, end: 18 },
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn reindexing_and_removal_are_incremental() {
+        let mut index = SearchIndex::new();
+        index.index_all(&[note("a", &["alpha"]), note("b", &["beta"])]);
+
+        index.index_note(&note("a", &["gamma"]));
+        assert_eq!(index.search("alpha"), Vec::new());
+        assert_eq!(ids(&index.search("gamma")), vec!["a"]);
+
+        index.remove_note("b");
+        assert_eq!(index.search("beta"), Vec::new());
+        // The title "a" and "gamma"
+        assert_eq!(index.total_length, 2);
+    }
+}
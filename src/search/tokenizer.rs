@@ -0,0 +1,60 @@
+/// A normalized term and where it occurs in the source text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    /// Lowercased term
+    pub term: String,
+    /// Character offset of the first character
+    pub start: usize,
+    /// Character offset one past the last character
+    pub end: usize,
+}
+
+/// Split text into lowercase alphanumeric terms with character offsets
+#[must_use]
+pub fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current: Option<Token> = None;
+
+    for (offset, c) in text.chars().enumerate() {
+        if c.is_alphanumeric() {
+            let token = current.get_or_insert_with(|| Token {
+                term: String::new(),
+                start: offset,
+                end: offset,
+            });
+            token.term.extend(c.to_lowercase());
+            token.end = offset + 1;
+        } else if let Some(token) = current.take() {
+            tokens.push(token);
+        }
+    }
+
+    tokens.extend(current);
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn terms(text: &str) -> Vec<String> {
+        tokenize(text).into_iter().map(|t| t.term).collect()
+    }
+
+    #[test]
+    fn splits_on_punctuation_and_lowercases() {
+        assert_eq!(
+            terms("Hello, World! It's v2."),
+            vec!["hello", "world", "it", "s", "v2"]
+        );
+    }
+
+    #[test]
+    fn offsets_are_in_characters() {
+        let tokens = tokenize("café au lait");
+        assert_eq!(tokens[0].start, 0);
+        assert_eq!(tokens[0].end, 4);
+        assert_eq!(tokens[1].start, 5);
+        assert_eq!(tokens[2].end, 12);
+    }
+}
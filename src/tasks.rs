@@ -0,0 +1,478 @@
+//! Tasks gathered from notes
+//!
+//! A task is either a heading starting with a TODO keyword (`* TODO Call
+//! Bob`, as Org writes them) or a list item starting with a checkbox
+//! (`- [ ] Call Bob`, as Markdown writes them). Both may carry a priority
+//! cookie (`[#A]`), dates and tags:
+//!
+//! ```text
+//! * TODO [#A] Write report :work:
+//!   DEADLINE: <2024-05-03 Fri> SCHEDULED: <2024-05-01 Wed>
+//! - [ ] [#B] Book flights due:2024-05-03 #travel
+//! - [x] Pack 📅 2024-05-02 ⏳ 2024-05-01
+//! ```
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{
+    document::{inlines_at_mut, plain_text},
+    error::{DocumentError, DocumentResult},
+    managers::tags::tag_matches,
+    models::{Block, Checkbox, ContainerBlock, Inline, LeafBlock, ListItem, Note},
+};
+
+/// Keywords that open a task heading, and what they mean
+const KEYWORDS: [(&str, TaskStatus); 7] = [
+    ("TODO", TaskStatus::Open),
+    ("NEXT", TaskStatus::Open),
+    ("WAITING", TaskStatus::Open),
+    ("DOING", TaskStatus::Open),
+    ("DONE", TaskStatus::Done),
+    ("CANCELLED", TaskStatus::Cancelled),
+    ("CANCELED", TaskStatus::Cancelled),
+];
+
+/// `SCHEDULED: <2024-05-01 Wed>` and `DEADLINE: [2024-05-03]` on the line
+/// after an Org heading
+static PLANNING: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(SCHEDULED|DEADLINE):\s*[<\[]([0-9]{4}-[0-9]{2}-[0-9]{2})[^>\]]*[>\]]").unwrap()
+});
+
+static DATE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^[0-9]{4}-[0-9]{2}-[0-9]{2}$").unwrap());
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum TaskStatus {
+    Open,
+    Done,
+    Cancelled,
+}
+
+/// Where a task is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, uniffi::Enum)]
+pub enum TaskKind {
+    /// A heading starting with a keyword such as `TODO`
+    Heading,
+    /// A list item with a checkbox such as `[ ]`
+    ListItem,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct Task {
+    pub note_id: String,
+    /// Inline container holding the task, see [`crate::document::TextRun`]
+    pub path: Vec<u64>,
+    pub kind: TaskKind,
+    pub status: TaskStatus,
+    /// The keyword as written, such as `TODO`, or the checkbox, such as `[x]`
+    pub marker: String,
+    /// The task text without its marker, priority, dates and tags
+    pub text: String,
+    /// `A` for `[#A]`
+    pub priority: Option<String>,
+    /// ISO date the task is scheduled for
+    pub scheduled: Option<String>,
+    /// ISO date the task is due
+    pub due: Option<String>,
+    pub tags: Vec<String>,
+}
+
+/// Which tasks [`crate::vault::Vault::tasks`] returns; unset fields match
+/// every task
+#[derive(Debug, Clone, Default, PartialEq, Eq, uniffi::Record)]
+pub struct TaskFilter {
+    pub status: Option<TaskStatus>,
+    pub note_id: Option<String>,
+    /// Tag or parent tag, see [`tag_matches`]
+    pub tag: Option<String>,
+    pub priority: Option<String>,
+    /// Only tasks due on or before this ISO date
+    pub due_before: Option<String>,
+}
+
+impl TaskFilter {
+    #[must_use]
+    pub fn matches(&self, task: &Task) -> bool {
+        self.status.is_none_or(|status| task.status == status)
+            && self
+                .note_id
+                .as_ref()
+                .is_none_or(|note_id| task.note_id == *note_id)
+            && self.tag.as_ref().is_none_or(|tag| {
+                let tag = tag.trim_start_matches('#');
+                task.tags.iter().any(|t| tag_matches(t, tag))
+            })
+            && self
+                .priority
+                .as_ref()
+                .is_none_or(|priority| task.priority.as_ref() == Some(priority))
+            && self.due_before.as_ref().is_none_or(|date| {
+                task.due
+                    .as_ref()
+                    .is_some_and(|due| due.as_str() <= date.as_str())
+            })
+    }
+}
+
+/// Every task in the note, in document order
+#[must_use]
+pub fn extract_tasks(note: &Note) -> Vec<Task> {
+    let mut tasks = Vec::new();
+    let mut path = Vec::new();
+    walk(&note.id, &note.blocks, &mut path, &mut tasks);
+    tasks
+}
+
+/// Mark an open task done, or a done or cancelled task open again
+///
+/// Headings switch between `TODO` and `DONE`, list items between `[ ]` and
+/// `[x]`. Returns the task as it is after the change.
+///
+/// # Errors
+///
+/// Returns [`DocumentError::InvalidIndex`] if there is no task at `path`
+pub fn toggle_task(note: &mut Note, path: &[u64]) -> DocumentResult<Task> {
+    let task = extract_tasks(note)
+        .into_iter()
+        .find(|task| task.path == path)
+        .ok_or(DocumentError::InvalidIndex)?;
+    if task.kind == TaskKind::ListItem {
+        let item = list_item_at_mut(&mut note.blocks, &path[..path.len() - 1])
+            .ok_or(DocumentError::InvalidIndex)?;
+        item.checkbox = Some(if task.status == TaskStatus::Open {
+            Checkbox::Checked
+        } else {
+            Checkbox::Unchecked
+        });
+    } else {
+        let marker = if task.status == TaskStatus::Open {
+            "DONE"
+        } else {
+            "TODO"
+        };
+        let inlines = inlines_at_mut(&mut note.blocks, path).ok_or(DocumentError::InvalidIndex)?;
+        let Some(Inline::Text { text }) = inlines.first_mut() else {
+            return Err(DocumentError::InvalidIndex);
+        };
+        let start = text.len() - text.trim_start().len();
+        if !text[start..].starts_with(&task.marker) {
+            return Err(DocumentError::InvalidIndex);
+        }
+        *text = format!(
+            "{}{marker}{}",
+            &text[..start],
+            &text[start + task.marker.len()..]
+        );
+    }
+
+    extract_tasks(note)
+        .into_iter()
+        .find(|task| task.path == path)
+        .ok_or(DocumentError::InvalidIndex)
+}
+
+fn walk(note_id: &str, blocks: &[Block], path: &mut Vec<u64>, tasks: &mut Vec<Task>) {
+    for (i, block) in blocks.iter().enumerate() {
+        path.push(i as u64);
+        match block {
+            Block::Leaf {
+                leaf: LeafBlock::Heading { content, .. },
+            } => {
+                let planning = match blocks.get(i + 1) {
+                    Some(Block::Leaf {
+                        leaf: LeafBlock::Paragraph { content },
+                    }) => plain_text(content),
+                    _ => String::new(),
+                };
+                if let Some(task) = heading_task(note_id, path, content, &planning) {
+                    tasks.push(task);
+                }
+            }
+            Block::Container { container } => match container {
+                ContainerBlock::Quote { blocks }
+                | ContainerBlock::Div {
+                    children: blocks, ..
+                } => walk(note_id, blocks, path, tasks),
+                ContainerBlock::List { items, .. } => {
+                    for (j, item) in items.iter().enumerate() {
+                        path.push(j as u64);
+                        if let Some(checkbox) = item.checkbox
+                            && let Some(Block::Leaf {
+                                leaf: LeafBlock::Paragraph { content },
+                            }) = item.blocks.first()
+                        {
+                            path.push(0);
+                            tasks.push(item_task(note_id, path, checkbox, content));
+                            path.pop();
+                        }
+                        walk(note_id, &item.blocks, path, tasks);
+                        path.pop();
+                    }
+                }
+                ContainerBlock::Table { .. } => {}
+            },
+            _ => {}
+        }
+        path.pop();
+    }
+}
+
+fn heading_task(note_id: &str, path: &[u64], content: &[Inline], planning: &str) -> Option<Task> {
+    let text: String = content
+        .iter()
+        .filter(|inline| !matches!(inline, Inline::Tag { .. }))
+        .map(|inline| plain_text(std::slice::from_ref(inline)))
+        .collect();
+    let (marker, rest) = text
+        .trim_start()
+        .split_once(' ')
+        .unwrap_or((text.trim(), ""));
+    let (_, status) = KEYWORDS.iter().find(|(keyword, _)| *keyword == marker)?;
+
+    let mut task = new_task(
+        note_id,
+        path,
+        TaskKind::Heading,
+        *status,
+        marker,
+        rest,
+        content,
+    );
+    for planned in PLANNING.captures_iter(planning) {
+        let date = Some(planned[2].to_string());
+        match &planned[1] {
+            "SCHEDULED" => task.scheduled = date,
+            _ => task.due = date,
+        }
+    }
+    Some(task)
+}
+
+fn item_task(note_id: &str, path: &[u64], checkbox: Checkbox, content: &[Inline]) -> Task {
+    let text: String = content
+        .iter()
+        .filter(|inline| !matches!(inline, Inline::Tag { .. }))
+        .map(|inline| plain_text(std::slice::from_ref(inline)))
+        .collect();
+    let status = match checkbox {
+        // `[-]` is partly done, so still open
+        Checkbox::Unchecked | Checkbox::Partial => TaskStatus::Open,
+        Checkbox::Checked => TaskStatus::Done,
+    };
+
+    new_task(
+        note_id,
+        path,
+        TaskKind::ListItem,
+        status,
+        checkbox.marker(),
+        &text,
+        content,
+    )
+}
+
+/// The list item at `path`, a list's path followed by the item's index
+fn list_item_at_mut<'a>(blocks: &'a mut [Block], path: &[u64]) -> Option<&'a mut ListItem> {
+    let (&index, rest) = path.split_first()?;
+    let Block::Container { container } = blocks.get_mut(usize::try_from(index).ok()?)? else {
+        return None;
+    };
+    match container {
+        ContainerBlock::Quote { blocks }
+        | ContainerBlock::Div {
+            children: blocks, ..
+        } => list_item_at_mut(blocks, rest),
+        ContainerBlock::List { items, .. } => {
+            let (&item, rest) = rest.split_first()?;
+            let item = items.get_mut(usize::try_from(item).ok()?)?;
+            if rest.is_empty() {
+                Some(item)
+            } else {
+                list_item_at_mut(&mut item.blocks, rest)
+            }
+        }
+        ContainerBlock::Table { .. } => None,
+    }
+}
+
+/// A task with the priority, Markdown-style dates and tags read from `rest`,
+/// the text after the marker
+fn new_task(
+    note_id: &str,
+    path: &[u64],
+    kind: TaskKind,
+    status: TaskStatus,
+    marker: &str,
+    rest: &str,
+    content: &[Inline],
+) -> Task {
+    let mut task = Task {
+        note_id: note_id.to_string(),
+        path: path.to_vec(),
+        kind,
+        status,
+        marker: marker.to_string(),
+        text: String::new(),
+        priority: None,
+        scheduled: None,
+        due: None,
+        tags: Vec::new(),
+    };
+
+    let mut words = Vec::new();
+    let mut rest = rest.split_whitespace().peekable();
+    while let Some(word) = rest.next() {
+        if task.priority.is_none()
+            && words.is_empty()
+            && let Some(priority) = word.strip_prefix("[#").and_then(|w| w.strip_suffix(']'))
+            && !priority.is_empty()
+        {
+            task.priority = Some(priority.to_string());
+            continue;
+        }
+
+        let (field, date) = match word {
+            "📅" | "⏳" if rest.peek().is_some_and(|next| DATE.is_match(next)) => {
+                (word, rest.next().unwrap_or_default())
+            }
+            _ => match word.split_once(':') {
+                Some((field @ ("due" | "scheduled"), date)) if DATE.is_match(date) => (field, date),
+                _ => {
+                    words.push(word);
+                    continue;
+                }
+            },
+        };
+        match field {
+            "⏳" | "scheduled" => task.scheduled = Some(date.to_string()),
+            _ => task.due = Some(date.to_string()),
+        }
+    }
+    task.text = words.join(" ");
+    collect_tags(content, &mut task.tags);
+    task
+}
+
+fn collect_tags(inlines: &[Inline], tags: &mut Vec<String>) {
+    for inline in inlines {
+        match inline {
+            Inline::Tag { name } if !tags.contains(name) => tags.push(name.clone()),
+            Inline::Bold { content }
+            | Inline::Italic { content }
+            | Inline::Strikethrough { content }
+            | Inline::Superscript { content }
+            | Inline::Subscript { content } => collect_tags(content, tags),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::formats::{NoteSerialization, markdown::MarkdownFormat, org::OrgFormat};
+
+    fn note(format: &dyn NoteSerialization, content: &str) -> Note {
+        format
+            .deserialize(content.as_bytes(), Some("plan"))
+            .unwrap()
+    }
+
+    #[test]
+    fn org_headings_with_keywords_are_tasks() {
+        let note = note(
+            &OrgFormat,
+            "* TODO [#A] Write report :work:urgent:\n\
+             DEADLINE: <2024-05-03 Fri> SCHEDULED: <2024-05-01 Wed>\n\
+             * DONE Send invoice\n\
+             * API design\n",
+        );
+
+        let tasks = extract_tasks(&note);
+
+        assert_eq!(
+            tasks[0],
+            Task {
+                note_id: "plan".into(),
+                path: vec![0],
+                kind: TaskKind::Heading,
+                status: TaskStatus::Open,
+                marker: "TODO".into(),
+                text: "Write report".into(),
+                priority: Some("A".into()),
+                scheduled: Some("2024-05-01".into()),
+                due: Some("2024-05-03".into()),
+                tags: vec!["work".into(), "urgent".into()],
+            }
+        );
+        assert_eq!(tasks[1].status, TaskStatus::Done);
+        assert_eq!(tasks.len(), 2);
+    }
+
+    #[test]
+    fn markdown_checkboxes_are_tasks() {
+        let note = note(
+            &MarkdownFormat::default(),
+            "# Plan\n\
+             - [ ] [#B] Book *flights* due:2024-05-03 #travel\n\
+             - [x] Pack 📅 2024-05-02 ⏳ 2024-05-01\n\
+             - [-] Rent a car\n\
+             - [link](target)\n",
+        );
+
+        let tasks = extract_tasks(&note);
+
+        let summary: Vec<(&str, TaskStatus)> = tasks
+            .iter()
+            .map(|task| (task.text.as_str(), task.status))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Book flights", TaskStatus::Open),
+                ("Pack", TaskStatus::Done),
+                ("Rent a car", TaskStatus::Open),
+            ]
+        );
+        assert_eq!(tasks[0].priority.as_deref(), Some("B"));
+        assert_eq!(tasks[0].due.as_deref(), Some("2024-05-03"));
+        assert_eq!(tasks[1].scheduled.as_deref(), Some("2024-05-01"));
+        assert_eq!(tasks[1].due.as_deref(), Some("2024-05-02"));
+        assert_eq!(tasks[0].tags, vec!["travel"]);
+        assert_eq!(tasks[0].path, vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn toggling_rewrites_the_marker() {
+        let format = Arc::new(MarkdownFormat::default());
+        let mut note = note(
+            format.as_ref(),
+            "# Plan\n- [ ] Book flights\n- [-] Rent a car\n",
+        );
+
+        assert_eq!(
+            toggle_task(&mut note, &[0, 0, 0]).unwrap().status,
+            TaskStatus::Done
+        );
+        assert_eq!(
+            toggle_task(&mut note, &[0, 1, 0]).unwrap().status,
+            TaskStatus::Done
+        );
+        assert!(toggle_task(&mut note, &[0]).is_err());
+        assert_eq!(
+            String::from_utf8(format.serialize(&note)).unwrap(),
+            "# Plan\n\n- [x] Book flights\n- [x] Rent a car\n"
+        );
+
+        let mut org = self::note(&OrgFormat, "* WAITING Call Bob\n");
+        let task = toggle_task(&mut org, &[0]).unwrap();
+        assert_eq!(
+            (task.status, task.marker.as_str()),
+            (TaskStatus::Done, "DONE")
+        );
+    }
+}
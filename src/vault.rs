@@ -1,50 +1,1268 @@
-use std::{cell::RefCell, sync::Arc};
+pub mod graph;
+pub mod health;
+pub mod tasks;
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
 
 use crate::{
+    document::{find_names, link_match, visit_links_mut, visit_tags_mut},
+    error::{LibnoteResult, RepositoryError, RepositoryResult},
+    formats::{
+        NoteFormat,
+        markdown::{MarkdownFormat, extract_attachments},
+    },
     managers::{
-        backlinks::BacklinkManager,
-        tags::{GlobalTagManager, ScopedTagManager},
+        backlinks::{Backlink, BacklinkManager},
+        links::{LinkResolution, LinkResolver, Mention},
+        tags::{GlobalTagManager, ScopedTagManager, TagCount, count_tags, rename_tag, tag_matches},
+    },
+    models::{Attachment, Inline, LinkTarget, Note, PropertyValue},
+    query::{Query, QueryContext, QuerySort, parse_query},
+    repo::{
+        NotesRepository,
+        file::{FileNotesRepository, FileProvider},
+        local::LocalFsProvider,
     },
-    models::{LinkTarget, Note},
-    repo::NotesRepository,
+    search::{SearchHit, SearchIndex},
 };
 
+/// Outcome of [`Vault::rename_note`]
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct RenameReport {
+    pub old_id: String,
+    /// ID the note ended up with, as assigned by the repository
+    pub new_id: String,
+    /// IDs of the notes whose links were rewritten, in the order they were
+    /// saved
+    pub updated_notes: Vec<String>,
+    pub rewritten_links: u64,
+}
+
+#[derive(uniffi::Object)]
 pub struct Vault {
     pub repo: Box<Arc<dyn NotesRepository>>,
+    format: Arc<dyn NoteFormat>,
+    indexes: RwLock<Indexes>,
+    /// Where attachment files live, for the [`health`] checks
+    attachments: RwLock<Option<Arc<dyn FileProvider>>>,
+}
+
+/// Everything the vault derives from its notes, behind one lock so that
+/// queries see a consistent state while a save updates it
+#[derive(Default)]
+struct Indexes {
     scoped_tags: ScopedTagManager,
     global_tags: Arc<GlobalTagManager>,
     backlinks: BacklinkManager,
+    resolver: LinkResolver,
+    /// `note_id` -> links as written, kept so they can be resolved again
+    /// when notes are added, renamed or given new aliases
+    links: HashMap<String, Vec<LinkTarget>>,
+    search: SearchIndex,
 }
 
 impl Vault {
-    pub fn new(repo: Arc<dyn NotesRepository>) -> Self {
+    /// Create a vault with empty indexes
+    ///
+    /// Call [`Vault::reindex`] (or use [`Vault::open`]) before querying
+    /// backlinks or tags.
+    pub fn new(repo: Arc<dyn NotesRepository>, format: Arc<dyn NoteFormat>) -> Self {
         Self {
             repo: Box::new(repo),
-            scoped_tags: ScopedTagManager::new(),
-            global_tags: GlobalTagManager::new(),
-            backlinks: BacklinkManager::new(),
+            format,
+            indexes: RwLock::new(Indexes::default()),
+            attachments: RwLock::new(None),
         }
     }
 
+    /// Create a vault and build its indexes from the repository
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if listing the notes in the repository fails
+    pub fn open(
+        repo: Arc<dyn NotesRepository>,
+        format: Arc<dyn NoteFormat>,
+    ) -> RepositoryResult<Self> {
+        let vault = Self::new(repo, format);
+        vault.reindex()?;
+        Ok(vault)
+    }
+
+    /// Use `global_tags` for externally assigned tags, such as a manager
+    /// loaded from a [`crate::managers::tags::TagStore`]
     #[must_use]
+    pub fn with_global_tags(self, global_tags: Arc<GlobalTagManager>) -> Self {
+        self.set_global_tags(global_tags);
+        self
+    }
+
+    /// Look attachments up in `store`, with paths relative to its root
+    ///
+    /// Without a store, missing and unused attachments cannot be reported.
+    #[must_use]
+    pub fn with_attachments(mut self, store: Box<dyn FileProvider>) -> Self {
+        self.attachments = RwLock::new(Some(store.into()));
+        self
+    }
+
+    /// The indexes, for reading; panics if an update panicked halfway
+    fn indexes(&self) -> RwLockReadGuard<'_, Indexes> {
+        self.indexes.read().unwrap()
+    }
+
+    /// The indexes, for updating; panics if an update panicked halfway
+    fn indexes_mut(&self) -> RwLockWriteGuard<'_, Indexes> {
+        self.indexes.write().unwrap()
+    }
+
+    /// The attachment store, if one is set
+    fn attachments(&self) -> Option<Arc<dyn FileProvider>> {
+        self.attachments.read().unwrap().clone()
+    }
+
+    /// Point links in `note` that `before` resolved to `old_id` at `new_id`,
+    /// unless they already reach it; returns how many were rewritten
+    fn rewrite_links(
+        &self,
+        note: &mut Note,
+        before: &LinkResolver,
+        old_id: &str,
+        new_id: &str,
+    ) -> u64 {
+        let indexes = self.indexes();
+        let mut rewritten = 0;
+        visit_links_mut(&mut note.blocks, |text, target| {
+            if target.contains("://") {
+                return;
+            }
+            let (name, anchor) = match target.split_once('#') {
+                Some((name, anchor)) => (name, Some(anchor)),
+                None => (target.as_str(), None),
+            };
+            if name.is_empty()
+                || before.resolve(name)
+                    != (LinkResolution::Resolved {
+                        note_id: old_id.to_string(),
+                    })
+                || indexes.resolver.resolve(name)
+                    == (LinkResolution::Resolved {
+                        note_id: new_id.to_string(),
+                    })
+            {
+                return;
+            }
+
+            let new_target = match anchor {
+                Some(anchor) => format!("{new_id}#{anchor}"),
+                None => new_id.to_string(),
+            };
+            // `[[old]]` shows its target as text, so keep the two in step
+            if let [Inline::Text { text }] = text.as_mut_slice()
+                && text == target
+            {
+                text.clone_from(&new_target);
+            }
+            *target = new_target;
+            rewritten += 1;
+        });
+        rewritten
+    }
+
+    /// Best-effort rollback of a failed [`Vault::rename_note`]
+    fn undo_rename(&self, old_id: &str, new_id: &str, saved: &[Note]) {
+        let _ = self.repo.rename_note(new_id, old_id);
+        for note in saved {
+            let _ = self.repo.save_note(note);
+        }
+        let _ = self.global_tags().move_tags(new_id, old_id);
+        let _ = self.reindex();
+    }
+
+    /// Rewrite and save the notes in `sources` that linked to a renamed note
+    ///
+    /// Each note is pushed to `saved` as it was before being saved, so a
+    /// failure part way through can be rolled back.
+    fn relink_sources(
+        &self,
+        sources: Vec<String>,
+        before: &LinkResolver,
+        report: &mut RenameReport,
+        saved: &mut Vec<Note>,
+    ) -> RepositoryResult<()> {
+        for source in sources {
+            let source = if source == report.old_id {
+                report.new_id.clone()
+            } else {
+                source
+            };
+            let Some(original) = self.repo.get_note(&source)? else {
+                continue;
+            };
+
+            let mut note = original.clone();
+            let rewritten = self.rewrite_links(&mut note, before, &report.old_id, &report.new_id);
+            if rewritten == 0 {
+                continue;
+            }
+            self.save_note(&note)?;
+            saved.push(original);
+            report.updated_notes.push(note.id);
+            report.rewritten_links += rewritten;
+        }
+        Ok(())
+    }
+
+    /// IDs of the notes matching a parsed query, ordered by `sort`
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if listing the notes in the repository fails
+    pub fn find_notes(&self, query: &Query, sort: QuerySort) -> RepositoryResult<Vec<String>> {
+        let notes = self.repo.list_notes()?;
+        let indexes = self.indexes();
+        let mut notes: Vec<Note> = notes
+            .into_iter()
+            .filter(|note| query.matches(note, &*indexes))
+            .collect();
+
+        match sort {
+            QuerySort::Id => notes.sort_by(|a, b| a.id.cmp(&b.id)),
+            QuerySort::Title => {
+                notes.sort_by_cached_key(|note| (note.title.to_lowercase(), note.id.clone()));
+            }
+            QuerySort::Backlinks => notes.sort_by_cached_key(|note| {
+                (
+                    std::cmp::Reverse(indexes.backlinks.sources_for_note(&note.id).len()),
+                    note.id.clone(),
+                )
+            }),
+        }
+
+        Ok(notes.into_iter().map(|note| note.id).collect())
+    }
+
+    /// Scoped and global tags of the note, sorted and without duplicates
+    #[must_use]
+    pub fn all_tags_for(&self, note: &Note) -> Vec<String> {
+        self.tags_for_note(&note.id)
+    }
+}
+
+#[uniffi::export]
+impl Vault {
+    /// Open the notes in a file repository and build the vault's indexes
+    ///
+    /// Links and tags are read from notes the way [`MarkdownFormat`] reads
+    /// them. From Rust, [`Vault::open`] takes any [`NotesRepository`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if listing the notes in the repository fails
+    #[uniffi::constructor]
+    pub fn from_repository(repo: Arc<FileNotesRepository>) -> RepositoryResult<Self> {
+        Self::open(repo, Arc::new(MarkdownFormat::default()))
+    }
+
+    /// Use `global_tags` for externally assigned tags, see
+    /// [`Vault::with_global_tags`]
+    #[uniffi::method]
+    pub fn set_global_tags(&self, global_tags: Arc<GlobalTagManager>) {
+        self.indexes_mut().global_tags = global_tags;
+    }
+
+    /// Look attachments up in a folder, see [`Vault::with_attachments`]
+    ///
+    /// Every file in the folder is an attachment, whichever extensions
+    /// `folder` was created with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the attachment lock is poisoned
+    #[uniffi::method]
+    pub fn set_attachment_folder(&self, folder: Arc<LocalFsProvider>) {
+        let store = Arc::unwrap_or_clone(folder).all_files();
+        *self.attachments.write().unwrap() = Some(Arc::new(store));
+    }
+
+    /// Rebuild the backlink, tag and search indexes from every note in the
+    /// repository
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if listing the notes in the repository fails
+    #[uniffi::method]
+    pub fn reindex(&self) -> RepositoryResult<()> {
+        let notes = self.repo.list_notes()?;
+        self.indexes_mut().index_all(&notes, self.format.as_ref());
+        Ok(())
+    }
+
+    /// Save a note and update the indexes for just that note
+    ///
+    /// Links and tags the note previously had are dropped before the new ones
+    /// are recorded, so the cost is proportional to the note, not the vault.
+    /// The exception is a change to the note's title or aliases, after which
+    /// every stored link is resolved again.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to the repository fails
+    #[uniffi::method]
+    pub fn save_note(&self, note: &Note) -> RepositoryResult<()> {
+        self.repo.save_note(note)?;
+        self.indexes_mut().index_note(note, self.format.as_ref());
+        Ok(())
+    }
+
+    /// Delete a note and drop its links and tags from the indexes
+    ///
+    /// Links from other notes that point at the deleted note are kept as
+    /// written, so they still show up as backlinks of a now-missing note.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if deleting from the repository or saving the global
+    /// tags fails
+    #[uniffi::method]
+    pub fn delete_note(&self, id: &str) -> RepositoryResult<()> {
+        self.repo.delete_note(id)?;
+        self.indexes_mut().unindex_note(id);
+        self.global_tags().clear_tags(id)
+    }
+
+    /// Give a note a new ID and rewrite the links that pointed at the old one
+    ///
+    /// The repository moves the note (for file repositories, its file), then
+    /// every note linking to it is updated and saved. Links that still reach
+    /// the note after the rename, such as `[[Title]]` or alias links, are left
+    /// as written. If reading or saving a linking note fails, the notes already
+    /// saved are restored and the note is renamed back before the error is
+    /// returned.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note does not exist, `new_id` is taken, or
+    /// writing to the repository or saving the global tags fails
+    #[uniffi::method]
+    pub fn rename_note(&self, old_id: &str, new_id: &str) -> RepositoryResult<RenameReport> {
+        let sources = self.indexes().backlinks.sources_for_note(old_id);
+        let renamed = self.repo.rename_note(old_id, new_id)?;
+
+        let before = {
+            let mut indexes = self.indexes_mut();
+            let before = indexes.resolver.clone();
+            indexes.unindex_note(old_id);
+            indexes.index_note(&renamed, self.format.as_ref());
+            before
+        };
+        if let Err(error) = self.global_tags().move_tags(old_id, &renamed.id) {
+            self.undo_rename(old_id, &renamed.id, &[]);
+            return Err(error);
+        }
+
+        let mut report = RenameReport {
+            old_id: old_id.to_string(),
+            new_id: renamed.id.clone(),
+            updated_notes: Vec::new(),
+            rewritten_links: 0,
+        };
+        let mut saved = Vec::new();
+        if let Err(error) = self.relink_sources(sources, &before, &mut report, &mut saved) {
+            self.undo_rename(old_id, &renamed.id, &saved);
+            return Err(error);
+        }
+
+        Ok(report)
+    }
+
+    /// Turn an unlinked mention into a link to the mentioned note and save the
+    /// note it appears in
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source note is missing, the mention no longer
+    /// matches its text, or saving fails
+    #[uniffi::method]
+    pub fn link_mention(&self, mention: &Mention) -> LibnoteResult<()> {
+        let mut note = self
+            .repo
+            .get_note(&mention.source)?
+            .ok_or_else(|| RepositoryError::not_found(&mention.source))?;
+        link_match(&mut note.blocks, &mention.location, &mention.target)?;
+        self.save_note(&note)?;
+        Ok(())
+    }
+
+    /// Rename a tag and its subtags (`old/...`) on every note
+    ///
+    /// Global tags are renamed in place. Scoped tags are renamed by rewriting
+    /// the note's `tags` property and its `#tag` inlines and saving the note;
+    /// tags a format extracts from elsewhere in the content are left as
+    /// written. Returns the IDs of the notes that were saved, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if saving the global tags, listing the notes or saving
+    /// one of them fails
+    #[uniffi::method]
+    pub fn rename_tag(&self, old: &str, new: &str) -> RepositoryResult<Vec<String>> {
+        let old = old.trim_start_matches('#');
+        let new = new.trim_start_matches('#');
+        self.global_tags().rename_tag(old, new)?;
+
+        let mut updated = Vec::new();
+        for mut note in self.repo.list_notes()? {
+            let mut changed = false;
+            if let Some(PropertyValue::List { items }) = note.properties.get("tags") {
+                let mut tags: Vec<PropertyValue> = Vec::with_capacity(items.len());
+                for item in items {
+                    let item = match item.as_str().and_then(|tag| rename_tag(tag, old, new)) {
+                        Some(tag) => {
+                            changed = true;
+                            PropertyValue::text(tag)
+                        }
+                        None => item.clone(),
+                    };
+                    if !tags.contains(&item) {
+                        tags.push(item);
+                    }
+                }
+                note.properties
+                    .insert("tags", PropertyValue::List { items: tags });
+            }
+            visit_tags_mut(&mut note.blocks, |name| {
+                if let Some(renamed) = rename_tag(name, old, new) {
+                    *name = renamed;
+                    changed = true;
+                }
+            });
+            if !changed {
+                continue;
+            }
+
+            self.save_note(&note)?;
+            updated.push(note.id);
+        }
+        updated.sort();
+        Ok(updated)
+    }
+
+    /// IDs of the notes linking to the note or to one of its headings or
+    /// blocks, sorted
+    #[must_use]
+    #[uniffi::method]
     pub fn backlinks_for_note(&self, note_id: &str) -> Vec<String> {
-        self.backlinks
-            .backlinks_for(&LinkTarget::Note(note_id.to_string()))
+        self.indexes().backlinks.sources_for_note(note_id)
+    }
+
+    /// Every link into the note, with the heading or block it points at
+    #[must_use]
+    #[uniffi::method]
+    pub fn references_to_note(&self, note_id: &str) -> Vec<Backlink> {
+        self.indexes().backlinks.references_to(note_id)
     }
 
+    /// Resolve a link target such as the text of `[[Some Title]]` to a note
+    ///
+    /// See [`LinkResolver`] for how notes are matched.
     #[must_use]
+    #[uniffi::method]
+    pub fn resolve_link(&self, target: &str) -> LinkResolution {
+        self.indexes().resolver.resolve(target)
+    }
+
+    #[must_use]
+    #[uniffi::method]
     pub fn backlinks_for_attachment(&self, attachment_id: &str) -> Vec<String> {
-        self.backlinks
+        self.indexes()
+            .backlinks
             .backlinks_for(&LinkTarget::Attachment(attachment_id.to_string()))
     }
 
+    /// IDs of the notes with the tag or one of its subtags, scoped or
+    /// global, sorted
     #[must_use]
-    pub fn all_tags_for(&self, note: &Note) -> Vec<String> {
-        let mut tags = Vec::new();
-        if let Some(tagged) = self.scoped_tags.tag_index.get(&note.id) {
-            tags.extend(tagged.iter().cloned());
+    #[uniffi::method]
+    pub fn notes_with_tag(&self, tag: &str) -> Vec<String> {
+        let tag = tag.trim_start_matches('#');
+        let indexes = self.indexes();
+        let mut notes: BTreeSet<String> = indexes
+            .scoped_tags
+            .notes_with_tag(tag)
+            .into_iter()
+            .collect();
+        notes.extend(indexes.global_tags.clone().notes_with_tag(tag));
+        notes.into_iter().collect()
+    }
+
+    /// The manager holding externally assigned tags
+    #[must_use]
+    #[uniffi::method]
+    pub fn global_tags(&self) -> Arc<GlobalTagManager> {
+        self.indexes().global_tags.clone()
+    }
+
+    /// Scoped and global tags of the note, sorted and without duplicates
+    #[must_use]
+    #[uniffi::method]
+    pub fn tags_for_note(&self, note_id: &str) -> Vec<String> {
+        let indexes = self.indexes();
+        let mut tags: BTreeSet<String> = indexes
+            .scoped_tags
+            .tags_for_note(note_id)
+            .into_iter()
+            .collect();
+        tags.extend(indexes.global_tags.clone().get_tags_for(note_id));
+        tags.into_iter().collect()
+    }
+
+    /// How many notes carry each tag, scoped or global, counting notes tagged
+    /// with a subtag under its parents too; sorted by tag
+    #[must_use]
+    #[uniffi::method]
+    pub fn tag_counts(&self) -> Vec<TagCount> {
+        count_tags(self.indexes().merged_tags().values())
+    }
+
+    /// Places where the note's title or one of its aliases appears in the plain
+    /// text of another note without being a link
+    ///
+    /// Matches are whole words and ignore case; text that is already a link is
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note does not exist or listing the notes fails
+    #[uniffi::method]
+    pub fn unlinked_mentions(&self, note_id: &str) -> RepositoryResult<Vec<Mention>> {
+        let note = self
+            .repo
+            .get_note(note_id)?
+            .ok_or_else(|| RepositoryError::not_found(note_id))?;
+        let mut names = vec![note.title.as_str()];
+        if let Some(aliases) = note.properties.get("aliases") {
+            names.extend(aliases.as_strings());
+        }
+
+        let mut mentions = Vec::new();
+        for source in self.repo.list_notes()? {
+            if source.id == note.id {
+                continue;
+            }
+            mentions.extend(
+                find_names(&source.blocks, &names)
+                    .into_iter()
+                    .map(|location| Mention {
+                        target: note.id.clone(),
+                        source: source.id.clone(),
+                        location,
+                    }),
+            );
+        }
+        mentions.sort_by(|a, b| a.source.cmp(&b.source));
+        Ok(mentions)
+    }
+
+    /// Full-text search over note content, best match first
+    #[must_use]
+    #[uniffi::method]
+    pub fn search(&self, query: &str) -> Vec<SearchHit> {
+        self.indexes().search.search(query)
+    }
+
+    /// IDs of the notes matching a query string, see [`crate::query`]
+    ///
+    /// # Errors
+    ///
+    /// Returns a parse error if the query is malformed, or a repository error
+    /// if listing the notes fails
+    #[uniffi::method]
+    pub fn query(&self, query: &str, sort: QuerySort) -> LibnoteResult<Vec<String>> {
+        let query = parse_query(query)?;
+        Ok(self.find_notes(&query, sort)?)
+    }
+}
+
+impl Indexes {
+    /// Rebuild every index from `notes`
+    fn index_all(&mut self, notes: &[Note], format: &dyn NoteFormat) {
+        let attachments: Vec<Attachment> = notes
+            .iter()
+            .flat_map(|note| extract_attachments(&note.blocks))
+            .collect();
+
+        self.resolver.index_all(notes);
+        self.links = notes
+            .iter()
+            .map(|note| (note.id.clone(), format.extract_links(note, &attachments)))
+            .collect();
+        self.relink();
+
+        self.scoped_tags
+            .index_all(notes, |note| extract_tags(format, note));
+
+        self.search.index_all(notes);
+    }
+
+    /// Record a note in the backlink, tag and search indexes
+    fn index_note(&mut self, note: &Note, format: &dyn NoteFormat) {
+        let attachments = extract_attachments(&note.blocks);
+        self.links
+            .insert(note.id.clone(), format.extract_links(note, &attachments));
+        if self.resolver.index_note(note) {
+            self.relink();
+        } else {
+            self.link_note(&note.id);
+        }
+
+        self.scoped_tags
+            .index_note(note, |note| extract_tags(format, note));
+
+        self.search.index_note(note);
+    }
+
+    /// Drop a note from the backlink, tag and search indexes
+    ///
+    /// Other notes' links are only resolved again if one of them named the
+    /// removed note.
+    fn unindex_note(&mut self, id: &str) {
+        self.links.remove(id);
+        let linked = self
+            .links
+            .values()
+            .flatten()
+            .filter_map(LinkTarget::note_id)
+            .any(|name| self.resolver.answers_to(id, name));
+        if self.resolver.remove_note(id) && linked {
+            self.relink();
+        } else {
+            self.backlinks.remove_note(id);
+        }
+        self.scoped_tags.remove_note(id);
+        self.search.remove_note(id);
+    }
+
+    /// Re-record every note's links against the current resolver
+    fn relink(&mut self) {
+        self.backlinks.clear();
+        let ids: Vec<String> = self.links.keys().cloned().collect();
+        for id in ids {
+            self.link_note(&id);
+        }
+    }
+
+    /// Record one note's stored links, resolved to note IDs where possible
+    fn link_note(&mut self, id: &str) {
+        let targets = self
+            .links
+            .get(id)
+            .into_iter()
+            .flatten()
+            .map(|target| self.resolver.resolve_target(target));
+        self.backlinks.index_links(id, targets);
+    }
+
+    /// Scoped and global tags of every note
+    fn merged_tags(&self) -> HashMap<String, BTreeSet<String>> {
+        let mut tags: HashMap<String, BTreeSet<String>> = HashMap::new();
+        for (note_id, global) in self.global_tags.assignments() {
+            tags.entry(note_id).or_default().extend(global);
+        }
+        for (tag, notes) in &self.scoped_tags.tag_index {
+            for note_id in notes {
+                tags.entry(note_id.clone()).or_default().insert(tag.clone());
+            }
         }
-        tags.extend(self.global_tags.clone().get_tags_for(&note.id));
         tags
     }
 }
+
+impl QueryContext for Indexes {
+    fn has_tag(&self, note_id: &str, tag: &str) -> bool {
+        self.scoped_tags.has_tag(note_id, tag)
+            || self
+                .global_tags
+                .clone()
+                .get_tags_for(note_id)
+                .iter()
+                .any(|t| tag_matches(t, tag))
+    }
+
+    fn links_to(&self, source: &str, target: &str) -> bool {
+        let resolve = |name: &str| match self.resolver.resolve(name) {
+            LinkResolution::Resolved { note_id } => note_id,
+            _ => name.to_string(),
+        };
+        self.backlinks
+            .links_to_note(&resolve(source), &resolve(target))
+    }
+}
+
+/// Extract content tags by rendering the note back into the vault's format,
+/// since [`crate::formats::NoteMetadata::extract_tags`] works on raw text
+fn extract_tags(format: &dyn NoteFormat, note: &Note) -> Vec<String> {
+    let content = format.serialize(note);
+    format.extract_tags(&String::from_utf8_lossy(&content))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        error::{LibnoteError, SerializationResult},
+        formats::{NoteMetadata, NoteSerialization, markdown::MarkdownFormat},
+        models::Block,
+        repo::memory::MemoryNotesRepository,
+    };
+
+    /// Markdown format that also treats `#word` tokens as tags
+    #[derive(Debug)]
+    struct HashtagFormat;
+
+    impl NoteSerialization for HashtagFormat {
+        fn deserialize(&self, data: &[u8], id_hint: Option<&str>) -> SerializationResult<Note> {
+            MarkdownFormat::default().deserialize(data, id_hint)
+        }
+
+        fn serialize(&self, note: &Note) -> Vec<u8> {
+            MarkdownFormat::default().serialize(note)
+        }
+    }
+
+    impl NoteMetadata for HashtagFormat {
+        fn extract_tags(&self, content: &str) -> Vec<String> {
+            content
+                .split_whitespace()
+                .filter_map(|word| word.strip_prefix('#'))
+                .filter(|tag| !tag.is_empty() && !tag.starts_with('#'))
+                .map(ToString::to_string)
+                .collect()
+        }
+
+        fn extract_links(&self, note: &Note, attachments: &[Attachment]) -> Vec<LinkTarget> {
+            MarkdownFormat::default().extract_links(note, attachments)
+        }
+    }
+
+    fn repo_with(notes: &[(&str, &str)]) -> Arc<dyn NotesRepository> {
+        let repo = MemoryNotesRepository::new(Arc::new(MarkdownFormat::default()));
+        for (id, content) in notes {
+            repo.insert_raw(content.as_bytes(), Some(id)).unwrap();
+        }
+        Arc::new(repo)
+    }
+
+    #[test]
+    fn open_indexes_backlinks() {
+        let repo = repo_with(&[
+            ("a", "# A\nSee [B](b) and ![diagram](diagram.png)"),
+            ("b", "# B\nBack to [A](a)"),
+            ("c", "# C\nAlso [B](b)"),
+        ]);
+
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        let mut backlinks = vault.backlinks_for_note("b");
+        backlinks.sort();
+        assert_eq!(backlinks, vec!["a", "c"]);
+        assert_eq!(vault.backlinks_for_note("a"), vec!["b"]);
+        assert_eq!(vault.backlinks_for_attachment("diagram.png"), vec!["a"]);
+    }
+
+    #[test]
+    fn wiki_links_resolve_by_title_and_alias() {
+        let repo = repo_with(&[
+            (
+                "roadmap",
+                "---\naliases: [Plan, Q3 goals]\n---\n# Product Roadmap\nNothing",
+            ),
+            (
+                "a",
+                "# A\nSee [[product roadmap]] and [[Q3 Goals|the goals]]",
+            ),
+            ("b", "# B\nSee [[Plan]] and [[Nowhere]]"),
+            ("c", "# Plan\nOne plan"),
+            ("d", "# D\nBack to [[A]]"),
+            ("e", "# Plan\nAnother plan"),
+        ]);
+
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        let mut backlinks = vault.backlinks_for_note("roadmap");
+        backlinks.sort();
+        assert_eq!(backlinks, vec!["a"]);
+        assert_eq!(vault.backlinks_for_note("a"), vec!["d"]);
+        assert_eq!(vault.backlinks_for_note("Nowhere"), vec!["b"]);
+        assert_eq!(
+            vault.resolve_link("plan"),
+            LinkResolution::Ambiguous {
+                candidates: vec!["c".into(), "e".into()],
+            }
+        );
+        assert_eq!(vault.backlinks_for_note("Plan"), vec!["b"]);
+        assert_eq!(
+            vault
+                .query(r#"links-to:"Product Roadmap""#, QuerySort::Id)
+                .unwrap(),
+            vec!["a"]
+        );
+    }
+
+    #[test]
+    fn linked_from_resolves_the_source_by_title() {
+        let repo = repo_with(&[
+            ("roadmap", "# Product Roadmap\nSee [[Ideas]]"),
+            ("ideas", "# Ideas\nSomeday"),
+            ("other", "# Other\nSee [[roadmap]]"),
+        ]);
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        assert_eq!(
+            vault
+                .query(r#"linked-from:"Product Roadmap""#, QuerySort::Id)
+                .unwrap(),
+            vec!["ideas"]
+        );
+        assert_eq!(
+            vault.query("linked-from:roadmap", QuerySort::Id).unwrap(),
+            vec!["ideas"]
+        );
+        assert_eq!(
+            vault.query("linked-from:Nowhere", QuerySort::Id).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn references_name_the_linked_heading() {
+        let repo = repo_with(&[
+            ("roadmap", "# Product Roadmap\n## Q3\nShip sync ^sync"),
+            ("a", "# A\nSee [[Product Roadmap#Q3]]"),
+            (
+                "b",
+                "# B\nSee [[product roadmap#^sync]] and [[Product Roadmap]]",
+            ),
+        ]);
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        assert_eq!(vault.backlinks_for_note("roadmap"), vec!["a", "b"]);
+        let targets: Vec<(String, LinkTarget)> = vault
+            .references_to_note("roadmap")
+            .into_iter()
+            .map(|backlink| (backlink.source, backlink.target))
+            .collect();
+        assert_eq!(
+            targets,
+            vec![
+                (
+                    "a".into(),
+                    LinkTarget::Heading {
+                        note: "roadmap".into(),
+                        slug: "q3".into(),
+                    }
+                ),
+                ("b".into(), LinkTarget::Note("roadmap".into())),
+                (
+                    "b".into(),
+                    LinkTarget::Block {
+                        note: "roadmap".into(),
+                        block_id: "sync".into(),
+                    }
+                ),
+            ]
+        );
+        assert_eq!(
+            vault.query("links-to:roadmap", QuerySort::Id).unwrap(),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn unlinked_mentions_can_become_links() {
+        let repo = repo_with(&[
+            (
+                "roadmap",
+                "---\naliases: [Q3 plan]\n---\n# Product Roadmap\nNothing",
+            ),
+            ("a", "# A\nThe product roadmap and the Q3 plan"),
+            (
+                "b",
+                "# B\nSee [Product Roadmap](roadmap), roadmaps are fine",
+            ),
+        ]);
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        let mentions = vault.unlinked_mentions("roadmap").unwrap();
+        let found: Vec<(&str, &str)> = mentions
+            .iter()
+            .map(|m| (m.source.as_str(), m.location.text.as_str()))
+            .collect();
+        assert_eq!(found, vec![("a", "product roadmap"), ("a", "Q3 plan")]);
+
+        vault.link_mention(&mentions[1]).unwrap();
+
+        assert_eq!(vault.backlinks_for_note("roadmap"), vec!["a", "b"]);
+        assert_eq!(vault.unlinked_mentions("roadmap").unwrap().len(), 1);
+        assert!(vault.link_mention(&mentions[1]).is_err());
+        assert!(matches!(
+            vault.unlinked_mentions("missing"),
+            Err(RepositoryError::NotFound(_))
+        ));
+    }
+
+    #[test]
+    fn rename_note_rewrites_links_that_would_break() {
+        let repo = repo_with(&[
+            ("roadmap", "# Product Roadmap\n## Q3\nShip sync"),
+            ("a", "# A\nSee [[roadmap]] and [[roadmap#Q3]]"),
+            ("b", "# B\nSee [[Product Roadmap]] and [details](roadmap)"),
+            ("c", "# C\nNothing"),
+        ]);
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        let report = vault.rename_note("roadmap", "plans-2024").unwrap();
+
+        assert_eq!(
+            report,
+            RenameReport {
+                old_id: "roadmap".into(),
+                new_id: "plans-2024".into(),
+                updated_notes: vec!["a".into(), "b".into()],
+                rewritten_links: 3,
+            }
+        );
+        assert!(vault.repo.get_note("roadmap").unwrap().is_none());
+        assert_eq!(vault.backlinks_for_note("plans-2024"), vec!["a", "b"]);
+        assert_eq!(vault.backlinks_for_note("roadmap"), Vec::<String>::new());
+
+        let a = vault.repo.get_note("a").unwrap().unwrap();
+        assert_eq!(
+            a.blocks[0],
+            Block::paragraph(vec![
+                Inline::Text {
+                    text: "See ".into(),
+                },
+                Inline::Link {
+                    text: vec![Inline::Text {
+                        text: "plans-2024".into(),
+                    }],
+                    target: "plans-2024".into(),
+                },
+                Inline::Text {
+                    text: " and ".into(),
+                },
+                Inline::Link {
+                    text: vec![Inline::Text {
+                        text: "plans-2024#Q3".into(),
+                    }],
+                    target: "plans-2024#Q3".into(),
+                },
+            ])
+        );
+        let b = MarkdownFormat::default().serialize(&vault.repo.get_note("b").unwrap().unwrap());
+        let reread = MarkdownFormat::default()
+            .deserialize(&b, Some("b"))
+            .unwrap();
+        assert_eq!(
+            MarkdownFormat::default().extract_links(&reread, &[]),
+            vec![
+                LinkTarget::Note("Product Roadmap".into()),
+                LinkTarget::Note("plans-2024".into()),
+            ]
+        );
+
+        assert!(matches!(
+            vault.rename_note("roadmap", "x"),
+            Err(RepositoryError::NotFound(_))
+        ));
+        assert!(matches!(
+            vault.rename_note("a", "b"),
+            Err(RepositoryError::AlreadyExists(_))
+        ));
+    }
+
+    /// Repository that cannot read one note
+    #[derive(Debug)]
+    struct UnreadableNote {
+        inner: Arc<dyn NotesRepository>,
+        id: &'static str,
+    }
+
+    impl NotesRepository for UnreadableNote {
+        fn list_notes(&self) -> RepositoryResult<Vec<Note>> {
+            self.inner.list_notes()
+        }
+
+        fn get_note(&self, id: &str) -> RepositoryResult<Option<Note>> {
+            if id == self.id {
+                return Err(RepositoryError::io_error(format!("cannot read {id}")));
+            }
+            self.inner.get_note(id)
+        }
+
+        fn save_note(&self, note: &Note) -> RepositoryResult<()> {
+            self.inner.save_note(note)
+        }
+
+        fn delete_note(&self, id: &str) -> RepositoryResult<()> {
+            self.inner.delete_note(id)
+        }
+
+        fn rename_note(&self, old_id: &str, new_id: &str) -> RepositoryResult<Note> {
+            self.inner.rename_note(old_id, new_id)
+        }
+    }
+
+    #[test]
+    fn failed_renames_are_rolled_back() {
+        let inner = repo_with(&[
+            ("roadmap", "# Product Roadmap\nQ3 goals"),
+            ("a", "# A\nSee [[roadmap]]"),
+            ("b", "# B\nSee [[roadmap]]"),
+        ]);
+        let a = inner.get_note("a").unwrap();
+        let repo = Arc::new(UnreadableNote {
+            inner: Arc::clone(&inner),
+            id: "b",
+        });
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        assert!(matches!(
+            vault.rename_note("roadmap", "plans-2024"),
+            Err(RepositoryError::IoError(_))
+        ));
+
+        assert!(inner.get_note("roadmap").unwrap().is_some());
+        assert!(inner.get_note("plans-2024").unwrap().is_none());
+        assert_eq!(inner.get_note("a").unwrap(), a);
+        assert_eq!(vault.backlinks_for_note("roadmap"), vec!["a", "b"]);
+        assert_eq!(vault.backlinks_for_note("plans-2024"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn new_names_resolve_existing_links() {
+        let repo = repo_with(&[("a", "# A\nSee [[Someday]]"), ("b", "# B\nNothing")]);
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+        assert_eq!(vault.resolve_link("someday"), LinkResolution::Unresolved);
+
+        let renamed = MarkdownFormat::default()
+            .deserialize(b"---\naliases: Someday\n---\n# B\nNothing", Some("b"))
+            .unwrap();
+        vault.save_note(&renamed).unwrap();
+        assert_eq!(vault.backlinks_for_note("b"), vec!["a"]);
+
+        vault.delete_note("b").unwrap();
+        assert_eq!(vault.backlinks_for_note("b"), Vec::<String>::new());
+        assert_eq!(vault.backlinks_for_note("Someday"), vec!["a"]);
+    }
+
+    #[test]
+    fn open_indexes_scoped_tags() {
+        let repo = repo_with(&[("a", "# A\nWork on #project"), ("b", "# B\nNothing")]);
+
+        let vault = Vault::open(repo, Arc::new(HashtagFormat)).unwrap();
+
+        assert_eq!(vault.notes_with_tag("project"), vec!["a"]);
+    }
+
+    #[test]
+    fn tags_merge_scoped_and_global() {
+        let repo = repo_with(&[
+            (
+                "a",
+                "---\ntags:\n  - project/beta\n---\n# A\nWork on #project/alpha",
+            ),
+            ("b", "# B\nAlso #project"),
+        ]);
+        let vault = Vault::open(repo, Arc::new(HashtagFormat)).unwrap();
+        vault
+            .global_tags()
+            .assign_tag("a", "project".into())
+            .unwrap();
+        vault
+            .global_tags()
+            .assign_tag("a", "project/alpha".into())
+            .unwrap();
+
+        let a = vault.repo.get_note("a").unwrap().unwrap();
+        assert_eq!(vault.all_tags_for(&a), vec!["project", "project/alpha"]);
+        assert_eq!(vault.notes_with_tag("#project"), vec!["a", "b"]);
+        let counts: Vec<(String, u64)> = vault
+            .tag_counts()
+            .into_iter()
+            .map(|count| (count.tag, count.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("project".into(), 2), ("project/alpha".into(), 1)]
+        );
+
+        assert_eq!(vault.rename_tag("project", "work").unwrap(), vec!["a", "b"]);
+        let a = vault.repo.get_note("a").unwrap().unwrap();
+        assert_eq!(
+            a.properties.get("tags").unwrap().as_strings(),
+            vec!["work/beta"]
+        );
+        assert_eq!(vault.tags_for_note("a"), vec!["work", "work/alpha"]);
+        assert_eq!(vault.notes_with_tag("project"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn save_note_replaces_stale_links_and_tags() {
+        let repo = repo_with(&[
+            ("a", "# A\nSee [B](b) #draft"),
+            ("b", "# B\nNothing"),
+            ("c", "# C\nNothing"),
+        ]);
+        let vault = Vault::open(repo, Arc::new(HashtagFormat)).unwrap();
+
+        let edited = MarkdownFormat::default()
+            .deserialize(b"# A\nNow see [C](c) #final", Some("a"))
+            .unwrap();
+        vault.save_note(&edited).unwrap();
+
+        assert_eq!(vault.backlinks_for_note("b"), Vec::<String>::new());
+        assert_eq!(vault.backlinks_for_note("c"), vec!["a"]);
+        assert_eq!(vault.notes_with_tag("draft"), Vec::<String>::new());
+        assert_eq!(vault.notes_with_tag("final"), vec!["a"]);
+        assert_eq!(vault.repo.get_note("a").unwrap().unwrap(), edited);
+    }
+
+    #[test]
+    fn delete_note_drops_its_links_and_tags() {
+        let repo = repo_with(&[
+            ("a", "# A\nSee [B](b) #draft"),
+            ("b", "# B\nBack to [A](a)"),
+        ]);
+        let vault = Vault::open(repo, Arc::new(HashtagFormat)).unwrap();
+
+        vault.delete_note("a").unwrap();
+
+        assert!(vault.repo.get_note("a").unwrap().is_none());
+        assert_eq!(vault.backlinks_for_note("b"), Vec::<String>::new());
+        assert_eq!(vault.notes_with_tag("draft"), Vec::<String>::new());
+        assert_eq!(vault.backlinks_for_note("a"), vec!["b"]);
+    }
+
+    #[test]
+    fn deleting_a_linked_name_resolves_links_again() {
+        let repo = repo_with(&[
+            ("x", "# Plan\nOld"),
+            ("y", "# Plan\nNew"),
+            ("z", "# Z\nUnrelated"),
+            ("c", "# C\nSee [the plan](Plan)"),
+        ]);
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+        assert_eq!(vault.backlinks_for_note("y"), Vec::<String>::new());
+
+        vault.delete_note("z").unwrap();
+        assert_eq!(vault.backlinks_for_note("Plan"), vec!["c"]);
+
+        vault.delete_note("x").unwrap();
+        assert_eq!(vault.backlinks_for_note("y"), vec!["c"]);
+        assert_eq!(vault.backlinks_for_note("Plan"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn search_follows_saves_and_deletes() {
+        let repo = repo_with(&[
+            ("a", "# A\nPlanning the roadmap"),
+            ("b", "# B\nGrocery list"),
+        ]);
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        let hits = vault.search("roadmap");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].note_id, "a");
+
+        let edited = MarkdownFormat::default()
+            .deserialize(b"# B\nRoadmap review", Some("b"))
+            .unwrap();
+        vault.save_note(&edited).unwrap();
+        vault.delete_note("a").unwrap();
+
+        let ids: Vec<String> = vault
+            .search("roadmap")
+            .into_iter()
+            .map(|hit| hit.note_id)
+            .collect();
+        assert_eq!(ids, vec!["b"]);
+    }
+
+    #[test]
+    fn query_filters_and_sorts_notes() {
+        let repo = repo_with(&[
+            (
+                "plan",
+                "# Zebra plan\n## TODO Q3 goals\nSee [roadmap](roadmap) #project",
+            ),
+            (
+                "notes",
+                "# Alpha notes\n## DONE Q3 review\nSee [roadmap](roadmap) #project",
+            ),
+            ("roadmap", "# Roadmap\nBack to [plan](plan)"),
+        ]);
+        let vault = Vault::open(repo, Arc::new(HashtagFormat)).unwrap();
+
+        assert_eq!(
+            vault
+                .query(
+                    r#"tag:project AND links-to:roadmap AND todo:TODO AND heading:"Q3""#,
+                    QuerySort::Id,
+                )
+                .unwrap(),
+            vec!["plan"]
+        );
+        assert_eq!(
+            vault.query("links-to:roadmap", QuerySort::Title).unwrap(),
+            vec!["notes", "plan"]
+        );
+        assert_eq!(
+            vault
+                .query("roadmap OR plan", QuerySort::Backlinks)
+                .unwrap(),
+            vec!["roadmap", "plan", "notes"]
+        );
+        assert!(matches!(
+            vault.query("tag:", QuerySort::Id),
+            Err(LibnoteError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn new_vault_starts_empty_until_reindexed() {
+        let repo = repo_with(&[("a", "# A\n[B](b)")]);
+
+        let vault = Vault::new(repo, Arc::new(MarkdownFormat::default()));
+        assert_eq!(vault.backlinks_for_note("b"), Vec::<String>::new());
+
+        vault.reindex().unwrap();
+        assert_eq!(vault.backlinks_for_note("b"), vec!["a"]);
+    }
+
+    #[test]
+    fn vault_updates_are_shared_between_threads() {
+        let repo = repo_with(&[("a", "# A\nSee [[B]]")]);
+        let vault = Arc::new(Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap());
+        assert_eq!(vault.backlinks_for_note("B"), vec!["a"]);
+
+        let writer = Arc::clone(&vault);
+        std::thread::spawn(move || {
+            let b = MarkdownFormat::default()
+                .deserialize(b"# B\nBack to [[a]]", Some("b"))
+                .unwrap();
+            writer.save_note(&b).unwrap();
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(vault.backlinks_for_note("b"), vec!["a"]);
+        assert_eq!(vault.backlinks_for_note("a"), vec!["b"]);
+    }
+}
@@ -0,0 +1,131 @@
+//! Link graph queries over the vault, see [`crate::graph`]
+
+use crate::{
+    graph::{LinkGraph, NoteRank, Subgraph},
+    managers::links::LinkResolution,
+    vault::Vault,
+};
+
+#[uniffi::export]
+impl Vault {
+    /// IDs of the notes the note links to, sorted
+    #[must_use]
+    #[uniffi::method]
+    pub fn outbound_neighbors(&self, note_id: &str) -> Vec<String> {
+        self.link_graph().outbound(note_id)
+    }
+
+    /// IDs of the notes linking to the note, sorted
+    #[must_use]
+    #[uniffi::method]
+    pub fn inbound_neighbors(&self, note_id: &str) -> Vec<String> {
+        self.link_graph().inbound(note_id)
+    }
+
+    /// The notes within `depth` links of the note in either direction, and
+    /// the links between them
+    #[must_use]
+    #[uniffi::method]
+    pub fn local_graph(&self, note_id: &str, depth: u32) -> Subgraph {
+        self.link_graph().local_graph(note_id, depth)
+    }
+
+    /// Groups of notes connected by links, largest first
+    #[must_use]
+    #[uniffi::method]
+    pub fn connected_components(&self) -> Vec<Vec<String>> {
+        self.link_graph().components()
+    }
+
+    /// A shortest chain of notes from one note to another, both included
+    ///
+    /// With `directed` set only links from one note to the next are followed.
+    /// `None` if the notes are not connected.
+    #[must_use]
+    #[uniffi::method]
+    pub fn shortest_path(&self, from: &str, to: &str, directed: bool) -> Option<Vec<String>> {
+        self.link_graph().shortest_path(from, to, directed)
+    }
+
+    /// `PageRank` of every note, highest first
+    #[must_use]
+    #[uniffi::method]
+    pub fn page_rank(&self) -> Vec<NoteRank> {
+        self.link_graph().page_rank()
+    }
+
+    /// The link graph as Graphviz DOT source
+    #[must_use]
+    #[uniffi::method]
+    pub fn export_dot(&self) -> String {
+        self.link_graph().to_dot()
+    }
+
+    /// The link graph as a `GraphML` document
+    #[must_use]
+    #[uniffi::method]
+    pub fn export_graphml(&self) -> String {
+        self.link_graph().to_graphml()
+    }
+}
+
+impl Vault {
+    /// Build the graph from the stored links, resolved against the current
+    /// notes
+    #[must_use]
+    pub fn link_graph(&self) -> LinkGraph {
+        let indexes = self.indexes();
+        let edges = indexes.links.iter().flat_map(|(source, targets)| {
+            targets
+                .iter()
+                .filter_map(|target| match indexes.resolver.resolve(target.note_id()?) {
+                    LinkResolution::Resolved { note_id } => Some((source.clone(), note_id)),
+                    _ => None,
+                })
+        });
+        LinkGraph::new(indexes.links.keys().cloned(), edges)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        formats::markdown::MarkdownFormat,
+        repo::{NotesRepository, memory::MemoryNotesRepository},
+    };
+
+    #[test]
+    fn graph_follows_resolved_links() {
+        let repo = MemoryNotesRepository::new(Arc::new(MarkdownFormat::default()));
+        for (id, content) in [
+            (
+                "home",
+                "# Home\n[[Plan]] and [[Ideas#Later]] and [[Nowhere]]",
+            ),
+            ("plan", "# Plan\nBack [[home]], see ![pic](pic.png)"),
+            ("ideas", "# Ideas\n## Later\nSomeday [[plan#^missing]]"),
+            ("lonely", "# Lonely\nNo links"),
+        ] {
+            repo.insert_raw(content.as_bytes(), Some(id)).unwrap();
+        }
+        let repo: Arc<dyn NotesRepository> = Arc::new(repo);
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        assert_eq!(vault.outbound_neighbors("home"), vec!["ideas", "plan"]);
+        assert_eq!(vault.inbound_neighbors("plan"), vec!["home", "ideas"]);
+        assert_eq!(
+            vault.connected_components(),
+            vec![vec!["home", "ideas", "plan"], vec!["lonely"]]
+        );
+        assert_eq!(
+            vault.shortest_path("ideas", "home", true),
+            Some(vec!["ideas".into(), "plan".into(), "home".into()])
+        );
+        assert_eq!(vault.local_graph("lonely", 3).nodes, vec!["lonely"]);
+        assert_eq!(vault.page_rank()[0].note_id, "plan");
+        assert!(vault.export_dot().contains("\"ideas\" -> \"plan\";"));
+    }
+}
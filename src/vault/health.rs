@@ -0,0 +1,376 @@
+//! Vault diagnostics: broken links, missing and unused attachments, and
+//! notes nothing links to
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::{
+    document::{block_ids, heading_slugs},
+    error::RepositoryResult,
+    managers::links::LinkResolution,
+    models::LinkTarget,
+    vault::{Indexes, Vault},
+};
+
+/// Why a link does not reach its target
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Enum)]
+pub enum BrokenLinkReason {
+    /// No note matches the link
+    MissingNote,
+    /// Several notes match the link equally well
+    AmbiguousNote { candidates: Vec<String> },
+    /// The note exists but has no heading with that slug
+    MissingHeading,
+    /// The note exists but has no block with that `^block-id`
+    MissingBlock,
+}
+
+/// A link that does not lead anywhere
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct BrokenLink {
+    /// ID of the note containing the link
+    pub source: String,
+    /// The link as written
+    pub target: LinkTarget,
+    pub reason: BrokenLinkReason,
+}
+
+/// An attachment reference whose file is not in the attachment store
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct MissingAttachment {
+    /// ID of the note referencing the attachment
+    pub source: String,
+    pub path: String,
+}
+
+/// Every diagnostic at once, see the individual [`Vault`] methods
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct HealthReport {
+    pub broken_links: Vec<BrokenLink>,
+    pub missing_attachments: Vec<MissingAttachment>,
+    pub orphan_notes: Vec<String>,
+    pub unused_attachments: Vec<String>,
+}
+
+#[uniffi::export]
+impl Vault {
+    /// Links to notes that do not exist or cannot be told apart, and to
+    /// headings or blocks missing from their note, sorted by source
+    ///
+    /// External links (`scheme://...`, `mailto:`) are not checked, nor are
+    /// links to files in the attachment store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading a linked note from the repository fails
+    #[uniffi::method]
+    pub fn broken_links(&self) -> RepositoryResult<Vec<BrokenLink>> {
+        // `note_id` -> (heading slugs, block ids), read once per linked note
+        let mut anchors: HashMap<String, (Vec<String>, Vec<String>)> = HashMap::new();
+        let mut broken = Vec::new();
+
+        let indexes = self.indexes();
+        for (source, target) in indexes.note_links() {
+            let Some(name) = target.note_id() else {
+                continue;
+            };
+            if is_external(name) || self.attachment_exists(name) {
+                continue;
+            }
+
+            let note_id = match indexes.resolver.resolve(name) {
+                LinkResolution::Resolved { note_id } => note_id,
+                LinkResolution::Ambiguous { candidates } => {
+                    broken.push(BrokenLink {
+                        source: source.to_string(),
+                        target: target.clone(),
+                        reason: BrokenLinkReason::AmbiguousNote { candidates },
+                    });
+                    continue;
+                }
+                LinkResolution::Unresolved => {
+                    broken.push(BrokenLink {
+                        source: source.to_string(),
+                        target: target.clone(),
+                        reason: BrokenLinkReason::MissingNote,
+                    });
+                    continue;
+                }
+            };
+            if matches!(target, LinkTarget::Note(_)) {
+                continue;
+            }
+
+            if !anchors.contains_key(&note_id) {
+                let blocks = self
+                    .repo
+                    .get_note(&note_id)?
+                    .map_or_else(Vec::new, |note| note.blocks);
+                anchors.insert(
+                    note_id.clone(),
+                    (heading_slugs(&blocks), block_ids(&blocks)),
+                );
+            }
+            let (headings, blocks) = &anchors[&note_id];
+            let reason = match target {
+                LinkTarget::Heading { slug, .. } if !headings.contains(slug) => {
+                    BrokenLinkReason::MissingHeading
+                }
+                LinkTarget::Block { block_id, .. } if !blocks.contains(block_id) => {
+                    BrokenLinkReason::MissingBlock
+                }
+                _ => continue,
+            };
+            broken.push(BrokenLink {
+                source: source.to_string(),
+                target: target.clone(),
+                reason,
+            });
+        }
+
+        Ok(broken)
+    }
+
+    /// Attachment references whose file is missing from the attachment store,
+    /// sorted by source
+    ///
+    /// Empty when the vault has no attachment store, see
+    /// [`Vault::with_attachments`].
+    #[must_use]
+    #[uniffi::method]
+    pub fn missing_attachments(&self) -> Vec<MissingAttachment> {
+        if self.attachments().is_none() {
+            return Vec::new();
+        }
+
+        self.indexes()
+            .note_links()
+            .into_iter()
+            .filter_map(|(source, target)| match target {
+                LinkTarget::Attachment(path) if !self.attachment_exists(path) => {
+                    Some(MissingAttachment {
+                        source: source.to_string(),
+                        path: path.clone(),
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// IDs of the notes no other note links to, sorted
+    #[must_use]
+    #[uniffi::method]
+    pub fn orphan_notes(&self) -> Vec<String> {
+        let indexes = self.indexes();
+        let mut orphans: Vec<String> = indexes
+            .links
+            .keys()
+            .filter(|id| {
+                indexes
+                    .backlinks
+                    .sources_for_note(id)
+                    .iter()
+                    .all(|source| source == *id)
+            })
+            .cloned()
+            .collect();
+        orphans.sort();
+        orphans
+    }
+
+    /// Files in the attachment store no note links to, sorted
+    ///
+    /// Empty when the vault has no attachment store.
+    #[must_use]
+    #[uniffi::method]
+    pub fn unused_attachments(&self) -> Vec<String> {
+        let Some(store) = self.attachments() else {
+            return Vec::new();
+        };
+
+        let indexes = self.indexes();
+        let used: BTreeSet<&str> = indexes
+            .note_links()
+            .into_iter()
+            .filter_map(|(_, target)| match target {
+                LinkTarget::Attachment(path) | LinkTarget::Note(path) => Some(normalize_path(path)),
+                _ => None,
+            })
+            .collect();
+        let mut unused: Vec<String> = store
+            .list()
+            .into_iter()
+            .filter(|path| !used.contains(normalize_path(path)))
+            .collect();
+        unused.sort();
+        unused
+    }
+
+    /// All of the diagnostics above
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading a linked note from the repository fails
+    #[uniffi::method]
+    pub fn health_report(&self) -> RepositoryResult<HealthReport> {
+        Ok(HealthReport {
+            broken_links: self.broken_links()?,
+            missing_attachments: self.missing_attachments(),
+            orphan_notes: self.orphan_notes(),
+            unused_attachments: self.unused_attachments(),
+        })
+    }
+}
+
+impl Indexes {
+    /// Every stored link as written, sorted by source
+    fn note_links(&self) -> Vec<(&str, &LinkTarget)> {
+        let mut links: Vec<(&str, &LinkTarget)> = self
+            .links
+            .iter()
+            .flat_map(|(source, targets)| targets.iter().map(move |t| (source.as_str(), t)))
+            .collect();
+        links.sort();
+        links.dedup();
+        links
+    }
+}
+
+impl Vault {
+    fn attachment_exists(&self, path: &str) -> bool {
+        self.attachments()
+            .is_some_and(|store| store.read(normalize_path(path)).is_some())
+    }
+}
+
+/// Attachment paths are relative to the store root
+fn normalize_path(path: &str) -> &str {
+    path.trim_start_matches("./").trim_start_matches('/')
+}
+
+fn is_external(target: &str) -> bool {
+    target.contains("://") || target.starts_with("mailto:")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        error::RepositoryError,
+        formats::markdown::MarkdownFormat,
+        repo::{NotesRepository, file::FileProvider, memory::MemoryNotesRepository},
+    };
+
+    #[derive(Debug)]
+    struct MockStore(Vec<&'static str>);
+
+    impl FileProvider for MockStore {
+        fn read(&self, path: &str) -> Option<Vec<u8>> {
+            self.0.contains(&path).then(Vec::new)
+        }
+
+        fn write(&mut self, path: &str, _data: &[u8]) -> RepositoryResult<()> {
+            Err(RepositoryError::invalid_id(path))
+        }
+
+        fn delete(&mut self, path: &str) -> RepositoryResult<()> {
+            Err(RepositoryError::not_found(path))
+        }
+
+        fn list(&self) -> Vec<String> {
+            self.0.iter().map(ToString::to_string).collect()
+        }
+    }
+
+    fn vault() -> Vault {
+        let repo = MemoryNotesRepository::new(Arc::new(MarkdownFormat::default()));
+        for (id, content) in [
+            (
+                "home",
+                "# Home\nSee [[Plan]], [[plan#Missing]], [[plan#^gone]], [[Nowhere]], [[Dup]]\n\
+                 ![pic](img/pic.png) ![lost](img/lost.png) [spec](files/spec.pdf) [site](https://example.com)",
+            ),
+            ("plan", "# Plan\n## Goals\nShip ^ship"),
+            ("d1", "# Dup\nOne"),
+            ("d2", "# Dup\nTwo"),
+            ("lonely", "# Lonely\nNo links"),
+        ] {
+            repo.insert_raw(content.as_bytes(), Some(id)).unwrap();
+        }
+        let repo: Arc<dyn NotesRepository> = Arc::new(repo);
+        Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap()
+    }
+
+    #[test]
+    fn reports_broken_links_orphans_and_attachments() {
+        let vault = vault().with_attachments(Box::new(MockStore(vec![
+            "img/pic.png",
+            "files/spec.pdf",
+            "img/unused.png",
+        ])));
+
+        let report = vault.health_report().unwrap();
+
+        let broken: Vec<(&LinkTarget, &BrokenLinkReason)> = report
+            .broken_links
+            .iter()
+            .map(|link| (&link.target, &link.reason))
+            .collect();
+        assert_eq!(
+            broken,
+            vec![
+                (
+                    &LinkTarget::Note("Dup".into()),
+                    &BrokenLinkReason::AmbiguousNote {
+                        candidates: vec!["d1".into(), "d2".into()],
+                    }
+                ),
+                (
+                    &LinkTarget::Note("Nowhere".into()),
+                    &BrokenLinkReason::MissingNote
+                ),
+                (
+                    &LinkTarget::Heading {
+                        note: "plan".into(),
+                        slug: "missing".into(),
+                    },
+                    &BrokenLinkReason::MissingHeading
+                ),
+                (
+                    &LinkTarget::Block {
+                        note: "plan".into(),
+                        block_id: "gone".into(),
+                    },
+                    &BrokenLinkReason::MissingBlock
+                ),
+            ]
+        );
+        assert!(report.broken_links.iter().all(|link| link.source == "home"));
+        assert_eq!(
+            report.missing_attachments,
+            vec![MissingAttachment {
+                source: "home".into(),
+                path: "img/lost.png".into(),
+            }]
+        );
+        assert_eq!(report.orphan_notes, vec!["d1", "d2", "home", "lonely"]);
+        assert_eq!(report.unused_attachments, vec!["img/unused.png"]);
+    }
+
+    #[test]
+    fn attachments_are_not_checked_without_a_store() {
+        let vault = vault();
+
+        assert_eq!(vault.missing_attachments(), Vec::new());
+        assert_eq!(vault.unused_attachments(), Vec::<String>::new());
+        assert!(
+            vault
+                .broken_links()
+                .unwrap()
+                .iter()
+                .any(|link| link.target == LinkTarget::Note("files/spec.pdf".into()))
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Tasks across the vault, see [`crate::tasks`]
+
+use crate::{
+    error::{LibnoteResult, RepositoryError, RepositoryResult},
+    tasks::{Task, TaskFilter, extract_tasks, toggle_task},
+    vault::Vault,
+};
+
+#[uniffi::export]
+impl Vault {
+    /// Tasks in every note that match `filter`, soonest due first
+    ///
+    /// Tasks without a due date come last; ties are in note ID then document
+    /// order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if listing the notes fails
+    #[uniffi::method]
+    pub fn tasks(&self, filter: &TaskFilter) -> RepositoryResult<Vec<Task>> {
+        let mut tasks: Vec<Task> = self
+            .repo
+            .list_notes()?
+            .iter()
+            .flat_map(extract_tasks)
+            .filter(|task| filter.matches(task))
+            .collect();
+        tasks.sort_by(|a, b| {
+            (a.due.is_none(), &a.due, &a.note_id, &a.path).cmp(&(
+                b.due.is_none(),
+                &b.due,
+                &b.note_id,
+                &b.path,
+            ))
+        });
+        Ok(tasks)
+    }
+
+    /// Toggle the task at `path` in a note (see [`toggle_task`]) and save the
+    /// note
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note does not exist, has no task at `path`, or
+    /// saving it fails
+    #[uniffi::method]
+    pub fn toggle_task(&self, note_id: &str, path: &[u64]) -> LibnoteResult<Task> {
+        let mut note = self
+            .repo
+            .get_note(note_id)?
+            .ok_or_else(|| RepositoryError::not_found(note_id))?;
+        let task = toggle_task(&mut note, path)?;
+        self.save_note(&note)?;
+        Ok(task)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        formats::markdown::MarkdownFormat,
+        repo::{NotesRepository, memory::MemoryNotesRepository},
+        tasks::TaskStatus,
+    };
+
+    #[test]
+    fn tasks_are_gathered_and_toggled_across_notes() {
+        let repo = MemoryNotesRepository::new(Arc::new(MarkdownFormat::default()));
+        for (id, content) in [
+            (
+                "home",
+                "# Home\n- [ ] Water plants #chores\n- [ ] Pay rent due:2024-05-01",
+            ),
+            (
+                "work",
+                "# Work\n- [x] Ship it due:2024-04-01\n- [ ] Plan Q3 due:2024-06-30",
+            ),
+        ] {
+            repo.insert_raw(content.as_bytes(), Some(id)).unwrap();
+        }
+        let repo: Arc<dyn NotesRepository> = Arc::new(repo);
+        let vault = Vault::open(repo, Arc::new(MarkdownFormat::default())).unwrap();
+
+        let open = TaskFilter {
+            status: Some(TaskStatus::Open),
+            ..TaskFilter::default()
+        };
+        let texts =
+            |tasks: Vec<Task>| -> Vec<String> { tasks.into_iter().map(|task| task.text).collect() };
+        assert_eq!(
+            texts(vault.tasks(&open).unwrap()),
+            vec!["Pay rent", "Plan Q3", "Water plants"]
+        );
+        assert_eq!(
+            texts(
+                vault
+                    .tasks(&TaskFilter {
+                        due_before: Some("2024-05-31".into()),
+                        ..TaskFilter::default()
+                    })
+                    .unwrap()
+            ),
+            vec!["Ship it", "Pay rent"]
+        );
+
+        let done = vault.toggle_task("home", &[0, 1, 0]).unwrap();
+        assert_eq!(done.status, TaskStatus::Done);
+        assert_eq!(
+            texts(vault.tasks(&open).unwrap()),
+            vec!["Plan Q3", "Water plants"]
+        );
+        assert!(vault.toggle_task("home", &[0]).is_err());
+    }
+}